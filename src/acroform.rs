@@ -0,0 +1,1120 @@
+// 表单（AcroForm）：读取、填写、扁平化和合并表单字段
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::annotations::{normal_appearance, process_page_annotations};
+use crate::content_stream::{parse_content, FontMetrics};
+use crate::filters::decode_stream;
+use crate::font_embed::{hex_string, EmbeddedFont};
+use crate::outline::{decode_text_string, text_string};
+use crate::pdf_utils::{deep_copy_object_with_map, object_to_f64, parse_rect};
+use crate::render_color::resolve;
+use crate::render_font::parse_to_unicode;
+use crate::standard_font::{encode_win_ansi, font_dictionary, literal_string, win_ansi_code, wrap_text};
+
+/// 遍历字段树时允许的最大嵌套深度
+const MAX_DEPTH: usize = 32;
+
+// 字段标志（/Ff）
+const FLAG_READ_ONLY: i64 = 1;
+const FLAG_REQUIRED: i64 = 1 << 1;
+const FLAG_MULTILINE: i64 = 1 << 12;
+const FLAG_PASSWORD: i64 = 1 << 13;
+const FLAG_RADIO: i64 = 1 << 15;
+const FLAG_PUSH_BUTTON: i64 = 1 << 16;
+const FLAG_COMBO: i64 = 1 << 17;
+const FLAG_EDIT: i64 = 1 << 18;
+const FLAG_MULTI_SELECT: i64 = 1 << 21;
+const FLAG_COMB: i64 = 1 << 24;
+
+/// 未指定字号（DA 中为 0）时多行文本和列表使用的字号
+const DEFAULT_FONT_SIZE: f64 = 12.0;
+/// 自动字号的下限
+const MIN_FONT_SIZE: f64 = 4.0;
+/// 列表框中选中项的背景色
+const SELECTION_COLOR: &str = "0.6 0.75 0.85 rg";
+/// 外观流中嵌入字体的资源名
+const EMBEDDED_FONT_NAME: &[u8] = b"EmbF";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FieldKind {
+    Text,
+    Checkbox,
+    Radio,
+    PushButton,
+    Combo,
+    List,
+    Signature,
+}
+
+impl FieldKind {
+    fn from_type(field_type: &[u8], flags: i64) -> Option<FieldKind> {
+        let kind = match field_type {
+            b"Tx" => FieldKind::Text,
+            b"Btn" if flags & FLAG_PUSH_BUTTON != 0 => FieldKind::PushButton,
+            b"Btn" if flags & FLAG_RADIO != 0 => FieldKind::Radio,
+            b"Btn" => FieldKind::Checkbox,
+            b"Ch" if flags & FLAG_COMBO != 0 => FieldKind::Combo,
+            b"Ch" => FieldKind::List,
+            b"Sig" => FieldKind::Signature,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FieldKind::Text => "text",
+            FieldKind::Checkbox => "checkbox",
+            FieldKind::Radio => "radio",
+            FieldKind::PushButton => "button",
+            FieldKind::Combo => "combo",
+            FieldKind::List => "list",
+            FieldKind::Signature => "signature",
+        }
+    }
+}
+
+/// 终端字段（带值的字段），可继承的属性已从父字段和 AcroForm 展开
+struct Field {
+    id: ObjectId,
+    name: String,
+    kind: FieldKind,
+    flags: i64,
+    value: Option<Object>,
+    /// 默认外观字符串，如 "/Helv 0 Tf 0 g"
+    default_appearance: Vec<u8>,
+    /// 对齐方式: 0 左、1 居中、2 右
+    quadding: i64,
+    max_len: Option<usize>,
+    /// 选择字段的选项 (导出值, 显示文本)
+    options: Vec<(String, String)>,
+    /// 字段的控件注释；字段与控件合并为同一对象时即字段本身
+    widgets: Vec<ObjectId>,
+}
+
+/// 沿字段树向下传递的可继承属性
+#[derive(Clone, Default)]
+struct Inherited {
+    name: String,
+    field_type: Option<Vec<u8>>,
+    flags: i64,
+    value: Option<Object>,
+    default_appearance: Vec<u8>,
+    quadding: i64,
+    max_len: Option<usize>,
+}
+
+fn get_dict<'a>(doc: &'a Document, dict: &'a Dictionary, key: &[u8]) -> Option<&'a Dictionary> {
+    dict.get(key).ok().and_then(|d| resolve(doc, d).as_dict().ok())
+}
+
+fn get_i64(doc: &Document, dict: &Dictionary, key: &[u8]) -> Option<i64> {
+    dict.get(key).ok().and_then(|v| resolve(doc, v).as_i64().ok())
+}
+
+/// 文档目录中的 /AcroForm 字典
+fn acroform(doc: &Document) -> Option<&Dictionary> {
+    get_dict(doc, doc.catalog().ok()?, b"AcroForm")
+}
+
+/// 写回 /AcroForm 字典：原本是间接对象时替换该对象，否则直接写入目录
+fn set_acroform(doc: &mut Document, form: Dictionary) -> Result<(), String> {
+    let catalog_id = doc.trailer.get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|e| format!("无法获取文档目录: {}", e))?;
+    let existing = doc.get_dictionary(catalog_id).ok()
+        .and_then(|c| c.get(b"AcroForm").ok())
+        .and_then(|f| f.as_reference().ok())
+        .filter(|id| doc.objects.contains_key(id));
+    match existing {
+        Some(form_id) => {
+            doc.objects.insert(form_id, Object::Dictionary(form));
+        }
+        None => {
+            let catalog = doc.get_dictionary_mut(catalog_id)
+                .map_err(|e| format!("无法获取文档目录: {}", e))?;
+            catalog.set("AcroForm", Object::Dictionary(form));
+        }
+    }
+    Ok(())
+}
+
+/// 读取表单中的全部终端字段
+fn read_fields(doc: &Document) -> Vec<Field> {
+    let Some(form) = acroform(doc) else { return Vec::new() };
+    let defaults = Inherited {
+        default_appearance: form.get(b"DA").and_then(Object::as_str).map(<[u8]>::to_vec).unwrap_or_default(),
+        quadding: get_i64(doc, form, b"Q").unwrap_or(0),
+        ..Inherited::default()
+    };
+
+    let mut fields = Vec::new();
+    let mut visited = HashSet::new();
+    let roots = form.get(b"Fields").ok().and_then(|f| resolve(doc, f).as_array().ok());
+    for root in roots.into_iter().flatten() {
+        if let Ok(id) = root.as_reference() {
+            collect_fields(doc, id, &defaults, &mut fields, &mut visited, 0);
+        }
+    }
+    fields
+}
+
+fn collect_fields(
+    doc: &Document,
+    id: ObjectId,
+    parent: &Inherited,
+    fields: &mut Vec<Field>,
+    visited: &mut HashSet<ObjectId>,
+    depth: usize,
+) {
+    if depth > MAX_DEPTH || !visited.insert(id) {
+        return;
+    }
+    let Ok(dict) = doc.get_dictionary(id) else { return };
+
+    let mut inherited = parent.clone();
+    if let Ok(partial) = dict.get(b"T").and_then(Object::as_str) {
+        let partial = decode_text_string(partial);
+        inherited.name = if parent.name.is_empty() { partial } else { format!("{}.{}", parent.name, partial) };
+    }
+    if let Ok(field_type) = dict.get(b"FT").and_then(Object::as_name) {
+        inherited.field_type = Some(field_type.to_vec());
+    }
+    if let Some(flags) = get_i64(doc, dict, b"Ff") {
+        inherited.flags = flags;
+    }
+    if let Ok(value) = dict.get(b"V") {
+        inherited.value = Some(resolve(doc, value).clone());
+    }
+    if let Ok(da) = dict.get(b"DA").and_then(Object::as_str) {
+        inherited.default_appearance = da.to_vec();
+    }
+    if let Some(quadding) = get_i64(doc, dict, b"Q") {
+        inherited.quadding = quadding;
+    }
+    if let Some(max_len) = get_i64(doc, dict, b"MaxLen") {
+        inherited.max_len = usize::try_from(max_len).ok();
+    }
+
+    // 带 /T 或 /Kids 的子节点是子字段，其余子节点是控件注释
+    let mut children = Vec::new();
+    let mut widgets = Vec::new();
+    if dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget".as_slice()) {
+        widgets.push(id);
+    }
+    let kids = dict.get(b"Kids").ok().and_then(|k| resolve(doc, k).as_array().ok());
+    for kid in kids.into_iter().flatten() {
+        let Ok(kid_id) = kid.as_reference() else { continue };
+        let Ok(kid_dict) = doc.get_dictionary(kid_id) else { continue };
+        if kid_dict.has(b"T") || kid_dict.has(b"Kids") {
+            children.push(kid_id);
+        } else {
+            widgets.push(kid_id);
+        }
+    }
+
+    if !children.is_empty() {
+        for child in children {
+            collect_fields(doc, child, &inherited, fields, visited, depth + 1);
+        }
+        return;
+    }
+
+    let Some(kind) = inherited.field_type.as_deref().and_then(|t| FieldKind::from_type(t, inherited.flags)) else { return };
+    let options = dict.get(b"Opt").ok()
+        .and_then(|o| resolve(doc, o).as_array().ok())
+        .map(|opts| opts.iter().filter_map(|opt| choice_option(doc, opt)).collect())
+        .unwrap_or_default();
+
+    fields.push(Field {
+        id,
+        name: inherited.name,
+        kind,
+        flags: inherited.flags,
+        value: inherited.value,
+        default_appearance: inherited.default_appearance,
+        quadding: inherited.quadding,
+        max_len: inherited.max_len,
+        options,
+        widgets,
+    });
+}
+
+/// 选项为文本字符串，或 [导出值 显示文本] 数组
+fn choice_option(doc: &Document, option: &Object) -> Option<(String, String)> {
+    match resolve(doc, option) {
+        Object::String(text, _) => {
+            let text = decode_text_string(text);
+            Some((text.clone(), text))
+        }
+        Object::Array(pair) => {
+            let text = |i: usize| pair.get(i).and_then(|t| resolve(doc, t).as_str().ok()).map(decode_text_string);
+            let export = text(0)?;
+            Some((export.clone(), text(1).unwrap_or(export)))
+        }
+        _ => None,
+    }
+}
+
+/// 控件外观字典 /AP /N 中除 Off 外的状态名
+fn on_states(doc: &Document, widget_id: ObjectId) -> Vec<String> {
+    let Ok(widget) = doc.get_dictionary(widget_id) else { return Vec::new() };
+    get_dict(doc, widget, b"AP")
+        .and_then(|ap| get_dict(doc, ap, b"N"))
+        .map(|states| {
+            states.iter()
+                .filter(|(name, _)| name.as_slice() != b"Off")
+                .map(|(name, _)| String::from_utf8_lossy(name).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 复选框和单选按钮当前选中的状态名，未选中时为 None
+fn button_state(doc: &Document, field: &Field) -> Option<String> {
+    let state = match &field.value {
+        Some(Object::Name(name)) => Some(name.clone()),
+        _ => field.widgets.iter()
+            .filter_map(|&id| doc.get_dictionary(id).ok())
+            .filter_map(|w| w.get(b"AS").and_then(Object::as_name).ok())
+            .find(|state| *state != b"Off")
+            .map(<[u8]>::to_vec),
+    };
+    state.filter(|s| s.as_slice() != b"Off").map(|s| String::from_utf8_lossy(&s).into_owned())
+}
+
+/// 表单字段的 JSON 表示，页码从 1 开始
+#[derive(Debug, Serialize)]
+pub(crate) struct FieldInfo {
+    /// 完整字段名，父字段名与子字段名以 "." 连接
+    name: String,
+    /// "text"、"checkbox"、"radio"、"button"、"combo"、"list" 或 "signature"
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// 文本和下拉框为字符串，复选框为布尔值，单选按钮为选中项（未选中为 null），多选列表为字符串数组
+    value: Value,
+    /// 选择字段的导出值，或复选框和单选按钮的选中状态名
+    options: Vec<String>,
+    /// 第一个控件的位置 [left, bottom, right, top]
+    rect: Option<[f64; 4]>,
+    /// 第一个控件所在的页码
+    page: Option<usize>,
+    read_only: bool,
+    required: bool,
+}
+
+/// 读取表单字段信息
+pub(crate) fn form_field_infos(doc: &Document) -> Vec<FieldInfo> {
+    // 按页面的 /Annots 确定控件所在页，不依赖可能缺失的 /P
+    let mut widget_pages = HashMap::new();
+    for (number, page_id) in doc.get_pages() {
+        let annots = doc.get_dictionary(page_id).ok()
+            .and_then(|p| p.get(b"Annots").ok())
+            .and_then(|a| resolve(doc, a).as_array().ok());
+        for annot in annots.into_iter().flatten() {
+            if let Ok(id) = annot.as_reference() {
+                widget_pages.entry(id).or_insert(number as usize);
+            }
+        }
+    }
+
+    read_fields(doc).iter().map(|field| {
+        let value = match field.kind {
+            FieldKind::Checkbox => Value::Bool(button_state(doc, field).is_some()),
+            FieldKind::Radio => button_state(doc, field).map_or(Value::Null, Value::String),
+            FieldKind::Text | FieldKind::Combo | FieldKind::List => match &field.value {
+                Some(Object::String(text, _)) => Value::String(decode_text_string(text)),
+                Some(Object::Array(items)) => Value::Array(
+                    items.iter()
+                        .filter_map(|item| resolve(doc, item).as_str().ok())
+                        .map(|text| Value::String(decode_text_string(text)))
+                        .collect(),
+                ),
+                _ => Value::Null,
+            },
+            FieldKind::PushButton | FieldKind::Signature => Value::Null,
+        };
+        let options = match field.kind {
+            FieldKind::Checkbox | FieldKind::Radio => {
+                let mut states: Vec<String> = Vec::new();
+                for state in field.widgets.iter().flat_map(|&id| on_states(doc, id)) {
+                    if !states.contains(&state) {
+                        states.push(state);
+                    }
+                }
+                states
+            }
+            _ => field.options.iter().map(|(export, _)| export.clone()).collect(),
+        };
+        let first_widget = field.widgets.first().and_then(|&id| doc.get_dictionary(id).ok());
+
+        FieldInfo {
+            name: field.name.clone(),
+            kind: field.kind.name(),
+            value,
+            options,
+            rect: first_widget
+                .and_then(|w| w.get(b"Rect").ok())
+                .and_then(|r| resolve(doc, r).as_array().ok())
+                .and_then(|r| parse_rect(r)),
+            page: field.widgets.iter().find_map(|id| widget_pages.get(id).copied()),
+            read_only: field.flags & FLAG_READ_ONLY != 0,
+            required: field.flags & FLAG_REQUIRED != 0,
+        }
+    }).collect()
+}
+
+/// 按字段名填写表单，并重新生成受影响控件的外观流
+/// 文本和选择字段的值为字符串（多选列表可为字符串数组），复选框为布尔值或状态名，单选按钮为状态名（null 表示取消选中）
+/// font_data 为表单字体无法显示的文字所用的字体；没有可用字体时由阅读器重新生成外观（NeedAppearances）
+pub(crate) fn fill_form_fields(doc: &mut Document, values: &serde_json::Map<String, Value>, font_data: Option<&[u8]>) -> Result<(), String> {
+    let form = acroform(doc).cloned().ok_or("文档中没有表单")?;
+    let fields = read_fields(doc);
+    let mut builder = AppearanceBuilder::new(form, font_data, false)?;
+
+    for (name, value) in values {
+        let field = fields.iter()
+            .find(|f| &f.name == name)
+            .ok_or_else(|| format!("未找到表单字段: {}", name))?;
+        let invalid = || format!("字段「{}」的值无效: {}", name, value);
+
+        match field.kind {
+            FieldKind::Text => {
+                let text = match value {
+                    Value::String(text) => text.clone(),
+                    Value::Number(number) => number.to_string(),
+                    Value::Null => String::new(),
+                    _ => return Err(invalid()),
+                };
+                set_field_value(doc, field.id, text_string(&text))?;
+                builder.refresh(doc, field, Some(&text))?;
+            }
+            FieldKind::Combo => {
+                let text = value.as_str().ok_or_else(invalid)?;
+                if !field.options.is_empty() && field.flags & FLAG_EDIT == 0 && !field.options.iter().any(|(export, _)| export == text) {
+                    return Err(format!("字段「{}」没有选项「{}」", name, text));
+                }
+                set_field_value(doc, field.id, text_string(text))?;
+                if let Ok(dict) = doc.get_dictionary_mut(field.id) {
+                    dict.remove(b"I");
+                }
+                builder.refresh(doc, field, Some(text))?;
+            }
+            FieldKind::List => {
+                let selected: Vec<&str> = match value {
+                    Value::String(text) => vec![text.as_str()],
+                    Value::Array(items) => items.iter().map(|item| item.as_str().ok_or_else(invalid)).collect::<Result<_, _>>()?,
+                    Value::Null => Vec::new(),
+                    _ => return Err(invalid()),
+                };
+                if selected.len() > 1 && field.flags & FLAG_MULTI_SELECT == 0 {
+                    return Err(format!("字段「{}」不支持多选", name));
+                }
+                let mut indices = Vec::new();
+                for text in &selected {
+                    let index = field.options.iter()
+                        .position(|(export, _)| export == text)
+                        .ok_or_else(|| format!("字段「{}」没有选项「{}」", name, text))?;
+                    indices.push(index);
+                }
+                indices.sort_unstable();
+
+                let value = match selected.as_slice() {
+                    [single] => text_string(single),
+                    _ => Object::Array(selected.iter().map(|text| text_string(text)).collect()),
+                };
+                set_field_value(doc, field.id, value)?;
+                if let Ok(dict) = doc.get_dictionary_mut(field.id) {
+                    dict.set("I", Object::Array(indices.into_iter().map(|i| Object::Integer(i as i64)).collect()));
+                }
+                builder.refresh(doc, field, None)?;
+            }
+            FieldKind::Checkbox | FieldKind::Radio => {
+                let state = match value {
+                    Value::Bool(false) | Value::Null => None,
+                    Value::Bool(true) if field.kind == FieldKind::Checkbox => {
+                        builder.ensure_checkbox_appearances(doc, field)?;
+                        let states: Vec<String> = field.widgets.iter().flat_map(|&id| on_states(doc, id)).collect();
+                        Some(states.into_iter().next().unwrap_or_else(|| "Yes".to_string()))
+                    }
+                    Value::String(state) if state == "Off" => None,
+                    Value::String(state) => {
+                        if !field.widgets.iter().any(|&id| on_states(doc, id).contains(state)) {
+                            return Err(format!("字段「{}」没有选项「{}」", name, state));
+                        }
+                        Some(state.clone())
+                    }
+                    _ => return Err(invalid()),
+                };
+                let state = state.unwrap_or_else(|| "Off".to_string());
+                set_field_value(doc, field.id, Object::Name(state.clone().into_bytes()))?;
+                for &widget_id in &field.widgets {
+                    let widget_state = if on_states(doc, widget_id).contains(&state) { state.as_str() } else { "Off" };
+                    if let Ok(widget) = doc.get_dictionary_mut(widget_id) {
+                        widget.set("AS", Object::Name(widget_state.as_bytes().to_vec()));
+                    }
+                }
+            }
+            FieldKind::PushButton | FieldKind::Signature => {
+                return Err(format!("字段「{}」不支持填写", name));
+            }
+        }
+    }
+
+    builder.finish(doc)
+}
+
+fn set_field_value(doc: &mut Document, field_id: ObjectId, value: Object) -> Result<(), String> {
+    let dict = doc.get_dictionary_mut(field_id)
+        .map_err(|e| format!("无法读取表单字段: {}", e))?;
+    dict.set("V", value);
+    Ok(())
+}
+
+/// 将表单控件的外观绘制到页面内容中，并删除控件注释和 /AcroForm
+/// 缺少外观或文档要求重新生成外观（NeedAppearances）的文本和选择字段会先生成外观；
+/// 表单字体和 font_data 都无法显示字段中的文字时返回错误，不会把无法显示的字符固定到页面中
+pub(crate) fn flatten_form_fields(doc: &mut Document, font_data: Option<&[u8]>) -> Result<(), String> {
+    let Some(form) = acroform(doc).cloned() else { return Ok(()) };
+    let need_appearances = form.get(b"NeedAppearances").and_then(Object::as_bool).unwrap_or(false);
+    let fields = read_fields(doc);
+
+    let mut builder = AppearanceBuilder::new(form, font_data, true)?;
+    for field in &fields {
+        if !matches!(field.kind, FieldKind::Text | FieldKind::Combo | FieldKind::List) {
+            continue;
+        }
+        let missing = field.widgets.iter().any(|&id| doc.get_dictionary(id).ok().and_then(|w| normal_appearance(doc, w)).is_none());
+        if need_appearances || missing {
+            let text = match (&field.value, field.kind) {
+                (Some(Object::String(text, _)), FieldKind::Text | FieldKind::Combo) => Some(decode_text_string(text)),
+                (_, FieldKind::List) => None,
+                _ => Some(String::new()),
+            };
+            builder.refresh(doc, field, text.as_deref())?;
+        }
+    }
+    builder.finish(doc)?;
+
+    let widgets: HashSet<ObjectId> = fields.iter().flat_map(|f| f.widgets.iter().copied()).collect();
+    let is_widget = |_: &Document, id: Option<ObjectId>, annot: &Dictionary| {
+        id.is_some_and(|id| widgets.contains(&id))
+            || annot.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget".as_slice())
+    };
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in page_ids {
+        process_page_annotations(doc, page_id, &is_widget, true)?;
+    }
+
+    let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    catalog.remove(b"AcroForm");
+    // 删除不再被引用的字段和控件对象
+    doc.prune_objects();
+    Ok(())
+}
+
+/// 从字段树中删除控件，控件全部被删除的字段一并删除（其值也随之删除）
+pub(crate) fn remove_form_widgets(doc: &mut Document, widgets: &HashSet<ObjectId>) -> Result<(), String> {
+    let Some(mut form) = acroform(doc).cloned() else { return Ok(()) };
+    let roots = form.get(b"Fields").ok()
+        .and_then(|f| resolve(doc, f).as_array().ok())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut visited = HashSet::new();
+    let kept: Vec<Object> = roots.into_iter()
+        .filter(|root| root.as_reference().map_or(true, |id| keep_field(doc, id, widgets, &mut visited, 0)))
+        .collect();
+    form.set("Fields", Object::Array(kept));
+    set_acroform(doc, form)
+}
+
+/// 删除 Kids 中被删除的控件，返回该节点是否保留
+fn keep_field(doc: &mut Document, id: ObjectId, widgets: &HashSet<ObjectId>, visited: &mut HashSet<ObjectId>, depth: usize) -> bool {
+    if widgets.contains(&id) {
+        return false;
+    }
+    if depth > MAX_DEPTH || !visited.insert(id) {
+        return true;
+    }
+    let kids = doc.get_dictionary(id).ok()
+        .and_then(|d| d.get(b"Kids").ok())
+        .and_then(|k| resolve(doc, k).as_array().ok())
+        .cloned()
+        .unwrap_or_default();
+    if kids.is_empty() {
+        return true;
+    }
+
+    let kept: Vec<Object> = kids.into_iter()
+        .filter(|kid| kid.as_reference().map_or(true, |kid| keep_field(doc, kid, widgets, visited, depth + 1)))
+        .collect();
+    if kept.is_empty() {
+        return false;
+    }
+    if let Ok(dict) = doc.get_dictionary_mut(id) {
+        dict.set("Kids", Object::Array(kept));
+    }
+    true
+}
+
+/// 解析后的默认外观字符串
+struct DefaultAppearance {
+    font: Vec<u8>,
+    /// 0 表示自动字号
+    size: f64,
+    /// 设置文字颜色的操作，如 "0 g"
+    color: String,
+}
+
+impl DefaultAppearance {
+    fn parse(da: &[u8]) -> DefaultAppearance {
+        let mut appearance = DefaultAppearance { font: b"Helv".to_vec(), size: 0.0, color: "0 g".to_string() };
+        for operation in parse_content(da) {
+            match operation.operator.as_str() {
+                "Tf" => {
+                    if let Some(font) = operation.operands.first().and_then(|f| f.as_name().ok()) {
+                        appearance.font = font.to_vec();
+                    }
+                    appearance.size = operation.operands.get(1).and_then(object_to_f64).unwrap_or(0.0).max(0.0);
+                }
+                "g" | "rg" | "k" => {
+                    let values: Vec<String> = operation.operands.iter().filter_map(object_to_f64).map(|v| v.to_string()).collect();
+                    appearance.color = format!("{} {}", values.join(" "), operation.operator);
+                }
+                _ => {}
+            }
+        }
+        appearance
+    }
+}
+
+/// 外观流中使用的字体
+struct AppearanceFont {
+    name: Vec<u8>,
+    /// 写入外观流资源的字体（引用或字典）
+    object: Object,
+    metrics: FontMetrics,
+    encoding: FontEncoding,
+}
+
+/// 外观流中文字的编码方式
+enum FontEncoding {
+    /// 简单字体，按 WinAnsiEncoding 编码
+    WinAnsi,
+    /// /DR 中使用 Identity-H 的复合字体，按 ToUnicode 反查双字节编码
+    Cid(HashMap<char, u16>),
+    /// 调用方提供的字体，所有外观生成后写入子集
+    Embedded(Rc<RefCell<EmbeddedFont>>),
+}
+
+impl AppearanceFont {
+    /// /DR 中的字体：简单字体按 WinAnsiEncoding 使用，复合字体需为 Identity-H 且带有 ToUnicode
+    fn from_resource(doc: &Document, name: &[u8], object: Object) -> Option<AppearanceFont> {
+        let font = resolve(doc, &object).as_dict().ok()?;
+        let metrics = FontMetrics::from_dict(doc, font);
+        let encoding = if font.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type0".as_slice()) {
+            if font.get(b"Encoding").ok().map(|e| resolve(doc, e)).and_then(|e| e.as_name().ok()) != Some(b"Identity-H".as_slice()) {
+                return None;
+            }
+            let to_unicode = font.get(b"ToUnicode").ok().and_then(|t| resolve(doc, t).as_stream().ok())?;
+            let mut codes = HashMap::new();
+            for (code, text) in parse_to_unicode(&decode_stream(doc, to_unicode).ok()?.data) {
+                let mut chars = text.chars();
+                if let (Some(c), None, Ok(code)) = (chars.next(), chars.next(), u16::try_from(code)) {
+                    codes.entry(c).or_insert(code);
+                }
+            }
+            FontEncoding::Cid(codes)
+        } else {
+            FontEncoding::WinAnsi
+        };
+        Some(AppearanceFont { name: name.to_vec(), object, metrics, encoding })
+    }
+
+    /// 能否显示文字（换行符除外）
+    fn can_encode(&self, text: &str) -> bool {
+        let mut chars = text.chars().filter(|&c| c != '\n' && c != '\r');
+        match &self.encoding {
+            FontEncoding::WinAnsi => chars.all(|c| win_ansi_code(c).is_some()),
+            FontEncoding::Cid(codes) => chars.all(|c| codes.contains_key(&c)),
+            FontEncoding::Embedded(font) => font.borrow().covers(&chars.collect::<String>()),
+        }
+    }
+
+    fn encode(&self, text: &str) -> Vec<u8> {
+        match &self.encoding {
+            FontEncoding::WinAnsi => encode_win_ansi(text),
+            FontEncoding::Cid(codes) => text.chars().flat_map(|c| codes.get(&c).copied().unwrap_or(0).to_be_bytes()).collect(),
+            FontEncoding::Embedded(font) => font.borrow_mut().encode(text),
+        }
+    }
+
+    fn width(&self, text: &str, size: f64) -> f64 {
+        match &self.encoding {
+            FontEncoding::Embedded(font) => font.borrow().text_width(text, size),
+            _ => self.metrics.codes(&self.encode(text)).into_iter().map(|code| self.metrics.width(code)).sum::<f64>() * size / 1000.0,
+        }
+    }
+
+    /// 文字在内容流中的字符串操作数
+    fn string(&self, text: &str) -> String {
+        match &self.encoding {
+            FontEncoding::WinAnsi => literal_string(&encode_win_ansi(text)),
+            _ => hex_string(&self.encode(text)),
+        }
+    }
+}
+
+/// 为控件生成外观流，累积对 /AcroForm 的修改（/DR 中的字体、NeedAppearances）
+struct AppearanceBuilder {
+    form: Dictionary,
+    changed: bool,
+    /// 调用方提供的字体及其对象 ID（用到时才分配）
+    embedded: Option<(Rc<RefCell<EmbeddedFont>>, Option<ObjectId>)>,
+    /// 文字无法显示时返回错误，而不是交给阅读器重新生成外观
+    strict: bool,
+}
+
+/// 控件在外观流坐标系中的尺寸和装饰
+struct WidgetFrame {
+    width: f64,
+    height: f64,
+    matrix: Option<[f64; 6]>,
+    /// 背景和边框
+    decoration: String,
+    border: f64,
+}
+
+impl WidgetFrame {
+    fn new(doc: &Document, widget: &Dictionary) -> Option<WidgetFrame> {
+        let rect = widget.get(b"Rect").ok().and_then(|r| resolve(doc, r).as_array().ok()).and_then(|r| parse_rect(r))?;
+        let (rect_width, rect_height) = ((rect[2] - rect[0]).abs(), (rect[3] - rect[1]).abs());
+        let characteristics = get_dict(doc, widget, b"MK");
+        let rotation = characteristics.and_then(|mk| get_i64(doc, mk, b"R")).unwrap_or(0).rem_euclid(360);
+
+        // 控件旋转时在旋转后的坐标系中排版，由 /Matrix 转回页面方向
+        let (width, height, matrix) = match rotation {
+            90 => (rect_height, rect_width, Some([0.0, 1.0, -1.0, 0.0, rect_width, 0.0])),
+            180 => (rect_width, rect_height, Some([-1.0, 0.0, 0.0, -1.0, rect_width, rect_height])),
+            270 => (rect_height, rect_width, Some([0.0, -1.0, 1.0, 0.0, 0.0, rect_height])),
+            _ => (rect_width, rect_height, None),
+        };
+
+        let color = |key: &[u8], stroke: bool| {
+            characteristics
+                .and_then(|mk| mk.get(key).ok())
+                .and_then(|c| resolve(doc, c).as_array().ok())
+                .and_then(|c| color_operator(c, stroke))
+        };
+        let border_width = get_dict(doc, widget, b"BS")
+            .and_then(|bs| bs.get(b"W").ok().and_then(object_to_f64))
+            .unwrap_or(1.0);
+
+        let mut decoration = String::new();
+        if let Some(background) = color(b"BG", false) {
+            decoration.push_str(&format!("{}\n0 0 {} {} re f\n", background, width, height));
+        }
+        let mut border = 0.0;
+        if let Some(stroke) = color(b"BC", true).filter(|_| border_width > 0.0) {
+            border = border_width;
+            decoration.push_str(&format!(
+                "{}\n{} w\n{} {} {} {} re S\n",
+                stroke, border, border / 2.0, border / 2.0, width - border, height - border
+            ));
+        }
+
+        Some(WidgetFrame { width, height, matrix, decoration, border })
+    }
+
+    /// 文字与控件边缘的距离
+    fn padding(&self) -> f64 {
+        self.border.max(1.0) + 1.0
+    }
+}
+
+/// 颜色数组（1 个分量为灰度，3 个为 RGB，4 个为 CMYK）对应的填充或描边操作，空数组表示透明
+fn color_operator(values: &[Object], stroke: bool) -> Option<String> {
+    let components: Vec<String> = values.iter().filter_map(object_to_f64).map(|v| v.to_string()).collect();
+    let operator = match (components.len(), stroke) {
+        (1, false) => "g",
+        (1, true) => "G",
+        (3, false) => "rg",
+        (3, true) => "RG",
+        (4, false) => "k",
+        (4, true) => "K",
+        _ => return None,
+    };
+    Some(format!("{} {}", components.join(" "), operator))
+}
+
+impl AppearanceBuilder {
+    fn new(form: Dictionary, font_data: Option<&[u8]>, strict: bool) -> Result<AppearanceBuilder, String> {
+        let embedded = font_data.map(EmbeddedFont::parse).transpose()?.map(|font| (Rc::new(RefCell::new(font)), None));
+        Ok(AppearanceBuilder { form, changed: false, embedded, strict })
+    }
+
+    /// 选择能显示所有文字的字体：依次为默认外观中的 /DR 字体、/DR 中的 Helv、调用方提供的字体；
+    /// 都不能显示时仍使用 /DR 字体，/DR 中没有可用字体时改用 Helvetica（按需加入 /DR）
+    fn font(&mut self, doc: &mut Document, name: &[u8], texts: &[&str]) -> AppearanceFont {
+        let resources = get_dict(doc, &self.form, b"DR").cloned().unwrap_or_default();
+        let mut fonts = get_dict(doc, &resources, b"Font").cloned().unwrap_or_default();
+
+        let mut fallback = None;
+        for candidate in [name, b"Helv".as_slice()] {
+            let Some(font) = fonts.get(candidate).ok().cloned().and_then(|object| AppearanceFont::from_resource(doc, candidate, object)) else { continue };
+            if texts.iter().all(|text| font.can_encode(text)) {
+                return font;
+            }
+            fallback.get_or_insert(font);
+        }
+        if let Some((embedded, id)) = &mut self.embedded {
+            if texts.iter().all(|text| embedded.borrow().covers(&text.replace(['\n', '\r'], ""))) {
+                let id = *id.get_or_insert_with(|| doc.new_object_id());
+                return AppearanceFont {
+                    name: EMBEDDED_FONT_NAME.to_vec(),
+                    object: Object::Reference(id),
+                    metrics: FontMetrics::default_metrics(),
+                    encoding: FontEncoding::Embedded(embedded.clone()),
+                };
+            }
+        }
+        if let Some(font) = fallback {
+            return font;
+        }
+
+        let font = font_dictionary("Helvetica");
+        let metrics = FontMetrics::from_dict(doc, &font);
+        let object = Object::Reference(doc.add_object(font));
+        fonts.set("Helv", object.clone());
+        let mut resources = resources;
+        resources.set("Font", Object::Dictionary(fonts));
+        self.form.set("DR", Object::Dictionary(resources));
+        self.changed = true;
+        AppearanceFont { name: b"Helv".to_vec(), object, metrics, encoding: FontEncoding::WinAnsi }
+    }
+
+    /// 重新生成文本或选择字段所有控件的外观；text 为显示的文字，列表框按字段的当前值绘制
+    fn refresh(&mut self, doc: &mut Document, field: &Field, text: Option<&str>) -> Result<(), String> {
+        let display = match field.kind {
+            FieldKind::Combo => text.map(|text| {
+                field.options.iter()
+                    .find(|(export, _)| export == text)
+                    .map_or_else(|| text.to_string(), |(_, display)| display.clone())
+            }),
+            _ => text.map(str::to_string),
+        };
+        let texts: Vec<&str> = match (field.kind, &display) {
+            (FieldKind::List, _) => field.options.iter().map(|(_, display)| display.as_str()).collect(),
+            (_, Some(_)) if field.flags & FLAG_PASSWORD != 0 => vec!["*"],
+            (_, Some(display)) => vec![display.as_str()],
+            (_, None) => Vec::new(),
+        };
+
+        let da = DefaultAppearance::parse(&field.default_appearance);
+        let font = self.font(doc, &da.font, &texts);
+        if !texts.iter().all(|text| font.can_encode(text)) {
+            if self.strict {
+                return Err(format!("字段「{}」包含表单字体无法显示的文字，请提供包含这些字符的字体", field.name));
+            }
+            // 交给阅读器重新生成外观
+            self.form.set("NeedAppearances", Object::Boolean(true));
+            self.changed = true;
+        }
+
+        for &widget_id in &field.widgets {
+            let Some(frame) = doc.get_dictionary(widget_id).ok().and_then(|w| WidgetFrame::new(doc, w)) else { continue };
+            let body = match (field.kind, &display) {
+                (FieldKind::List, _) => self.list_content(doc, field, &frame, &font, &da),
+                (_, Some(display)) => text_content(field, &frame, &font, &da, display),
+                (_, None) => String::new(),
+            };
+            let content = format!("{}/Tx BMC\n{}EMC\n", frame.decoration, body);
+
+            let mut font_resources = Dictionary::new();
+            font_resources.set(font.name.clone(), font.object.clone());
+            let mut resources = Dictionary::new();
+            resources.set("Font", Object::Dictionary(font_resources));
+            let appearance_id = add_appearance_stream(doc, &frame, resources, content);
+
+            let widget = doc.get_dictionary_mut(widget_id)
+                .map_err(|e| format!("无法读取表单控件: {}", e))?;
+            let mut appearances = Dictionary::new();
+            appearances.set("N", Object::Reference(appearance_id));
+            widget.set("AP", Object::Dictionary(appearances));
+        }
+        Ok(())
+    }
+
+    /// 列表框：逐行绘制选项，选中项加背景色
+    fn list_content(&self, doc: &Document, field: &Field, frame: &WidgetFrame, font: &AppearanceFont, da: &DefaultAppearance) -> String {
+        // 填写后字段自身的 /V 优先于读取时继承的值
+        let value = doc.get_dictionary(field.id).ok()
+            .and_then(|d| d.get(b"V").ok())
+            .map(|v| resolve(doc, v))
+            .or(field.value.as_ref());
+        let selected: Vec<String> = match value {
+            Some(Object::String(text, _)) => vec![decode_text_string(text)],
+            Some(Object::Array(items)) => items.iter().filter_map(|i| resolve(doc, i).as_str().ok()).map(decode_text_string).collect(),
+            _ => Vec::new(),
+        };
+        let size = if da.size > 0.0 { da.size } else { DEFAULT_FONT_SIZE };
+        let leading = size * 1.15;
+        let padding = frame.padding();
+
+        let mut content = format!("q\n{} {} {} {} re W n\n", frame.border, frame.border, frame.width - 2.0 * frame.border, frame.height - 2.0 * frame.border);
+        let mut text = format!("BT\n/{} {} Tf\n{}\n", String::from_utf8_lossy(&font.name), size, da.color);
+        for (row, (export, display)) in field.options.iter().enumerate() {
+            let top = frame.height - padding - row as f64 * leading;
+            if top - leading < 0.0 {
+                break;
+            }
+            if selected.contains(export) {
+                content.push_str(&format!("{}\n{} {} {} {} re f\n", SELECTION_COLOR, frame.border, top - leading, frame.width - 2.0 * frame.border, leading));
+            }
+            let baseline = top - leading + (leading - size) / 2.0 + size * 0.22;
+            text.push_str(&format!("1 0 0 1 {} {} Tm\n{} Tj\n", padding, baseline, font.string(display)));
+        }
+        content.push_str(&text);
+        content.push_str("ET\nQ\n");
+        content
+    }
+
+    /// 复选框缺少外观时生成 Yes/Off 两种状态的外观（ZapfDingbats 对勾）
+    fn ensure_checkbox_appearances(&mut self, doc: &mut Document, field: &Field) -> Result<(), String> {
+        for &widget_id in &field.widgets {
+            if !on_states(doc, widget_id).is_empty() {
+                continue;
+            }
+            let Some(frame) = doc.get_dictionary(widget_id).ok().and_then(|w| WidgetFrame::new(doc, w)) else { continue };
+            let da = DefaultAppearance::parse(&field.default_appearance);
+            // 对勾字符，可由 /MK /CA 指定
+            let symbol = doc.get_dictionary(widget_id).ok()
+                .and_then(|w| get_dict(doc, w, b"MK"))
+                .and_then(|mk| mk.get(b"CA").and_then(Object::as_str).ok())
+                .and_then(|ca| ca.first().copied())
+                .unwrap_or(b'4');
+
+            let size = if da.size > 0.0 { da.size } else { (frame.width.min(frame.height) - 2.0 * frame.padding()).max(MIN_FONT_SIZE) };
+            // ZapfDingbats 中对勾（'4'）宽 0.846 字号，其余符号按 0.8 估算
+            let symbol_width = if symbol == b'4' { 0.846 } else { 0.8 } * size;
+            let on = format!(
+                "{}q\nBT\n/ZaDb {} Tf\n{}\n1 0 0 1 {} {} Tm\n{} Tj\nET\nQ\n",
+                frame.decoration, size, da.color,
+                (frame.width - symbol_width) / 2.0, (frame.height - size * 0.7) / 2.0,
+                literal_string(&[symbol])
+            );
+
+            let mut zapf = Dictionary::new();
+            zapf.set("Type", Object::Name(b"Font".to_vec()));
+            zapf.set("Subtype", Object::Name(b"Type1".to_vec()));
+            zapf.set("BaseFont", Object::Name(b"ZapfDingbats".to_vec()));
+            let mut fonts = Dictionary::new();
+            fonts.set("ZaDb", Object::Reference(doc.add_object(zapf)));
+            let mut resources = Dictionary::new();
+            resources.set("Font", Object::Dictionary(fonts));
+
+            let on_id = add_appearance_stream(doc, &frame, resources, on);
+            let off_id = add_appearance_stream(doc, &frame, Dictionary::new(), frame.decoration.clone());
+            let mut states = Dictionary::new();
+            states.set("Yes", Object::Reference(on_id));
+            states.set("Off", Object::Reference(off_id));
+            let mut appearances = Dictionary::new();
+            appearances.set("N", Object::Dictionary(states));
+
+            let widget = doc.get_dictionary_mut(widget_id)
+                .map_err(|e| format!("无法读取表单控件: {}", e))?;
+            widget.set("AP", Object::Dictionary(appearances));
+        }
+        Ok(())
+    }
+
+    /// 写入用到的嵌入字体和修改过的 /AcroForm
+    fn finish(self, doc: &mut Document) -> Result<(), String> {
+        if let Some((font, Some(id))) = &self.embedded {
+            font.borrow().write(doc, *id)?;
+        }
+        if self.changed {
+            set_acroform(doc, self.form)?;
+        }
+        Ok(())
+    }
+}
+
+/// 单行或多行文本（下拉框与单行文本相同）
+fn text_content(field: &Field, frame: &WidgetFrame, font: &AppearanceFont, da: &DefaultAppearance, text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let text = if field.flags & FLAG_PASSWORD != 0 { "*".repeat(text.chars().count()) } else { text.to_string() };
+    let padding = frame.padding();
+    let available = frame.width - 2.0 * padding;
+    let multiline = field.kind == FieldKind::Text && field.flags & FLAG_MULTILINE != 0;
+
+    let mut lines: Vec<(f64, f64, String)> = Vec::new();
+    let size;
+    if multiline {
+        let leading_factor = 1.15;
+        let mut candidate = if da.size > 0.0 { da.size } else { DEFAULT_FONT_SIZE };
+        let wrapped = loop {
+            let wrapped = wrap_text(&text, available, |t| font.width(t, candidate));
+            let fits = wrapped.len() as f64 * candidate * leading_factor <= frame.height - 2.0 * padding;
+            // 自动字号时缩小字号直到所有行都能放下
+            if fits || da.size > 0.0 || candidate <= MIN_FONT_SIZE {
+                break wrapped;
+            }
+            candidate = (candidate - 0.5).max(MIN_FONT_SIZE);
+        };
+        size = candidate;
+        let leading = size * leading_factor;
+        for (row, line) in wrapped.into_iter().enumerate() {
+            let baseline = frame.height - padding - size * 0.78 - row as f64 * leading;
+            lines.push((align(field.quadding, padding, available, font.width(&line, size)), baseline, line));
+        }
+    } else {
+        let text = text.replace(['\r', '\n'], " ");
+        size = if da.size > 0.0 {
+            da.size
+        } else {
+            // 自动字号：按控件高度确定，再缩小到能放下整行文字
+            let by_height = (frame.height - 2.0 * padding) / 1.15;
+            let width = font.width(&text, by_height);
+            let by_width = if width > available && width > 0.0 { by_height * available / width } else { by_height };
+            by_width.max(MIN_FONT_SIZE)
+        };
+        let baseline = (frame.height - size) / 2.0 + size * 0.22;
+
+        match field.max_len.filter(|&n| n > 0 && field.flags & FLAG_COMB != 0) {
+            // 等分格子，每格居中一个字符
+            Some(cells) => {
+                let cell = frame.width / cells as f64;
+                for (i, c) in text.chars().take(cells).enumerate() {
+                    let c = c.to_string();
+                    lines.push((i as f64 * cell + (cell - font.width(&c, size)) / 2.0, baseline, c));
+                }
+            }
+            None => lines.push((align(field.quadding, padding, available, font.width(&text, size)), baseline, text)),
+        }
+    }
+
+    let mut content = format!(
+        "q\n{} {} {} {} re W n\nBT\n/{} {} Tf\n{}\n",
+        frame.border, frame.border, frame.width - 2.0 * frame.border, frame.height - 2.0 * frame.border,
+        String::from_utf8_lossy(&font.name), size, da.color
+    );
+    for (x, y, line) in lines {
+        content.push_str(&format!("1 0 0 1 {} {} Tm\n{} Tj\n", x, y, font.string(&line)));
+    }
+    content.push_str("ET\nQ\n");
+    content
+}
+
+/// 按对齐方式计算行首位置
+fn align(quadding: i64, padding: f64, available: f64, width: f64) -> f64 {
+    match quadding {
+        1 => padding + (available - width) / 2.0,
+        2 => padding + available - width,
+        _ => padding,
+    }
+}
+
+fn add_appearance_stream(doc: &mut Document, frame: &WidgetFrame, resources: Dictionary, content: String) -> ObjectId {
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Form".to_vec()));
+    dict.set("BBox", vec![0.into(), 0.into(), Object::Real(frame.width as f32), Object::Real(frame.height as f32)]);
+    if let Some(matrix) = frame.matrix {
+        dict.set("Matrix", matrix.iter().map(|&v| Object::Real(v as f32)).collect::<Vec<_>>());
+    }
+    dict.set("Resources", Object::Dictionary(resources));
+    doc.add_object(Stream::new(dict, content.into_bytes()))
+}
+
+/// 合并时累积各文件的表单
+#[derive(Default)]
+pub(crate) struct FormMerger {
+    fields: Vec<Object>,
+    fonts: Dictionary,
+    default_appearance: Option<Object>,
+    need_appearances: bool,
+    sig_flags: i64,
+    names: HashSet<String>,
+}
+
+impl FormMerger {
+    /// 复制源文档的表单字段（与页面共用 id_map，控件注释不会重复复制）
+    /// 与已有字段同名的顶层字段改名为 "名称_2"、"名称_3"……
+    pub(crate) fn add(&mut self, src_doc: &Document, dst_doc: &mut Document, id_map: &mut HashMap<ObjectId, ObjectId>) {
+        let Some(form) = acroform(src_doc) else { return };
+
+        let roots = form.get(b"Fields").ok().and_then(|f| resolve(src_doc, f).as_array().ok());
+        for root in roots.into_iter().flatten() {
+            let copied = deep_copy_object_with_map(src_doc, dst_doc, root, id_map);
+            let Ok(copied_id) = copied.as_reference() else { continue };
+            let Ok(field) = dst_doc.get_dictionary_mut(copied_id) else { continue };
+
+            if let Ok(name) = field.get(b"T").and_then(Object::as_str) {
+                let name = decode_text_string(name);
+                let unique = if self.names.contains(&name) {
+                    (2..).map(|n| format!("{}_{}", name, n)).find(|n| !self.names.contains(n)).unwrap_or_default()
+                } else {
+                    name.clone()
+                };
+                if unique != name {
+                    field.set("T", text_string(&unique));
+                }
+                self.names.insert(unique);
+            }
+            self.fields.push(copied);
+        }
+
+        // 同名字体保留先出现的
+        if let Some(fonts) = get_dict(src_doc, form, b"DR").and_then(|dr| get_dict(src_doc, dr, b"Font")) {
+            for (name, font) in fonts.iter() {
+                if !self.fonts.has(name) {
+                    let copied = deep_copy_object_with_map(src_doc, dst_doc, font, id_map);
+                    self.fonts.set(name.clone(), copied);
+                }
+            }
+        }
+        if self.default_appearance.is_none() {
+            self.default_appearance = form.get(b"DA").ok().cloned();
+        }
+        self.need_appearances |= form.get(b"NeedAppearances").and_then(Object::as_bool).unwrap_or(false);
+        self.sig_flags |= get_i64(src_doc, form, b"SigFlags").unwrap_or(0);
+    }
+
+    /// 将合并后的表单写入目录，没有任何字段时不写入
+    pub(crate) fn write(self, doc: &mut Document, catalog_id: ObjectId) -> Result<(), String> {
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+        let mut form = Dictionary::new();
+        form.set("Fields", Object::Array(self.fields));
+        if !self.fonts.is_empty() {
+            let mut resources = Dictionary::new();
+            resources.set("Font", Object::Dictionary(self.fonts));
+            form.set("DR", Object::Dictionary(resources));
+        }
+        if let Some(da) = self.default_appearance {
+            form.set("DA", da);
+        }
+        if self.need_appearances {
+            form.set("NeedAppearances", Object::Boolean(true));
+        }
+        if self.sig_flags != 0 {
+            form.set("SigFlags", Object::Integer(self.sig_flags));
+        }
+
+        let catalog = doc.get_dictionary_mut(catalog_id)
+            .map_err(|e| format!("无法获取文档目录: {}", e))?;
+        catalog.set("AcroForm", Object::Dictionary(form));
+        Ok(())
+    }
+}
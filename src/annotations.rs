@@ -0,0 +1,662 @@
+// 注释：列出、添加、删除和扁平化页面注释
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::content_stream::Matrix;
+use crate::font_embed::{hex_string, EmbeddedFont};
+use crate::outline::{decode_text_string, text_string};
+use crate::page_size::parse_color;
+use crate::pdf_utils::{add_xobject_to_page, object_to_f64, parse_page_selection, parse_rect, rect_to_object, wrap_page_contents};
+use crate::render_color::resolve;
+use crate::standard_font::{encode_win_ansi, font_dictionary, helvetica_width, literal_string, win_ansi_code, wrap_text};
+
+fn subtype(annot: &Dictionary) -> &[u8] {
+    annot.get(b"Subtype").and_then(Object::as_name).unwrap_or(b"")
+}
+
+/// 页面的 /Annots 数组（可能是间接对象）
+fn page_annotations(doc: &Document, page_id: ObjectId) -> Vec<Object> {
+    doc.get_dictionary(page_id).ok()
+        .and_then(|p| p.get(b"Annots").ok())
+        .and_then(|a| resolve(doc, a).as_array().ok())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// 注释的 JSON 表示，页码从 1 开始
+#[derive(Debug, Serialize)]
+pub(crate) struct AnnotationInfo {
+    page: usize,
+    /// 在该页 /Annots 中的序号（从 0 开始）
+    index: usize,
+    /// 子类型，如 "Text"、"Highlight"、"Link"、"Widget"
+    subtype: String,
+    /// [left, bottom, right, top]
+    rect: Option<[f64; 4]>,
+    /// 注释内容（/Contents）
+    contents: Option<String>,
+    /// 作者（/T）
+    author: Option<String>,
+    /// 修改时间（/M），原样返回 PDF 日期字符串，如 "D:20240101120000+08'00'"
+    modified: Option<String>,
+    /// 颜色，如 "#ffff00"
+    color: Option<String>,
+}
+
+/// 列出文档中所有页面的注释
+pub(crate) fn list_page_annotations(doc: &Document) -> Vec<AnnotationInfo> {
+    let mut infos = Vec::new();
+    for (number, page_id) in doc.get_pages() {
+        for (index, annot) in page_annotations(doc, page_id).iter().enumerate() {
+            let Ok(annot) = resolve(doc, annot).as_dict() else { continue };
+            let text = |key: &[u8]| annot.get(key).ok().and_then(|v| resolve(doc, v).as_str().ok()).map(decode_text_string);
+            infos.push(AnnotationInfo {
+                page: number as usize,
+                index,
+                subtype: String::from_utf8_lossy(subtype(annot)).into_owned(),
+                rect: annot.get(b"Rect").ok().and_then(|r| resolve(doc, r).as_array().ok()).and_then(|r| parse_rect(r)),
+                contents: text(b"Contents"),
+                author: text(b"T"),
+                modified: text(b"M"),
+                color: annot.get(b"C").ok().and_then(|c| resolve(doc, c).as_array().ok()).and_then(|c| color_to_hex(c)),
+            });
+        }
+    }
+    infos
+}
+
+/// 注释颜色数组转换为 "#rrggbb"，灰度和 CMYK 按近似 RGB 换算
+fn color_to_hex(values: &[Object]) -> Option<String> {
+    let v: Vec<f64> = values.iter().filter_map(object_to_f64).map(|v| v.clamp(0.0, 1.0)).collect();
+    let (r, g, b) = match v.as_slice() {
+        [gray] => (*gray, *gray, *gray),
+        [r, g, b] => (*r, *g, *b),
+        [c, m, y, k] => ((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)),
+        _ => return None,
+    };
+    let byte = |v: f64| (v * 255.0).round() as u8;
+    Some(format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b)))
+}
+
+/// 删除注释的筛选条件（以 JSON 字符串传入），各条件同时满足的注释才会被删除
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct AnnotationFilter {
+    /// 子类型，如 ["Highlight", "Text"]；为空表示全部类型。表单控件（Widget）不会被删除
+    subtypes: Vec<String>,
+    /// 页码范围，如 "1-3,5"；为空表示全部页面
+    pages: String,
+    /// 作者（/T）；为空表示不限
+    authors: Vec<String>,
+}
+
+impl AnnotationFilter {
+    fn matches(&self, doc: &Document, annot: &Dictionary) -> bool {
+        let subtype = subtype(annot);
+        if subtype == b"Widget" {
+            return false;
+        }
+        if !self.subtypes.is_empty() && !self.subtypes.iter().any(|s| s.as_bytes() == subtype) {
+            return false;
+        }
+        if !self.authors.is_empty() {
+            let author = annot.get(b"T").ok().and_then(|t| resolve(doc, t).as_str().ok()).map(decode_text_string);
+            if !author.is_some_and(|author| self.authors.contains(&author)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 删除符合筛选条件的注释
+pub(crate) fn remove_matching_annotations(doc: &mut Document, filter: &AnnotationFilter) -> Result<(), String> {
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let selected = parse_page_selection(&filter.pages, page_ids.len())?;
+
+    for index in selected {
+        process_page_annotations(doc, page_ids[index], &|doc, _, annot| filter.matches(doc, annot), false)?;
+    }
+    Ok(())
+}
+
+/// 将除表单控件、链接和弹出窗口外的注释外观绘制到页面内容中，并删除这些注释
+/// 没有外观流的注释无法绘制，保持不变
+pub(crate) fn flatten_all_annotations(doc: &mut Document) -> Result<(), String> {
+    let select = |doc: &Document, _: Option<ObjectId>, annot: &Dictionary| {
+        !matches!(subtype(annot), b"Widget" | b"Link" | b"Popup")
+            && (is_hidden(doc, annot) || normal_appearance(doc, annot).is_some())
+    };
+
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in page_ids {
+        process_page_annotations(doc, page_id, &select, true)?;
+    }
+    Ok(())
+}
+
+/// 删除页面上被选中的注释，draw 为 true 时先将其正常外观绘制到页面内容中（隐藏的注释不绘制）
+/// 被删除注释的弹出窗口（Popup）一并删除
+pub(crate) fn process_page_annotations(
+    doc: &mut Document,
+    page_id: ObjectId,
+    select: &dyn Fn(&Document, Option<ObjectId>, &Dictionary) -> bool,
+    draw: bool,
+) -> Result<(), String> {
+    let annots = page_annotations(doc, page_id);
+    if annots.is_empty() {
+        return Ok(());
+    }
+
+    let mut removed = vec![false; annots.len()];
+    let mut removed_ids = HashSet::new();
+    let mut content = String::new();
+    for (index, annot) in annots.iter().enumerate() {
+        let id = annot.as_reference().ok();
+        let Some(dict) = resolve(doc, annot).as_dict().ok().cloned() else { continue };
+        if !select(doc, id, &dict) {
+            continue;
+        }
+        removed[index] = true;
+        removed_ids.extend(id);
+
+        if !draw || is_hidden(doc, &dict) {
+            continue;
+        }
+        let Some((xobject_id, placement)) = appearance_placement(doc, &dict) else { continue };
+        let name = add_xobject_to_page(doc, page_id, "Fm", xobject_id)?;
+        let [a, b, c, d, e, f] = placement.0;
+        content.push_str(&format!("q\n{} {} {} {} {} {} cm\n/{} Do\nQ\n", a, b, c, d, e, f, name));
+    }
+
+    // 父注释被删除的弹出窗口一并删除，保留的注释去掉指向已删除弹出窗口的 /Popup
+    for (index, annot) in annots.iter().enumerate() {
+        let Ok(dict) = resolve(doc, annot).as_dict() else { continue };
+        let parent = dict.get(b"Parent").and_then(Object::as_reference).ok();
+        if subtype(dict) == b"Popup" && parent.is_some_and(|p| removed_ids.contains(&p)) {
+            removed[index] = true;
+            removed_ids.extend(annot.as_reference().ok());
+        }
+    }
+    for (index, annot) in annots.iter().enumerate() {
+        let Ok(id) = annot.as_reference() else { continue };
+        if removed[index] {
+            continue;
+        }
+        if let Ok(dict) = doc.get_dictionary_mut(id) {
+            if dict.get(b"Popup").and_then(Object::as_reference).is_ok_and(|p| removed_ids.contains(&p)) {
+                dict.remove(b"Popup");
+            }
+        }
+    }
+
+    let kept: Vec<Object> = annots.iter()
+        .zip(&removed)
+        .filter(|(_, removed)| !**removed)
+        .map(|(annot, _)| annot.clone())
+        .collect();
+    if kept.len() != annots.len() {
+        let page = doc.get_dictionary_mut(page_id)
+            .map_err(|e| format!("无法读取页面: {}", e))?;
+        if kept.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", Object::Array(kept));
+        }
+    }
+    if !content.is_empty() {
+        wrap_page_contents(doc, page_id, content.into_bytes(), false)?;
+    }
+
+    Ok(())
+}
+
+/// Hidden（位 2）和 NoView（位 6）
+fn is_hidden(doc: &Document, annot: &Dictionary) -> bool {
+    let flags = annot.get(b"F").ok().and_then(|f| resolve(doc, f).as_i64().ok()).unwrap_or(0);
+    flags & (2 | 32) != 0
+}
+
+/// 注释当前的正常外观流（/AP /N，有多个状态时按 /AS 选择）
+pub(crate) fn normal_appearance(doc: &Document, annot: &Dictionary) -> Option<ObjectId> {
+    let appearances = resolve(doc, annot.get(b"AP").ok()?).as_dict().ok()?;
+    let normal = appearances.get(b"N").ok()?;
+    let appearance = match normal.as_reference() {
+        Ok(id) if doc.get_object(id).ok()?.as_stream().is_ok() => return Some(id),
+        _ => resolve(doc, normal).as_dict().ok()?.get(annot.get(b"AS").and_then(Object::as_name).ok()?).ok()?,
+    };
+    let id = appearance.as_reference().ok()?;
+    doc.get_object(id).ok()?.as_stream().ok()?;
+    Some(id)
+}
+
+/// 注释的外观流及将其映射到注释矩形的变换
+fn appearance_placement(doc: &mut Document, annot: &Dictionary) -> Option<(ObjectId, Matrix)> {
+    let rect = annot.get(b"Rect").ok().and_then(|r| resolve(doc, r).as_array().ok()).and_then(|r| parse_rect(r))?;
+    let appearance_id = normal_appearance(doc, annot)?;
+
+    let stream = doc.get_object_mut(appearance_id).ok()?.as_stream_mut().ok()?;
+    let bbox = stream.dict.get(b"BBox").ok().and_then(|b| b.as_array().ok()).and_then(|b| parse_rect(b))?;
+    let matrix = stream.dict.get(b"Matrix").ok()
+        .and_then(|m| m.as_array().ok())
+        .and_then(|m| Matrix::from_objects(m))
+        .unwrap_or(Matrix::IDENTITY);
+    // 外观流可能省略 /Subtype，作为 XObject 使用时必须补上
+    stream.dict.set("Type", Object::Name(b"XObject".to_vec()));
+    stream.dict.set("Subtype", Object::Name(b"Form".to_vec()));
+
+    // 外观流变换后的边框映射到注释矩形
+    let bounds = matrix.apply_rect(&bbox);
+    let (bw, bh) = (bounds[2] - bounds[0], bounds[3] - bounds[1]);
+    if bw <= 0.0 || bh <= 0.0 {
+        return None;
+    }
+    let (sx, sy) = ((rect[2] - rect[0]) / bw, (rect[3] - rect[1]) / bh);
+    Some((appearance_id, Matrix([sx, 0.0, 0.0, sy, rect[0] - bounds[0] * sx, rect[1] - bounds[1] * sy])))
+}
+
+/// 要添加的注释（以 JSON 数组传入），坐标为 PDF 页面坐标（点，原点在左下角）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct NewAnnotation {
+    /// "Highlight"、"Underline"、"StrikeOut"、"Text"、"FreeText"、"Square"、"Circle"、"Line"、"Ink" 或 "Link"
+    #[serde(rename = "type")]
+    kind: String,
+    /// 页码，从 1 开始
+    page: usize,
+    /// [left, bottom, right, top]
+    rect: Option<[f64; 4]>,
+    /// 高亮、下划线和删除线覆盖的文字区域，每个区域为 [left, bottom, right, top]
+    rects: Vec<[f64; 4]>,
+    /// 直接指定 QuadPoints（每 8 个数一组），设置后忽略 rects
+    quad_points: Vec<f64>,
+    /// 直线的起点和终点 [x1, y1, x2, y2]
+    line: Option<[f64; 4]>,
+    /// 手绘线条，每条为 [x1, y1, x2, y2, ...]
+    paths: Vec<Vec<f64>>,
+    contents: Option<String>,
+    author: Option<String>,
+    /// 线条、标记或图标颜色；FreeText 为文字颜色
+    color: Option<String>,
+    /// 矩形、椭圆和 FreeText 的填充色
+    fill: Option<String>,
+    /// 不透明度 0-1
+    opacity: Option<f64>,
+    /// 线宽（点）
+    width: Option<f64>,
+    /// 便签图标，如 "Note"、"Comment"、"Key"
+    icon: Option<String>,
+    /// 便签是否默认打开
+    open: bool,
+    /// FreeText 的字号，默认 12；没有提供字体时使用 Helvetica，只能显示 WinAnsi 字符
+    font_size: Option<f64>,
+    /// 链接打开的网址
+    uri: Option<String>,
+    /// 链接跳转的页码（从 1 开始）
+    dest_page: Option<usize>,
+}
+
+/// 注释字典和其外观流的内容与资源
+struct BuiltAnnotation {
+    dict: Dictionary,
+    appearance: Option<(String, Dictionary)>,
+}
+
+/// FreeText 外观使用的嵌入字体，所有注释共用一个字体对象
+struct AnnotationFont {
+    embedded: EmbeddedFont,
+    id: ObjectId,
+    used: bool,
+}
+
+/// 嵌入字体在外观流资源中的名称
+const EMBEDDED_FONT_NAME: &str = "EmbF";
+
+/// 添加注释并生成外观流，不读取注释字典的阅读器（以及扁平化）也能正确显示
+/// 提供 font_data 时 FreeText 的文字使用该字体（嵌入子集），否则使用 Helvetica
+pub(crate) fn add_new_annotations(doc: &mut Document, annotations: &[NewAnnotation], font_data: Option<&[u8]>) -> Result<(), String> {
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let mut font = match font_data {
+        Some(data) => Some(AnnotationFont { embedded: EmbeddedFont::parse(data)?, id: doc.new_object_id(), used: false }),
+        None => None,
+    };
+
+    for (index, spec) in annotations.iter().enumerate() {
+        let number = index + 1;
+        let page_id = spec.page.checked_sub(1)
+            .and_then(|i| page_ids.get(i).copied())
+            .ok_or_else(|| format!("第 {} 个注释的页码 {} 超出范围（共 {} 页）", number, spec.page, page_ids.len()))?;
+
+        let BuiltAnnotation { mut dict, appearance } = build_annotation(spec, number, &page_ids, &mut font)?;
+        dict.set("Type", Object::Name(b"Annot".to_vec()));
+        dict.set("P", Object::Reference(page_id));
+        // Print 标志
+        dict.set("F", Object::Integer(4));
+        if let Some(contents) = &spec.contents {
+            dict.set("Contents", text_string(contents));
+        }
+        if let Some(author) = &spec.author {
+            dict.set("T", text_string(author));
+        }
+
+        if let Some((content, mut resources)) = appearance {
+            let rect = dict.get(b"Rect").ok().and_then(|r| r.as_array().ok()).and_then(|r| parse_rect(r)).unwrap_or_default();
+            let mut content = content;
+            if let Some(opacity) = spec.opacity {
+                dict.set("CA", Object::Real(opacity.clamp(0.0, 1.0) as f32));
+                let mut states = resources.get(b"ExtGState").and_then(Object::as_dict).cloned().unwrap_or_default();
+                let mut state = states.get(b"GS0").and_then(Object::as_dict).cloned().unwrap_or_default();
+                state.set("CA", Object::Real(opacity.clamp(0.0, 1.0) as f32));
+                state.set("ca", Object::Real(opacity.clamp(0.0, 1.0) as f32));
+                states.set("GS0", Object::Dictionary(state));
+                resources.set("ExtGState", Object::Dictionary(states));
+            }
+            if resources.has(b"ExtGState") {
+                content = format!("/GS0 gs\n{}", content);
+            }
+
+            // 外观流的 BBox 与注释矩形相同，内容直接使用页面坐标
+            let mut form = Dictionary::new();
+            form.set("Type", Object::Name(b"XObject".to_vec()));
+            form.set("Subtype", Object::Name(b"Form".to_vec()));
+            form.set("BBox", rect_to_object(&rect));
+            form.set("Resources", Object::Dictionary(resources));
+            let appearance_id = doc.add_object(Stream::new(form, content.into_bytes()));
+            let mut appearances = Dictionary::new();
+            appearances.set("N", Object::Reference(appearance_id));
+            dict.set("AP", Object::Dictionary(appearances));
+        }
+
+        let annot_id = doc.add_object(dict);
+        push_page_annotation(doc, page_id, annot_id)?;
+    }
+
+    // 所有外观都生成后才知道用到的字形
+    if let Some(font) = font.filter(|f| f.used) {
+        font.embedded.write(doc, font.id)?;
+    }
+    Ok(())
+}
+
+/// 将注释加入页面的 /Annots，/Annots 为间接对象时修改该数组
+fn push_page_annotation(doc: &mut Document, page_id: ObjectId, annot_id: ObjectId) -> Result<(), String> {
+    let existing = doc.get_dictionary(page_id)
+        .map_err(|e| format!("无法读取页面: {}", e))?
+        .get(b"Annots").ok().cloned();
+    match existing {
+        Some(Object::Reference(array_id)) if doc.get_object(array_id).is_ok_and(|a| a.as_array().is_ok()) => {
+            if let Ok(Object::Array(annots)) = doc.get_object_mut(array_id) {
+                annots.push(Object::Reference(annot_id));
+            }
+        }
+        existing => {
+            let mut annots = match existing {
+                Some(Object::Array(annots)) => annots,
+                _ => Vec::new(),
+            };
+            annots.push(Object::Reference(annot_id));
+            let page = doc.get_dictionary_mut(page_id)
+                .map_err(|e| format!("无法读取页面: {}", e))?;
+            page.set("Annots", Object::Array(annots));
+        }
+    }
+    Ok(())
+}
+
+fn build_annotation(spec: &NewAnnotation, number: usize, page_ids: &[ObjectId], font: &mut Option<AnnotationFont>) -> Result<BuiltAnnotation, String> {
+    let missing = |what: &str| format!("第 {} 个注释缺少 {}", number, what);
+    let subtype = match spec.kind.to_ascii_lowercase().as_str() {
+        "highlight" => "Highlight",
+        "underline" => "Underline",
+        "strikeout" => "StrikeOut",
+        "text" => "Text",
+        "freetext" => "FreeText",
+        "square" => "Square",
+        "circle" => "Circle",
+        "line" => "Line",
+        "ink" => "Ink",
+        "link" => "Link",
+        _ => return Err(format!("不支持的注释类型: {}", spec.kind)),
+    };
+    let default_color = match subtype {
+        "Highlight" | "Text" => (1.0, 1.0, 0.0),
+        "FreeText" => (0.0, 0.0, 0.0),
+        _ => (1.0, 0.0, 0.0),
+    };
+    let color = spec.color.as_deref().map(parse_color).transpose()?.unwrap_or(default_color);
+    let fill = spec.fill.as_deref().map(parse_color).transpose()?;
+    let width = spec.width.unwrap_or(if subtype == "FreeText" { 0.0 } else { 1.0 }).max(0.0);
+    let (r, g, b) = color;
+    let stroke = format!("{} {} {} RG\n{} w\n", r, g, b, width);
+
+    let mut dict = Dictionary::new();
+    dict.set("Subtype", Object::Name(subtype.as_bytes().to_vec()));
+    if subtype != "Link" {
+        dict.set("C", color_object(color));
+    }
+    if matches!(subtype, "Square" | "Circle" | "Line" | "Ink" | "FreeText") {
+        let mut border = Dictionary::new();
+        border.set("W", Object::Real(width as f32));
+        dict.set("BS", Object::Dictionary(border));
+    }
+    let mut resources = Dictionary::new();
+
+    let (rect, content) = match subtype {
+        "Highlight" | "Underline" | "StrikeOut" => {
+            let quads: Vec<f64> = if !spec.quad_points.is_empty() {
+                if !spec.quad_points.len().is_multiple_of(8) {
+                    return Err(format!("第 {} 个注释的 quad_points 数量必须是 8 的倍数", number));
+                }
+                spec.quad_points.clone()
+            } else {
+                // 每个区域依次为左上、右上、左下、右下四个角
+                spec.rects.iter()
+                    .map(|&r| normalize_rect(r))
+                    .flat_map(|[l, b, r, t]| [l, t, r, t, l, b, r, b])
+                    .collect()
+            };
+            if quads.is_empty() {
+                return Err(missing("rects 或 quad_points"));
+            }
+            dict.set("QuadPoints", Object::Array(quads.iter().map(|&v| Object::Real(v as f32)).collect()));
+
+            let mut content = String::new();
+            for quad in quads.chunks(8) {
+                let [x1, y1, x2, y2, x3, y3, x4, y4] = [quad[0], quad[1], quad[2], quad[3], quad[4], quad[5], quad[6], quad[7]];
+                let thickness = ((y1 - y3).abs() / 16.0).max(0.5);
+                match subtype {
+                    "Highlight" => content.push_str(&format!(
+                        "{} {} {} rg\n{} {} m {} {} l {} {} l {} {} l h f\n", r, g, b, x1, y1, x2, y2, x4, y4, x3, y3
+                    )),
+                    "Underline" => content.push_str(&format!(
+                        "{} {} {} RG\n{} w\n{} {} m {} {} l S\n", r, g, b, thickness, x3, y3 + thickness, x4, y4 + thickness
+                    )),
+                    _ => content.push_str(&format!(
+                        "{} {} {} RG\n{} w\n{} {} m {} {} l S\n", r, g, b, thickness,
+                        (x1 + x3) / 2.0, (y1 + y3) / 2.0, (x2 + x4) / 2.0, (y2 + y4) / 2.0
+                    )),
+                }
+            }
+            if subtype == "Highlight" {
+                // 正片叠底，文字在高亮下仍然清晰
+                let mut state = Dictionary::new();
+                state.set("BM", Object::Name(b"Multiply".to_vec()));
+                let mut states = Dictionary::new();
+                states.set("GS0", Object::Dictionary(state));
+                resources.set("ExtGState", Object::Dictionary(states));
+            }
+            (bounding_box(&quads, 0.0), content)
+        }
+        "Square" | "Circle" => {
+            let rect = normalize_rect(spec.rect.ok_or_else(|| missing("rect"))?);
+            if let Some(fill) = fill {
+                dict.set("IC", color_object(fill));
+            }
+            let inset = width / 2.0;
+            let [l, b, r, t] = [rect[0] + inset, rect[1] + inset, rect[2] - inset, rect[3] - inset];
+            let path = if subtype == "Square" {
+                format!("{} {} {} {} re\n", l, b, r - l, t - b)
+            } else {
+                ellipse_path((l + r) / 2.0, (b + t) / 2.0, (r - l) / 2.0, (t - b) / 2.0)
+            };
+            let paint = match (fill, width > 0.0) {
+                (Some(_), true) => "B",
+                (Some(_), false) => "f",
+                (None, true) => "S",
+                (None, false) => "n",
+            };
+            let fill_color = fill.map(|(r, g, b)| format!("{} {} {} rg\n", r, g, b)).unwrap_or_default();
+            (rect, format!("{}{}{}{}\n", stroke, fill_color, path, paint))
+        }
+        "Line" => {
+            let [x1, y1, x2, y2] = spec.line.ok_or_else(|| missing("line"))?;
+            dict.set("L", Object::Array([x1, y1, x2, y2].iter().map(|&v| Object::Real(v as f32)).collect()));
+            (bounding_box(&[x1, y1, x2, y2], width), format!("{}1 J\n{} {} m {} {} l S\n", stroke, x1, y1, x2, y2))
+        }
+        "Ink" => {
+            let paths: Vec<&Vec<f64>> = spec.paths.iter().filter(|p| p.len() >= 2).collect();
+            if paths.is_empty() {
+                return Err(missing("paths"));
+            }
+            let mut content = format!("{}1 J\n1 j\n", stroke);
+            for path in &paths {
+                for (i, [x, y]) in path.as_chunks::<2>().0.iter().enumerate() {
+                    content.push_str(&format!("{} {} {}\n", x, y, if i == 0 { "m" } else { "l" }));
+                }
+                content.push_str("S\n");
+            }
+            dict.set("InkList", Object::Array(
+                paths.iter().map(|p| Object::Array(p.iter().map(|&v| Object::Real(v as f32)).collect())).collect(),
+            ));
+            let points: Vec<f64> = paths.iter().flat_map(|p| p.iter().copied()).collect();
+            (bounding_box(&points, width), content)
+        }
+        "Text" => {
+            let rect = normalize_rect(spec.rect.ok_or_else(|| missing("rect"))?);
+            dict.set("Name", Object::Name(spec.icon.as_deref().unwrap_or("Note").as_bytes().to_vec()));
+            dict.set("Open", Object::Boolean(spec.open));
+            // 便签图标：带边框的纸张和三行横线
+            let [l, b, r, t] = rect;
+            let (w, h) = (r - l, t - b);
+            let mut content = format!("{} {} {} rg\n0 G\n0.5 w\n{} {} {} {} re B\n", color.0, color.1, color.2, l + 0.5, b + 0.5, w - 1.0, h - 1.0);
+            for fraction in [0.3, 0.5, 0.7] {
+                content.push_str(&format!("{} {} m {} {} l S\n", l + w * 0.2, b + h * fraction, l + w * 0.8, b + h * fraction));
+            }
+            (rect, content)
+        }
+        "FreeText" => {
+            let rect = normalize_rect(spec.rect.ok_or_else(|| missing("rect"))?);
+            let text = spec.contents.as_deref().ok_or_else(|| missing("contents"))?;
+            let size = spec.font_size.unwrap_or(12.0);
+            let font_name = if font.is_some() { EMBEDDED_FONT_NAME } else { "Helv" };
+            dict.set("DA", Object::string_literal(format!("/{} {} Tf {} {} {} rg", font_name, size, r, g, b)));
+
+            let [left, bottom, right, top] = rect;
+            let mut content = String::new();
+            if let Some((fr, fg, fb)) = fill {
+                content.push_str(&format!("{} {} {} rg\n{} {} {} {} re f\n", fr, fg, fb, left, bottom, right - left, top - bottom));
+            }
+            if width > 0.0 {
+                content.push_str(&format!("{}{} {} {} {} re S\n", stroke, left + width / 2.0, bottom + width / 2.0, right - left - width, top - bottom - width));
+            }
+            let padding = width + 2.0;
+            let lines = match font.as_ref() {
+                Some(font) => wrap_text(text, right - left - 2.0 * padding, |line| font.embedded.text_width(line, size)),
+                None => wrap_text(text, right - left - 2.0 * padding, |line| helvetica_width(&encode_win_ansi(line), size)),
+            };
+            // 无法显示的字符报错，而不是写成 "?"
+            let displayable = |line: &String| match font.as_ref() {
+                Some(font) => font.embedded.covers(line),
+                None => line.chars().all(|c| win_ansi_code(c).is_some()),
+            };
+            if !lines.iter().all(displayable) {
+                return Err(match font {
+                    Some(_) => format!("第 {} 个注释的文字包含字体中没有的字符", number),
+                    None => format!("第 {} 个注释的文字包含 Helvetica 无法显示的字符，请使用 add_annotations_with_font 提供字体", number),
+                });
+            }
+            content.push_str(&format!(
+                "q\n{} {} {} {} re W n\nBT\n/{} {} Tf\n{} {} {} rg\n",
+                left, bottom, right - left, top - bottom, font_name, size, r, g, b
+            ));
+            for (row, line) in lines.iter().enumerate() {
+                let baseline = top - padding - size * 0.9 - row as f64 * size * 1.2;
+                let string = match font.as_mut() {
+                    Some(font) => hex_string(&font.embedded.encode(line)),
+                    None => literal_string(&encode_win_ansi(line)),
+                };
+                content.push_str(&format!("1 0 0 1 {} {} Tm\n{} Tj\n", left + padding, baseline, string));
+            }
+            content.push_str("ET\nQ\n");
+
+            let mut fonts = Dictionary::new();
+            match font.as_mut() {
+                Some(font) => {
+                    font.used = true;
+                    fonts.set(EMBEDDED_FONT_NAME, Object::Reference(font.id));
+                }
+                None => fonts.set("Helv", Object::Dictionary(font_dictionary("Helvetica"))),
+            }
+            resources.set("Font", Object::Dictionary(fonts));
+            (rect, content)
+        }
+        _ => {
+            // 链接：无边框，打开网址或跳转到页面
+            let rect = normalize_rect(spec.rect.ok_or_else(|| missing("rect"))?);
+            dict.set("Border", vec![0.into(), 0.into(), 0.into()]);
+            match (&spec.uri, spec.dest_page) {
+                (Some(uri), _) => {
+                    let mut action = Dictionary::new();
+                    action.set("S", Object::Name(b"URI".to_vec()));
+                    action.set("URI", Object::string_literal(uri.as_str()));
+                    dict.set("A", Object::Dictionary(action));
+                }
+                (None, Some(page)) => {
+                    let target = page.checked_sub(1)
+                        .and_then(|i| page_ids.get(i).copied())
+                        .ok_or_else(|| format!("第 {} 个注释的目标页码 {} 超出范围（共 {} 页）", number, page, page_ids.len()))?;
+                    dict.set("Dest", vec![Object::Reference(target), Object::Name(b"Fit".to_vec())]);
+                }
+                (None, None) => return Err(missing("uri 或 dest_page")),
+            }
+            dict.set("Rect", rect_to_object(&rect));
+            return Ok(BuiltAnnotation { dict, appearance: None });
+        }
+    };
+
+    dict.set("Rect", rect_to_object(&rect));
+    Ok(BuiltAnnotation { dict, appearance: Some((content, resources)) })
+}
+
+fn color_object((r, g, b): (f64, f64, f64)) -> Object {
+    Object::Array(vec![Object::Real(r as f32), Object::Real(g as f32), Object::Real(b as f32)])
+}
+
+fn normalize_rect([x1, y1, x2, y2]: [f64; 4]) -> [f64; 4] {
+    [x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)]
+}
+
+/// 坐标序列 [x1, y1, x2, y2, ...] 的外接矩形，向外扩展 margin
+fn bounding_box(points: &[f64], margin: f64) -> [f64; 4] {
+    let mut rect = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+    for &[x, y] in points.as_chunks::<2>().0 {
+        rect = [rect[0].min(x), rect[1].min(y), rect[2].max(x), rect[3].max(y)];
+    }
+    [rect[0] - margin, rect[1] - margin, rect[2] + margin, rect[3] + margin]
+}
+
+/// 用四段贝塞尔曲线近似椭圆
+fn ellipse_path(cx: f64, cy: f64, rx: f64, ry: f64) -> String {
+    const K: f64 = 0.552_284_75;
+    let (kx, ky) = (rx * K, ry * K);
+    format!(
+        "{} {} m\n{} {} {} {} {} {} c\n{} {} {} {} {} {} c\n{} {} {} {} {} {} c\n{} {} {} {} {} {} c\n",
+        cx + rx, cy,
+        cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry,
+        cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy,
+        cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry,
+        cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy,
+    )
+}
@@ -0,0 +1,366 @@
+// 附件：列出、提取和嵌入文件
+// 文档级附件位于目录 /Names 的 /EmbeddedFiles 名称树中，页面附件由 FileAttachment 注释的 /FS 引用
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use crate::filters::decode_stream;
+use crate::outline::{decode_text_string, text_string};
+use crate::pdf_utils::{deep_copy_object_with_map, object_to_f64};
+use crate::render_color::resolve;
+
+/// 遍历名称树时允许的最大深度
+const MAX_DEPTH: usize = 32;
+
+/// 附件的 JSON 表示
+#[derive(Debug, Serialize)]
+pub(crate) struct AttachmentInfo {
+    /// 附件名称：文档级附件为名称树中的键，页面附件为文件名；extract_attachment 按此名称查找
+    name: String,
+    /// 文件名（/UF 或 /F）
+    file_name: String,
+    /// 说明（/Desc）
+    description: Option<String>,
+    /// MIME 类型，如 "text/plain"
+    mime: Option<String>,
+    /// 文件大小（字节），优先使用 /Params 中的 /Size
+    size: Option<usize>,
+    /// 创建时间，原样返回 PDF 日期字符串，如 "D:20240101120000Z"
+    created: Option<String>,
+    /// 修改时间
+    modified: Option<String>,
+    /// 页面附件所在的页码（从 1 开始），文档级附件为 null
+    page: Option<usize>,
+}
+
+/// 读取名称树的全部条目（键已解码），值保持原样（通常是引用）
+fn name_tree_entries(doc: &Document, node: &Object, depth: usize, entries: &mut Vec<(String, Object)>) {
+    let Ok(node) = resolve(doc, node).as_dict() else { return };
+    if depth > MAX_DEPTH {
+        return;
+    }
+    if let Ok(names) = node.get(b"Names").and_then(|n| resolve(doc, n).as_array()) {
+        for [key, value] in names.as_chunks::<2>().0 {
+            if let Ok(key) = resolve(doc, key).as_str() {
+                entries.push((decode_text_string(key), value.clone()));
+            }
+        }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(|k| resolve(doc, k).as_array()) {
+        for kid in kids {
+            name_tree_entries(doc, kid, depth + 1, entries);
+        }
+    }
+}
+
+/// 目录 /Names 字典（可能是间接对象）
+fn names_dictionary(doc: &Document) -> Option<&Dictionary> {
+    let names = doc.catalog().ok()?.get(b"Names").ok()?;
+    resolve(doc, names).as_dict().ok()
+}
+
+/// /EmbeddedFiles 名称树中的全部条目
+fn embedded_file_entries(doc: &Document) -> Vec<(String, Object)> {
+    let mut entries = Vec::new();
+    if let Some(tree) = names_dictionary(doc).and_then(|n| n.get(b"EmbeddedFiles").ok()) {
+        name_tree_entries(doc, tree, 0, &mut entries);
+    }
+    entries
+}
+
+/// 用给定条目重写 /EmbeddedFiles 名称树（单个叶节点，键按字节序排列）
+fn write_embedded_files(doc: &mut Document, entries: Vec<(String, Object)>) -> Result<(), String> {
+    let mut entries: Vec<(Vec<u8>, Object)> = entries.into_iter()
+        .filter_map(|(name, value)| Some((text_string(&name).as_str().ok()?.to_vec(), value)))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let names: Vec<Object> = entries.into_iter()
+        .flat_map(|(key, value)| [Object::String(key, StringFormat::Literal), value])
+        .collect();
+    let mut tree = Dictionary::new();
+    tree.set("Names", Object::Array(names));
+    let tree_id = doc.add_object(Object::Dictionary(tree));
+
+    let catalog = doc.catalog().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    let names_id = catalog.get(b"Names").and_then(Object::as_reference).ok();
+    let mut names_dict = names_dictionary(doc).cloned().unwrap_or_default();
+    names_dict.set("EmbeddedFiles", Object::Reference(tree_id));
+    match names_id {
+        Some(id) => {
+            doc.objects.insert(id, Object::Dictionary(names_dict));
+        }
+        None => {
+            let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+            catalog.set("Names", Object::Dictionary(names_dict));
+        }
+    }
+    Ok(())
+}
+
+/// 文件规范中的嵌入文件流（流总是间接对象），优先使用 /UF
+fn embedded_stream<'a>(doc: &'a Document, filespec: &Dictionary) -> Option<&'a Stream> {
+    let ef = resolve(doc, filespec.get(b"EF").ok()?).as_dict().ok()?;
+    let id = ef.get(b"UF").or_else(|_| ef.get(b"F")).and_then(Object::as_reference).ok()?;
+    doc.get_object(id).and_then(Object::as_stream).ok()
+}
+
+fn attachment_info(doc: &Document, name: Option<String>, filespec: &Dictionary, stream: &Stream, page: Option<usize>) -> AttachmentInfo {
+    let text = |dict: &Dictionary, key: &[u8]| dict.get(key).ok().and_then(|v| resolve(doc, v).as_str().ok()).map(decode_text_string);
+    let file_name = text(filespec, b"UF").or_else(|| text(filespec, b"F")).unwrap_or_default();
+    let params = stream.dict.get(b"Params").ok().and_then(|p| resolve(doc, p).as_dict().ok());
+    let size = params
+        .and_then(|p| p.get(b"Size").ok())
+        .and_then(|s| object_to_f64(resolve(doc, s)))
+        .map(|s| s as usize)
+        .or_else(|| decode_stream(doc, stream).ok().map(|d| d.data.len()));
+    AttachmentInfo {
+        name: name.unwrap_or_else(|| file_name.clone()),
+        description: text(filespec, b"Desc"),
+        mime: stream.dict.get(b"Subtype").and_then(Object::as_name).ok().map(|m| String::from_utf8_lossy(m).into_owned()),
+        size,
+        created: params.and_then(|p| text(p, b"CreationDate")),
+        modified: params.and_then(|p| text(p, b"ModDate")),
+        page,
+        file_name,
+    }
+}
+
+/// 文档中的全部附件及其嵌入文件流：先是名称树中的附件，再按页面顺序列出附件注释
+/// 同一文件规范同时出现在两处时只列出一次
+fn collect_attachments(doc: &Document) -> Vec<(AttachmentInfo, &Stream)> {
+    let mut attachments = Vec::new();
+    let mut seen: HashSet<ObjectId> = HashSet::new();
+
+    for (name, value) in embedded_file_entries(doc) {
+        let Some(filespec) = resolve(doc, &value).as_dict().ok() else { continue };
+        let Some(stream) = embedded_stream(doc, filespec) else { continue };
+        if let Ok(id) = value.as_reference() {
+            seen.insert(id);
+        }
+        attachments.push((attachment_info(doc, Some(name), filespec, stream, None), stream));
+    }
+
+    for (number, page_id) in doc.get_pages() {
+        let annots = doc.get_dictionary(page_id).ok()
+            .and_then(|p| p.get(b"Annots").ok())
+            .and_then(|a| resolve(doc, a).as_array().ok());
+        for annot in annots.into_iter().flatten() {
+            let Ok(annot) = resolve(doc, annot).as_dict() else { continue };
+            if annot.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"FileAttachment".as_slice()) {
+                continue;
+            }
+            let Ok(fs) = annot.get(b"FS") else { continue };
+            if fs.as_reference().is_ok_and(|id| !seen.insert(id)) {
+                continue;
+            }
+            let Ok(filespec) = resolve(doc, fs).as_dict() else { continue };
+            let Some(stream) = embedded_stream(doc, filespec) else { continue };
+            attachments.push((attachment_info(doc, None, filespec, stream, Some(number as usize)), stream));
+        }
+    }
+    attachments
+}
+
+/// 列出文档中的附件
+pub(crate) fn list_document_attachments(doc: &Document) -> Vec<AttachmentInfo> {
+    collect_attachments(doc).into_iter().map(|(info, _)| info).collect()
+}
+
+/// 按名称读取附件内容，名称相同时取第一个
+pub(crate) fn attachment_data(doc: &Document, name: &str) -> Result<Vec<u8>, String> {
+    let (_, stream) = collect_attachments(doc).into_iter()
+        .find(|(info, _)| info.name == name)
+        .ok_or_else(|| format!("未找到附件: {}", name))?;
+    decode_stream(doc, stream).map(|d| d.data)
+}
+
+/// 嵌入文件作为文档级附件，已有同名附件时替换
+pub(crate) fn embed_attachment(
+    doc: &mut Document,
+    name: &str,
+    data: &[u8],
+    mime: Option<&str>,
+    description: Option<&str>,
+) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("附件名称不能为空".to_string());
+    }
+
+    let date = Object::String(current_pdf_date().into_bytes(), StringFormat::Literal);
+    let mut params = Dictionary::new();
+    params.set("Size", Object::Integer(data.len() as i64));
+    params.set("CreationDate", date.clone());
+    params.set("ModDate", date);
+
+    let mut stream_dict = Dictionary::new();
+    stream_dict.set("Type", Object::Name(b"EmbeddedFile".to_vec()));
+    if let Some(mime) = mime.filter(|m| !m.is_empty()) {
+        stream_dict.set("Subtype", Object::Name(mime.as_bytes().to_vec()));
+    }
+    stream_dict.set("Params", Object::Dictionary(params));
+    stream_dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder.write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("无法压缩附件数据: {}", e))?;
+    let stream_id = doc.add_object(Stream::new(stream_dict, compressed).with_compression(false));
+
+    let mut ef = Dictionary::new();
+    ef.set("F", Object::Reference(stream_id));
+    ef.set("UF", Object::Reference(stream_id));
+    let mut filespec = Dictionary::new();
+    filespec.set("Type", Object::Name(b"Filespec".to_vec()));
+    filespec.set("F", text_string(name));
+    filespec.set("UF", text_string(name));
+    if let Some(description) = description.filter(|d| !d.is_empty()) {
+        filespec.set("Desc", text_string(description));
+    }
+    filespec.set("EF", Object::Dictionary(ef));
+    let filespec_id = doc.add_object(Object::Dictionary(filespec));
+
+    let mut entries = embedded_file_entries(doc);
+    entries.retain(|(key, _)| key != name);
+    entries.push((name.to_string(), Object::Reference(filespec_id)));
+    write_embedded_files(doc, entries)
+}
+
+/// 合并文档时收集各输入文件的文档级附件
+#[derive(Default)]
+pub(crate) struct AttachmentMerger {
+    entries: Vec<(String, Object)>,
+    names: HashSet<String>,
+}
+
+impl AttachmentMerger {
+    /// 复制源文档的附件，与已有附件同名时改名为 "文件名_2.扩展名"、"文件名_3.扩展名"……
+    pub(crate) fn add(&mut self, src_doc: &Document, dst_doc: &mut Document, id_map: &mut HashMap<ObjectId, ObjectId>) {
+        for (name, value) in embedded_file_entries(src_doc) {
+            let copied = deep_copy_object_with_map(src_doc, dst_doc, &value, id_map);
+            let unique = if self.names.contains(&name) {
+                let (stem, extension) = match name.rsplit_once('.') {
+                    Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+                    _ => (name.as_str(), String::new()),
+                };
+                (2..).map(|n| format!("{}_{}{}", stem, n, extension)).find(|n| !self.names.contains(n)).unwrap_or_default()
+            } else {
+                name
+            };
+            self.names.insert(unique.clone());
+            self.entries.push((unique, copied));
+        }
+    }
+
+    /// 将合并后的附件写入目录，没有附件时不写入
+    pub(crate) fn write(self, doc: &mut Document) -> Result<(), String> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        write_embedded_files(doc, self.entries)
+    }
+}
+
+/// PDF/A-3 中的附件必须是关联文件：文档级附件加入目录的 /AF，文件规范缺少 /AFRelationship 时
+/// 设为 /Unspecified，嵌入文件流补上 MIME 类型（/Subtype）和修改时间
+pub(crate) fn associate_embedded_files(doc: &mut Document) -> Result<(), String> {
+    let entries = embedded_file_entries(doc);
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    // /AF 只能引用间接的文件规范
+    let direct = entries.iter().any(|(_, value)| value.as_reference().is_err());
+    let mut filespec_ids = Vec::with_capacity(entries.len());
+    let mut rewritten = Vec::with_capacity(entries.len());
+    for (name, value) in entries {
+        let id = match value {
+            Object::Reference(id) => id,
+            other => doc.add_object(other),
+        };
+        filespec_ids.push(id);
+        rewritten.push((name, Object::Reference(id)));
+    }
+    if direct {
+        write_embedded_files(doc, rewritten)?;
+    }
+
+    let date = Object::String(current_pdf_date().into_bytes(), StringFormat::Literal);
+    for &id in &filespec_ids {
+        let stream_ids: Vec<ObjectId> = doc.get_dictionary(id).ok()
+            .and_then(|f| f.get(b"EF").ok())
+            .and_then(|ef| resolve(doc, ef).as_dict().ok())
+            .map(|ef| ef.iter().filter_map(|(_, s)| s.as_reference().ok()).collect())
+            .unwrap_or_default();
+        for stream_id in stream_ids {
+            let Ok(Object::Stream(stream)) = doc.get_object_mut(stream_id) else { continue };
+            if !stream.dict.has(b"Subtype") {
+                stream.dict.set("Subtype", Object::Name(b"application/octet-stream".to_vec()));
+            }
+            match stream.dict.get_mut(b"Params") {
+                Ok(Object::Dictionary(params)) => {
+                    if !params.has(b"ModDate") {
+                        params.set("ModDate", date.clone());
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => stream.dict.set("Params", Dictionary::from_iter(vec![("ModDate", date.clone())])),
+            }
+        }
+        if let Ok(filespec) = doc.get_dictionary_mut(id) {
+            if !filespec.has(b"AFRelationship") {
+                filespec.set("AFRelationship", Object::Name(b"Unspecified".to_vec()));
+            }
+        }
+    }
+
+    let catalog = doc.catalog().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    let mut associated: Vec<Object> = catalog.get(b"AF").ok()
+        .and_then(|af| resolve(doc, af).as_array().ok().cloned())
+        .unwrap_or_default();
+    for id in filespec_ids {
+        if !associated.contains(&Object::Reference(id)) {
+            associated.push(Object::Reference(id));
+        }
+    }
+    let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    catalog.set("AF", Object::Array(associated));
+    Ok(())
+}
+
+/// 当前时间的 PDF 日期字符串（UTC），如 "D:20240101120000Z"
+pub(crate) fn current_pdf_date() -> String {
+    let seconds = (now_millis() / 1000.0) as i64;
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // 由 1970-01-01 起的天数计算公历日期
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "D:{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        year, month, day, time / 3600, time % 3600 / 60, time % 60
+    )
+}
+
+/// 自 1970-01-01 起的毫秒数；wasm 中 std::time 不可用，使用 JavaScript 的 Date
+#[cfg(target_arch = "wasm32")]
+fn now_millis() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_millis() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_millis() as f64)
+}
@@ -0,0 +1,326 @@
+// CFF 字体程序的子集化
+// OpenType（CFF 轮廓）字体嵌入时只保留用到的字形：字形编号不变，
+// 未用到的字形替换为空字形，子程序、字符集和 FDSelect 原样保留
+
+use std::collections::HashSet;
+
+const OP_CHARSET: u16 = 15;
+const OP_ENCODING: u16 = 16;
+const OP_CHARSTRINGS: u16 = 17;
+const OP_PRIVATE: u16 = 18;
+const OP_SUBRS: u16 = 19;
+const OP_ROS: u16 = 0x0c1e;
+const OP_FDARRAY: u16 = 0x0c24;
+const OP_FDSELECT: u16 = 0x0c25;
+/// Type 2 字形程序中的 endchar
+const EMPTY_CHARSTRING: [u8; 1] = [14];
+
+/// DICT 中的一项：操作数的原始字节、解析出的整数操作数和操作符
+struct DictEntry {
+    raw: Vec<u8>,
+    operands: Vec<i32>,
+    op: u16,
+}
+
+/// 字体的 Private DICT 及其局部子程序
+struct PrivateDict {
+    entries: Vec<DictEntry>,
+    subrs: Option<Vec<u8>>,
+}
+
+/// 生成保留原字形编号的 CFF 子集，keep 为要保留的字形编号（.notdef 总是保留）
+pub(crate) fn subset_cff(cff: &[u8], keep: &HashSet<u16>) -> Result<Vec<u8>, String> {
+    let invalid = || "CFF 字体数据无效".to_string();
+    let header_size = *cff.get(2).ok_or_else(invalid)? as usize;
+    let name_end = index_end(cff, header_size).ok_or_else(invalid)?;
+    let top_end = index_end(cff, name_end).ok_or_else(invalid)?;
+    let strings_end = index_end(cff, top_end).ok_or_else(invalid)?;
+    let gsubrs_end = index_end(cff, strings_end).ok_or_else(invalid)?;
+
+    let top_dict = index_items(cff, name_end).and_then(|items| items.first().copied()).ok_or_else(invalid)?;
+    let top = parse_dict(top_dict).ok_or_else(invalid)?;
+    let offset = |op: u16| top.iter().find(|e| e.op == op).and_then(|e| e.operands.last().copied());
+
+    let charstrings_offset = offset(OP_CHARSTRINGS).ok_or_else(invalid)? as usize;
+    let charstrings = index_items(cff, charstrings_offset).ok_or_else(invalid)?;
+    let glyph_count = charstrings.len();
+    if glyph_count == 0 {
+        return Err(invalid());
+    }
+
+    // 0~2 为预定义字符集，不是偏移
+    let charset = match offset(OP_CHARSET) {
+        Some(offset) if offset > 2 => {
+            let offset = offset as usize;
+            Some(cff.get(offset..charset_end(cff, offset, glyph_count).ok_or_else(invalid)?).ok_or_else(invalid)?)
+        }
+        _ => None,
+    };
+    let cid_keyed = top.iter().any(|e| e.op == OP_ROS);
+    let fd_select = match offset(OP_FDSELECT) {
+        Some(offset) if cid_keyed => {
+            let offset = offset as usize;
+            Some(cff.get(offset..fd_select_end(cff, offset, glyph_count).ok_or_else(invalid)?).ok_or_else(invalid)?)
+        }
+        _ => None,
+    };
+
+    // CID 字体的每个 Font DICT 各有一个 Private DICT，普通字体只有顶层的一个
+    let mut font_dicts = Vec::new();
+    let mut privates = Vec::new();
+    match offset(OP_FDARRAY) {
+        Some(fd_array) if cid_keyed => {
+            for item in index_items(cff, fd_array as usize).ok_or_else(invalid)? {
+                let dict = parse_dict(item).ok_or_else(invalid)?;
+                privates.push(read_private(cff, &dict).ok_or_else(invalid)?);
+                font_dicts.push(dict);
+            }
+        }
+        _ => privates.push(read_private(cff, &top).ok_or_else(invalid)?),
+    }
+
+    let new_charstrings = write_index(
+        &charstrings
+            .iter()
+            .enumerate()
+            .map(|(glyph, data)| if glyph == 0 || keep.contains(&(glyph as u16)) { *data } else { &EMPTY_CHARSTRING[..] })
+            .collect::<Vec<_>>(),
+    );
+
+    // 所有偏移都写成 5 字节整数，DICT 的长度与偏移的取值无关，可以先确定布局
+    let private_sizes: Vec<usize> = privates.iter().map(|p| write_dict(&p.entries, &|_| None).len()).collect();
+    let fd_array_size = (!font_dicts.is_empty()).then(|| {
+        write_index(&font_dicts.iter().map(|d| write_dict(d, &|_| None)).collect::<Vec<_>>()).len()
+    });
+    let top_size = write_index(&[write_dict(&top, &|_| None)]).len();
+
+    let mut position = gsubrs_end - top_end + name_end + top_size;
+    let charset_position = position;
+    position += charset.map_or(0, <[u8]>::len);
+    let fd_select_position = position;
+    position += fd_select.map_or(0, <[u8]>::len);
+    let charstrings_position = position;
+    position += new_charstrings.len();
+    let fd_array_position = position;
+    position += fd_array_size.unwrap_or(0);
+    let mut private_positions = Vec::new();
+    for (private, size) in privates.iter().zip(&private_sizes) {
+        private_positions.push(position);
+        position += size + private.subrs.as_ref().map_or(0, Vec::len);
+    }
+
+    let new_top = write_dict(&top, &|op| match op {
+        OP_CHARSET if charset.is_some() => Some(vec![charset_position as i32]),
+        OP_CHARSTRINGS => Some(vec![charstrings_position as i32]),
+        OP_FDSELECT if fd_select.is_some() => Some(vec![fd_select_position as i32]),
+        OP_FDARRAY if fd_array_size.is_some() => Some(vec![fd_array_position as i32]),
+        OP_PRIVATE if font_dicts.is_empty() => Some(vec![private_sizes[0] as i32, private_positions[0] as i32]),
+        _ => None,
+    });
+
+    let mut output = cff[..name_end].to_vec();
+    output.extend(write_index(&[new_top]));
+    output.extend_from_slice(&cff[top_end..gsubrs_end]);
+    output.extend_from_slice(charset.unwrap_or_default());
+    output.extend_from_slice(fd_select.unwrap_or_default());
+    output.extend(new_charstrings);
+    if fd_array_size.is_some() {
+        let dicts: Vec<Vec<u8>> = font_dicts
+            .iter()
+            .enumerate()
+            .map(|(index, dict)| write_dict(dict, &|op| {
+                (op == OP_PRIVATE).then(|| vec![private_sizes[index] as i32, private_positions[index] as i32])
+            }))
+            .collect();
+        output.extend(write_index(&dicts));
+    }
+    for (private, size) in privates.iter().zip(&private_sizes) {
+        // 局部子程序紧跟在 Private DICT 之后，偏移相对于 Private DICT 的起点
+        output.extend(write_dict(&private.entries, &|op| (op == OP_SUBRS).then(|| vec![*size as i32])));
+        output.extend_from_slice(private.subrs.as_deref().unwrap_or_default());
+    }
+    debug_assert_eq!(output.len(), position);
+
+    Ok(output)
+}
+
+/// 读取 DICT 中 Private 操作符指向的 Private DICT 和局部子程序
+fn read_private(cff: &[u8], dict: &[DictEntry]) -> Option<PrivateDict> {
+    let Some(entry) = dict.iter().find(|e| e.op == OP_PRIVATE) else {
+        return Some(PrivateDict { entries: Vec::new(), subrs: None });
+    };
+    let [size, offset] = entry.operands[..] else { return None };
+    let (size, offset) = (size as usize, offset as usize);
+    let mut entries = parse_dict(cff.get(offset..offset.checked_add(size)?)?)?;
+    // 局部子程序的偏移相对于 Private DICT 的起点
+    let subrs = match entries.iter().find(|e| e.op == OP_SUBRS).and_then(|e| e.operands.last()) {
+        Some(&subrs_offset) => {
+            let start = offset.checked_add(subrs_offset as usize)?;
+            Some(cff.get(start..index_end(cff, start)?)?.to_vec())
+        }
+        None => None,
+    };
+    if subrs.is_none() {
+        entries.retain(|e| e.op != OP_SUBRS);
+    }
+    Some(PrivateDict { entries, subrs })
+}
+
+/// INDEX 结构结束的位置
+fn index_end(data: &[u8], start: usize) -> Option<usize> {
+    let count = read_u16(data, start)? as usize;
+    if count == 0 {
+        return Some(start + 2);
+    }
+    let off_size = *data.get(start + 2)? as usize;
+    let data_start = start + 3 + (count + 1) * off_size - 1;
+    let end = data_start + read_offset(data, start + 3 + count * off_size, off_size)?;
+    (end <= data.len()).then_some(end)
+}
+
+/// INDEX 中的各项数据
+fn index_items(data: &[u8], start: usize) -> Option<Vec<&[u8]>> {
+    let count = read_u16(data, start)? as usize;
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    let off_size = *data.get(start + 2)? as usize;
+    let data_start = start + 3 + (count + 1) * off_size - 1;
+    (0..count)
+        .map(|i| {
+            let from = read_offset(data, start + 3 + i * off_size, off_size)?;
+            let to = read_offset(data, start + 3 + (i + 1) * off_size, off_size)?;
+            data.get(data_start + from..data_start + to)
+        })
+        .collect()
+}
+
+fn write_index(items: &[impl AsRef<[u8]>]) -> Vec<u8> {
+    let mut output = (items.len() as u16).to_be_bytes().to_vec();
+    if items.is_empty() {
+        return output;
+    }
+    let total: usize = items.iter().map(|item| item.as_ref().len()).sum::<usize>() + 1;
+    let off_size = match total {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFF_FFFF => 3,
+        _ => 4,
+    };
+    output.push(off_size as u8);
+    let mut offset = 1usize;
+    output.extend_from_slice(&(offset as u32).to_be_bytes()[4 - off_size..]);
+    for item in items {
+        offset += item.as_ref().len();
+        output.extend_from_slice(&(offset as u32).to_be_bytes()[4 - off_size..]);
+    }
+    for item in items {
+        output.extend_from_slice(item.as_ref());
+    }
+    output
+}
+
+/// 字符集结束的位置，字符集不含 .notdef
+fn charset_end(data: &[u8], start: usize, glyph_count: usize) -> Option<usize> {
+    let mut pos = start + 1;
+    match data.get(start)? {
+        0 => pos += (glyph_count - 1) * 2,
+        format @ (1 | 2) => {
+            let mut covered = 1;
+            while covered < glyph_count {
+                let left = if *format == 1 {
+                    *data.get(pos + 2)? as usize
+                } else {
+                    read_u16(data, pos + 2)? as usize
+                };
+                covered += left + 1;
+                pos += if *format == 1 { 3 } else { 4 };
+            }
+        }
+        _ => return None,
+    }
+    (pos <= data.len()).then_some(pos)
+}
+
+/// FDSelect 结束的位置
+fn fd_select_end(data: &[u8], start: usize, glyph_count: usize) -> Option<usize> {
+    let end = match data.get(start)? {
+        0 => start + 1 + glyph_count,
+        3 => start + 3 + read_u16(data, start + 1)? as usize * 3 + 2,
+        _ => return None,
+    };
+    (end <= data.len()).then_some(end)
+}
+
+/// 解析 DICT，实数操作数只保留原始字节
+fn parse_dict(data: &[u8]) -> Option<Vec<DictEntry>> {
+    let mut entries = Vec::new();
+    let mut raw = Vec::new();
+    let mut operands = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let b0 = data[pos];
+        let (value, len) = match b0 {
+            0..=21 => {
+                let op = if b0 == 12 {
+                    pos += 1;
+                    0x0c00 | *data.get(pos)? as u16
+                } else {
+                    b0 as u16
+                };
+                pos += 1;
+                entries.push(DictEntry { raw: std::mem::take(&mut raw), operands: std::mem::take(&mut operands), op });
+                continue;
+            }
+            28 => (read_u16(data, pos + 1)? as i16 as i32, 3),
+            29 => (i32::from_be_bytes(data.get(pos + 1..pos + 5)?.try_into().ok()?), 5),
+            30 => {
+                // 实数以半字节编码，0xf 结束
+                let len = data[pos + 1..].iter().position(|&b| b & 0x0f == 0x0f || b >> 4 == 0x0f)? + 2;
+                (0, len)
+            }
+            32..=246 => (b0 as i32 - 139, 1),
+            247..=250 => ((b0 as i32 - 247) * 256 + *data.get(pos + 1)? as i32 + 108, 2),
+            251..=254 => (-(b0 as i32 - 251) * 256 - *data.get(pos + 1)? as i32 - 108, 2),
+            _ => return None,
+        };
+        raw.extend_from_slice(data.get(pos..pos + len)?);
+        operands.push(value);
+        pos += len;
+    }
+    Some(entries)
+}
+
+/// 写出 DICT，偏移类操作符一律写成 5 字节整数，取值由 replace 给出（None 时保留原值）
+/// 自定义编码在 CID 字体中用不到，直接丢弃
+fn write_dict(entries: &[DictEntry], replace: &dyn Fn(u16) -> Option<Vec<i32>>) -> Vec<u8> {
+    let mut output = Vec::new();
+    for entry in entries {
+        if entry.op == OP_ENCODING && entry.operands.first().is_some_and(|&offset| offset > 1) {
+            continue;
+        }
+        if matches!(entry.op, OP_CHARSET | OP_CHARSTRINGS | OP_FDSELECT | OP_FDARRAY | OP_PRIVATE | OP_SUBRS) {
+            let values = replace(entry.op).unwrap_or_else(|| entry.operands.clone());
+            for value in values {
+                output.push(29);
+                output.extend_from_slice(&value.to_be_bytes());
+            }
+        } else {
+            output.extend_from_slice(&entry.raw);
+        }
+        if entry.op > 0xff {
+            output.push(12);
+        }
+        output.push(entry.op as u8);
+    }
+    output
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn read_offset(data: &[u8], offset: usize, size: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset + size)?;
+    Some(bytes.iter().fold(0usize, |value, &b| (value << 8) | b as usize))
+}
@@ -0,0 +1,925 @@
+// 页面内容流解析与扫描
+// lopdf 自带的解析器遇到注释或内联图片（BI/ID/EI）时会静默截断，
+// 这里实现一个容错的解析器，并提供变换矩阵与绘制区域计算
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::pdf_utils::{get_inherited_attribute, object_to_f64, parse_rect};
+use crate::standard_font::StandardFont;
+
+/// 矩形 [x0, y0, x1, y1]
+pub(crate) type Rect = [f64; 4];
+
+/// 仿射变换矩阵 [a b c d e f]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Matrix(pub [f64; 6]);
+
+impl Matrix {
+    pub(crate) const IDENTITY: Matrix = Matrix([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    pub(crate) fn translate(tx: f64, ty: f64) -> Matrix {
+        Matrix([1.0, 0.0, 0.0, 1.0, tx, ty])
+    }
+
+    /// 从操作数读取矩阵（cm、Tm 或 /Matrix 数组）
+    pub(crate) fn from_objects(values: &[Object]) -> Option<Matrix> {
+        if values.len() != 6 {
+            return None;
+        }
+        let mut m = [0.0; 6];
+        for (v, obj) in m.iter_mut().zip(values) {
+            *v = object_to_f64(obj)?;
+        }
+        Some(Matrix(m))
+    }
+
+    /// 先应用 self 再应用 other
+    pub(crate) fn then(&self, other: &Matrix) -> Matrix {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Matrix([
+            a * a2 + b * c2,
+            a * b2 + b * d2,
+            c * a2 + d * c2,
+            c * b2 + d * d2,
+            e * a2 + f * c2 + e2,
+            e * b2 + f * d2 + f2,
+        ])
+    }
+
+    pub(crate) fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// 变换矩形并返回外接矩形
+    pub(crate) fn apply_rect(&self, rect: &Rect) -> Rect {
+        let corners = [
+            self.apply(rect[0], rect[1]),
+            self.apply(rect[2], rect[1]),
+            self.apply(rect[0], rect[3]),
+            self.apply(rect[2], rect[3]),
+        ];
+        bounding_rect(corners.iter().copied()).unwrap_or(*rect)
+    }
+
+    /// 逆矩阵，不可逆时返回 None
+    pub(crate) fn inverse(&self) -> Option<Matrix> {
+        let [a, b, c, d, e, f] = self.0;
+        let det = a * d - b * c;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        Some(Matrix([
+            d / det,
+            -b / det,
+            -c / det,
+            a / det,
+            (c * f - d * e) / det,
+            (b * e - a * f) / det,
+        ]))
+    }
+
+    /// 线宽等长度量的近似缩放系数
+    pub(crate) fn scale_factor(&self) -> f64 {
+        let [a, b, c, d, _, _] = self.0;
+        (a * d - b * c).abs().sqrt()
+    }
+}
+
+fn bounding_rect(points: impl Iterator<Item = (f64, f64)>) -> Option<Rect> {
+    points.fold(None, |acc: Option<Rect>, (x, y)| match acc {
+        None => Some([x, y, x, y]),
+        Some([x0, y0, x1, y1]) => Some([x0.min(x), y0.min(y), x1.max(x), y1.max(y)]),
+    })
+}
+
+pub(crate) fn union_rect(a: &Rect, b: &Rect) -> Rect {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+}
+
+pub(crate) fn intersect_rect(a: &Rect, b: &Rect) -> Option<Rect> {
+    let rect = [a[0].max(b[0]), a[1].max(b[1]), a[2].min(b[2]), a[3].min(b[3])];
+    if rect[0] < rect[2] && rect[1] < rect[3] {
+        Some(rect)
+    } else {
+        None
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 内容流解析
+// ---------------------------------------------------------------------------
+
+/// 解析内容流为操作列表
+/// 内联图片解析为 "BI" 操作，操作数为 [图片字典, 图片数据]
+pub(crate) fn parse_content(data: &[u8]) -> Vec<Operation> {
+    let mut lexer = Lexer { data, pos: 0, depth: 0 };
+    let mut operations = Vec::new();
+    let mut operands = Vec::new();
+
+    while let Some(token) = lexer.next_token() {
+        match token {
+            Token::Object(obj) => operands.push(obj),
+            Token::Keyword(keyword) => {
+                if keyword == b"BI" {
+                    if let Some((dict, image_data)) = lexer.read_inline_image() {
+                        operations.push(Operation::new(
+                            "BI",
+                            vec![Object::Dictionary(dict), Object::String(image_data, StringFormat::Hexadecimal)],
+                        ));
+                    }
+                    operands.clear();
+                    continue;
+                }
+                let operator = String::from_utf8_lossy(&keyword).into_owned();
+                operations.push(Operation::new(&operator, std::mem::take(&mut operands)));
+            }
+            // 不成对的分隔符等无法识别的内容直接跳过
+            Token::ArrayEnd | Token::DictEnd => operands.clear(),
+        }
+    }
+
+    operations
+}
+
+enum Token {
+    Object(Object),
+    Keyword(Vec<u8>),
+    ArrayEnd,
+    DictEnd,
+}
+
+/// 数组和字典的最大嵌套层数，超过后不再深入，防止恶意内容流耗尽栈空间
+const MAX_NESTING: usize = 32;
+
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// 当前所在数组和字典的嵌套层数
+    depth: usize,
+}
+
+fn is_whitespace(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(c: u8) -> bool {
+    matches!(c, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if is_whitespace(c) {
+                self.pos += 1;
+            } else if c == b'%' {
+                while let Some(c) = self.peek() {
+                    if c == b'\r' || c == b'\n' {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_whitespace();
+        let mut c = self.peek()?;
+
+        // 跳过孤立的 ')'、'>'、'{'、'}' 等
+        while matches!(c, b')' | b'{' | b'}') || (c == b'>' && self.data.get(self.pos + 1) != Some(&b'>')) {
+            self.pos += 1;
+            self.skip_whitespace();
+            c = self.peek()?;
+        }
+
+        match c {
+            b'(' => {
+                self.pos += 1;
+                Some(Token::Object(Object::String(self.read_literal_string(), StringFormat::Literal)))
+            }
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                Some(Token::Object(Object::Dictionary(self.nested(Self::read_dictionary))))
+            }
+            b'<' => {
+                self.pos += 1;
+                Some(Token::Object(Object::String(self.read_hex_string(), StringFormat::Hexadecimal)))
+            }
+            b'>' if self.data.get(self.pos + 1) == Some(&b'>') => {
+                self.pos += 2;
+                Some(Token::DictEnd)
+            }
+            b'[' => {
+                self.pos += 1;
+                Some(Token::Object(Object::Array(self.nested(Self::read_array))))
+            }
+            b']' => {
+                self.pos += 1;
+                Some(Token::ArrayEnd)
+            }
+            b'/' => {
+                self.pos += 1;
+                Some(Token::Object(Object::Name(self.read_name())))
+            }
+            _ => {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if is_whitespace(c) || is_delimiter(c) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                let word = &self.data[start..self.pos];
+                Some(parse_word(word))
+            }
+        }
+    }
+
+    fn read_literal_string(&mut self) -> Vec<u8> {
+        let mut result = Vec::new();
+        let mut depth = 1;
+
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                b'(' => {
+                    depth += 1;
+                    result.push(c);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    result.push(c);
+                }
+                b'\\' => {
+                    let Some(escaped) = self.peek() else { break };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => result.push(b'\n'),
+                        b'r' => result.push(b'\r'),
+                        b't' => result.push(b'\t'),
+                        b'b' => result.push(0x08),
+                        b'f' => result.push(0x0c),
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            result.push(value as u8);
+                        }
+                        other => result.push(other),
+                    }
+                }
+                _ => result.push(c),
+            }
+        }
+
+        result
+    }
+
+    fn read_hex_string(&mut self) -> Vec<u8> {
+        let mut digits = Vec::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == b'>' {
+                break;
+            }
+            if let Some(d) = (c as char).to_digit(16) {
+                digits.push(d as u8);
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect()
+    }
+
+    fn read_name(&mut self) -> Vec<u8> {
+        let mut name = Vec::new();
+        while let Some(c) = self.peek() {
+            if is_whitespace(c) || is_delimiter(c) {
+                break;
+            }
+            self.pos += 1;
+            if c == b'#' {
+                let hex = self.data.get(self.pos..self.pos + 2)
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                if let Some(value) = hex {
+                    name.push(value);
+                    self.pos += 2;
+                    continue;
+                }
+            }
+            name.push(c);
+        }
+        name
+    }
+
+    /// 读取嵌套的数组或字典；超过最大嵌套层数时返回空对象，其中的内容按当前层继续解析
+    fn nested<T: Default>(&mut self, read: fn(&mut Self) -> T) -> T {
+        if self.depth >= MAX_NESTING {
+            return T::default();
+        }
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn read_array(&mut self) -> Vec<Object> {
+        let mut items = Vec::new();
+        while let Some(token) = self.next_token() {
+            match token {
+                Token::Object(obj) => items.push(obj),
+                Token::Keyword(word) => items.push(Object::Name(word)),
+                Token::ArrayEnd => break,
+                Token::DictEnd => {}
+            }
+        }
+        items
+    }
+
+    fn read_dictionary(&mut self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        let mut key: Option<Vec<u8>> = None;
+        while let Some(token) = self.next_token() {
+            let value = match token {
+                Token::DictEnd => break,
+                Token::ArrayEnd => continue,
+                Token::Object(obj) => obj,
+                Token::Keyword(word) => Object::Name(word),
+            };
+            match key.take() {
+                None => match value {
+                    Object::Name(name) => key = Some(name),
+                    _ => continue,
+                },
+                Some(k) => dict.set(k, value),
+            }
+        }
+        dict
+    }
+
+    /// 读取内联图片：BI 之后的键值对、ID 与 EI 之间的数据
+    fn read_inline_image(&mut self) -> Option<(Dictionary, Vec<u8>)> {
+        let mut dict = Dictionary::new();
+        loop {
+            match self.next_token()? {
+                Token::Keyword(word) if word == b"ID" => break,
+                Token::Object(Object::Name(key)) => {
+                    let value = match self.next_token()? {
+                        Token::Object(obj) => obj,
+                        Token::Keyword(word) => Object::Name(word),
+                        _ => continue,
+                    };
+                    dict.set(key, value);
+                }
+                _ => {}
+            }
+        }
+
+        // ID 之后紧跟一个空白字符
+        if self.peek().is_some_and(is_whitespace) {
+            self.pos += 1;
+        }
+        let start = self.pos;
+
+        // 数据以空白 + "EI" + 空白/结尾 结束
+        let mut end = start;
+        while end + 2 <= self.data.len() {
+            let at_ei = &self.data[end..end + 2] == b"EI"
+                && (end == start || is_whitespace(self.data[end - 1]))
+                && self.data.get(end + 2).is_none_or(|&c| is_whitespace(c) || is_delimiter(c));
+            if at_ei {
+                let mut data_end = end;
+                if data_end > start && is_whitespace(self.data[data_end - 1]) {
+                    data_end -= 1;
+                }
+                self.pos = end + 2;
+                return Some((dict, self.data[start..data_end].to_vec()));
+            }
+            end += 1;
+        }
+
+        self.pos = self.data.len();
+        None
+    }
+}
+
+fn parse_word(word: &[u8]) -> Token {
+    match word {
+        b"true" => return Token::Object(Object::Boolean(true)),
+        b"false" => return Token::Object(Object::Boolean(false)),
+        b"null" => return Token::Object(Object::Null),
+        _ => {}
+    }
+
+    let is_numeric = !word.is_empty()
+        && word.iter().all(|c| c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.'))
+        && word.iter().any(|c| c.is_ascii_digit());
+    if is_numeric {
+        let text = String::from_utf8_lossy(word);
+        if !word.contains(&b'.') {
+            if let Ok(i) = text.parse::<i64>() {
+                return Token::Object(Object::Integer(i));
+            }
+        }
+        if let Ok(r) = text.parse::<f64>() {
+            return Token::Object(Object::Real(r as f32));
+        }
+    }
+
+    Token::Keyword(word.to_vec())
+}
+
+/// 将操作列表编码为内容流，"BI" 操作还原为 BI/ID/EI 内联图片
+pub(crate) fn encode_content(operations: &[Operation]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for op in operations {
+        if let (Some(Object::Dictionary(dict)), Some(Object::String(data, _)), "BI") = (op.operands.first(), op.operands.get(1), op.operator.as_str()) {
+            let entries = dict.iter()
+                .flat_map(|(key, value)| [Object::Name(key.clone()), value.clone()])
+                .collect();
+            buffer.extend_from_slice(b"BI\n");
+            buffer.extend(Content { operations: vec![Operation::new("ID", entries)] }.encode().unwrap_or_default());
+            buffer.push(b' ');
+            buffer.extend_from_slice(data);
+            buffer.extend_from_slice(b"\nEI\n");
+            continue;
+        }
+        buffer.extend(Content { operations: vec![op.clone()] }.encode().unwrap_or_default());
+        buffer.push(b'\n');
+    }
+    buffer
+}
+
+/// 读取页面所有内容流并解析
+pub(crate) fn page_operations(doc: &Document, page_id: ObjectId) -> Vec<Operation> {
+    doc.get_page_content(page_id)
+        .map(|content| parse_content(&content))
+        .unwrap_or_default()
+}
+
+// ---------------------------------------------------------------------------
+// 绘制区域扫描
+// ---------------------------------------------------------------------------
+
+/// 字体的字宽信息，用于估算文字区域
+pub(crate) struct FontMetrics {
+    pub(crate) two_byte: bool,
+    first_char: u32,
+    widths: Vec<f64>,
+    cid_widths: HashMap<u32, f64>,
+    default_width: f64,
+}
+
+impl FontMetrics {
+    pub(crate) fn from_dict(doc: &Document, font: &Dictionary) -> FontMetrics {
+        let deref = |obj: &Object| doc.dereference(obj).map(|(_, o)| o.clone()).ok();
+        let subtype = font.get(b"Subtype").and_then(Object::as_name).unwrap_or(b"");
+
+        if subtype == b"Type0" {
+            let mut metrics = FontMetrics {
+                two_byte: true,
+                first_char: 0,
+                widths: Vec::new(),
+                cid_widths: HashMap::new(),
+                default_width: 1000.0,
+            };
+            let descendant = font.get(b"DescendantFonts").ok()
+                .and_then(deref)
+                .and_then(|d| d.as_array().ok().and_then(|a| a.first().cloned()))
+                .and_then(|d| deref(&d))
+                .and_then(|d| d.as_dict().ok().cloned());
+            if let Some(descendant) = descendant {
+                if let Some(dw) = descendant.get(b"DW").ok().and_then(object_to_f64) {
+                    metrics.default_width = dw;
+                }
+                let w = descendant.get(b"W").ok().and_then(deref);
+                if let Some(Object::Array(w)) = w {
+                    let mut i = 0;
+                    while i + 1 < w.len() {
+                        let Some(first) = object_to_f64(&w[i]) else { break };
+                        match deref(&w[i + 1]) {
+                            Some(Object::Array(list)) => {
+                                for (offset, width) in list.iter().enumerate() {
+                                    if let Some(width) = object_to_f64(width) {
+                                        metrics.cid_widths.insert(first as u32 + offset as u32, width);
+                                    }
+                                }
+                                i += 2;
+                            }
+                            Some(last) => {
+                                let (Some(last), Some(width)) = (object_to_f64(&last), w.get(i + 2).and_then(object_to_f64)) else { break };
+                                for cid in first as u32..=last as u32 {
+                                    metrics.cid_widths.insert(cid, width);
+                                }
+                                i += 3;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+            }
+            return metrics;
+        }
+
+        let base_font = font.get(b"BaseFont").and_then(Object::as_name).unwrap_or(b"");
+        let fallback = if base_font.starts_with(b"Courier") { 600.0 } else { 500.0 };
+        let missing_width = font.get(b"FontDescriptor").ok()
+            .and_then(deref)
+            .and_then(|d| d.as_dict().ok().and_then(|d| d.get(b"MissingWidth").ok().and_then(object_to_f64)));
+
+        let widths: Option<Vec<f64>> = font.get(b"Widths").ok()
+            .and_then(deref)
+            .and_then(|w| w.as_array().ok().map(|w| w.iter().map(|v| object_to_f64(v).unwrap_or(0.0)).collect()));
+        // 未给出字宽的标准字体使用内置字宽
+        let standard = std::str::from_utf8(base_font).ok().and_then(|name| StandardFont::parse(name).ok());
+        if let (None, Some(standard)) = (&widths, standard) {
+            return FontMetrics {
+                two_byte: false,
+                first_char: 0,
+                widths: (0..=255u8).map(|code| standard.char_width(code) as f64).collect(),
+                cid_widths: HashMap::new(),
+                default_width: fallback,
+            };
+        }
+
+        FontMetrics {
+            two_byte: false,
+            first_char: font.get(b"FirstChar").ok().and_then(object_to_f64).unwrap_or(0.0) as u32,
+            widths: widths.unwrap_or_default(),
+            cid_widths: HashMap::new(),
+            default_width: missing_width.filter(|w| *w > 0.0).unwrap_or(fallback),
+        }
+    }
+
+    pub(crate) fn default_metrics() -> FontMetrics {
+        FontMetrics {
+            two_byte: false,
+            first_char: 0,
+            widths: Vec::new(),
+            cid_widths: HashMap::new(),
+            default_width: 500.0,
+        }
+    }
+
+    /// 拆分字符串为字符编码
+    pub(crate) fn codes(&self, bytes: &[u8]) -> Vec<u32> {
+        if self.two_byte {
+            bytes.chunks(2).map(|c| ((c[0] as u32) << 8) | *c.get(1).unwrap_or(&0) as u32).collect()
+        } else {
+            bytes.iter().map(|&b| b as u32).collect()
+        }
+    }
+
+    /// 字宽（千分之一文字空间单位）
+    pub(crate) fn width(&self, code: u32) -> f64 {
+        if self.two_byte {
+            return self.cid_widths.get(&code).copied().unwrap_or(self.default_width);
+        }
+        code.checked_sub(self.first_char)
+            .and_then(|i| self.widths.get(i as usize))
+            .copied()
+            .filter(|w| *w > 0.0)
+            .unwrap_or(self.default_width)
+    }
+}
+
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Matrix,
+    clip: Rect,
+    line_width: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    horizontal_scaling: f64,
+    leading: f64,
+    font: Option<Rc<FontMetrics>>,
+    font_size: f64,
+    render_mode: i64,
+    rise: f64,
+}
+
+/// 逐个操作扫描内容流，报告每次绘制在页面（默认用户空间）上覆盖的区域
+pub(crate) struct ContentScanner<'a> {
+    doc: &'a Document,
+    /// 按字体对象缓存；不同资源字典中的同名字体可能是不同的字体
+    fonts: HashMap<ObjectId, Rc<FontMetrics>>,
+    default_font: Rc<FontMetrics>,
+}
+
+impl<'a> ContentScanner<'a> {
+    pub(crate) fn new(doc: &'a Document) -> Self {
+        ContentScanner { doc, fonts: HashMap::new(), default_font: Rc::new(FontMetrics::default_metrics()) }
+    }
+
+    /// 扫描整页内容，初始裁剪区域为页面 MediaBox
+    pub(crate) fn scan_page(&mut self, page_id: ObjectId, callback: &mut dyn FnMut(Rect)) {
+        let media_box = get_inherited_attribute(self.doc, page_id, b"MediaBox")
+            .and_then(|b| self.doc.dereference(b).ok())
+            .and_then(|(_, b)| b.as_array().ok().and_then(|b| parse_rect(b)))
+            .unwrap_or([0.0, 0.0, 612.0, 792.0]);
+        let resources = get_inherited_attribute(self.doc, page_id, b"Resources")
+            .and_then(|r| self.doc.dereference(r).ok())
+            .and_then(|(_, r)| r.as_dict().ok().cloned())
+            .unwrap_or_default();
+        let operations = page_operations(self.doc, page_id);
+
+        self.scan(&operations, &resources, Matrix::IDENTITY, media_box, 0, callback);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn scan(
+        &mut self,
+        operations: &[Operation],
+        resources: &Dictionary,
+        base_ctm: Matrix,
+        clip: Rect,
+        depth: usize,
+        callback: &mut dyn FnMut(Rect),
+    ) {
+        let mut state = GraphicsState {
+            ctm: base_ctm,
+            clip,
+            line_width: 1.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            font: None,
+            font_size: 0.0,
+            render_mode: 0,
+            rise: 0.0,
+        };
+        let mut stack: Vec<GraphicsState> = Vec::new();
+        let mut path: Vec<(f64, f64)> = Vec::new();
+        let mut pending_clip = false;
+        let mut text_matrix = Matrix::IDENTITY;
+        let mut line_matrix = Matrix::IDENTITY;
+
+        for op in operations {
+            let nums: Vec<f64> = op.operands.iter().filter_map(object_to_f64).collect();
+            let mut emit = |bounds: Rect, clip: &Rect| {
+                if let Some(bounds) = intersect_rect(&bounds, clip) {
+                    callback(bounds);
+                }
+            };
+
+            match op.operator.as_str() {
+                "q" => stack.push(state.clone()),
+                "Q" => {
+                    if let Some(saved) = stack.pop() {
+                        state = saved;
+                    }
+                }
+                "cm" => {
+                    if let Some(m) = Matrix::from_objects(&op.operands) {
+                        state.ctm = m.then(&state.ctm);
+                    }
+                }
+                "w" => {
+                    if let Some(&w) = nums.first() {
+                        state.line_width = w;
+                    }
+                }
+
+                // 路径构造
+                "m" | "l" if nums.len() >= 2 => path.push(state.ctm.apply(nums[0], nums[1])),
+                "c" if nums.len() >= 6 => {
+                    for i in 0..3 {
+                        path.push(state.ctm.apply(nums[i * 2], nums[i * 2 + 1]));
+                    }
+                }
+                "v" | "y" if nums.len() >= 4 => {
+                    path.push(state.ctm.apply(nums[0], nums[1]));
+                    path.push(state.ctm.apply(nums[2], nums[3]));
+                }
+                "re" if nums.len() >= 4 => {
+                    let (x, y, w, h) = (nums[0], nums[1], nums[2], nums[3]);
+                    for (px, py) in [(x, y), (x + w, y), (x, y + h), (x + w, y + h)] {
+                        path.push(state.ctm.apply(px, py));
+                    }
+                }
+                "W" | "W*" => pending_clip = true,
+
+                // 路径绘制
+                "S" | "s" | "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" | "n" => {
+                    if let Some(bounds) = bounding_rect(path.iter().copied()) {
+                        if op.operator != "n" {
+                            let strokes = !matches!(op.operator.as_str(), "f" | "F" | "f*");
+                            let half = if strokes { state.line_width.max(1.0) * state.ctm.scale_factor() / 2.0 } else { 0.0 };
+                            let bounds = [bounds[0] - half, bounds[1] - half, bounds[2] + half, bounds[3] + half];
+                            emit(bounds, &state.clip);
+                        }
+                        if pending_clip {
+                            // 裁剪区域为空时用零面积矩形表示
+                            state.clip = intersect_rect(&state.clip, &bounds)
+                                .unwrap_or([bounds[0], bounds[1], bounds[0], bounds[1]]);
+                        }
+                    }
+                    path.clear();
+                    pending_clip = false;
+                }
+                "sh" => emit(state.clip, &state.clip),
+
+                // 文字
+                "BT" => {
+                    text_matrix = Matrix::IDENTITY;
+                    line_matrix = Matrix::IDENTITY;
+                }
+                "Tc" => state.char_spacing = nums.first().copied().unwrap_or(0.0),
+                "Tw" => state.word_spacing = nums.first().copied().unwrap_or(0.0),
+                "Tz" => state.horizontal_scaling = nums.first().copied().unwrap_or(100.0) / 100.0,
+                "TL" => state.leading = nums.first().copied().unwrap_or(0.0),
+                "Ts" => state.rise = nums.first().copied().unwrap_or(0.0),
+                "Tr" => state.render_mode = nums.first().copied().unwrap_or(0.0) as i64,
+                "Tf" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        state.font = Some(self.load_font(resources, name));
+                    }
+                    state.font_size = nums.last().copied().unwrap_or(0.0);
+                }
+                "Td" | "TD" if nums.len() >= 2 => {
+                    if op.operator == "TD" {
+                        state.leading = -nums[1];
+                    }
+                    line_matrix = Matrix::translate(nums[0], nums[1]).then(&line_matrix);
+                    text_matrix = line_matrix;
+                }
+                "Tm" => {
+                    if let Some(m) = Matrix::from_objects(&op.operands) {
+                        line_matrix = m;
+                        text_matrix = m;
+                    }
+                }
+                "T*" => {
+                    line_matrix = Matrix::translate(0.0, -state.leading).then(&line_matrix);
+                    text_matrix = line_matrix;
+                }
+                "Tj" | "'" | "\"" | "TJ" => {
+                    if op.operator == "'" || op.operator == "\"" {
+                        if op.operator == "\"" && nums.len() >= 2 {
+                            state.word_spacing = nums[0];
+                            state.char_spacing = nums[1];
+                        }
+                        line_matrix = Matrix::translate(0.0, -state.leading).then(&line_matrix);
+                        text_matrix = line_matrix;
+                    }
+                    let items: Vec<Object> = match op.operands.last() {
+                        Some(Object::Array(items)) if op.operator == "TJ" => items.clone(),
+                        Some(s @ Object::String(..)) => vec![s.clone()],
+                        _ => continue,
+                    };
+                    if let Some(bounds) = self.text_bounds(&items, &state, &mut text_matrix) {
+                        // 渲染模式 3 为不可见文字（如 OCR 文字层）
+                        if state.render_mode != 3 {
+                            emit(bounds, &state.clip);
+                        }
+                    }
+                }
+
+                // 图片与表单
+                "BI" => emit(state.ctm.apply_rect(&[0.0, 0.0, 1.0, 1.0]), &state.clip),
+                "Do" => {
+                    let Some(Object::Name(name)) = op.operands.first() else { continue };
+                    let xobject = resources.get(b"XObject").ok()
+                        .and_then(|x| self.doc.dereference(x).ok())
+                        .and_then(|(_, x)| x.as_dict().ok())
+                        .and_then(|x| x.get(name).ok())
+                        .and_then(|x| self.doc.dereference(x).ok())
+                        .and_then(|(_, x)| x.as_stream().ok());
+                    let Some(xobject) = xobject else { continue };
+
+                    match xobject.dict.get(b"Subtype").and_then(Object::as_name) {
+                        Ok(b"Image") => emit(state.ctm.apply_rect(&[0.0, 0.0, 1.0, 1.0]), &state.clip),
+                        Ok(b"Form") if depth < 8 => {
+                            let matrix = xobject.dict.get(b"Matrix").ok()
+                                .and_then(|m| m.as_array().ok())
+                                .and_then(|m| Matrix::from_objects(m))
+                                .unwrap_or(Matrix::IDENTITY);
+                            let form_ctm = matrix.then(&state.ctm);
+                            let mut form_clip = state.clip;
+                            let bbox = xobject.dict.get(b"BBox").ok()
+                                .and_then(|b| b.as_array().ok())
+                                .and_then(|b| parse_rect(b));
+                            if let Some(bbox) = bbox {
+                                match intersect_rect(&form_clip, &form_ctm.apply_rect(&bbox)) {
+                                    Some(c) => form_clip = c,
+                                    None => continue,
+                                }
+                            }
+                            let form_resources = xobject.dict.get(b"Resources").ok()
+                                .and_then(|r| self.doc.dereference(r).ok())
+                                .and_then(|(_, r)| r.as_dict().ok().cloned())
+                                .unwrap_or_else(|| resources.clone());
+                            let content = xobject.decompressed_content().unwrap_or_else(|_| xobject.content.clone());
+                            let form_ops = parse_content(&content);
+                            self.scan(&form_ops, &form_resources, form_ctm, form_clip, depth + 1, callback);
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn load_font(&mut self, resources: &Dictionary, name: &[u8]) -> Rc<FontMetrics> {
+        let doc = self.doc;
+        let entry = resources.get(b"Font").ok()
+            .and_then(|f| doc.dereference(f).ok())
+            .and_then(|(_, f)| f.as_dict().ok())
+            .and_then(|f| f.get(name).ok());
+        match entry {
+            Some(Object::Reference(id)) => {
+                if let Some(font) = self.fonts.get(id) {
+                    return font.clone();
+                }
+                let font = match doc.get_dictionary(*id) {
+                    Ok(dict) => Rc::new(FontMetrics::from_dict(doc, dict)),
+                    Err(_) => self.default_font.clone(),
+                };
+                self.fonts.insert(*id, font.clone());
+                font
+            }
+            Some(Object::Dictionary(dict)) => Rc::new(FontMetrics::from_dict(doc, dict)),
+            _ => self.default_font.clone(),
+        }
+    }
+
+    /// 计算一次文字绘制的区域，并推进文字矩阵
+    fn text_bounds(&self, items: &[Object], state: &GraphicsState, text_matrix: &mut Matrix) -> Option<Rect> {
+        let metrics = state.font.as_deref().unwrap_or(&self.default_font);
+        let size = state.font_size;
+        let scaling = state.horizontal_scaling;
+
+        let mut x = 0.0;
+        let mut bounds: Option<Rect> = None;
+        let start_matrix = *text_matrix;
+
+        for item in items {
+            match item {
+                Object::String(bytes, _) => {
+                    for code in metrics.codes(bytes) {
+                        let glyph_width = metrics.width(code) / 1000.0 * size * scaling;
+                        let glyph = [x, state.rise - 0.25 * size, x + glyph_width, state.rise + 0.85 * size];
+                        let glyph = start_matrix.then(&state.ctm).apply_rect(&glyph);
+                        bounds = Some(bounds.map_or(glyph, |b| union_rect(&b, &glyph)));
+
+                        let mut advance = metrics.width(code) / 1000.0 * size + state.char_spacing;
+                        if !metrics.two_byte && code == 32 {
+                            advance += state.word_spacing;
+                        }
+                        x += advance * scaling;
+                    }
+                }
+                other => {
+                    if let Some(adjust) = object_to_f64(other) {
+                        x -= adjust / 1000.0 * size * scaling;
+                    }
+                }
+            }
+        }
+
+        *text_matrix = Matrix::translate(x, 0.0).then(&start_matrix);
+        bounds
+    }
+}
+
+/// 计算页面上所有可见内容的外接矩形
+pub(crate) fn page_content_bounds(doc: &Document, page_id: ObjectId) -> Option<Rect> {
+    let mut bounds: Option<Rect> = None;
+    let mut scanner = ContentScanner::new(doc);
+    scanner.scan_page(page_id, &mut |rect| {
+        bounds = Some(bounds.map_or(rect, |b| union_rect(&b, &rect)));
+    });
+    bounds
+}
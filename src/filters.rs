@@ -0,0 +1,286 @@
+// PDF 流过滤器解码
+// lopdf 只支持 FlateDecode/LZWDecode，且不解码图片流；这里补充 ASCIIHex、ASCII85、RunLength
+// 以及预测器，图片编码（DCT、CCITT 等）原样返回交给调用方处理
+
+use flate2::read::ZlibDecoder;
+use lopdf::{Dictionary, Document, Object, Stream};
+use std::io::Read;
+
+use crate::render_color::resolve;
+
+/// 单个流解码后的最大长度，防止压缩炸弹耗尽 wasm 内存
+const MAX_DECODED_SIZE: usize = 256 << 20;
+/// 预测器参数中允许的最大列数
+const MAX_PREDICTOR_COLUMNS: i64 = 1 << 16;
+
+/// 图片专用的编码，只能出现在过滤器链的最后
+pub(crate) enum ImageCodec {
+    Dct,
+    CcittFax(Dictionary),
+    Jpx,
+    Jbig2,
+}
+
+/// 解码后的流数据
+pub(crate) struct DecodedStream {
+    pub data: Vec<u8>,
+    /// 数据仍使用的图片编码
+    pub codec: Option<ImageCodec>,
+}
+
+/// 依次应用流的所有过滤器
+pub(crate) fn decode_stream(doc: &Document, stream: &Stream) -> Result<DecodedStream, String> {
+    let dict = &stream.dict;
+    let filters = filter_list(doc, dict, b"Filter", b"F");
+    let params = param_list(doc, dict, filters.len());
+
+    let mut data = stream.content.clone();
+    for (index, filter) in filters.iter().enumerate() {
+        let params = params.get(index).and_then(Option::as_ref);
+        let codec = match filter.as_slice() {
+            b"FlateDecode" | b"Fl" => {
+                data = apply_predictor(inflate(&data)?, params);
+                None
+            }
+            b"LZWDecode" | b"LZW" => {
+                let early_change = params
+                    .and_then(|p| p.get(b"EarlyChange").ok())
+                    .and_then(|v| v.as_i64().ok())
+                    .unwrap_or(1);
+                data = apply_predictor(lzw_decode(&data, early_change != 0)?, params);
+                None
+            }
+            b"ASCIIHexDecode" | b"AHx" => {
+                data = ascii_hex_decode(&data);
+                None
+            }
+            b"ASCII85Decode" | b"A85" => {
+                data = ascii85_decode(&data);
+                None
+            }
+            b"RunLengthDecode" | b"RL" => {
+                data = run_length_decode(&data);
+                None
+            }
+            b"Crypt" => None,
+            b"DCTDecode" | b"DCT" => Some(ImageCodec::Dct),
+            b"CCITTFaxDecode" | b"CCF" => Some(ImageCodec::CcittFax(params.cloned().unwrap_or_default())),
+            b"JPXDecode" => Some(ImageCodec::Jpx),
+            b"JBIG2Decode" => Some(ImageCodec::Jbig2),
+            other => return Err(format!("不支持的过滤器: {}", String::from_utf8_lossy(other))),
+        };
+
+        if codec.is_some() {
+            if index + 1 != filters.len() {
+                return Err("图片编码必须是最后一个过滤器".to_string());
+            }
+            return Ok(DecodedStream { data, codec });
+        }
+    }
+
+    Ok(DecodedStream { data, codec: None })
+}
+
+/// 读取过滤器名称列表（支持单个名称或数组，以及内联图片的缩写键）
+pub(crate) fn filter_list(doc: &Document, dict: &Dictionary, key: &[u8], short_key: &[u8]) -> Vec<Vec<u8>> {
+    let value = dict.get(key).or_else(|_| dict.get(short_key)).ok();
+    match value.map(|v| resolve(doc, v)) {
+        Some(Object::Name(name)) => vec![name.clone()],
+        Some(Object::Array(names)) => names
+            .iter()
+            .filter_map(|n| doc.dereference(n).ok().and_then(|(_, n)| n.as_name().ok()).map(|n| n.to_vec()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn param_list(doc: &Document, dict: &Dictionary, count: usize) -> Vec<Option<Dictionary>> {
+    let value = dict.get(b"DecodeParms").or_else(|_| dict.get(b"DP")).ok();
+    let as_dict = |obj: &Object| doc.dereference(obj).ok().and_then(|(_, o)| o.as_dict().ok().cloned());
+    match value.map(|v| resolve(doc, v)) {
+        Some(Object::Array(items)) => items.iter().map(as_dict).collect(),
+        Some(obj) => {
+            let mut params = vec![as_dict(obj)];
+            params.resize(count.max(1), None);
+            params
+        }
+        None => Vec::new(),
+    }
+}
+
+fn too_large() -> String {
+    format!("解码后的流数据超过 {} MB", MAX_DECODED_SIZE >> 20)
+}
+
+/// zlib 解压，数据损坏时保留已解出的部分
+fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(data.len().saturating_mul(2).min(MAX_DECODED_SIZE));
+    let _ = ZlibDecoder::new(data).take(MAX_DECODED_SIZE as u64 + 1).read_to_end(&mut output);
+    if output.len() > MAX_DECODED_SIZE {
+        return Err(too_large());
+    }
+    Ok(output)
+}
+
+/// LZW 解压，数据损坏时保留已解出的部分
+fn lzw_decode(data: &[u8], early_change: bool) -> Result<Vec<u8>, String> {
+    let mut decoder = if early_change {
+        weezl::decode::Decoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8)
+    } else {
+        weezl::decode::Decoder::new(weezl::BitOrder::Msb, 8)
+    };
+    let mut output = Vec::new();
+    let mut buffer = [0u8; 8192];
+    let mut input = data;
+    loop {
+        let result = decoder.decode_bytes(input, &mut buffer);
+        input = &input[result.consumed_in..];
+        output.extend_from_slice(&buffer[..result.consumed_out]);
+        if output.len() > MAX_DECODED_SIZE {
+            return Err(too_large());
+        }
+        match result.status {
+            Ok(weezl::LzwStatus::Ok) if result.consumed_in + result.consumed_out > 0 => {}
+            _ => break,
+        }
+    }
+    Ok(output)
+}
+
+fn ascii_hex_decode(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data
+        .iter()
+        .take_while(|&&c| c != b'>')
+        .filter_map(|&c| (c as char).to_digit(16).map(|d| d as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+fn ascii85_decode(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() * 4 / 5);
+    let mut group = [0u8; 5];
+    let mut count = 0;
+
+    let data = data.strip_prefix(b"<~").unwrap_or(data);
+    for &c in data {
+        match c {
+            b'~' => break,
+            b'z' if count == 0 => output.extend_from_slice(&[0, 0, 0, 0]),
+            b'!'..=b'u' => {
+                group[count] = c - b'!';
+                count += 1;
+                if count == 5 {
+                    let value = group.iter().fold(0u32, |acc, &d| acc.wrapping_mul(85).wrapping_add(d as u32));
+                    output.extend_from_slice(&value.to_be_bytes());
+                    count = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 末尾不足 5 个字符的分组用 'u' 补齐
+    if count > 1 {
+        for digit in group.iter_mut().skip(count) {
+            *digit = 84;
+        }
+        let value = group.iter().fold(0u32, |acc, &d| acc.wrapping_mul(85).wrapping_add(d as u32));
+        output.extend_from_slice(&value.to_be_bytes()[..count - 1]);
+    }
+
+    output
+}
+
+fn run_length_decode(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let length = data[pos] as usize;
+        pos += 1;
+        match length {
+            0..=127 => {
+                let end = (pos + length + 1).min(data.len());
+                output.extend_from_slice(&data[pos..end]);
+                pos = end;
+            }
+            128 => break,
+            _ => {
+                if let Some(&byte) = data.get(pos) {
+                    output.extend(std::iter::repeat_n(byte, 257 - length));
+                }
+                pos += 1;
+            }
+        }
+    }
+    output
+}
+
+/// 应用 TIFF（2）或 PNG（10-15）预测器；参数无效时原样返回数据
+fn apply_predictor(data: Vec<u8>, params: Option<&Dictionary>) -> Vec<u8> {
+    let Some(params) = params else { return data };
+    let get = |key: &[u8], default: i64| params.get(key).and_then(Object::as_i64).unwrap_or(default);
+
+    let predictor = get(b"Predictor", 1);
+    if predictor < 2 {
+        return data;
+    }
+    let (colors, bits, columns) = (get(b"Colors", 1), get(b"BitsPerComponent", 8), get(b"Columns", 1));
+    if !(1..=32).contains(&colors) || ![1, 2, 4, 8, 16].contains(&bits) || !(1..=MAX_PREDICTOR_COLUMNS).contains(&columns) {
+        return data;
+    }
+    let (colors, bits, columns) = (colors as usize, bits as usize, columns as usize);
+    let bytes_per_pixel = (colors * bits).div_ceil(8);
+    let Some(row_len) = (colors * bits).checked_mul(columns).map(|bits| bits.div_ceil(8)).filter(|&len| len > 0) else {
+        return data;
+    };
+
+    if predictor == 2 {
+        let mut data = data;
+        if bits == 8 {
+            for row in data.chunks_mut(row_len) {
+                for i in bytes_per_pixel..row.len() {
+                    row[i] = row[i].wrapping_add(row[i - bytes_per_pixel]);
+                }
+            }
+        }
+        return data;
+    }
+
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = vec![0u8; row_len];
+    for chunk in data.chunks(row_len + 1) {
+        let filter = chunk[0];
+        let mut row = chunk[1..].to_vec();
+        row.resize(row_len, 0);
+        for i in 0..row_len {
+            let left = if i >= bytes_per_pixel { row[i - bytes_per_pixel] } else { 0 };
+            let up = previous[i];
+            let up_left = if i >= bytes_per_pixel { previous[i - bytes_per_pixel] } else { 0 };
+            row[i] = row[i].wrapping_add(match filter {
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => 0,
+            });
+        }
+        output.extend_from_slice(&row);
+        previous = row;
+    }
+    output
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
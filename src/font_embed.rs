@@ -0,0 +1,475 @@
+// TrueType/OpenType 字体嵌入
+// 解析用户提供的 TTF/OTF 字体，按实际用到的字形生成子集，
+// 以 Type0 字体（Identity-H 编码）和 ToUnicode CMap 写入 PDF：
+// TrueType 轮廓写为 CIDFontType2，CFF 轮廓写为 CIDFontType0
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use ttf_parser::{Face, GlyphId, RawFace, Tag};
+use crate::cff::subset_cff;
+
+/// 子集中保留的字形数据以外的表（字形提示和度量相关）
+const COPIED_TABLES: [&[u8; 4]; 6] = [b"OS/2", b"cvt ", b"fpgm", b"gasp", b"name", b"prep"];
+
+/// 字形轮廓的格式
+#[derive(Clone, Copy, PartialEq)]
+enum Outlines {
+    /// glyf 表，子集中的字形重新编号，CID 即新字形 ID
+    TrueType,
+    /// CFF 表，子集保留原字形编号；CID 字体使用字符集中的 CID，否则 CID 即字形 ID
+    Cff,
+}
+
+/// 可嵌入 PDF 的字体，记录排版过程中用到的字形
+pub(crate) struct EmbeddedFont {
+    data: Vec<u8>,
+    outlines: Outlines,
+    units_per_em: f64,
+    /// 原字体中的字形 ID → 写入 PDF 的 CID
+    glyph_map: HashMap<u16, u16>,
+    /// 用到的原字形 ID；TrueType 字体按 CID 排列
+    glyphs: Vec<u16>,
+    /// CID 对应的文字，用于生成 ToUnicode
+    unicode: HashMap<u16, char>,
+    postscript_name: String,
+    /// 字符 → (原字形 ID, 前进宽度) 的缓存
+    metrics: RefCell<HashMap<char, (u16, u16)>>,
+}
+
+impl EmbeddedFont {
+    pub(crate) fn parse(data: &[u8]) -> Result<EmbeddedFont, String> {
+        let face = Face::parse(data, 0).map_err(|e| format!("无法解析字体: {}", e))?;
+        let raw = face.raw_face();
+        let outlines = if raw.table(Tag::from_bytes(b"glyf")).is_some() && raw.table(Tag::from_bytes(b"loca")).is_some() {
+            Outlines::TrueType
+        } else if face.tables().cff.is_some() {
+            Outlines::Cff
+        } else {
+            return Err("仅支持 TrueType（glyf）或 CFF 轮廓的字体".to_string());
+        };
+        if !face.tables().cmap.is_some_and(|cmap| cmap.subtables.into_iter().any(|s| s.is_unicode())) {
+            return Err("字体缺少 Unicode 字符映射表".to_string());
+        }
+
+        let postscript_name = face
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .map(|name| name.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect::<String>())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "EmbeddedFont".to_string());
+
+        Ok(EmbeddedFont {
+            data: data.to_vec(),
+            outlines,
+            units_per_em: face.units_per_em() as f64,
+            glyph_map: HashMap::from([(0, 0)]),
+            glyphs: vec![0],
+            unicode: HashMap::new(),
+            postscript_name,
+            metrics: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn face(&self) -> Face<'_> {
+        // 数据在 parse 中已校验过
+        Face::parse(&self.data, 0).expect("字体数据已校验")
+    }
+
+    /// 字符对应的原字形 ID 和前进宽度，缺失的字符使用 .notdef
+    fn glyph(&self, c: char) -> (u16, u16) {
+        if let Some(&metrics) = self.metrics.borrow().get(&c) {
+            return metrics;
+        }
+        let face = self.face();
+        let glyph = face.glyph_index(c).unwrap_or_default();
+        let metrics = (glyph.0, face.glyph_hor_advance(glyph).unwrap_or(0));
+        self.metrics.borrow_mut().insert(c, metrics);
+        metrics
+    }
+
+    /// 文本的宽度（点）
+    pub(crate) fn text_width(&self, text: &str, font_size: f64) -> f64 {
+        let units: u32 = text.chars().map(|c| self.glyph(c).1 as u32).sum();
+        units as f64 * font_size / self.units_per_em
+    }
+
+    /// 字体是否包含文本中的所有字符
+    pub(crate) fn covers(&self, text: &str) -> bool {
+        text.chars().all(|c| self.glyph(c).0 != 0)
+    }
+
+    /// 将文本编码为 CID（每个字形 2 字节），并记录用到的字形
+    pub(crate) fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let (glyph, _) = self.glyph(c);
+            let cid = match self.outlines {
+                Outlines::TrueType => add_glyph(&mut self.glyphs, &mut self.glyph_map, glyph),
+                Outlines::Cff => match self.glyph_map.get(&glyph) {
+                    Some(&cid) => cid,
+                    None => {
+                        let cid = self.face().tables().cff.and_then(|cff| cff.glyph_cid(GlyphId(glyph))).unwrap_or(glyph);
+                        self.glyphs.push(glyph);
+                        self.glyph_map.insert(glyph, cid);
+                        cid
+                    }
+                },
+            };
+            if glyph != 0 {
+                self.unicode.entry(cid).or_insert(c);
+            }
+            encoded.extend_from_slice(&cid.to_be_bytes());
+        }
+        encoded
+    }
+
+    /// 将字体子集写入文档，font_id 为 Type0 字体字典使用的对象 ID
+    pub(crate) fn write(&self, doc: &mut Document, font_id: ObjectId) -> Result<(), String> {
+        let face = self.face();
+        let scale = 1000.0 / self.units_per_em;
+        let width = |glyph: u16| {
+            let advance = face.glyph_hor_advance(GlyphId(glyph)).unwrap_or(0);
+            (advance as f64 * scale).round() as i64
+        };
+
+        // 字体文件，以及按 CID 排列的 (CID, 宽度)
+        let (file_key, mut file_stream, glyphs, widths) = match self.outlines {
+            Outlines::TrueType => {
+                let (font_file, glyphs) = self.build_subset()?;
+                let mut file_dict = Dictionary::new();
+                file_dict.set("Length1", Object::Integer(font_file.len() as i64));
+                let widths = glyphs.iter().enumerate().map(|(cid, &glyph)| (cid as u16, width(glyph))).collect();
+                ("FontFile2", Stream::new(file_dict, font_file), glyphs, widths)
+            }
+            Outlines::Cff => {
+                let cff = RawFace::parse(&self.data, 0)
+                    .ok()
+                    .and_then(|raw| raw.table(Tag::from_bytes(b"CFF ")))
+                    .ok_or_else(|| "字体缺少 CFF 表".to_string())?;
+                let keep: HashSet<u16> = self.glyphs.iter().copied().collect();
+                let mut file_dict = Dictionary::new();
+                file_dict.set("Subtype", Object::Name(b"CIDFontType0C".to_vec()));
+                let mut widths: Vec<(u16, i64)> = self.glyphs.iter().map(|&glyph| (self.glyph_map[&glyph], width(glyph))).collect();
+                widths.sort_unstable();
+                ("FontFile3", Stream::new(file_dict, subset_cff(cff, &keep)?), self.glyphs.clone(), widths)
+            }
+        };
+        let base_font = format!("{}+{}", subset_tag(&glyphs), self.postscript_name);
+        let _ = file_stream.compress();
+        let file_id = doc.add_object(file_stream);
+
+        let bbox = face.global_bounding_box();
+        let ascent = face.ascender() as f64 * scale;
+        let mut descriptor = Dictionary::new();
+        descriptor.set("Type", Object::Name(b"FontDescriptor".to_vec()));
+        descriptor.set("FontName", Object::Name(base_font.as_bytes().to_vec()));
+        // 符号字体：字形通过 CID 直接选取，不依赖标准编码
+        descriptor.set("Flags", Object::Integer(4));
+        descriptor.set("FontBBox", Object::Array(
+            [bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max]
+                .iter()
+                .map(|&v| Object::Integer((v as f64 * scale).round() as i64))
+                .collect(),
+        ));
+        descriptor.set("ItalicAngle", Object::Real(face.italic_angle()));
+        descriptor.set("Ascent", Object::Integer(ascent.round() as i64));
+        descriptor.set("Descent", Object::Integer((face.descender() as f64 * scale).round() as i64));
+        descriptor.set("CapHeight", Object::Integer(
+            face.capital_height().map(|h| h as f64 * scale).unwrap_or(ascent).round() as i64,
+        ));
+        descriptor.set("StemV", Object::Integer(80));
+        descriptor.set(file_key, Object::Reference(file_id));
+        let descriptor_id = doc.add_object(descriptor);
+
+        let mut system_info = Dictionary::new();
+        system_info.set("Registry", Object::string_literal("Adobe"));
+        system_info.set("Ordering", Object::string_literal("Identity"));
+        system_info.set("Supplement", Object::Integer(0));
+
+        let mut cid_font = Dictionary::new();
+        cid_font.set("Type", Object::Name(b"Font".to_vec()));
+        let subtype: &[u8] = if self.outlines == Outlines::TrueType { b"CIDFontType2" } else { b"CIDFontType0" };
+        cid_font.set("Subtype", Object::Name(subtype.to_vec()));
+        cid_font.set("BaseFont", Object::Name(base_font.as_bytes().to_vec()));
+        cid_font.set("CIDSystemInfo", Object::Dictionary(system_info));
+        cid_font.set("FontDescriptor", Object::Reference(descriptor_id));
+        cid_font.set("W", Object::Array(width_array(&widths)));
+        if self.outlines == Outlines::TrueType {
+            cid_font.set("CIDToGIDMap", Object::Name(b"Identity".to_vec()));
+        }
+        let cid_font_id = doc.add_object(cid_font);
+
+        let mut to_unicode = Stream::new(Dictionary::new(), self.to_unicode_cmap().into_bytes());
+        let _ = to_unicode.compress();
+        let to_unicode_id = doc.add_object(to_unicode);
+
+        let mut type0 = Dictionary::new();
+        type0.set("Type", Object::Name(b"Font".to_vec()));
+        type0.set("Subtype", Object::Name(b"Type0".to_vec()));
+        type0.set("BaseFont", Object::Name(base_font.into_bytes()));
+        type0.set("Encoding", Object::Name(b"Identity-H".to_vec()));
+        type0.set("DescendantFonts", Object::Array(vec![Object::Reference(cid_font_id)]));
+        type0.set("ToUnicode", Object::Reference(to_unicode_id));
+        doc.objects.insert(font_id, Object::Dictionary(type0));
+        Ok(())
+    }
+
+    /// 生成只包含子集字形的 TrueType 字体文件，字形按新 ID 重新编号
+    /// 复合字形引用的部件追加在子集末尾，返回字体文件和最终的字形列表
+    fn build_subset(&self) -> Result<(Vec<u8>, Vec<u16>), String> {
+        let mut glyphs = self.glyphs.clone();
+        let mut glyph_map = self.glyph_map.clone();
+        let raw = RawFace::parse(&self.data, 0).map_err(|e| format!("无法解析字体: {}", e))?;
+        let table = |tag: &[u8; 4]| raw.table(Tag::from_bytes(tag));
+        let required = |tag: &[u8; 4]| table(tag).ok_or_else(|| format!("字体缺少 {} 表", String::from_utf8_lossy(tag)));
+
+        let head = required(b"head")?;
+        let hhea = required(b"hhea")?;
+        let maxp = required(b"maxp")?;
+        let hmtx = required(b"hmtx")?;
+        let loca = required(b"loca")?;
+        let glyf = required(b"glyf")?;
+        if head.len() < 54 || hhea.len() < 36 || maxp.len() < 6 {
+            return Err("字体表数据不完整".to_string());
+        }
+
+        let long_loca = read_u16(head, 50) != 0;
+        let glyph_count = read_u16(maxp, 4) as usize;
+        let glyph_range = |glyph: u16| -> &[u8] {
+            let glyph = glyph as usize;
+            if glyph >= glyph_count {
+                return &[];
+            }
+            let (start, end) = if long_loca {
+                (read_u32(loca, glyph * 4) as usize, read_u32(loca, glyph * 4 + 4) as usize)
+            } else {
+                (read_u16(loca, glyph * 2) as usize * 2, read_u16(loca, glyph * 2 + 2) as usize * 2)
+            };
+            glyf.get(start..end).unwrap_or(&[])
+        };
+
+        // 复制字形数据，复合字形的部件引用改为新 ID（部件按需加入子集）
+        let mut new_glyf = Vec::new();
+        let mut new_loca = Vec::new();
+        let mut index = 0;
+        while index < glyphs.len() {
+            let mut glyph = glyph_range(glyphs[index]).to_vec();
+            if glyph.len() >= 10 && (read_u16(&glyph, 0) as i16) < 0 {
+                remap_components(&mut glyph, &mut glyphs, &mut glyph_map);
+            }
+            new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+            new_glyf.extend_from_slice(&glyph);
+            while new_glyf.len() % 4 != 0 {
+                new_glyf.push(0);
+            }
+            index += 1;
+        }
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+        // 所有字形都写入完整的度量
+        let metric_count = (read_u16(hhea, 34) as usize).max(1);
+        let mut new_hmtx = Vec::with_capacity(glyphs.len() * 4);
+        for &glyph in &glyphs {
+            let glyph = glyph as usize;
+            let advance = read_u16(hmtx, glyph.min(metric_count - 1) * 4);
+            let lsb = if glyph < metric_count {
+                read_u16(hmtx, glyph * 4 + 2)
+            } else {
+                read_u16(hmtx, metric_count * 4 + (glyph - metric_count) * 2)
+            };
+            new_hmtx.extend_from_slice(&advance.to_be_bytes());
+            new_hmtx.extend_from_slice(&lsb.to_be_bytes());
+        }
+
+        let count = glyphs.len() as u16;
+        let mut new_head = head.to_vec();
+        new_head[8..12].copy_from_slice(&[0; 4]);
+        new_head[50..52].copy_from_slice(&1u16.to_be_bytes());
+        let mut new_hhea = hhea.to_vec();
+        new_hhea[34..36].copy_from_slice(&count.to_be_bytes());
+        let mut new_maxp = maxp.to_vec();
+        new_maxp[4..6].copy_from_slice(&count.to_be_bytes());
+        // post 表第 3 版不含字形名称
+        let mut new_post = table(b"post").filter(|p| p.len() >= 32).map(|p| p[..32].to_vec()).unwrap_or_else(|| vec![0; 32]);
+        new_post[0..4].copy_from_slice(&0x0003_0000u32.to_be_bytes());
+
+        let mut tables: Vec<([u8; 4], Vec<u8>)> = vec![
+            (*b"glyf", new_glyf),
+            (*b"head", new_head),
+            (*b"hhea", new_hhea),
+            (*b"hmtx", new_hmtx),
+            (*b"loca", new_loca),
+            (*b"maxp", new_maxp),
+            (*b"post", new_post),
+        ];
+        for tag in COPIED_TABLES {
+            if let Some(data) = table(tag) {
+                tables.push((*tag, data.to_vec()));
+            }
+        }
+        Ok((write_font_file(tables), glyphs))
+    }
+
+    /// CID → Unicode 的 CMap
+    fn to_unicode_cmap(&self) -> String {
+        let mut entries: Vec<(u16, char)> = self.unicode.iter().map(|(&id, &c)| (id, c)).collect();
+        entries.sort_unstable();
+
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+             1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+        );
+        // 每段 bfchar 最多 100 项
+        for chunk in entries.chunks(100) {
+            cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+            for (id, c) in chunk {
+                let utf16: String = c.encode_utf16(&mut [0; 2]).iter().map(|u| format!("{:04X}", u)).collect();
+                cmap.push_str(&format!("<{:04X}> <{}>\n", id, utf16));
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap
+    }
+}
+
+/// 将字形加入子集，返回子集中的字形 ID
+fn add_glyph(glyphs: &mut Vec<u16>, glyph_map: &mut HashMap<u16, u16>, glyph: u16) -> u16 {
+    *glyph_map.entry(glyph).or_insert_with(|| {
+        glyphs.push(glyph);
+        (glyphs.len() - 1) as u16
+    })
+}
+
+/// 复合字形的部件引用改为子集中的 ID
+fn remap_components(glyph: &mut [u8], glyphs: &mut Vec<u16>, glyph_map: &mut HashMap<u16, u16>) {
+    const ARGS_ARE_WORDS: u16 = 0x0001;
+    const HAVE_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const HAVE_X_AND_Y_SCALE: u16 = 0x0040;
+    const HAVE_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut pos = 10;
+    while pos + 4 <= glyph.len() {
+        let flags = read_u16(glyph, pos);
+        let new_id = add_glyph(glyphs, glyph_map, read_u16(glyph, pos + 2));
+        glyph[pos + 2..pos + 4].copy_from_slice(&new_id.to_be_bytes());
+
+        pos += 4 + if flags & ARGS_ARE_WORDS != 0 { 4 } else { 2 };
+        pos += if flags & HAVE_SCALE != 0 {
+            2
+        } else if flags & HAVE_X_AND_Y_SCALE != 0 {
+            4
+        } else if flags & HAVE_TWO_BY_TWO != 0 {
+            8
+        } else {
+            0
+        };
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+}
+
+/// 按 CID 排列的宽度写为 /W 数组，连续的 CID 合为一组，如 [0 [500 600] 10 [700]]
+fn width_array(widths: &[(u16, i64)]) -> Vec<Object> {
+    let mut array = Vec::new();
+    let mut group: Vec<Object> = Vec::new();
+    let mut next = None;
+    for &(cid, width) in widths {
+        if next != Some(cid) {
+            if !group.is_empty() {
+                array.push(Object::Array(std::mem::take(&mut group)));
+            }
+            array.push(Object::Integer(cid as i64));
+        }
+        group.push(Object::Integer(width));
+        next = cid.checked_add(1);
+    }
+    if !group.is_empty() {
+        array.push(Object::Array(group));
+    }
+    array
+}
+
+/// 编码后的文字写为 PDF 十六进制字符串，如 "<0001002A>"
+pub(crate) fn hex_string(encoded: &[u8]) -> String {
+    let digits: String = encoded.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("<{}>", digits)
+}
+
+/// 子集字体名前缀：由字形集合得出的 6 个大写字母
+fn subset_tag(glyphs: &[u16]) -> String {
+    let mut hash: u32 = 2166136261;
+    for &glyph in glyphs {
+        for byte in glyph.to_be_bytes() {
+            hash = (hash ^ byte as u32).wrapping_mul(16777619);
+        }
+    }
+    (0..6)
+        .map(|_| {
+            let letter = (b'A' + (hash % 26) as u8) as char;
+            hash /= 26;
+            letter
+        })
+        .collect()
+}
+
+/// 按表目录格式组装字体文件，并写入校验和
+fn write_font_file(mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|table| table.0);
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut output = Vec::new();
+    output.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    output.extend_from_slice(&count.to_be_bytes());
+    output.extend_from_slice(&search_range.to_be_bytes());
+    output.extend_from_slice(&entry_selector.to_be_bytes());
+    output.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = None;
+    for (tag, data) in &tables {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        output.extend_from_slice(tag);
+        output.extend_from_slice(&checksum(data).to_be_bytes());
+        output.extend_from_slice(&(offset as u32).to_be_bytes());
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().div_ceil(4) * 4;
+    }
+    for (_, data) in &tables {
+        output.extend_from_slice(data);
+        output.resize(output.len().div_ceil(4) * 4, 0);
+    }
+
+    if let Some(head) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&output));
+        output[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    output
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]])).unwrap_or(0)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]])).unwrap_or(0)
+}
@@ -0,0 +1,493 @@
+// 图片解码与嵌入 PDF
+// 负责把图片文件拆分为帧（页），并以合适的压缩方式写入 PDF 图片 XObject
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageFormat};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::io::{Cursor, Write};
+use tiff::decoder::{ChunkType, Decoder as TiffDecoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::ColorType as TiffColorType;
+
+/// 已嵌入 PDF 的图片 XObject
+pub(crate) struct EmbeddedImage {
+    pub id: ObjectId,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 图片文件中的一帧（GIF 动画帧或 TIFF 页面）
+pub(crate) struct ImageFrame {
+    data: FrameData,
+    /// 图片记录的 DPI
+    pub dpi: Option<(f64, f64)>,
+}
+
+enum FrameData {
+    /// JPEG 数据，原样嵌入（DCTDecode）
+    Jpeg(JpegInfo, Vec<u8>),
+    /// CCITT Group 4 压缩的黑白图像，原样嵌入（CCITTFaxDecode）
+    CcittG4 {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+        black_is_1: bool,
+    },
+    /// 解码后的像素数据，无损压缩嵌入（FlateDecode）
+    Raw(RawImage),
+}
+
+/// 解码后的像素数据，16 位数据为大端序
+struct RawImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    bits: u8,
+    samples: Vec<u8>,
+    alpha: Option<Vec<u8>>,
+}
+
+/// 将图片文件拆分为帧
+/// 多帧 GIF 和多页 TIFF 每帧（页）各返回一项，其他图片只返回一项
+pub(crate) fn decode_frames(image_bytes: &[u8]) -> Result<Vec<ImageFrame>, String> {
+    if let Some(info) = read_jpeg_info(image_bytes) {
+        if info.is_pdf_compatible() {
+            return Ok(vec![ImageFrame {
+                data: FrameData::Jpeg(info, image_bytes.to_vec()),
+                dpi: read_image_dpi(image_bytes),
+            }]);
+        }
+        // 无损、分层、算术编码或 12 位的 JPEG 不能直接作为 DCTDecode 嵌入，解码后重新编码
+        let img = image::load_from_memory_with_format(image_bytes, ImageFormat::Jpeg)
+            .map_err(|e| format!("不支持的 JPEG 编码（SOF{}，{} 位）且无法解码: {}", info.marker - 0xC0, info.bits, e))?;
+        return Ok(vec![ImageFrame {
+            data: FrameData::Raw(raw_from_dynamic(img)),
+            dpi: read_image_dpi(image_bytes),
+        }]);
+    }
+
+    match image::guess_format(image_bytes) {
+        Ok(ImageFormat::Tiff) => decode_tiff_frames(image_bytes),
+        Ok(ImageFormat::Gif) => decode_gif_frames(image_bytes),
+        _ => {
+            let img = image::load_from_memory(image_bytes)
+                .map_err(|e| format!("无法加载图片: {}", e))?;
+            Ok(vec![ImageFrame {
+                data: FrameData::Raw(raw_from_dynamic(img)),
+                dpi: read_image_dpi(image_bytes),
+            }])
+        }
+    }
+}
+
+/// 解码 GIF 的所有帧，每帧都是合成后的完整画面
+fn decode_gif_frames(image_bytes: &[u8]) -> Result<Vec<ImageFrame>, String> {
+    let decoder = GifDecoder::new(Cursor::new(image_bytes))
+        .map_err(|e| format!("无法加载 GIF 图片: {}", e))?;
+    let frames = decoder.into_frames().collect_frames()
+        .map_err(|e| format!("无法解码 GIF 图片: {}", e))?;
+    if frames.is_empty() {
+        return Err("GIF 图片不包含任何帧".to_string());
+    }
+
+    Ok(frames
+        .into_iter()
+        .map(|frame| ImageFrame {
+            data: FrameData::Raw(raw_from_dynamic(DynamicImage::ImageRgba8(frame.into_buffer()))),
+            dpi: None,
+        })
+        .collect())
+}
+
+/// 解码 TIFF 的所有页面
+fn decode_tiff_frames(image_bytes: &[u8]) -> Result<Vec<ImageFrame>, String> {
+    let mut decoder = TiffDecoder::new(Cursor::new(image_bytes))
+        .map_err(|e| format!("无法加载 TIFF 图片: {}", e))?;
+
+    let mut frames = Vec::new();
+    loop {
+        let page = frames.len() + 1;
+        let data = match read_tiff_ccitt_g4(&mut decoder, image_bytes) {
+            Some(data) => data,
+            None => read_tiff_pixels(&mut decoder)
+                .map_err(|e| format!("TIFF 第 {} 页: {}", page, e))?,
+        };
+        frames.push(ImageFrame { data, dpi: read_tiff_dpi(&mut decoder) });
+
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image()
+            .map_err(|e| format!("无法读取 TIFF 第 {} 页: {}", page + 1, e))?;
+    }
+
+    Ok(frames)
+}
+
+/// 当前 TIFF 页面为单条带的 CCITT G4 图像时，直接取出压缩数据
+fn read_tiff_ccitt_g4(decoder: &mut TiffDecoder<Cursor<&[u8]>>, image_bytes: &[u8]) -> Option<FrameData> {
+    let tag = |decoder: &mut TiffDecoder<Cursor<&[u8]>>, tag: Tag| {
+        decoder.find_tag_unsigned::<u32>(tag).ok().flatten()
+    };
+
+    if tag(decoder, Tag::Compression)? != 4 || tag(decoder, Tag::BitsPerSample).unwrap_or(1) != 1 {
+        return None;
+    }
+    // 反向位序和 T6 非压缩模式无法直接交给 CCITTFaxDecode
+    if tag(decoder, Tag::FillOrder).unwrap_or(1) != 1
+        || tag(decoder, Tag::Unknown(293)).unwrap_or(0) & 0x2 != 0
+    {
+        return None;
+    }
+    // 多条带各自独立编码，无法拼接为一个流
+    if decoder.get_chunk_type() != ChunkType::Strip || decoder.strip_count().ok()? != 1 {
+        return None;
+    }
+
+    let offset = decoder.find_tag_unsigned_vec::<u64>(Tag::StripOffsets).ok()??;
+    let length = decoder.find_tag_unsigned_vec::<u64>(Tag::StripByteCounts).ok()??;
+    let start = *offset.first()? as usize;
+    let data = image_bytes.get(start..start.checked_add(*length.first()? as usize)?)?;
+    let (width, height) = decoder.dimensions().ok()?;
+
+    Some(FrameData::CcittG4 {
+        width,
+        height,
+        data: data.to_vec(),
+        // TIFF 默认 0 为白色（WhiteIsZero），BlackIsZero 的图像需要反转
+        black_is_1: tag(decoder, Tag::PhotometricInterpretation) == Some(1),
+    })
+}
+
+/// 解码当前 TIFF 页面的像素数据
+fn read_tiff_pixels(decoder: &mut TiffDecoder<Cursor<&[u8]>>) -> Result<FrameData, String> {
+    let (width, height) = decoder.dimensions().map_err(|e| e.to_string())?;
+    let color_type = decoder.colortype().map_err(|e| e.to_string())?;
+    let pixels = decoder.read_image().map_err(|e| format!("无法解码: {}", e))?;
+
+    let (color_space, channels, has_alpha, bits) = match color_type {
+        TiffColorType::Gray(bits @ (1 | 2 | 4 | 8 | 16)) => ("DeviceGray", 1, false, bits),
+        TiffColorType::GrayA(bits @ (8 | 16)) => ("DeviceGray", 1, true, bits),
+        TiffColorType::RGB(bits @ (8 | 16)) => ("DeviceRGB", 3, false, bits),
+        TiffColorType::RGBA(bits @ (8 | 16)) => ("DeviceRGB", 3, true, bits),
+        TiffColorType::CMYK(bits @ (8 | 16)) => ("DeviceCMYK", 4, false, bits),
+        TiffColorType::CMYKA(bits @ (8 | 16)) => ("DeviceCMYK", 4, true, bits),
+        other => return Err(format!("不支持的颜色类型: {:?}", other)),
+    };
+
+    let samples_per_row = width as usize * (channels + has_alpha as usize);
+    let expected_len = height as usize * (samples_per_row * bits as usize).div_ceil(8);
+    let (samples, alpha) = match pixels {
+        DecodingResult::U8(data) if bits <= 8 && data.len() >= expected_len => {
+            if has_alpha {
+                let (color, alpha) = split_alpha(&data[..expected_len], channels);
+                (color, Some(alpha))
+            } else {
+                (data[..expected_len].to_vec(), None)
+            }
+        }
+        DecodingResult::U16(data) if bits == 16 && data.len() * 2 >= expected_len => {
+            let data = &data[..expected_len / 2];
+            if has_alpha {
+                let (color, alpha) = split_alpha(data, channels);
+                (to_be_bytes(&color), Some(to_be_bytes(&alpha)))
+            } else {
+                (to_be_bytes(data), None)
+            }
+        }
+        _ => return Err("不支持的像素格式".to_string()),
+    };
+
+    Ok(FrameData::Raw(RawImage { width, height, color_space, bits, samples, alpha }))
+}
+
+/// 读取当前 TIFF 页面的分辨率
+fn read_tiff_dpi(decoder: &mut TiffDecoder<Cursor<&[u8]>>) -> Option<(f64, f64)> {
+    let resolution = |decoder: &mut TiffDecoder<Cursor<&[u8]>>, tag: Tag| {
+        match decoder.find_tag(tag).ok()?? {
+            tiff::decoder::ifd::Value::Rational(n, d) if d > 0 => Some(n as f64 / d as f64),
+            value => value.into_u32().ok().map(|v| v as f64),
+        }
+    };
+
+    let x = resolution(decoder, Tag::XResolution)?;
+    let y = resolution(decoder, Tag::YResolution).unwrap_or(x);
+    // 单位：2 英寸（默认）、3 厘米、1 无单位
+    let scale = match decoder.find_tag_unsigned::<u16>(Tag::ResolutionUnit).ok().flatten().unwrap_or(2) {
+        2 => 1.0,
+        3 => 2.54,
+        _ => return None,
+    };
+
+    if x > 0.0 && y > 0.0 { Some((x * scale, y * scale)) } else { None }
+}
+
+/// 将解码后的图片按颜色类型拆分为颜色数据和透明通道
+fn raw_from_dynamic(img: DynamicImage) -> RawImage {
+    let (width, height) = (img.width(), img.height());
+    let (color_space, bits, samples, alpha): (&str, u8, Vec<u8>, Option<Vec<u8>>) = match img {
+        DynamicImage::ImageLuma8(buf) => ("DeviceGray", 8, buf.into_raw(), None),
+        DynamicImage::ImageLumaA8(buf) => {
+            let (color, alpha) = split_alpha(buf.as_raw(), 1);
+            ("DeviceGray", 8, color, Some(alpha))
+        }
+        DynamicImage::ImageRgb8(buf) => ("DeviceRGB", 8, buf.into_raw(), None),
+        DynamicImage::ImageRgba8(buf) => {
+            let (color, alpha) = split_alpha(buf.as_raw(), 3);
+            ("DeviceRGB", 8, color, Some(alpha))
+        }
+        DynamicImage::ImageLuma16(buf) => ("DeviceGray", 16, to_be_bytes(buf.as_raw()), None),
+        DynamicImage::ImageLumaA16(buf) => {
+            let (color, alpha) = split_alpha(buf.as_raw(), 1);
+            ("DeviceGray", 16, to_be_bytes(&color), Some(to_be_bytes(&alpha)))
+        }
+        DynamicImage::ImageRgb16(buf) => ("DeviceRGB", 16, to_be_bytes(buf.as_raw()), None),
+        other => {
+            if other.color().has_alpha() {
+                let buf = other.to_rgba16();
+                let (color, alpha) = split_alpha(buf.as_raw(), 3);
+                ("DeviceRGB", 16, to_be_bytes(&color), Some(to_be_bytes(&alpha)))
+            } else {
+                ("DeviceRGB", 16, to_be_bytes(other.to_rgb16().as_raw()), None)
+            }
+        }
+    };
+
+    RawImage { width, height, color_space, bits, samples, alpha }
+}
+
+/// 将一帧图片嵌入 PDF 文档，返回图片 XObject
+/// 透明通道单独作为 SMask 嵌入
+pub(crate) fn embed_frame(doc: &mut Document, frame: ImageFrame) -> Result<EmbeddedImage, String> {
+    let mut image_dict = Dictionary::new();
+    image_dict.set("Type", Object::Name(b"XObject".to_vec()));
+    image_dict.set("Subtype", Object::Name(b"Image".to_vec()));
+
+    let (width, height, stream) = match frame.data {
+        FrameData::Jpeg(info, data) => {
+            let color_space = match info.components {
+                1 => "DeviceGray",
+                3 => "DeviceRGB",
+                4 => "DeviceCMYK",
+                n => return Err(format!("不支持的 JPEG 颜色分量数: {}", n)),
+            };
+            image_dict.set("ColorSpace", Object::Name(color_space.as_bytes().to_vec()));
+            image_dict.set("BitsPerComponent", Object::Integer(info.bits as i64));
+            image_dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+            // Photoshop 等软件写出的 CMYK JPEG 颜色值是反相的
+            if info.components == 4 && info.adobe {
+                image_dict.set("Decode", Object::Array([1, 0, 1, 0, 1, 0, 1, 0].iter().map(|&v| Object::Integer(v)).collect()));
+            }
+            set_dimensions(&mut image_dict, info.width, info.height);
+            (info.width, info.height, Stream::new(image_dict, data).with_compression(false))
+        }
+        FrameData::CcittG4 { width, height, data, black_is_1 } => {
+            let mut params = Dictionary::new();
+            params.set("K", Object::Integer(-1));
+            params.set("Columns", Object::Integer(width as i64));
+            params.set("Rows", Object::Integer(height as i64));
+            if black_is_1 {
+                params.set("BlackIs1", Object::Boolean(true));
+            }
+            image_dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+            image_dict.set("BitsPerComponent", Object::Integer(1));
+            image_dict.set("Filter", Object::Name(b"CCITTFaxDecode".to_vec()));
+            image_dict.set("DecodeParms", Object::Dictionary(params));
+            set_dimensions(&mut image_dict, width, height);
+            (width, height, Stream::new(image_dict, data).with_compression(false))
+        }
+        FrameData::Raw(raw) => {
+            image_dict.set("ColorSpace", Object::Name(raw.color_space.as_bytes().to_vec()));
+            image_dict.set("BitsPerComponent", Object::Integer(raw.bits as i64));
+            set_dimensions(&mut image_dict, raw.width, raw.height);
+
+            // 完全不透明的透明通道不需要嵌入
+            let alpha = raw.alpha.filter(|a| {
+                let opaque: &[u8] = if raw.bits == 16 { &[0xFF, 0xFF] } else { &[0xFF] };
+                a.chunks(opaque.len()).any(|v| v != opaque)
+            });
+            if let Some(alpha) = alpha {
+                let mut mask_dict = Dictionary::new();
+                mask_dict.set("Type", Object::Name(b"XObject".to_vec()));
+                mask_dict.set("Subtype", Object::Name(b"Image".to_vec()));
+                mask_dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+                mask_dict.set("BitsPerComponent", Object::Integer(raw.bits as i64));
+                set_dimensions(&mut mask_dict, raw.width, raw.height);
+                let mask_id = doc.add_object(Object::Stream(flate_image_stream(mask_dict, &alpha)?));
+                image_dict.set("SMask", Object::Reference(mask_id));
+            }
+
+            (raw.width, raw.height, flate_image_stream(image_dict, &raw.samples)?)
+        }
+    };
+
+    Ok(EmbeddedImage {
+        id: doc.add_object(Object::Stream(stream)),
+        width,
+        height,
+    })
+}
+
+fn set_dimensions(dict: &mut Dictionary, width: u32, height: u32) {
+    dict.set("Width", Object::Integer(width as i64));
+    dict.set("Height", Object::Integer(height as i64));
+}
+
+/// 使用 FlateDecode 压缩图片数据
+pub(crate) fn flate_image_stream(mut dict: Dictionary, samples: &[u8]) -> Result<Stream, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(samples)
+        .and_then(|_| encoder.finish())
+        .map(|compressed| {
+            dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+            Stream::new(dict, compressed).with_compression(false)
+        })
+        .map_err(|e| format!("无法压缩图片数据: {}", e))
+}
+
+/// 将交错存放的像素拆分为颜色分量和透明通道
+fn split_alpha<T: Copy>(pixels: &[T], color_channels: usize) -> (Vec<T>, Vec<T>) {
+    let pixel_count = pixels.len() / (color_channels + 1);
+    let mut color = Vec::with_capacity(pixel_count * color_channels);
+    let mut alpha = Vec::with_capacity(pixel_count);
+    for pixel in pixels.chunks_exact(color_channels + 1) {
+        color.extend_from_slice(&pixel[..color_channels]);
+        alpha.push(pixel[color_channels]);
+    }
+    (color, alpha)
+}
+
+fn to_be_bytes(samples: &[u16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_be_bytes()).collect()
+}
+
+/// JPEG 帧头信息
+struct JpegInfo {
+    /// SOF 标记（0xC0-0xCF）
+    marker: u8,
+    width: u32,
+    height: u32,
+    components: u8,
+    bits: u8,
+    /// 是否包含 Adobe APP14 段
+    adobe: bool,
+}
+
+impl JpegInfo {
+    /// 能否原样作为 DCTDecode 嵌入：PDF 阅读器只普遍支持 8 位的基线、扩展顺序和渐进式（Huffman 编码）JPEG
+    fn is_pdf_compatible(&self) -> bool {
+        matches!(self.marker, 0xC0..=0xC2) && self.bits == 8
+    }
+}
+
+/// 解析 JPEG 的 SOF 段，获取尺寸和颜色分量数
+fn read_jpeg_info(image_bytes: &[u8]) -> Option<JpegInfo> {
+    if !image_bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut adobe = false;
+    let mut pos = 2;
+    while pos + 4 <= image_bytes.len() {
+        if image_bytes[pos] != 0xFF {
+            return None;
+        }
+        let marker = image_bytes[pos + 1];
+        // 填充字节
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        // 没有长度字段的独立标记
+        if (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
+            pos += 2;
+            continue;
+        }
+
+        let length = u16::from_be_bytes([image_bytes[pos + 2], image_bytes[pos + 3]]) as usize;
+        let segment = image_bytes.get(pos + 4..pos + 2 + length)?;
+
+        if marker == 0xEE && segment.starts_with(b"Adobe") {
+            adobe = true;
+        }
+
+        // SOF0-SOF15，排除 DHT(C4)、JPG(C8)、DAC(CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            if segment.len() < 6 {
+                return None;
+            }
+            let info = JpegInfo {
+                marker,
+                bits: segment[0],
+                height: u16::from_be_bytes([segment[1], segment[2]]) as u32,
+                width: u16::from_be_bytes([segment[3], segment[4]]) as u32,
+                components: segment[5],
+                adobe,
+            };
+            // 高度为 0 时由 DNL 段指定，交给通用解码流程处理
+            return if info.width > 0 && info.height > 0 { Some(info) } else { None };
+        }
+
+        if marker == 0xDA {
+            return None;
+        }
+        pos += 2 + length;
+    }
+
+    None
+}
+
+
+/// 读取图片中记录的 DPI（JPEG 的 JFIF 头或 PNG 的 pHYs 块）
+pub(crate) fn read_image_dpi(image_bytes: &[u8]) -> Option<(f64, f64)> {
+    let valid = |(x, y): (f64, f64)| if x > 0.0 && y > 0.0 { Some((x, y)) } else { None };
+
+    if image_bytes.starts_with(&[0xFF, 0xD8]) {
+        // 遍历 JPEG 段，查找 APP0 JFIF
+        let mut pos = 2;
+        while pos + 4 <= image_bytes.len() && image_bytes[pos] == 0xFF {
+            let marker = image_bytes[pos + 1];
+            let length = u16::from_be_bytes([image_bytes[pos + 2], image_bytes[pos + 3]]) as usize;
+            let segment = image_bytes.get(pos + 4..pos + 2 + length)?;
+            if marker == 0xE0 && segment.len() >= 12 && segment.starts_with(b"JFIF\0") {
+                let units = segment[7];
+                let x = u16::from_be_bytes([segment[8], segment[9]]) as f64;
+                let y = u16::from_be_bytes([segment[10], segment[11]]) as f64;
+                return match units {
+                    1 => valid((x, y)),
+                    2 => valid((x * 2.54, y * 2.54)),
+                    _ => None,
+                };
+            }
+            // 到达图像数据后不再有 APP 段
+            if marker == 0xDA {
+                break;
+            }
+            pos += 2 + length;
+        }
+        return None;
+    }
+
+    if image_bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let mut pos = 8;
+        while pos + 8 <= image_bytes.len() {
+            let length = u32::from_be_bytes(image_bytes[pos..pos + 4].try_into().ok()?) as usize;
+            let chunk_type = &image_bytes[pos + 4..pos + 8];
+            if chunk_type == b"pHYs" {
+                let data = image_bytes.get(pos + 8..pos + 8 + 9)?;
+                let x = u32::from_be_bytes(data[0..4].try_into().ok()?) as f64;
+                let y = u32::from_be_bytes(data[4..8].try_into().ok()?) as f64;
+                // 单位 1 表示每米像素数
+                return if data[8] == 1 { valid((x * 0.0254, y * 0.0254)) } else { None };
+            }
+            if chunk_type == b"IDAT" {
+                break;
+            }
+            pos += 12 + length;
+        }
+    }
+
+    None
+}
@@ -1,8 +1,17 @@
 use wasm_bindgen::prelude::*;
-use image::{ImageFormat, DynamicImage, GenericImageView, imageops::FilterType};
+use image::{ImageFormat, GenericImageView, imageops::FilterType};
 use image::codecs::jpeg::JpegEncoder;
 use lopdf::{Document, Object, Dictionary, Stream};
+use serde::Deserialize;
 use std::io::Cursor;
+use crate::image_embed::{decode_frames, embed_frame};
+use crate::outline::{write_outline, DestView, OutlineItem};
+use crate::font_embed::{hex_string, EmbeddedFont};
+use crate::standard_font::{encode_win_ansi, font_dictionary, helvetica_width, literal_string, truncate_text, wrap_text};
+use crate::pdf_utils::build_page_tree;
+use crate::linearize::save_document;
+use crate::pdfa::{convert_document, PdfALevel};
+use crate::page_size::{compute_aligned_placement, compute_placement, parse_color, parse_paper_size, Alignment, FitMode, MM_TO_PT};
 
 /// 图片格式转换
 #[wasm_bindgen]
@@ -133,118 +142,706 @@ pub fn get_image_info(image_bytes: &[u8]) -> Result<String, JsValue> {
     Ok(info.to_string())
 }
 
+/// 图片转 PDF 的页面布局选项（以 JSON 字符串传入）
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct ImagePdfOptions {
+    /// 页面尺寸: "A4"（默认）、"Letter" 等纸张名称、自定义 "595x842"，或 "image" 使页面与图片一致
+    page_size: String,
+    /// 页面方向: "portrait"、"landscape"、"auto"（跟随每张图片的方向）；不设置时按 page_size 原样使用
+    orientation: Option<String>,
+    /// 四周边距（毫米）
+    margin_mm: f64,
+    /// 分别指定上、右、下、左边距（毫米），设置后忽略 margin_mm
+    margins_mm: Option<[f64; 4]>,
+    /// 缩放模式: "fit"（默认）、"fill"、"stretch"、"actual_size"
+    fit_mode: String,
+    /// 对齐方式: "center"（默认）、"top"、"bottom-left" 等
+    align: String,
+    /// 页面背景色，如 "#ffffff"；不设置时不绘制背景
+    background_color: Option<String>,
+    /// 图片没有 DPI 信息时使用的 DPI
+    default_dpi: f64,
+    /// 每张图片的单独设置，按顺序与图片对应；设置后为每张图片生成书签
+    images: Vec<ImageDescriptor>,
+    /// 图片标题的字号（点）
+    caption_font_size: f64,
+    /// 直接输出 PDF/A，如 "2b"（也可以是 "1b"、"3b"）；图片标题需要使用嵌入字体
+    pdfa: Option<String>,
+    /// 输出线性化（快速 Web 查看）文件
+    linearize: bool,
+}
+
+/// 单张图片的设置
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ImageDescriptor {
+    /// 显示在图片下方的标题（默认使用标准字体 Helvetica，仅支持西文字符）
+    caption: Option<String>,
+    /// 顺时针旋转角度: 0、90、180、270
+    rotation: i32,
+    /// 覆盖全局的页面尺寸
+    page_size: Option<String>,
+    /// 书签标题，不设置时使用图片标题或 "图片 N"
+    bookmark: Option<String>,
+}
+
+impl Default for ImagePdfOptions {
+    fn default() -> Self {
+        ImagePdfOptions {
+            page_size: "A4".to_string(),
+            orientation: None,
+            margin_mm: 0.0,
+            margins_mm: None,
+            fit_mode: "fit".to_string(),
+            align: "center".to_string(),
+            background_color: None,
+            default_dpi: 96.0,
+            images: Vec::new(),
+            caption_font_size: 12.0,
+            pdfa: None,
+            linearize: false,
+        }
+    }
+}
+
+/// 解析后的页面布局
+#[derive(Clone)]
+struct PageLayout {
+    /// None 表示页面与图片大小一致
+    paper: Option<(f64, f64)>,
+    orientation: Option<Orientation>,
+    /// 上、右、下、左边距（点）
+    margins: [f64; 4],
+    mode: FitMode,
+    align: Alignment,
+    background: Option<(f64, f64, f64)>,
+    default_dpi: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Orientation {
+    Portrait,
+    Landscape,
+    Auto,
+}
+
+impl PageLayout {
+    fn from_options(options: &ImagePdfOptions) -> Result<PageLayout, String> {
+        let paper = parse_layout_paper(&options.page_size)?;
+        let orientation = match options.orientation.as_deref().map(|o| o.trim().to_lowercase()) {
+            None => None,
+            Some(o) if o == "portrait" => Some(Orientation::Portrait),
+            Some(o) if o == "landscape" => Some(Orientation::Landscape),
+            Some(o) if o == "auto" => Some(Orientation::Auto),
+            Some(o) => return Err(format!("不支持的页面方向: {}", o)),
+        };
+        let margins = options.margins_mm.unwrap_or([options.margin_mm; 4]);
+        if margins.iter().any(|m| *m < 0.0) {
+            return Err("边距不能为负数".to_string());
+        }
+        if options.default_dpi <= 0.0 {
+            return Err("default_dpi 必须大于 0".to_string());
+        }
+
+        Ok(PageLayout {
+            paper,
+            orientation,
+            margins: margins.map(|m| m * MM_TO_PT),
+            mode: FitMode::parse(&options.fit_mode)?,
+            align: Alignment::parse(&options.align)?,
+            background: options.background_color.as_deref().map(parse_color).transpose()?,
+            default_dpi: options.default_dpi,
+        })
+    }
+
+    /// 使用另一纸张尺寸的布局，其余设置不变
+    fn with_page_size(&self, page_size: &str) -> Result<PageLayout, String> {
+        Ok(PageLayout { paper: parse_layout_paper(page_size)?, ..self.clone() })
+    }
+
+    /// 计算图片所在页面的尺寸，以及图片在页面上的位置 (x, y, width, height)
+    /// image_width/image_height 为按 DPI 换算后的点数
+    fn place(&self, image_width: f64, image_height: f64) -> ((f64, f64), [f64; 4]) {
+        self.place_on_page(self.page_size(image_width, image_height), image_width, image_height)
+    }
+
+    /// 计算图片所在页面的尺寸
+    fn page_size(&self, image_width: f64, image_height: f64) -> (f64, f64) {
+        let [top, right, bottom, left] = self.margins;
+
+        match (self.paper, self.orientation) {
+            (None, _) => (image_width + left + right, image_height + top + bottom),
+            (Some(paper), None) => paper,
+            (Some((w, h)), Some(orientation)) => {
+                let (short, long) = (w.min(h), w.max(h));
+                let landscape = match orientation {
+                    Orientation::Portrait => false,
+                    Orientation::Landscape => true,
+                    Orientation::Auto => image_width > image_height,
+                };
+                if landscape { (long, short) } else { (short, long) }
+            }
+        }
+    }
+
+    /// 在给定尺寸的页面上按边距、缩放模式和对齐方式放置图片
+    fn place_on_page(&self, (page_width, page_height): (f64, f64), image_width: f64, image_height: f64) -> ((f64, f64), [f64; 4]) {
+        let [top, right, bottom, left] = self.margins;
+        let area_width = (page_width - left - right).max(1.0);
+        let area_height = (page_height - top - bottom).max(1.0);
+        let mode = if self.paper.is_none() { FitMode::ActualSize } else { self.mode };
+        let (scale_x, scale_y, offset_x, offset_y) =
+            compute_aligned_placement(image_width, image_height, area_width, area_height, mode, self.align);
+
+        (
+            (page_width, page_height),
+            [left + offset_x, bottom + offset_y, image_width * scale_x, image_height * scale_y],
+        )
+    }
+}
+
+/// 图片标题、标签和页眉使用的字体：默认为标准字体 Helvetica，提供字体文件时改用嵌入字体
+struct LabelFont {
+    embedded: Option<EmbeddedFont>,
+}
+
+impl LabelFont {
+    fn new(font_data: Option<&[u8]>) -> Result<LabelFont, String> {
+        Ok(LabelFont { embedded: font_data.map(EmbeddedFont::parse).transpose()? })
+    }
+
+    /// 文本的宽度（点）
+    fn width(&self, text: &str, font_size: f64) -> f64 {
+        match &self.embedded {
+            Some(font) => font.text_width(text, font_size),
+            None => helvetica_width(&encode_win_ansi(text), font_size),
+        }
+    }
+
+    /// 文本在内容流中的字符串操作数，并记录嵌入字体用到的字形
+    fn string(&mut self, text: &str) -> String {
+        match &mut self.embedded {
+            Some(font) => hex_string(&font.encode(text)),
+            None => literal_string(&encode_win_ansi(text)),
+        }
+    }
+
+    /// 在所有文字都写入内容流之后写入字体
+    fn write(&self, doc: &mut Document, font_id: lopdf::ObjectId) -> Result<(), String> {
+        match &self.embedded {
+            Some(font) => font.write(doc, font_id),
+            None => {
+                doc.objects.insert(font_id, Object::Dictionary(font_dictionary("Helvetica")));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 解析布局的纸张尺寸，"image" 表示页面与图片大小一致（返回 None）
+fn parse_layout_paper(page_size: &str) -> Result<Option<(f64, f64)>, String> {
+    match page_size.trim().to_lowercase().as_str() {
+        "image" | "fit_image" => Ok(None),
+        _ => parse_paper_size(page_size).map(Some),
+    }
+}
+
 /// 图片转 PDF
 /// 将多个图片转换为一个 PDF 文件，每个图片占一页
 #[wasm_bindgen]
 pub fn images_to_pdf(images: Vec<js_sys::Uint8Array>) -> Result<Vec<u8>, JsValue> {
+    let images: Vec<Vec<u8>> = images.iter().map(|i| i.to_vec()).collect();
+
+    images_to_pdf_bytes(&images, &ImagePdfOptions::default(), None)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 带页面布局选项的图片转 PDF
+/// options: JSON 字符串，例如 {"page_size": "A4", "orientation": "auto", "margin_mm": 10, "fit_mode": "fit"}
+#[wasm_bindgen]
+pub fn images_to_pdf_with_options(images: Vec<js_sys::Uint8Array>, options: &str) -> Result<Vec<u8>, JsValue> {
+    let images: Vec<Vec<u8>> = images.iter().map(|i| i.to_vec()).collect();
+
+    images_to_pdf_from_bytes(&images, options, None)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 嵌入指定字体的图片转 PDF，图片标题可以使用中文等任意字符
+/// options 同 images_to_pdf_with_options，font_bytes 为 TTF/OTF 字体文件
+#[wasm_bindgen]
+pub fn images_to_pdf_with_font(images: Vec<js_sys::Uint8Array>, options: &str, font_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let images: Vec<Vec<u8>> = images.iter().map(|i| i.to_vec()).collect();
+
+    images_to_pdf_from_bytes(&images, options, Some(font_bytes))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 图片转 PDF 的 Rust 接口，图片以字节数组传入（Uint8Array 只能在 wasm 环境中构造）
+/// options 同 images_to_pdf_with_options；提供 font_bytes 时同 images_to_pdf_with_font
+pub fn images_to_pdf_from_bytes(images: &[Vec<u8>], options: &str, font_bytes: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let options: ImagePdfOptions = serde_json::from_str(options)
+        .map_err(|e| format!("无效的图片转 PDF 选项: {}", e))?;
+
+    images_to_pdf_bytes(images, &options, font_bytes)
+}
+
+fn images_to_pdf_bytes(images: &[Vec<u8>], options: &ImagePdfOptions, font_data: Option<&[u8]>) -> Result<Vec<u8>, String> {
     if images.is_empty() {
-        return Err(JsValue::from_str("至少需要一张图片"));
+        return Err("至少需要一张图片".to_string());
+    }
+
+    let layout = PageLayout::from_options(options)?;
+    if options.images.len() > images.len() {
+        return Err(format!("图片设置有 {} 项，但只有 {} 张图片", options.images.len(), images.len()));
     }
+    if options.caption_font_size <= 0.0 {
+        return Err("caption_font_size 必须大于 0".to_string());
+    }
+    let font_size = options.caption_font_size;
+    let pdfa = options.pdfa.as_deref().map(PdfALevel::parse).transpose()?;
+    // PDF/A 要求嵌入字体，标准字体 Helvetica 不能用于标题
+    if pdfa.is_some() && font_data.is_none() && options.images.iter().any(|image| image.caption.is_some()) {
+        return Err("输出 PDF/A 时图片标题必须使用嵌入字体，请使用 images_to_pdf_with_font 提供字体".to_string());
+    }
+    let mut font = LabelFont::new(font_data)?;
+    let default_descriptor = ImageDescriptor::default();
 
     // 创建新的 PDF 文档
     let mut doc = Document::with_version("1.5");
     let pages_id = doc.new_object_id();
     let mut page_ids = Vec::new();
+    let mut outline = Vec::new();
+    let mut font_id = None;
+
+    for (idx, image_bytes) in images.iter().enumerate() {
+        let descriptor = options.images.get(idx).unwrap_or(&default_descriptor);
+        let rotation = descriptor.rotation.rem_euclid(360);
+        if rotation % 90 != 0 {
+            return Err(format!("第 {} 张图片的旋转角度必须是 90 的倍数", idx + 1));
+        }
+        let layout = match &descriptor.page_size {
+            Some(page_size) => layout.with_page_size(page_size)?,
+            None => layout.clone(),
+        };
+
+        // 多帧 GIF 和多页 TIFF 的每一帧单独成页
+        let frames = decode_frames(image_bytes)
+            .map_err(|e| format!("无法加载第 {} 张图片: {}", idx + 1, e))?;
+
+        for (frame_index, frame) in frames.into_iter().enumerate() {
+            let dpi = frame.dpi;
+            let image = embed_frame(&mut doc, frame)
+                .map_err(|e| format!("无法转换第 {} 张图片: {}", idx + 1, e))?;
+            let (width, height) = (image.width, image.height);
+            let image_id = image.id;
+
+            // 创建资源字典
+            let mut resources = Dictionary::new();
+            let mut xobject = Dictionary::new();
+            xobject.set("Im1", Object::Reference(image_id));
+            resources.set("XObject", Object::Dictionary(xobject));
+
+            // 按 DPI 换算图片的实际尺寸（点），旋转 90/270 度时宽高互换
+            let (dpi_x, dpi_y) = dpi.unwrap_or((layout.default_dpi, layout.default_dpi));
+            let mut image_width = width as f64 * 72.0 / dpi_x;
+            let mut image_height = height as f64 * 72.0 / dpi_y;
+            if rotation % 180 != 0 {
+                std::mem::swap(&mut image_width, &mut image_height);
+            }
+
+            // 标题折行后在图片下方预留空间
+            let caption_lines = match &descriptor.caption {
+                Some(caption) => {
+                    let (page_width, _) = layout.page_size(image_width, image_height);
+                    let [_, right, _, left] = layout.margins;
+                    let wrap_width = if layout.paper.is_none() { image_width } else { page_width - left - right };
+                    wrap_text(caption, wrap_width, |text| font.width(text, font_size))
+                }
+                None => Vec::new(),
+            };
+            let line_height = font_size * 1.2;
+            let caption_gap = font_size * 0.5;
+            let layout = if caption_lines.is_empty() {
+                layout.clone()
+            } else {
+                let mut layout = layout.clone();
+                layout.margins[2] += caption_gap + caption_lines.len() as f64 * line_height;
+                layout
+            };
+
+            // 计算页面尺寸和图片位置
+            let ((page_width, page_height), [x, y, scaled_width, scaled_height]) =
+                layout.place(image_width, image_height);
+
+            // 创建内容流：绘制背景，并将图片裁剪到边距以内
+            let mut content = String::new();
+            if let Some((r, g, b)) = layout.background {
+                content.push_str(&format!(
+                    "q\n{} {} {} rg\n0 0 {} {} re f\nQ\n",
+                    r, g, b, page_width, page_height
+                ));
+            }
+            let [top, right, bottom, left] = layout.margins;
+            let (sw, sh) = (scaled_width, scaled_height);
+            let matrix = match rotation {
+                90 => [0.0, -sh, sw, 0.0, x, y + sh],
+                180 => [-sw, 0.0, 0.0, -sh, x + sw, y + sh],
+                270 => [0.0, sh, -sw, 0.0, x + sw, y],
+                _ => [sw, 0.0, 0.0, sh, x, y],
+            };
+            content.push_str(&format!(
+                "q\n{} {} {} {} re W n\n{} {} {} {} {} {} cm\n/Im1 Do\nQ",
+                left,
+                bottom,
+                page_width - left - right,
+                page_height - top - bottom,
+                matrix[0], matrix[1], matrix[2], matrix[3], matrix[4], matrix[5]
+            ));
+
+            // 标题：在图片下方居中，不超出左右边距
+            if !caption_lines.is_empty() {
+                content.push_str("\nBT\n0 g\n");
+                content.push_str(&format!("/F1 {} Tf\n", font_size));
+                let center = x + scaled_width / 2.0;
+                for (line_index, line) in caption_lines.iter().enumerate() {
+                    let line_width = font.width(line, font_size);
+                    let line_x = (center - line_width / 2.0)
+                        .min(page_width - right - line_width)
+                        .max(left);
+                    let baseline = y - caption_gap - font_size - line_index as f64 * line_height;
+                    content.push_str(&format!(
+                        "1 0 0 1 {} {} Tm\n{} Tj\n",
+                        line_x,
+                        baseline,
+                        font.string(line)
+                    ));
+                }
+                content.push_str("ET");
+
+                let font_id = *font_id.get_or_insert_with(|| doc.new_object_id());
+                let mut fonts = Dictionary::new();
+                fonts.set("F1", Object::Reference(font_id));
+                resources.set("Font", Object::Dictionary(fonts));
+            }
+
+            let content_stream = Stream::new(Dictionary::new(), content.into_bytes());
+            let content_id = doc.add_object(Object::Stream(content_stream));
+
+            // 创建页面对象
+            let mut page = Dictionary::new();
+            page.set("Type", Object::Name(b"Page".to_vec()));
+            page.set("Parent", Object::Reference(pages_id));
+            page.set("MediaBox", Object::Array(vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Real(page_width as f32),
+                Object::Real(page_height as f32),
+            ]));
+            page.set("Contents", Object::Reference(content_id));
+            page.set("Resources", Object::Dictionary(resources));
+
+            let page_id = doc.add_object(Object::Dictionary(page));
+            page_ids.push(Object::Reference(page_id));
+
+            // 书签指向图片的第一页
+            if frame_index == 0 && !options.images.is_empty() {
+                let title = descriptor.bookmark.clone()
+                    .or_else(|| descriptor.caption.clone())
+                    .unwrap_or_else(|| format!("图片 {}", idx + 1));
+                outline.push(OutlineItem::new(title, page_id, DestView::Fit));
+            }
+        }
+    }
+
+    if let Some(font_id) = font_id {
+        font.write(&mut doc, font_id)?;
+    }
+
+    // 创建页面树和目录
+    let catalog_id = build_page_tree(&mut doc, pages_id, page_ids);
+
+    if !outline.is_empty() {
+        write_outline(&mut doc, catalog_id, &outline)?;
+    }
+
+    if let Some(level) = pdfa {
+        convert_document(&mut doc, level, None)?;
+    }
+
+    // 保存 PDF
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, options.linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+
+    Ok(buffer)
+}
+
+/// 图片网格（联系表）的布局选项（以 JSON 字符串传入）
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct GridPdfOptions {
+    /// 页面尺寸: "A4"（默认）、"Letter" 等纸张名称或自定义 "595x842"
+    page_size: String,
+    /// 页面方向: "portrait"、"landscape"；不设置时按 page_size 原样使用
+    orientation: Option<String>,
+    /// 四周边距（毫米）
+    margin_mm: f64,
+    /// 单元格之间的间距（毫米）
+    gutter_mm: f64,
+    /// 缩放模式: "fit"（默认）、"fill"、"stretch"
+    fit_mode: String,
+    /// 每张图片下方的标签（如文件名），按顺序与图片对应
+    labels: Vec<String>,
+    /// 标签字号（点）
+    label_font_size: f64,
+    /// 页眉文本，支持 {page} 和 {pages} 占位符
+    header: Option<String>,
+    /// 页眉字号（点）
+    header_font_size: f64,
+    /// 页面背景色，如 "#ffffff"；不设置时不绘制背景
+    background_color: Option<String>,
+    /// 输出线性化（快速 Web 查看）文件
+    linearize: bool,
+}
+
+impl Default for GridPdfOptions {
+    fn default() -> Self {
+        GridPdfOptions {
+            page_size: "A4".to_string(),
+            orientation: None,
+            margin_mm: 10.0,
+            gutter_mm: 5.0,
+            fit_mode: "fit".to_string(),
+            labels: Vec::new(),
+            label_font_size: 8.0,
+            header: None,
+            header_font_size: 12.0,
+            background_color: None,
+            linearize: false,
+        }
+    }
+}
+
+/// 图片网格 PDF（联系表）
+/// 每页按 cols 列 rows 行排列多张图片，图片缩放到单元格内
+/// options: JSON 字符串，例如 {"page_size": "A4", "gutter_mm": 5, "labels": ["a.jpg"], "header": "第 {page} 页"}
+#[wasm_bindgen]
+pub fn images_to_pdf_grid(images: Vec<js_sys::Uint8Array>, cols: u32, rows: u32, options: &str) -> Result<Vec<u8>, JsValue> {
+    let images: Vec<Vec<u8>> = images.iter().map(|i| i.to_vec()).collect();
+
+    images_to_pdf_grid_from_bytes(&images, cols, rows, options, None)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 嵌入指定字体的图片网格 PDF，标签和页眉可以使用中文等任意字符
+/// options 同 images_to_pdf_grid，font_bytes 为 TTF/OTF 字体文件
+#[wasm_bindgen]
+pub fn images_to_pdf_grid_with_font(
+    images: Vec<js_sys::Uint8Array>,
+    cols: u32,
+    rows: u32,
+    options: &str,
+    font_bytes: &[u8],
+) -> Result<Vec<u8>, JsValue> {
+    let images: Vec<Vec<u8>> = images.iter().map(|i| i.to_vec()).collect();
+
+    images_to_pdf_grid_from_bytes(&images, cols, rows, options, Some(font_bytes))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 图片网格 PDF 的 Rust 接口，图片以字节数组传入（Uint8Array 只能在 wasm 环境中构造）
+/// options 同 images_to_pdf_grid；提供 font_bytes 时同 images_to_pdf_grid_with_font
+pub fn images_to_pdf_grid_from_bytes(
+    images: &[Vec<u8>],
+    cols: u32,
+    rows: u32,
+    options: &str,
+    font_bytes: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    let options: GridPdfOptions = serde_json::from_str(options)
+        .map_err(|e| format!("无效的图片网格选项: {}", e))?;
+
+    images_to_pdf_grid_bytes(images, cols, rows, &options, font_bytes)
+}
+
+/// 网格的最大行数和列数
+const MAX_GRID_SIZE: u32 = 100;
+
+fn images_to_pdf_grid_bytes(
+    images: &[Vec<u8>],
+    cols: u32,
+    rows: u32,
+    options: &GridPdfOptions,
+    font_data: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    if images.is_empty() {
+        return Err("至少需要一张图片".to_string());
+    }
+    if cols == 0 || rows == 0 {
+        return Err("行数和列数必须大于 0".to_string());
+    }
+    if cols > MAX_GRID_SIZE || rows > MAX_GRID_SIZE {
+        return Err(format!("行数和列数不能超过 {}", MAX_GRID_SIZE));
+    }
+    if options.margin_mm < 0.0 || options.gutter_mm < 0.0 {
+        return Err("边距和间距不能为负数".to_string());
+    }
+    if options.label_font_size <= 0.0 || options.header_font_size <= 0.0 {
+        return Err("字号必须大于 0".to_string());
+    }
+
+    let (paper_width, paper_height) = parse_paper_size(&options.page_size)?;
+    let (short, long) = (paper_width.min(paper_height), paper_width.max(paper_height));
+    let (page_width, page_height) = match options.orientation.as_deref().map(|o| o.trim().to_lowercase()) {
+        None => (paper_width, paper_height),
+        Some(o) if o == "portrait" => (short, long),
+        Some(o) if o == "landscape" => (long, short),
+        Some(o) => return Err(format!("不支持的页面方向: {}", o)),
+    };
+    let mode = match FitMode::parse(&options.fit_mode)? {
+        FitMode::ActualSize => return Err("图片网格不支持 actual_size 缩放模式".to_string()),
+        mode => mode,
+    };
+    let background = options.background_color.as_deref().map(parse_color).transpose()?;
+    let mut font = LabelFont::new(font_data)?;
+
+    // 计算网格区域和单元格尺寸
+    let margin = options.margin_mm * MM_TO_PT;
+    let gutter = options.gutter_mm * MM_TO_PT;
+    let header_height = if options.header.is_some() { options.header_font_size * 1.5 } else { 0.0 };
+    let label_height = if options.labels.is_empty() { 0.0 } else { options.label_font_size * 1.5 };
+    let grid_width = page_width - 2.0 * margin;
+    let grid_height = page_height - 2.0 * margin - header_height;
+    let cell_width = (grid_width - gutter * (cols - 1) as f64) / cols as f64;
+    let cell_height = (grid_height - gutter * (rows - 1) as f64) / rows as f64;
+    if cell_width <= 0.0 || cell_height - label_height <= 0.0 {
+        return Err("页面空间不足以容纳指定的行列数".to_string());
+    }
+
+    // 创建新的 PDF 文档，多帧 GIF 和多页 TIFF 的每一帧各占一个单元格
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let mut cells = Vec::new();
+    for (idx, image_bytes) in images.iter().enumerate() {
+        let frames = decode_frames(image_bytes)
+            .map_err(|e| format!("无法加载第 {} 张图片: {}", idx + 1, e))?;
+        for frame in frames {
+            let (dpi_x, dpi_y) = frame.dpi.unwrap_or((72.0, 72.0));
+            let image = embed_frame(&mut doc, frame)
+                .map_err(|e| format!("无法转换第 {} 张图片: {}", idx + 1, e))?;
+            let aspect_width = image.width as f64 / dpi_x;
+            let aspect_height = image.height as f64 / dpi_y;
+            cells.push((image.id, aspect_width, aspect_height, options.labels.get(idx)));
+        }
+    }
+
+    let font_id = (options.header.is_some() || !options.labels.is_empty()).then(|| doc.new_object_id());
+
+    let per_page = cols.checked_mul(rows).ok_or("行数和列数过大")? as usize;
+    let page_count = cells.len().div_ceil(per_page);
+    let mut page_ids = Vec::new();
+
+    for (page_index, page_cells) in cells.chunks(per_page).enumerate() {
+        let mut content = String::new();
+        if let Some((r, g, b)) = background {
+            content.push_str(&format!(
+                "q\n{} {} {} rg\n0 0 {} {} re f\nQ\n",
+                r, g, b, page_width, page_height
+            ));
+        }
+
+        // 页眉：居中显示在网格上方
+        if let Some(header) = &options.header {
+            let text = header
+                .replace("{page}", &(page_index + 1).to_string())
+                .replace("{pages}", &page_count.to_string());
+            let size = options.header_font_size;
+            let line = truncate_text(&text, grid_width, |text| font.width(text, size));
+            content.push_str(&format!(
+                "BT\n0 g\n/F1 {} Tf\n1 0 0 1 {} {} Tm\n{} Tj\nET\n",
+                size,
+                margin + (grid_width - font.width(&line, size)) / 2.0,
+                page_height - margin - size,
+                font.string(&line)
+            ));
+        }
+
+        let mut xobjects = Dictionary::new();
+        for (cell_index, (image_id, aspect_width, aspect_height, label)) in page_cells.iter().enumerate() {
+            let col = (cell_index % cols as usize) as f64;
+            let row = (cell_index / cols as usize) as f64;
+            let cell_x = margin + col * (cell_width + gutter);
+            let cell_top = page_height - margin - header_height - row * (cell_height + gutter);
+            let image_box_y = cell_top - cell_height + label_height;
+            let image_box_height = cell_height - label_height;
+
+            // 图片缩放到单元格内并居中，裁剪掉超出单元格的部分
+            let (scale_x, scale_y, offset_x, offset_y) =
+                compute_placement(*aspect_width, *aspect_height, cell_width, image_box_height, mode);
+            let name = format!("Im{}", cell_index + 1);
+            content.push_str(&format!(
+                "q\n{} {} {} {} re W n\n{} 0 0 {} {} {} cm\n/{} Do\nQ\n",
+                cell_x,
+                image_box_y,
+                cell_width,
+                image_box_height,
+                aspect_width * scale_x,
+                aspect_height * scale_y,
+                cell_x + offset_x,
+                image_box_y + offset_y,
+                name
+            ));
+            xobjects.set(name, Object::Reference(*image_id));
+
+            // 标签：居中显示在图片下方，过长时截断
+            if let Some(label) = label {
+                let size = options.label_font_size;
+                let line = truncate_text(label, cell_width, |text| font.width(text, size));
+                content.push_str(&format!(
+                    "BT\n0 g\n/F1 {} Tf\n1 0 0 1 {} {} Tm\n{} Tj\nET\n",
+                    size,
+                    cell_x + (cell_width - font.width(&line, size)) / 2.0,
+                    cell_top - cell_height + size * 0.4,
+                    font.string(&line)
+                ));
+            }
+        }
 
-    for (idx, image_js) in images.iter().enumerate() {
-        let image_bytes = image_js.to_vec();
-        
-        // 加载图片
-        let img = image::load_from_memory(&image_bytes)
-            .map_err(|e| JsValue::from_str(&format!("无法加载第 {} 张图片: {}", idx + 1, e)))?;
-
-        let (width, height) = img.dimensions();
-        
-        // 将图片转换为 JPEG（PDF 中常用）
-        let mut jpeg_buffer = Vec::new();
-        let mut encoder = JpegEncoder::new_with_quality(&mut jpeg_buffer, 85);
-        encoder.encode(
-            img.as_bytes(),
-            width,
-            height,
-            img.color().into(),
-        ).map_err(|e| JsValue::from_str(&format!("无法转换第 {} 张图片: {}", idx + 1, e)))?;
-
-        // 创建图片对象
-        let mut image_dict = Dictionary::new();
-        image_dict.set("Type", Object::Name(b"XObject".to_vec()));
-        image_dict.set("Subtype", Object::Name(b"Image".to_vec()));
-        image_dict.set("Width", Object::Integer(width as i64));
-        image_dict.set("Height", Object::Integer(height as i64));
-        image_dict.set("ColorSpace", Object::Name(b"DeviceRGB".to_vec()));
-        image_dict.set("BitsPerComponent", Object::Integer(8));
-        image_dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
-
-        let image_stream = Stream::new(image_dict, jpeg_buffer);
-        let image_id = doc.add_object(Object::Stream(image_stream));
-
-        // 创建资源字典
         let mut resources = Dictionary::new();
-        let mut xobject = Dictionary::new();
-        xobject.set("Im1", Object::Reference(image_id));
-        resources.set("XObject", Object::Dictionary(xobject));
-
-        // 创建内容流：绘制图片以适应页面
-        // 使用 A4 页面大小（595 x 842 点）
-        let page_width = 595.0;
-        let page_height = 842.0;
-        
-        // 计算缩放比例以适应页面
-        let scale_x = page_width / width as f64;
-        let scale_y = page_height / height as f64;
-        let scale = scale_x.min(scale_y);
-        
-        let scaled_width = width as f64 * scale;
-        let scaled_height = height as f64 * scale;
-        
-        // 居中图片
-        let x = (page_width - scaled_width) / 2.0;
-        let y = (page_height - scaled_height) / 2.0;
-
-        let content = format!(
-            "q\n{} 0 0 {} {} {} cm\n/Im1 Do\nQ",
-            scaled_width, scaled_height, x, y
-        );
-
-        let content_stream = Stream::new(Dictionary::new(), content.into_bytes());
-        let content_id = doc.add_object(Object::Stream(content_stream));
-
-        // 创建页面对象
+        resources.set("XObject", Object::Dictionary(xobjects));
+        if let Some(font_id) = font_id {
+            let mut fonts = Dictionary::new();
+            fonts.set("F1", Object::Reference(font_id));
+            resources.set("Font", Object::Dictionary(fonts));
+        }
+
+        let content_id = doc.add_object(Object::Stream(Stream::new(Dictionary::new(), content.into_bytes())));
+
         let mut page = Dictionary::new();
         page.set("Type", Object::Name(b"Page".to_vec()));
         page.set("Parent", Object::Reference(pages_id));
         page.set("MediaBox", Object::Array(vec![
             Object::Integer(0),
             Object::Integer(0),
-            Object::Integer(page_width as i64),
-            Object::Integer(page_height as i64),
+            Object::Real(page_width as f32),
+            Object::Real(page_height as f32),
         ]));
         page.set("Contents", Object::Reference(content_id));
         page.set("Resources", Object::Dictionary(resources));
-
-        let page_id = doc.add_object(Object::Dictionary(page));
-        page_ids.push(Object::Reference(page_id));
+        page_ids.push(Object::Reference(doc.add_object(Object::Dictionary(page))));
     }
 
-    // 创建页面树
-    let mut pages = Dictionary::new();
-    pages.set("Type", Object::Name(b"Pages".to_vec()));
-    pages.set("Kids", Object::Array(page_ids));
-    pages.set("Count", Object::Integer(images.len() as i64));
-    doc.objects.insert(pages_id, Object::Dictionary(pages));
-
-    // 创建目录
-    let mut catalog = Dictionary::new();
-    catalog.set("Type", Object::Name(b"Catalog".to_vec()));
-    catalog.set("Pages", Object::Reference(pages_id));
-    let catalog_id = doc.add_object(Object::Dictionary(catalog));
+    if let Some(font_id) = font_id {
+        font.write(&mut doc, font_id)?;
+    }
 
-    doc.trailer.set("Root", Object::Reference(catalog_id));
+    build_page_tree(&mut doc, pages_id, page_ids);
 
     // 保存 PDF
     let mut buffer = Vec::new();
-    doc.save_to(&mut buffer)
-        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+    save_document(&mut doc, &mut buffer, options.linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
 
     Ok(buffer)
 }
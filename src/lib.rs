@@ -3,6 +3,27 @@ use std::panic;
 
 mod pdf_utils;
 mod image_utils;
+mod page_size;
+mod content_stream;
+mod image_embed;
+mod outline;
+mod acroform;
+mod annotations;
+mod redact;
+mod sanitize;
+mod attachments;
+mod repair;
+mod pdfa;
+mod linearize;
+mod standard_font;
+mod filters;
+mod render;
+mod render_color;
+mod render_font;
+mod type1;
+mod cff;
+mod font_embed;
+mod text_pdf;
 
 pub use pdf_utils::*;
 pub use image_utils::*;
@@ -0,0 +1,492 @@
+// 线性化（快速 Web 查看）输出：按 PDF 规范附录 F 重新排列对象，把显示首页所需的对象集中在文件开头，
+// 并写入线性化参数字典、首页交叉引用表和提示流（页面偏移提示表、共享对象提示表）
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use lopdf::xref::XrefType;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Write};
+
+use crate::repair::{collect_references, find, marker_id, reader_for};
+
+/// 文件头第二行的二进制注释：提示传输工具按二进制处理文件，PDF/A 要求其中至少有 4 个大于 127 的字节
+const BINARY_COMMENT: &[u8; 4] = b"\xE2\xE3\xCF\xD3";
+
+/// 保存文档；linearize 为 true 时按线性化格式写出
+pub(crate) fn save_document(doc: &mut Document, buffer: &mut Vec<u8>, linearize: bool) -> io::Result<()> {
+    // lopdf 只写出 "%PDF-x.y" 一行文件头：先在版本号后接一行等长的 ASCII 占位注释，
+    // 写出后再替换为二进制字节，对象偏移不受影响
+    let start = buffer.len();
+    let version = std::mem::take(&mut doc.version);
+    doc.version = format!("{}\n%____", version);
+    let result = if linearize {
+        write_linearized(doc, buffer)
+    } else {
+        doc.save_to(buffer)
+    };
+    doc.version = version;
+    result?;
+
+    let comment = start + format!("%PDF-{}\n%", doc.version).len();
+    if let Some(bytes) = buffer.get_mut(comment..comment + BINARY_COMMENT.len()) {
+        debug_assert_eq!(bytes, b"____");
+        bytes.copy_from_slice(BINARY_COMMENT);
+    }
+    Ok(())
+}
+
+/// 判断文件是否为线性化文件：第一个对象是线性化参数字典，且记录的文件长度与实际一致
+/// （增量更新过的文件长度不符，不再视为线性化）
+pub(crate) fn is_linearized_pdf(buffer: &[u8]) -> bool {
+    let head = &buffer[..buffer.len().min(1024)];
+    let Some((id, offset)) = find(head, b"obj", 0).and_then(|pos| marker_id(buffer, pos)) else {
+        return false;
+    };
+    let Ok(object) = reader_for(buffer, [(id, offset)]).get_object(id) else {
+        return false;
+    };
+    let Ok(dict) = object.as_dict() else {
+        return false;
+    };
+    dict.has(b"Linearized") && dict.get(b"L").and_then(Object::as_i64).is_ok_and(|length| length == buffer.len() as i64)
+}
+
+fn is_page_node(object: &Object) -> bool {
+    matches!(object.type_name(), Ok("Page" | "Pages"))
+}
+
+/// lopdf 保存时会跳过的对象
+fn is_skipped(object: &Object) -> bool {
+    matches!(object.type_name(), Ok("ObjStm" | "XRef" | "Linearized"))
+}
+
+/// 从 seeds 出发按广度优先收集引用的对象；enter_pages 为 false 时不进入其他页面和页面树节点
+fn reachable(doc: &Document, seeds: Vec<ObjectId>, enter_pages: bool) -> Vec<ObjectId> {
+    let mut seen: HashSet<ObjectId> = seeds.iter().copied().collect();
+    let mut queue: VecDeque<ObjectId> = seeds.into();
+    let mut order = Vec::new();
+    while let Some(id) = queue.pop_front() {
+        let Ok(object) = doc.get_object(id) else { continue };
+        if is_skipped(object) {
+            continue;
+        }
+        order.push(id);
+
+        let mut references = Vec::new();
+        collect_references(object, &mut references);
+        for reference in references {
+            if !seen.insert(reference) {
+                continue;
+            }
+            if enter_pages || !doc.get_object(reference).is_ok_and(is_page_node) {
+                queue.push_back(reference);
+            }
+        }
+    }
+    order
+}
+
+/// 对象在线性化文件中的分组
+struct Layout {
+    /// 文档目录及打开文档时就需要的对象（规范中的第 4 部分）
+    catalog: Vec<ObjectId>,
+    /// 首页对象，页面对象在最前（第 6 部分）
+    first_page: Vec<ObjectId>,
+    /// 其余各页的页面对象和只被该页使用的对象（第 7 部分）
+    pages: Vec<Vec<ObjectId>>,
+    /// 被多个页面使用的对象（第 8 部分）
+    shared: Vec<ObjectId>,
+    /// 页面树、书签、文档信息等其他对象（第 9 部分）
+    rest: Vec<ObjectId>,
+    /// 其余各页引用的共享对象在共享对象提示表中的序号
+    shared_refs: Vec<Vec<usize>>,
+}
+
+fn layout_objects(doc: &Document, root: ObjectId, page_ids: &[ObjectId]) -> Layout {
+    let mut assigned = HashSet::from([root]);
+
+    let mut seeds = Vec::new();
+    if let Ok(catalog) = doc.get_dictionary(root) {
+        for key in ["ViewerPreferences", "OpenAction"] {
+            if let Ok(value) = catalog.get(key.as_bytes()) {
+                collect_references(value, &mut seeds);
+            }
+        }
+    }
+    seeds.retain(|&id| !doc.get_object(id).is_ok_and(is_page_node));
+    let mut catalog = vec![root];
+    catalog.extend(reachable(doc, seeds, false).into_iter().filter(|&id| assigned.insert(id)));
+
+    let page_objects: Vec<Vec<ObjectId>> = page_ids.iter().map(|&id| reachable(doc, vec![id], false)).collect();
+    let first_page: Vec<ObjectId> = page_objects[0].iter().copied().filter(|&id| assigned.insert(id)).collect();
+
+    let mut usage: HashMap<ObjectId, usize> = HashMap::new();
+    for id in page_objects[1..].iter().flatten().filter(|id| !assigned.contains(id)) {
+        *usage.entry(*id).or_default() += 1;
+    }
+    let pages: Vec<Vec<ObjectId>> = page_objects[1..].iter()
+        .map(|objects| objects.iter().copied().filter(|id| usage.get(id) == Some(&1) && assigned.insert(*id)).collect())
+        .collect();
+    let shared: Vec<ObjectId> = page_objects[1..].iter().flatten().copied()
+        .filter(|&id| assigned.insert(id))
+        .collect();
+
+    let mut seeds = Vec::new();
+    for key in ["Root", "Info"] {
+        if let Ok(value) = doc.trailer.get(key.as_bytes()) {
+            collect_references(value, &mut seeds);
+        }
+    }
+    let rest = reachable(doc, seeds, true).into_iter().filter(|&id| assigned.insert(id)).collect();
+
+    // 首页对象排在共享对象提示表的前面，之后是共享对象
+    let mut identifiers: HashMap<ObjectId, usize> = HashMap::new();
+    for (index, &id) in first_page.iter().chain(&shared).enumerate() {
+        identifiers.insert(id, index);
+    }
+    let shared_refs = page_objects[1..].iter()
+        .map(|objects| objects.iter().filter_map(|id| identifiers.get(id).copied()).collect())
+        .collect();
+
+    Layout { catalog, first_page, pages, shared, rest, shared_refs }
+}
+
+/// 把引用改为新的对象号；指向不存在对象的引用改为 null
+fn renumber(object: &mut Object, numbers: &HashMap<ObjectId, u32>) {
+    match object {
+        Object::Reference(id) => {
+            let id = *id;
+            *object = numbers.get(&id).map_or(Object::Null, |&number| Object::Reference((number, 0)));
+        }
+        Object::Array(items) => items.iter_mut().for_each(|item| renumber(item, numbers)),
+        Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, value)| renumber(value, numbers)),
+        Object::Stream(stream) => stream.dict.iter_mut().for_each(|(_, value)| renumber(value, numbers)),
+        _ => {}
+    }
+}
+
+/// 对象号到字节范围（起止位置）的映射
+type Spans = HashMap<u32, (usize, usize)>;
+
+/// 按交叉引用表找出每个对象在 lopdf 输出中的字节范围，以及第一个对象之前的文件头长度
+fn object_spans(buffer: &[u8]) -> Option<(Spans, usize)> {
+    let startxref = buffer.windows(9).rposition(|w| w == b"startxref")?;
+    let xref_start: usize = std::str::from_utf8(&buffer[startxref + 9..]).ok()?
+        .split_whitespace().next()?.parse().ok()?;
+
+    let mut offsets = Vec::new();
+    let mut pos = xref_start + b"xref\n".len();
+    while !buffer.get(pos..)?.starts_with(b"trailer") {
+        let eol = find(buffer, b"\n", pos)?;
+        let header = std::str::from_utf8(&buffer[pos..eol]).ok()?;
+        let mut numbers = header.split_whitespace().map(|n| n.parse::<usize>());
+        let (Some(Ok(start)), Some(Ok(count))) = (numbers.next(), numbers.next()) else {
+            return None;
+        };
+        pos = eol + 1;
+        for number in start..start + count {
+            let entry = buffer.get(pos..pos + 20)?;
+            if entry[17] == b'n' {
+                let offset = std::str::from_utf8(&entry[..10]).ok()?.parse().ok()?;
+                offsets.push((offset, number as u32));
+            }
+            pos += 20;
+        }
+    }
+
+    offsets.sort_unstable();
+    let header = offsets.first().map_or(xref_start, |&(offset, _)| offset);
+    let ends = offsets.iter().skip(1).map(|&(offset, _)| offset).chain([xref_start]);
+    let spans = offsets.iter().zip(ends).map(|(&(start, number), end)| (number, (start, end))).collect();
+    Some((spans, header))
+}
+
+/// 按位写入提示表，每组数据结束后补齐到整字节
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    byte: u8,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: usize, width: u32) {
+        for shift in (0..width).rev() {
+            self.byte = (self.byte << 1) | ((value as u64 >> shift) & 1) as u8;
+            self.bits += 1;
+            if self.bits == 8 {
+                self.data.push(self.byte);
+                self.byte = 0;
+                self.bits = 0;
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.bits > 0 {
+            self.data.push(self.byte << (8 - self.bits));
+            self.byte = 0;
+            self.bits = 0;
+        }
+    }
+}
+
+/// 表示 value 需要的位数
+fn bit_width(value: usize) -> u32 {
+    usize::BITS - value.leading_zeros()
+}
+
+/// 页面偏移提示表中每一页的数据
+struct PageHint {
+    objects: usize,
+    length: usize,
+    shared: Vec<usize>,
+}
+
+/// 页面偏移提示表（规范表 F.3、F.4）；内容流偏移记为 0、长度记为整页长度，与 qpdf 相同
+fn page_offset_table(pages: &[PageHint], first_page_location: usize) -> Vec<u8> {
+    let least_objects = pages.iter().map(|p| p.objects).min().unwrap_or(0);
+    let least_length = pages.iter().map(|p| p.length).min().unwrap_or(0);
+    let object_bits = bit_width(pages.iter().map(|p| p.objects - least_objects).max().unwrap_or(0));
+    let length_bits = bit_width(pages.iter().map(|p| p.length - least_length).max().unwrap_or(0));
+    let shared_count_bits = bit_width(pages.iter().map(|p| p.shared.len()).max().unwrap_or(0));
+    let shared_id_bits = bit_width(pages.iter().flat_map(|p| p.shared.iter().copied()).max().unwrap_or(0));
+
+    let mut writer = BitWriter::default();
+    writer.write(least_objects, 32);
+    writer.write(first_page_location, 32);
+    writer.write(object_bits as usize, 16);
+    writer.write(least_length, 32);
+    writer.write(length_bits as usize, 16);
+    writer.write(0, 32);
+    writer.write(0, 16);
+    writer.write(least_length, 32);
+    writer.write(length_bits as usize, 16);
+    writer.write(shared_count_bits as usize, 16);
+    writer.write(shared_id_bits as usize, 16);
+    writer.write(0, 16);
+    writer.write(1, 16);
+
+    pages.iter().for_each(|p| writer.write(p.objects - least_objects, object_bits));
+    writer.flush();
+    pages.iter().for_each(|p| writer.write(p.length - least_length, length_bits));
+    writer.flush();
+    pages.iter().for_each(|p| writer.write(p.shared.len(), shared_count_bits));
+    writer.flush();
+    pages.iter().flat_map(|p| &p.shared).for_each(|&id| writer.write(id, shared_id_bits));
+    writer.flush();
+    pages.iter().for_each(|p| writer.write(p.length - least_length, length_bits));
+    writer.flush();
+    writer.data
+}
+
+/// 共享对象提示表（规范表 F.5、F.6），每个对象单独成组
+fn shared_object_table(first_shared: (u32, usize), first_page_entries: usize, lengths: &[usize]) -> Vec<u8> {
+    let least_length = lengths.iter().copied().min().unwrap_or(0);
+    let length_bits = bit_width(lengths.iter().map(|l| l - least_length).max().unwrap_or(0));
+
+    let mut writer = BitWriter::default();
+    writer.write(first_shared.0 as usize, 32);
+    writer.write(first_shared.1, 32);
+    writer.write(first_page_entries, 32);
+    writer.write(lengths.len(), 32);
+    writer.write(0, 16);
+    writer.write(least_length, 32);
+    writer.write(length_bits as usize, 16);
+
+    lengths.iter().for_each(|&l| writer.write(l - least_length, length_bits));
+    writer.flush();
+    // 不提供 MD5 签名
+    lengths.iter().for_each(|_| writer.write(0, 1));
+    writer.flush();
+    writer.data
+}
+
+/// 首页交叉引用表的尾部字典条目（对象号已换成新编号）
+fn trailer_entries(trailer: &Dictionary, numbers: &HashMap<ObjectId, u32>) -> String {
+    let mut entries = String::new();
+    for key in ["Root", "Info"] {
+        if let Some(number) = trailer.get(key.as_bytes()).and_then(Object::as_reference).ok().and_then(|id| numbers.get(&id)) {
+            entries.push_str(&format!("/{} {} 0 R", key, number));
+        }
+    }
+    if let Ok(ids) = trailer.get(b"ID").and_then(Object::as_array) {
+        let hex: Vec<String> = ids.iter()
+            .filter_map(|id| id.as_str().ok())
+            .map(|id| format!("<{}>", id.iter().map(|b| format!("{:02X}", b)).collect::<String>()))
+            .collect();
+        entries.push_str(&format!("/ID [{}]", hex.join("")));
+    }
+    entries
+}
+
+/// 线性化参数字典对象，用空格补齐到固定长度，以便先排版再填入偏移
+fn linearization_object(number: u32, values: [usize; 7], reserved: usize) -> Vec<u8> {
+    let [length, hint_offset, hint_length, first_page, end, pages, xref] = values;
+    let mut text = format!(
+        "{} 0 obj\n<</Linearized 1/L {}/H [{} {}]/O {}/E {}/N {}/T {}>>",
+        number, length, hint_offset, hint_length, first_page, end, pages, xref
+    );
+    let width = reserved.max(text.len());
+    text.push_str(&" ".repeat(width - text.len()));
+    text.push_str("\nendobj\n");
+    text.into_bytes()
+}
+
+fn xref_entry(offset: usize) -> String {
+    format!("{:010} 00000 n \n", offset)
+}
+
+/// 按线性化格式写出文档。对象重新编号：其余各页、共享对象和其他对象从 1 开始，
+/// 线性化参数字典、文档目录、提示流和首页对象编在最后，由首页交叉引用表单独索引
+fn write_linearized(doc: &mut Document, buffer: &mut Vec<u8>) -> io::Result<()> {
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let root = doc.trailer.get(b"Root").and_then(Object::as_reference).ok();
+    // 加密文档的密钥与对象号相关，不能重新编号；没有页面或页面重复引用时也按普通格式保存
+    let unique_pages = page_ids.iter().collect::<HashSet<_>>().len() == page_ids.len();
+    let (Some(root), false, true) = (root, page_ids.is_empty() || doc.trailer.has(b"Encrypt"), unique_pages) else {
+        return doc.save_to(buffer);
+    };
+
+    let layout = layout_objects(doc, root, &page_ids);
+    let main: Vec<ObjectId> = layout.pages.iter().flatten().chain(&layout.shared).chain(&layout.rest).copied().collect();
+    let first_number = main.len() as u32 + 1;
+    let linearization_number = first_number;
+    let hint_number = first_number + 1 + layout.catalog.len() as u32;
+    let first_page_number = hint_number + 1;
+    let max_number = hint_number + layout.first_page.len() as u32;
+
+    let mut numbers: HashMap<ObjectId, u32> = HashMap::new();
+    for (index, &id) in main.iter().enumerate() {
+        numbers.insert(id, index as u32 + 1);
+    }
+    for (index, &id) in layout.catalog.iter().enumerate() {
+        numbers.insert(id, linearization_number + 1 + index as u32);
+    }
+    for (index, &id) in layout.first_page.iter().enumerate() {
+        numbers.insert(id, first_page_number + index as u32);
+    }
+
+    // 先用 lopdf 按新编号写出全部对象，再截取各对象的字节重新排列
+    let mut output = Document::new();
+    output.version = doc.version.clone();
+    output.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+    for (id, &number) in &numbers {
+        if let Ok(object) = doc.get_object(*id) {
+            let mut object = object.clone();
+            renumber(&mut object, &numbers);
+            output.objects.insert((number, 0), object);
+        }
+    }
+    output.max_id = max_number;
+    let mut written = Vec::new();
+    output.save_to(&mut written)?;
+    let (spans, header_length) = object_spans(&written)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "无法解析交叉引用表"))?;
+    let chunk = |number: u32| spans.get(&number).map_or(&[][..], |&(start, end)| &written[start..end]);
+    let length = |number: u32| chunk(number).len();
+
+    let catalog_numbers: Vec<u32> = layout.catalog.iter().map(|id| numbers[id]).collect();
+    let first_page_numbers: Vec<u32> = layout.first_page.iter().map(|id| numbers[id]).collect();
+    let main_numbers: Vec<u32> = (1..first_number).collect();
+
+    // 线性化参数字典和首页交叉引用表按最大取值预留长度
+    let linearization_length = linearization_object(linearization_number, [u32::MAX as usize; 7], 0).len();
+    let entries = trailer_entries(&doc.trailer, &numbers);
+    let first_trailer = |prev: usize| format!("trailer\n<</Size {}{}/Prev {}>>", max_number + 1, entries, prev);
+    let trailer_length = first_trailer(u32::MAX as usize).len();
+    let first_xref_count = (max_number - first_number + 1) as usize;
+    let first_xref_length = format!("xref\n{} {}\n", first_number, first_xref_count).len()
+        + 20 * first_xref_count + trailer_length + "\nstartxref\n0\n%%EOF\n".len();
+
+    // 不含提示流时各对象的位置，提示表中的偏移都按此计算
+    let mut positions: HashMap<u32, usize> = HashMap::new();
+    let mut pos = header_length + linearization_length + first_xref_length;
+    for &number in &catalog_numbers {
+        positions.insert(number, pos);
+        pos += length(number);
+    }
+    let hint_offset = pos;
+    for &number in first_page_numbers.iter().chain(&main_numbers) {
+        positions.insert(number, pos);
+        pos += length(number);
+    }
+    let main_xref = pos;
+
+    let mut page_hints = vec![PageHint {
+        objects: first_page_numbers.len(),
+        length: first_page_numbers.iter().map(|&n| length(n)).sum(),
+        shared: Vec::new(),
+    }];
+    for (objects, shared) in layout.pages.iter().zip(&layout.shared_refs) {
+        page_hints.push(PageHint {
+            objects: objects.len(),
+            length: objects.iter().map(|id| length(numbers[id])).sum(),
+            shared: shared.clone(),
+        });
+    }
+    let shared_numbers: Vec<u32> = layout.shared.iter().map(|id| numbers[id]).collect();
+    let first_shared = shared_numbers.first().map_or((0, 0), |&n| (n, positions[&n]));
+    let group_lengths: Vec<usize> = first_page_numbers.iter().chain(&shared_numbers).map(|&n| length(n)).collect();
+
+    let mut hint_data = page_offset_table(&page_hints, positions[&first_page_number]);
+    let shared_offset = hint_data.len();
+    hint_data.extend(shared_object_table(first_shared, first_page_numbers.len(), &group_lengths));
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&hint_data)?;
+    let hint_data = encoder.finish()?;
+    let mut hint = format!("{} 0 obj\n<</Filter/FlateDecode/Length {}/S {}>>stream\n", hint_number, hint_data.len(), shared_offset).into_bytes();
+    hint.extend(&hint_data);
+    hint.extend(b"\nendstream\nendobj\n");
+
+    // 提示流之后的对象整体后移提示流的长度
+    let real = |position: usize| if position >= hint_offset { position + hint.len() } else { position };
+    let first_xref_offset = header_length + linearization_length;
+    let main_xref_real = real(main_xref);
+    let main_xref_header = format!("xref\n0 {}\n", first_number);
+    let main_trailer = format!("trailer\n<</Size {}>>\nstartxref\n{}\n%%EOF", first_number, first_xref_offset);
+    let file_length = main_xref_real + main_xref_header.len() + 20 * first_number as usize + main_trailer.len();
+    let first_page_end = real(positions.get(&1).copied().unwrap_or(main_xref));
+
+    buffer.extend(&written[..header_length]);
+    buffer.extend(linearization_object(linearization_number, [
+        file_length,
+        hint_offset,
+        hint.len(),
+        first_page_number as usize,
+        first_page_end,
+        page_ids.len(),
+        main_xref_real + main_xref_header.len() - 1,
+    ], linearization_length - "\nendobj\n".len()));
+
+    buffer.extend(format!("xref\n{} {}\n", first_number, first_xref_count).as_bytes());
+    buffer.extend(xref_entry(header_length).as_bytes());
+    for &number in &catalog_numbers {
+        buffer.extend(xref_entry(positions[&number]).as_bytes());
+    }
+    buffer.extend(xref_entry(hint_offset).as_bytes());
+    for &number in &first_page_numbers {
+        buffer.extend(xref_entry(real(positions[&number])).as_bytes());
+    }
+    let trailer = first_trailer(main_xref_real);
+    buffer.extend(trailer.as_bytes());
+    buffer.extend(" ".repeat(trailer_length - trailer.len()).as_bytes());
+    buffer.extend(b"\nstartxref\n0\n%%EOF\n");
+
+    for &number in &catalog_numbers {
+        buffer.extend(chunk(number));
+    }
+    buffer.extend(&hint);
+    for &number in first_page_numbers.iter().chain(&main_numbers) {
+        buffer.extend(chunk(number));
+    }
+
+    buffer.extend(main_xref_header.as_bytes());
+    buffer.extend(b"0000000000 65535 f \n");
+    for &number in &main_numbers {
+        buffer.extend(xref_entry(real(positions[&number])).as_bytes());
+    }
+    buffer.extend(main_trailer.as_bytes());
+    Ok(())
+}
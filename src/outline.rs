@@ -0,0 +1,473 @@
+// 书签（文档大纲）
+
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::page_size::parse_color;
+use crate::pdf_utils::object_to_f64;
+use crate::render_color::resolve;
+
+/// 读取书签和解析命名目标时允许的最大嵌套深度
+const MAX_DEPTH: usize = 64;
+
+/// 书签跳转后页面的显示方式，None 表示保持当前值
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DestView {
+    Xyz { left: Option<f64>, top: Option<f64>, zoom: Option<f64> },
+    Fit,
+    FitH(Option<f64>),
+    FitV(Option<f64>),
+    /// [left, bottom, right, top]
+    FitR([f64; 4]),
+    FitB,
+    FitBH(Option<f64>),
+    FitBV(Option<f64>),
+}
+
+impl DestView {
+    /// 由目标数组中页面之后的部分解析，如 /XYZ 0 792 null
+    fn parse(args: &[Object]) -> Option<DestView> {
+        let number = |index: usize| args.get(index + 1).and_then(object_to_f64);
+        let view = match args.first()?.as_name().ok()? {
+            b"XYZ" => DestView::Xyz { left: number(0), top: number(1), zoom: number(2).filter(|z| *z != 0.0) },
+            b"Fit" => DestView::Fit,
+            b"FitH" => DestView::FitH(number(0)),
+            b"FitV" => DestView::FitV(number(0)),
+            b"FitR" => DestView::FitR([number(0)?, number(1)?, number(2)?, number(3)?]),
+            b"FitB" => DestView::FitB,
+            b"FitBH" => DestView::FitBH(number(0)),
+            b"FitBV" => DestView::FitBV(number(0)),
+            _ => return None,
+        };
+        Some(view)
+    }
+
+    fn to_objects(self) -> Vec<Object> {
+        let number = |value: Option<f64>| value.map_or(Object::Null, |v| Object::Real(v as f32));
+        let (name, args): (&[u8], Vec<Object>) = match self {
+            DestView::Xyz { left, top, zoom } => (b"XYZ", vec![number(left), number(top), number(zoom)]),
+            DestView::Fit => (b"Fit", Vec::new()),
+            DestView::FitH(top) => (b"FitH", vec![number(top)]),
+            DestView::FitV(left) => (b"FitV", vec![number(left)]),
+            DestView::FitR(rect) => (b"FitR", rect.iter().map(|&v| number(Some(v))).collect()),
+            DestView::FitB => (b"FitB", Vec::new()),
+            DestView::FitBH(top) => (b"FitBH", vec![number(top)]),
+            DestView::FitBV(left) => (b"FitBV", vec![number(left)]),
+        };
+        std::iter::once(Object::Name(name.to_vec())).chain(args).collect()
+    }
+}
+
+/// 书签项
+pub(crate) struct OutlineItem {
+    pub title: String,
+    /// 跳转的目标页面和显示方式；None 表示该项不跳转
+    pub dest: Option<(ObjectId, DestView)>,
+    /// 是否展开子项
+    pub open: bool,
+    /// 标题颜色，RGB 分量取值 0-1
+    pub color: Option<(f64, f64, f64)>,
+    pub bold: bool,
+    pub italic: bool,
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    /// 跳转到指定页面的书签，子项默认展开
+    pub(crate) fn new(title: String, page_id: ObjectId, view: DestView) -> OutlineItem {
+        OutlineItem {
+            title,
+            dest: Some((page_id, view)),
+            open: true,
+            color: None,
+            bold: false,
+            italic: false,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// 书签的 JSON 表示，页码从 1 开始
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct OutlineEntry {
+    title: String,
+    /// 目标页码；null 表示该项不跳转
+    page: Option<usize>,
+    /// 显示方式: "Fit"（默认）、"XYZ"、"FitH"、"FitV"、"FitR"、"FitB"、"FitBH"、"FitBV"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    left: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top: Option<f64>,
+    /// 缩放比例，1 表示 100%（仅 XYZ）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zoom: Option<f64>,
+    /// FitR 的显示区域 [left, bottom, right, top]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rect: Option<[f64; 4]>,
+    /// 是否展开子项
+    open: bool,
+    /// 标题颜色，如 "#ff0000"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    bold: bool,
+    italic: bool,
+    children: Vec<OutlineEntry>,
+}
+
+/// 将书签转换为 JSON 表示，page_numbers 为页面对象 → 页码
+pub(crate) fn outline_to_entries(items: &[OutlineItem], page_numbers: &HashMap<ObjectId, usize>) -> Vec<OutlineEntry> {
+    items
+        .iter()
+        .map(|item| {
+            let mut entry = OutlineEntry {
+                title: item.title.clone(),
+                open: item.open && !item.children.is_empty(),
+                color: item.color.map(|(r, g, b)| {
+                    let channel = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+                    format!("#{:02x}{:02x}{:02x}", channel(r), channel(g), channel(b))
+                }),
+                bold: item.bold,
+                italic: item.italic,
+                children: outline_to_entries(&item.children, page_numbers),
+                ..OutlineEntry::default()
+            };
+            if let Some((page_id, view)) = item.dest {
+                entry.page = page_numbers.get(&page_id).copied();
+                let fit = match view {
+                    DestView::Xyz { left, top, zoom } => {
+                        (entry.left, entry.top, entry.zoom) = (left, top, zoom);
+                        "XYZ"
+                    }
+                    DestView::Fit => "Fit",
+                    DestView::FitH(top) => {
+                        entry.top = top;
+                        "FitH"
+                    }
+                    DestView::FitV(left) => {
+                        entry.left = left;
+                        "FitV"
+                    }
+                    DestView::FitR(rect) => {
+                        entry.rect = Some(rect);
+                        "FitR"
+                    }
+                    DestView::FitB => "FitB",
+                    DestView::FitBH(top) => {
+                        entry.top = top;
+                        "FitBH"
+                    }
+                    DestView::FitBV(left) => {
+                        entry.left = left;
+                        "FitBV"
+                    }
+                };
+                entry.fit = Some(fit.to_string());
+            }
+            entry
+        })
+        .collect()
+}
+
+/// 由 JSON 表示生成书签，page_ids 为按页码排列的页面对象
+pub(crate) fn entries_to_outline(entries: &[OutlineEntry], page_ids: &[ObjectId]) -> Result<Vec<OutlineItem>, String> {
+    entries
+        .iter()
+        .map(|entry| {
+            let dest = match entry.page {
+                None => None,
+                Some(page) => {
+                    let page_id = page.checked_sub(1).and_then(|index| page_ids.get(index)).ok_or_else(|| {
+                        format!("书签「{}」的页码 {} 超出范围（共 {} 页）", entry.title, page, page_ids.len())
+                    })?;
+                    let view = match entry.fit.as_deref().unwrap_or("Fit").to_lowercase().as_str() {
+                        "xyz" => DestView::Xyz { left: entry.left, top: entry.top, zoom: entry.zoom },
+                        "fit" => DestView::Fit,
+                        "fith" => DestView::FitH(entry.top),
+                        "fitv" => DestView::FitV(entry.left),
+                        "fitr" => DestView::FitR(entry.rect.ok_or_else(|| format!("书签「{}」缺少 FitR 的 rect", entry.title))?),
+                        "fitb" => DestView::FitB,
+                        "fitbh" => DestView::FitBH(entry.top),
+                        "fitbv" => DestView::FitBV(entry.left),
+                        _ => return Err(format!("不支持的显示方式: {}", entry.fit.as_deref().unwrap_or_default())),
+                    };
+                    Some((*page_id, view))
+                }
+            };
+            Ok(OutlineItem {
+                title: entry.title.clone(),
+                dest,
+                open: entry.open,
+                color: entry.color.as_deref().map(parse_color).transpose()?,
+                bold: entry.bold,
+                italic: entry.italic,
+                children: entries_to_outline(&entry.children, page_ids)?,
+            })
+        })
+        .collect()
+}
+
+/// 将文本编码为 PDF 文本字符串：纯 ASCII 原样写入，否则使用带 BOM 的 UTF-16BE
+pub(crate) fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::String(text.as_bytes().to_vec(), StringFormat::Literal);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(|u| u.to_be_bytes()));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// 解码 PDF 文本字符串：带 BOM 的 UTF-16BE 或 UTF-8，其余按 Latin-1 近似 PDFDocEncoding
+pub(crate) fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16.as_chunks::<2>().0.iter().map(|&b| u16::from_be_bytes(b)).collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(utf8).into_owned();
+    }
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// 读取文档的书签；无法解析目标的书签项保留标题，不跳转
+pub(crate) fn read_outline(doc: &Document) -> Vec<OutlineItem> {
+    let first = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Outlines").ok())
+        .and_then(|outlines| resolve(doc, outlines).as_dict().ok())
+        .and_then(|outlines| outlines.get(b"First").ok());
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let mut visited = HashSet::new();
+    read_outline_items(doc, first, &pages, &mut visited, 0)
+}
+
+fn read_outline_items(
+    doc: &Document,
+    first: Option<&Object>,
+    pages: &[ObjectId],
+    visited: &mut HashSet<ObjectId>,
+    depth: usize,
+) -> Vec<OutlineItem> {
+    let mut items = Vec::new();
+    let mut next = first.and_then(|o| o.as_reference().ok());
+    while let Some(id) = next {
+        // 防止链接成环的损坏文件
+        if depth > MAX_DEPTH || !visited.insert(id) {
+            break;
+        }
+        let Ok(dict) = doc.get_dictionary(id) else { break };
+
+        let title = match dict.get(b"Title").map(|t| resolve(doc, t)) {
+            Ok(Object::String(bytes, _)) => decode_text_string(bytes),
+            _ => String::new(),
+        };
+        let dest = match dict.get(b"Dest") {
+            Ok(dest) => resolve_dest(doc, dest, pages, 0),
+            Err(_) => dict
+                .get(b"A")
+                .ok()
+                .and_then(|action| resolve(doc, action).as_dict().ok())
+                .filter(|action| action.get(b"S").and_then(Object::as_name).is_ok_and(|s| s == b"GoTo"))
+                .and_then(|action| action.get(b"D").ok())
+                .and_then(|dest| resolve_dest(doc, dest, pages, 0)),
+        };
+        let color = dict
+            .get(b"C")
+            .ok()
+            .and_then(|c| resolve(doc, c).as_array().ok())
+            .and_then(|c| Some((object_to_f64(c.first()?)?, object_to_f64(c.get(1)?)?, object_to_f64(c.get(2)?)?)));
+        let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+
+        items.push(OutlineItem {
+            title,
+            dest,
+            open: dict.get(b"Count").and_then(Object::as_i64).is_ok_and(|count| count > 0),
+            color,
+            bold: flags & 2 != 0,
+            italic: flags & 1 != 0,
+            children: read_outline_items(doc, dict.get(b"First").ok(), pages, visited, depth + 1),
+        });
+        next = dict.get(b"Next").ok().and_then(|o| o.as_reference().ok());
+    }
+    items
+}
+
+/// 解析跳转目标：目标数组、命名目标（名称或字符串）或带 /D 的字典
+fn resolve_dest(doc: &Document, dest: &Object, pages: &[ObjectId], depth: usize) -> Option<(ObjectId, DestView)> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    match resolve(doc, dest) {
+        Object::Array(array) => {
+            let page_id = match array.first()? {
+                Object::Reference(id) => *id,
+                // 整数表示页面序号（从 0 开始），一般只出现在跨文档跳转中
+                Object::Integer(index) => *pages.get(usize::try_from(*index).ok()?)?,
+                _ => return None,
+            };
+            Some((page_id, DestView::parse(&array[1..]).unwrap_or(DestView::Fit)))
+        }
+        Object::Dictionary(dict) => resolve_dest(doc, dict.get(b"D").ok()?, pages, depth + 1),
+        Object::Name(name) => {
+            let dests = doc.catalog().ok()?.get(b"Dests").ok()?;
+            let target = resolve(doc, dests).as_dict().ok()?.get(name).ok()?;
+            resolve_dest(doc, target, pages, depth + 1)
+        }
+        Object::String(name, _) => {
+            let names = resolve(doc, doc.catalog().ok()?.get(b"Names").ok()?).as_dict().ok()?;
+            let target = find_in_name_tree(doc, names.get(b"Dests").ok()?, name, 0)?;
+            resolve_dest(doc, target, pages, depth + 1)
+        }
+        _ => None,
+    }
+}
+
+/// 在名称树中查找键对应的值
+fn find_in_name_tree<'a>(doc: &'a Document, node: &'a Object, key: &[u8], depth: usize) -> Option<&'a Object> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let node = resolve(doc, node).as_dict().ok()?;
+    if let Ok(names) = node.get(b"Names").and_then(Object::as_array) {
+        let found = names
+            .chunks(2)
+            .find(|pair| matches!(resolve(doc, &pair[0]), Object::String(name, _) if name == key))
+            .and_then(|pair| pair.get(1));
+        if found.is_some() {
+            return found;
+        }
+    }
+    node.get(b"Kids")
+        .and_then(Object::as_array)
+        .ok()?
+        .iter()
+        .find_map(|kid| find_in_name_tree(doc, kid, key, depth + 1))
+}
+
+/// 按页面对象的映射改写书签的目标，映射中没有的页面改为不跳转
+pub(crate) fn remap_outline(items: &mut [OutlineItem], page_map: &HashMap<ObjectId, ObjectId>) {
+    for item in items {
+        item.dest = item.dest.and_then(|(page_id, view)| Some((*page_map.get(&page_id)?, view)));
+        remap_outline(&mut item.children, page_map);
+    }
+}
+
+/// 为文档写入书签，替换并删除目录中原有的 /Outlines；items 为空时只删除原有书签
+pub(crate) fn write_outline(doc: &mut Document, catalog_id: ObjectId, items: &[OutlineItem]) -> Result<(), String> {
+    let old_outlines = doc
+        .get_dictionary(catalog_id)
+        .map_err(|e| format!("无法获取文档目录: {}", e))?
+        .get(b"Outlines")
+        .and_then(Object::as_reference)
+        .ok();
+    if let Some(old_outlines) = old_outlines {
+        remove_outline_tree(doc, old_outlines);
+    }
+
+    let outlines_id = (!items.is_empty()).then(|| {
+        let outlines_id = doc.new_object_id();
+        let (first, last, count) = write_outline_items(doc, outlines_id, items);
+
+        let mut outlines = Dictionary::new();
+        outlines.set("Type", Object::Name(b"Outlines".to_vec()));
+        if let (Some(first), Some(last)) = (first, last) {
+            outlines.set("First", Object::Reference(first));
+            outlines.set("Last", Object::Reference(last));
+            outlines.set("Count", Object::Integer(count));
+        }
+        doc.objects.insert(outlines_id, Object::Dictionary(outlines));
+        outlines_id
+    });
+
+    let catalog = doc.get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|e| format!("无法获取文档目录: {}", e))?;
+    match outlines_id {
+        Some(outlines_id) => {
+            catalog.set("Outlines", Object::Reference(outlines_id));
+            catalog.set("PageMode", Object::Name(b"UseOutlines".to_vec()));
+        }
+        None => {
+            catalog.remove(b"Outlines");
+            if catalog.get(b"PageMode").and_then(Object::as_name).is_ok_and(|mode| mode == b"UseOutlines") {
+                catalog.remove(b"PageMode");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 删除原有书签树的全部节点
+/// 只删除带 /Title 和 /Parent 的书签项，错误的 /First、/Next 指向的页面等其他对象保持不变；
+/// 记录访问过的节点，循环引用的书签树也能结束
+fn remove_outline_tree(doc: &mut Document, root: ObjectId) {
+    let mut visited = HashSet::new();
+    let mut pending = Vec::new();
+    if let Some(Object::Dictionary(dict)) = doc.objects.remove(&root) {
+        visited.insert(root);
+        pending.extend(dict.get(b"First").and_then(Object::as_reference));
+    }
+    while let Some(id) = pending.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let is_item = doc.get_dictionary(id).is_ok_and(|dict| dict.has(b"Title") && dict.has(b"Parent"));
+        if !is_item {
+            continue;
+        }
+        let Some(Object::Dictionary(dict)) = doc.objects.remove(&id) else { continue };
+        for key in [b"First".as_slice(), b"Next"] {
+            if let Ok(next) = dict.get(key).and_then(Object::as_reference) {
+                pending.push(next);
+            }
+        }
+    }
+}
+
+/// 写入同一层级的书签项，返回 (第一项, 最后一项, 可见的项数)
+/// 展开的书签 /Count 为可见子孙数，折叠的书签为其相反数
+fn write_outline_items(doc: &mut Document, parent_id: ObjectId, items: &[OutlineItem]) -> (Option<ObjectId>, Option<ObjectId>, i64) {
+    let ids: Vec<ObjectId> = items.iter().map(|_| doc.new_object_id()).collect();
+    let mut visible = 0;
+
+    for (index, item) in items.iter().enumerate() {
+        let mut dict = Dictionary::new();
+        dict.set("Title", text_string(&item.title));
+        dict.set("Parent", Object::Reference(parent_id));
+        if let Some((page_id, view)) = item.dest {
+            let mut dest = vec![Object::Reference(page_id)];
+            dest.extend(view.to_objects());
+            dict.set("Dest", Object::Array(dest));
+        }
+        if index > 0 {
+            dict.set("Prev", Object::Reference(ids[index - 1]));
+        }
+        if let Some(next) = ids.get(index + 1) {
+            dict.set("Next", Object::Reference(*next));
+        }
+        if let Some((r, g, b)) = item.color {
+            dict.set("C", Object::Array(vec![Object::Real(r as f32), Object::Real(g as f32), Object::Real(b as f32)]));
+        }
+        let flags = i64::from(item.italic) | i64::from(item.bold) << 1;
+        if flags != 0 {
+            dict.set("F", Object::Integer(flags));
+        }
+
+        let (first, last, count) = write_outline_items(doc, ids[index], &item.children);
+        if let (Some(first), Some(last)) = (first, last) {
+            dict.set("First", Object::Reference(first));
+            dict.set("Last", Object::Reference(last));
+            dict.set("Count", Object::Integer(if item.open { count } else { -count }));
+        }
+
+        visible += 1 + if item.open { count } else { 0 };
+        doc.objects.insert(ids[index], Object::Dictionary(dict));
+    }
+
+    (ids.first().copied(), ids.last().copied(), visible)
+}
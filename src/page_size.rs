@@ -0,0 +1,195 @@
+// 纸张尺寸与内容摆放计算
+// 所有尺寸均以 PDF 点（1/72 英寸）为单位
+
+pub(crate) const MM_TO_PT: f64 = 72.0 / 25.4;
+
+/// ISO A 系列尺寸（毫米，竖版），下标即编号 A0-A10
+const A_SERIES_MM: [(f64, f64); 11] = [
+    (841.0, 1189.0),
+    (594.0, 841.0),
+    (420.0, 594.0),
+    (297.0, 420.0),
+    (210.0, 297.0),
+    (148.0, 210.0),
+    (105.0, 148.0),
+    (74.0, 105.0),
+    (52.0, 74.0),
+    (37.0, 52.0),
+    (26.0, 37.0),
+];
+
+/// ISO B 系列尺寸（毫米，竖版），下标即编号 B0-B10
+const B_SERIES_MM: [(f64, f64); 11] = [
+    (1000.0, 1414.0),
+    (707.0, 1000.0),
+    (500.0, 707.0),
+    (353.0, 500.0),
+    (250.0, 353.0),
+    (176.0, 250.0),
+    (125.0, 176.0),
+    (88.0, 125.0),
+    (62.0, 88.0),
+    (44.0, 62.0),
+    (31.0, 44.0),
+];
+
+/// 解析纸张尺寸
+/// 支持 "A0"-"A10"、"B0"-"B10"、"Letter"、"Legal"、"Tabloid"、"Executive"，
+/// 以及自定义尺寸 "595x842"（点）、"210x297mm"、"8.5x11in"
+pub(crate) fn parse_paper_size(name: &str) -> Result<(f64, f64), String> {
+    let normalized = name.trim().to_lowercase();
+
+    match normalized.as_str() {
+        "letter" => return Ok((612.0, 792.0)),
+        "legal" => return Ok((612.0, 1008.0)),
+        "tabloid" | "ledger" => return Ok((792.0, 1224.0)),
+        "executive" => return Ok((522.0, 756.0)),
+        _ => {}
+    }
+
+    // A/B 系列
+    if let Some(series) = normalized.chars().next().filter(|c| *c == 'a' || *c == 'b') {
+        if let Ok(index) = normalized[1..].parse::<usize>() {
+            let table = if series == 'a' { &A_SERIES_MM } else { &B_SERIES_MM };
+            if let Some(&(w, h)) = table.get(index) {
+                return Ok((w * MM_TO_PT, h * MM_TO_PT));
+            }
+        }
+    }
+
+    // 自定义尺寸
+    let (dims, unit) = if let Some(dims) = normalized.strip_suffix("mm") {
+        (dims, MM_TO_PT)
+    } else if let Some(dims) = normalized.strip_suffix("in") {
+        (dims, 72.0)
+    } else {
+        (normalized.strip_suffix("pt").unwrap_or(&normalized), 1.0)
+    };
+
+    let parts: Vec<&str> = dims.split(['x', '*']).collect();
+    if parts.len() == 2 {
+        if let (Ok(w), Ok(h)) = (parts[0].trim().parse::<f64>(), parts[1].trim().parse::<f64>()) {
+            if w > 0.0 && h > 0.0 {
+                return Ok((w * unit, h * unit));
+            }
+        }
+    }
+
+    Err(format!("无法识别的纸张尺寸: {}", name))
+}
+
+/// 内容缩放模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FitMode {
+    /// 等比缩放，完整显示内容（可能留白）
+    Fit,
+    /// 等比缩放，填满目标区域（可能裁掉部分内容）
+    Fill,
+    /// 非等比拉伸，填满目标区域
+    Stretch,
+    /// 保持原始尺寸，不缩放
+    ActualSize,
+}
+
+impl FitMode {
+    pub(crate) fn parse(mode: &str) -> Result<FitMode, String> {
+        match mode.trim().to_lowercase().as_str() {
+            "" | "fit" | "contain" => Ok(FitMode::Fit),
+            "fill" | "cover" => Ok(FitMode::Fill),
+            "stretch" => Ok(FitMode::Stretch),
+            "actual_size" | "actual" | "none" => Ok(FitMode::ActualSize),
+            _ => Err(format!("不支持的缩放模式: {}", mode)),
+        }
+    }
+}
+
+/// 内容在目标区域中的对齐方式
+/// horizontal: 0 左对齐、0.5 居中、1 右对齐；vertical: 0 底部、0.5 居中、1 顶部
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Alignment {
+    pub horizontal: f64,
+    pub vertical: f64,
+}
+
+impl Alignment {
+    pub(crate) const CENTER: Alignment = Alignment { horizontal: 0.5, vertical: 0.5 };
+
+    /// 解析对齐方式，如 "center"、"top"、"bottom-right"、"top-left"
+    pub(crate) fn parse(align: &str) -> Result<Alignment, String> {
+        let mut alignment = Alignment::CENTER;
+        for part in align.trim().to_lowercase().split(['-', ' ', '_']).filter(|p| !p.is_empty()) {
+            match part {
+                "center" | "middle" => {}
+                "left" => alignment.horizontal = 0.0,
+                "right" => alignment.horizontal = 1.0,
+                "top" => alignment.vertical = 1.0,
+                "bottom" => alignment.vertical = 0.0,
+                _ => return Err(format!("不支持的对齐方式: {}", align)),
+            }
+        }
+        Ok(alignment)
+    }
+}
+
+/// 计算将 src 尺寸的内容居中放入 dst 区域时的缩放与偏移
+/// 返回 (scale_x, scale_y, offset_x, offset_y)
+pub(crate) fn compute_placement(
+    src_width: f64,
+    src_height: f64,
+    dst_width: f64,
+    dst_height: f64,
+    mode: FitMode,
+) -> (f64, f64, f64, f64) {
+    compute_aligned_placement(src_width, src_height, dst_width, dst_height, mode, Alignment::CENTER)
+}
+
+/// 计算将 src 尺寸的内容按指定对齐方式放入 dst 区域时的缩放与偏移
+/// 返回 (scale_x, scale_y, offset_x, offset_y)
+pub(crate) fn compute_aligned_placement(
+    src_width: f64,
+    src_height: f64,
+    dst_width: f64,
+    dst_height: f64,
+    mode: FitMode,
+    align: Alignment,
+) -> (f64, f64, f64, f64) {
+    let scale_x = dst_width / src_width;
+    let scale_y = dst_height / src_height;
+
+    let (scale_x, scale_y) = match mode {
+        FitMode::Fit => {
+            let scale = scale_x.min(scale_y);
+            (scale, scale)
+        }
+        FitMode::Fill => {
+            let scale = scale_x.max(scale_y);
+            (scale, scale)
+        }
+        FitMode::Stretch => (scale_x, scale_y),
+        FitMode::ActualSize => (1.0, 1.0),
+    };
+
+    let offset_x = (dst_width - src_width * scale_x) * align.horizontal;
+    let offset_y = (dst_height - src_height * scale_y) * align.vertical;
+
+    (scale_x, scale_y, offset_x, offset_y)
+}
+
+/// 解析颜色字符串 "#rrggbb" 或 "#rgb"，返回 0-1 范围的 RGB 分量
+pub(crate) fn parse_color(color: &str) -> Result<(f64, f64, f64), String> {
+    let hex = color.trim().trim_start_matches('#');
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("无效的颜色: {}", color));
+    }
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return Err(format!("无效的颜色: {}", color)),
+    };
+    let component = |i: usize| {
+        u8::from_str_radix(&expanded[i..i + 2], 16)
+            .map(|v| v as f64 / 255.0)
+            .map_err(|_| format!("无效的颜色: {}", color))
+    };
+    Ok((component(0)?, component(2)?, component(4)?))
+}
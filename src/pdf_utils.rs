@@ -1,154 +1,467 @@
 use wasm_bindgen::prelude::*;
 use lopdf::{Document, Object, Stream, Dictionary, ObjectId};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::annotations::{add_new_annotations, flatten_all_annotations, list_page_annotations, remove_matching_annotations, AnnotationFilter, NewAnnotation};
+use crate::attachments::{attachment_data, embed_attachment, list_document_attachments, AttachmentMerger};
+use crate::acroform::{fill_form_fields, flatten_form_fields, form_field_infos, FormMerger};
+use crate::content_stream::page_content_bounds;
+use crate::image_embed::{decode_frames, embed_frame};
+use crate::outline::{entries_to_outline, outline_to_entries, read_outline, remap_outline, write_outline, DestView, OutlineEntry, OutlineItem};
+use crate::page_size::{compute_placement, parse_paper_size, FitMode, MM_TO_PT};
+use crate::linearize::{is_linearized_pdf, save_document};
+use crate::pdfa::{check_pdfa_document, convert_document, PdfALevel};
+use crate::repair::{repair_document, validate_document};
+use crate::redact::{redact_document, RedactOptions};
+use crate::sanitize::{sanitize_document, SanitizePolicy};
+use crate::render::{check_dpi, encode_pixmap, page_display_size, render_page, RenderOptions};
+use crate::text_pdf::{text_to_pdf_bytes, TextPdfOptions};
+
+/// 合并选项（以 JSON 字符串传入）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MergeOptions {
+    /// 统一页面尺寸，例如 "A4"、"Letter"、"210x297mm"；不设置时保持原尺寸
+    page_size: Option<String>,
+    /// 统一页面尺寸时的缩放模式: "fit"（默认）、"fill"、"stretch"
+    fit_mode: Option<String>,
+    /// 在相邻两个文件之间插入一页空白页
+    blank_page_between: bool,
+    /// 保证每个文件从奇数页开始（双面打印时每个文件从正面开始）
+    start_on_odd_page: bool,
+    /// 为每个文件生成一个指向其第一页的书签，原文件的书签作为子项保留
+    bookmarks: bool,
+    /// 每个文件的书签标题（如文件名），按顺序与文件对应；设置后自动生成书签，缺少的使用 "文件 N"
+    bookmark_titles: Vec<String>,
+    /// 保留各文件的文档级附件，同名附件自动改名
+    keep_attachments: bool,
+    /// 页面或其引用的对象不存在时报错，而不是跳过页面或把引用替换为 null
+    strict: bool,
+    /// 直接输出 PDF/A，如 "2b"（也可以是 "1b"、"3b"）；输入文件的字体必须已嵌入，否则请先合并再用 convert_to_pdfa_with_font 转换
+    pdfa: Option<String>,
+    /// 输出线性化（快速 Web 查看）文件
+    linearize: bool,
+}
 
 /// PDF 合并功能
 /// 接收多个 PDF 文件的字节数组，返回合并后的 PDF
 #[wasm_bindgen]
 pub fn merge_pdfs(pdf_files: Vec<js_sys::Uint8Array>) -> Result<Vec<u8>, JsValue> {
+    let pdf_files: Vec<Vec<u8>> = pdf_files.iter().map(|f| f.to_vec()).collect();
+
+    merge_pdf_bytes(&pdf_files, &MergeOptions::default())
+        .map(|(pdf, _)| pdf)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 带选项的 PDF 合并
+/// options: JSON 字符串，例如 {"page_size": "A4", "fit_mode": "fit"}
+#[wasm_bindgen]
+pub fn merge_pdfs_with_options(
+    pdf_files: Vec<js_sys::Uint8Array>,
+    options: &str,
+) -> Result<Vec<u8>, JsValue> {
+    let options: MergeOptions = serde_json::from_str(options)
+        .map_err(|e| JsValue::from_str(&format!("无效的合并选项: {}", e)))?;
+    let pdf_files: Vec<Vec<u8>> = pdf_files.iter().map(|f| f.to_vec()).collect();
+
+    merge_pdf_bytes(&pdf_files, &options)
+        .map(|(pdf, _)| pdf)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 带选项合并 PDF，同时返回缺失的页面和对象
+/// 源文件中页面或其引用的对象不存在时，默认跳过页面、把引用替换为 null 并记录警告；
+/// 选项中 "strict": true 时直接报错
+#[wasm_bindgen]
+pub fn merge_pdfs_with_warnings(
+    pdf_files: Vec<js_sys::Uint8Array>,
+    options: &str,
+) -> Result<PdfWithWarnings, JsValue> {
+    let pdf_files: Vec<Vec<u8>> = pdf_files.iter().map(|f| f.to_vec()).collect();
+
+    merge_pdfs_from_bytes(&pdf_files, options)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// merge_pdfs_with_warnings 的 Rust 接口，PDF 以字节数组传入，错误以字符串返回
+/// （Uint8Array 和 JsValue 只能在 wasm 环境中构造）
+pub fn merge_pdfs_from_bytes(pdf_files: &[Vec<u8>], options: &str) -> Result<PdfWithWarnings, String> {
+    let options: MergeOptions = serde_json::from_str(options)
+        .map_err(|e| format!("无效的合并选项: {}", e))?;
+
+    merge_pdf_bytes(pdf_files, &options)
+        .and_then(|(pdf, warnings)| PdfWithWarnings::new(pdf, &warnings))
+}
+
+fn merge_pdf_bytes(pdf_files: &[Vec<u8>], options: &MergeOptions) -> Result<(Vec<u8>, Vec<CopyWarning>), String> {
     if pdf_files.is_empty() {
-        return Err(JsValue::from_str("至少需要一个 PDF 文件"));
+        return Err("至少需要一个 PDF 文件".to_string());
+    }
+    let pdfa = options.pdfa.as_deref().map(PdfALevel::parse).transpose()?;
+    if options.keep_attachments && pdfa.is_some_and(|level| level != PdfALevel::A3b) {
+        return Err("PDF/A-1b 和 PDF/A-2b 不允许附件，keep_attachments 只能与 PDF/A-3b 一起使用".to_string());
     }
 
     // 创建新的 PDF 文档
     let mut merged_doc = Document::with_version("1.5");
     let pages_id = merged_doc.new_object_id();
     let mut all_page_ids = Vec::new();
+    let bookmarks = options.bookmarks || !options.bookmark_titles.is_empty();
+    let mut outline = Vec::new();
+    let mut form = FormMerger::default();
+    let mut attachments = AttachmentMerger::default();
+    let mut checker = CopyChecker::new(options.strict);
 
     // 逐个处理每个 PDF 文件
-    for (idx, pdf_bytes_js) in pdf_files.iter().enumerate() {
-        let pdf_bytes = pdf_bytes_js.to_vec();
-        
+    for (idx, pdf_bytes) in pdf_files.iter().enumerate() {
         // 加载 PDF 文档
-        let doc = Document::load_mem(&pdf_bytes)
-            .map_err(|e| JsValue::from_str(&format!("无法加载第 {} 个 PDF: {}", idx + 1, e)))?;
+        let doc = Document::load_mem(pdf_bytes)
+            .map_err(|e| format!("无法加载第 {} 个 PDF: {}", idx + 1, e))?;
+
+        // 在文件之间插入空白页，尺寸与上一页一致
+        if let Some(Object::Reference(last_page_id)) = all_page_ids.last() {
+            let last_page_id = *last_page_id;
+            let mut blank_count = usize::from(options.blank_page_between);
+            if options.start_on_odd_page && (all_page_ids.len() + blank_count) % 2 == 1 {
+                blank_count += 1;
+            }
+            for _ in 0..blank_count {
+                let blank_id = add_blank_page_like(&mut merged_doc, pages_id, last_page_id);
+                all_page_ids.push(Object::Reference(blank_id));
+            }
+        }
 
         // 为每个文档创建单独的ID映射表
         let mut id_map: HashMap<ObjectId, ObjectId> = HashMap::new();
 
         // 获取该文档的所有页面
-        let pages: Vec<_> = doc.get_pages().into_iter().collect();
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        reserve_page_ids(&doc, &pages, &mut merged_doc, &mut id_map);
         
         // 复制每一页及其资源
-        for (_page_num, page_id) in pages {
-            if let Ok(page_obj) = doc.get_object(page_id) {
-                // 深度复制页面对象及其所有引用的资源
-                let new_page_obj = deep_copy_object_with_map(&doc, &mut merged_doc, page_obj, &mut id_map);
-                let new_page_id = merged_doc.add_object(new_page_obj);
-                
-                // 记录页面ID映射
-                id_map.insert(page_id, new_page_id);
-                
-                // 更新页面的父引用
-                if let Ok(Object::Dictionary(ref mut page_dict)) = merged_doc.get_object_mut(new_page_id) {
-                    page_dict.set("Parent", Object::Reference(pages_id));
-                }
-                
+        checker.missing_pages(Some(idx + 1), &doc)?;
+        let mut first_page_id = None;
+        let mut missing = Vec::new();
+        for (page_idx, page_id) in pages.into_iter().enumerate() {
+            if let Some(new_page_id) = copy_page(&doc, &mut merged_doc, page_id, pages_id, &mut id_map, &mut missing) {
+                first_page_id.get_or_insert(new_page_id);
                 all_page_ids.push(Object::Reference(new_page_id));
             }
+            checker.missing_objects(Some(idx + 1), page_idx, &mut missing)?;
+        }
+
+        if let Some(first_page_id) = first_page_id.filter(|_| bookmarks) {
+            let title = options.bookmark_titles.get(idx).cloned().unwrap_or_else(|| format!("文件 {}", idx + 1));
+            let mut item = OutlineItem::new(title, first_page_id, DestView::Fit);
+            item.children = read_outline(&doc);
+            remap_outline(&mut item.children, &id_map);
+            item.open = false;
+            outline.push(item);
+        }
+
+        // 复制表单字段，同名字段自动改名
+        form.add(&doc, &mut merged_doc, &mut id_map);
+        if options.keep_attachments {
+            attachments.add(&doc, &mut merged_doc, &mut id_map);
         }
     }
 
-    // 创建页面树
-    let mut pages_dict = Dictionary::new();
-    pages_dict.set("Type", Object::Name(b"Pages".to_vec()));
-    pages_dict.set("Kids", Object::Array(all_page_ids.clone()));
-    pages_dict.set("Count", Object::Integer(all_page_ids.len() as i64));
-    merged_doc.objects.insert(pages_id, Object::Dictionary(pages_dict));
+    let catalog_id = build_page_tree(&mut merged_doc, pages_id, all_page_ids);
+    if !outline.is_empty() {
+        write_outline(&mut merged_doc, catalog_id, &outline)?;
+    }
+    form.write(&mut merged_doc, catalog_id)?;
+    attachments.write(&mut merged_doc)?;
 
-    // 创建目录
-    let mut catalog = Dictionary::new();
-    catalog.set("Type", Object::Name(b"Catalog".to_vec()));
-    catalog.set("Pages", Object::Reference(pages_id));
-    let catalog_id = merged_doc.add_object(Object::Dictionary(catalog));
+    // 统一页面尺寸
+    if let Some(page_size) = &options.page_size {
+        let target = parse_paper_size(page_size)?;
+        let mode = FitMode::parse(options.fit_mode.as_deref().unwrap_or("fit"))?;
+        normalize_document_pages(&mut merged_doc, target, mode)?;
+    }
 
-    merged_doc.trailer.set("Root", Object::Reference(catalog_id));
+    if let Some(level) = pdfa {
+        convert_document(&mut merged_doc, level, None)?;
+    }
 
     // 保存合并后的文档
     let mut buffer = Vec::new();
-    merged_doc.save_to(&mut buffer)
-        .map_err(|e| JsValue::from_str(&format!("无法保存合并的 PDF: {}", e)))?;
+    save_document(&mut merged_doc, &mut buffer, options.linearize)
+        .map_err(|e| format!("无法保存合并的 PDF: {}", e))?;
 
-    Ok(buffer)
+    Ok((buffer, checker.warnings))
+}
+
+/// 输出的 PDF 和复制页面时发现的问题
+#[wasm_bindgen]
+pub struct PdfWithWarnings {
+    pdf: Vec<u8>,
+    warnings: String,
+}
+
+#[wasm_bindgen]
+impl PdfWithWarnings {
+    /// 输出的 PDF 字节数组
+    #[wasm_bindgen(getter)]
+    pub fn pdf(&self) -> Vec<u8> {
+        self.pdf.clone()
+    }
+
+    /// 缺失的页面和对象（JSON 数组字符串）
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> String {
+        self.warnings.clone()
+    }
+}
+
+impl PdfWithWarnings {
+    fn new(pdf: Vec<u8>, warnings: &[CopyWarning]) -> Result<PdfWithWarnings, String> {
+        let warnings = serde_json::to_string(warnings)
+            .map_err(|e| format!("无法生成警告 JSON: {}", e))?;
+        Ok(PdfWithWarnings { pdf, warnings })
+    }
+}
+
+/// 分割出的多个 PDF 和复制页面时发现的问题
+#[wasm_bindgen]
+pub struct PdfsWithWarnings {
+    pdfs: Vec<Vec<u8>>,
+    warnings: String,
+}
+
+#[wasm_bindgen]
+impl PdfsWithWarnings {
+    /// 输出的 PDF 字节数组组成的数组
+    #[wasm_bindgen(getter)]
+    pub fn pdfs(&self) -> js_sys::Array {
+        pdf_array(&self.pdfs)
+    }
+
+    /// 缺失的页面和对象（JSON 数组字符串）
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> String {
+        self.warnings.clone()
+    }
+}
+
+impl PdfsWithWarnings {
+    fn new(pdfs: Vec<Vec<u8>>, warnings: &[CopyWarning]) -> Result<PdfsWithWarnings, String> {
+        let warnings = serde_json::to_string(warnings)
+            .map_err(|e| format!("无法生成警告 JSON: {}", e))?;
+        Ok(PdfsWithWarnings { pdfs, warnings })
+    }
+}
+
+fn pdf_array(pdfs: &[Vec<u8>]) -> js_sys::Array {
+    pdfs.iter().map(|pdf| js_sys::Uint8Array::from(&pdf[..])).collect()
+}
+
+/// 复制页面时发现的问题：页面或其引用的对象在源文档中不存在
+#[derive(Debug, Serialize)]
+struct CopyWarning {
+    /// 第几个输入文件（从 1 开始），只有合并和交错合并时有
+    file: Option<usize>,
+    /// 页码，即页面在源文件页面树中的位置（从 1 开始）；不存在的页面也计入，
+    /// 因此页面树中有不存在的页面时，其后页面的页码比 page_ranges 中的页码大
+    page: usize,
+    /// 不存在的对象，如 "12 0"
+    object: String,
+    message: String,
+}
+
+/// 收集复制页面时的问题；严格模式下遇到第一个问题即返回错误
+struct CopyChecker {
+    strict: bool,
+    warnings: Vec<CopyWarning>,
+    /// 每个文件中不存在的页面在页面树中的位置（升序），用于换算页码
+    skipped: HashMap<Option<usize>, Vec<usize>>,
+}
+
+impl CopyChecker {
+    fn new(strict: bool) -> CopyChecker {
+        CopyChecker { strict, warnings: Vec::new(), skipped: HashMap::new() }
+    }
+
+    /// 检查页面树引用的页面对象是否存在，不存在的页面被跳过
+    fn missing_pages(&mut self, file: Option<usize>, doc: &Document) -> Result<(), String> {
+        for (position, page_id) in missing_pages(doc) {
+            self.skipped.entry(file).or_default().push(position);
+            let message = format!("{}第 {} 页（对象 {} {}）不存在", file_prefix(file), position, page_id.0, page_id.1);
+            self.report(file, position, page_id, message)?;
+        }
+        Ok(())
+    }
+
+    /// 页面引用的对象不存在，引用被替换为 null；page_idx 为 get_pages 中的序号（从 0 开始）
+    fn missing_objects(&mut self, file: Option<usize>, page_idx: usize, missing: &mut Vec<ObjectId>) -> Result<(), String> {
+        missing.sort();
+        missing.dedup();
+        let page = self.page_number(file, page_idx);
+        for id in missing.drain(..) {
+            let message = format!("{}第 {} 页引用了不存在的对象 {} {}", file_prefix(file), page, id.0, id.1);
+            self.report(file, page, id, message)?;
+        }
+        Ok(())
+    }
+
+    /// 把 get_pages 中的序号换算为页面树中的位置，跳过的页面也计入
+    fn page_number(&self, file: Option<usize>, page_idx: usize) -> usize {
+        let mut page = page_idx + 1;
+        for &position in self.skipped.get(&file).into_iter().flatten() {
+            if position <= page {
+                page += 1;
+            }
+        }
+        page
+    }
+
+    fn report(&mut self, file: Option<usize>, page: usize, id: ObjectId, message: String) -> Result<(), String> {
+        if self.strict {
+            return Err(message);
+        }
+        self.warnings.push(CopyWarning { file, page, object: format!("{} {}", id.0, id.1), message });
+        Ok(())
+    }
+}
+
+fn file_prefix(file: Option<usize>) -> String {
+    file.map_or(String::new(), |file| format!("第 {} 个 PDF 的", file))
+}
+
+/// 页面树中引用了不存在对象的页面，返回 (在页面树中的位置, 对象 ID)，位置从 1 开始
+/// get_pages 会直接跳过这些页面
+fn missing_pages(doc: &Document) -> Vec<(usize, ObjectId)> {
+    let mut missing = Vec::new();
+    if let Ok(root_id) = get_root_pages_id(doc) {
+        collect_missing_pages(doc, root_id, &mut 0, &mut missing, &mut HashSet::new(), 0);
+    }
+    missing
+}
+
+fn collect_missing_pages(
+    doc: &Document,
+    node_id: ObjectId,
+    position: &mut usize,
+    missing: &mut Vec<(usize, ObjectId)>,
+    visited: &mut HashSet<ObjectId>,
+    depth: usize,
+) {
+    // 限制深度并跳过重复的节点，防止错误的页面树形成循环
+    if depth > 32 || !visited.insert(node_id) {
+        return;
+    }
+    let Ok(kids) = doc.get_dictionary(node_id).and_then(|n| n.get(b"Kids")).and_then(Object::as_array) else { return };
+    for kid in kids {
+        let Ok(kid_id) = kid.as_reference() else { continue };
+        match doc.get_dictionary(kid_id) {
+            Ok(kid) if kid.type_is(b"Pages") => collect_missing_pages(doc, kid_id, position, missing, visited, depth + 1),
+            Ok(kid) if kid.type_is(b"Page") => *position += 1,
+            Ok(_) => {}
+            Err(_) => {
+                *position += 1;
+                missing.push((*position, kid_id));
+            }
+        }
+    }
 }
 
 /// PDF 分割功能
 /// 将一个 PDF 文件分割成多个单页 PDF
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn split_pdf(pdf_bytes: &[u8], linearize: Option<bool>) -> Result<js_sys::Array, JsValue> {
+    split_pdf_bytes(pdf_bytes, false, linearize.unwrap_or(false))
+        .map(|(pdfs, _)| pdf_array(&pdfs))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 分割为单页 PDF，同时返回缺失的页面和对象
+/// strict 为 true 时，页面或其引用的对象不存在直接报错；否则跳过页面、把引用替换为 null 并记录警告
 #[wasm_bindgen]
-pub fn split_pdf(pdf_bytes: &[u8]) -> Result<js_sys::Array, JsValue> {
+pub fn split_pdf_with_warnings(pdf_bytes: &[u8], strict: bool, linearize: Option<bool>) -> Result<PdfsWithWarnings, JsValue> {
+    split_pdf_bytes(pdf_bytes, strict, linearize.unwrap_or(false))
+        .and_then(|(pdfs, warnings)| PdfsWithWarnings::new(pdfs, &warnings))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+fn split_pdf_bytes(pdf_bytes: &[u8], strict: bool, linearize: bool) -> Result<(Vec<Vec<u8>>, Vec<CopyWarning>), String> {
     // 加载 PDF 文档
     let doc = Document::load_mem(pdf_bytes)
-        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+    let pages: Vec<usize> = (0..doc.get_pages().len()).collect();
+
+    split_single_pages(&doc, &pages, strict, linearize)
+}
 
-    let page_count = doc.get_pages().len();
-    let result = js_sys::Array::new_with_length(page_count as u32);
+/// 把选中的页面（序号从 0 开始）分别保存为单页 PDF
+fn split_single_pages(doc: &Document, pages_to_extract: &[usize], strict: bool, linearize: bool) -> Result<(Vec<Vec<u8>>, Vec<CopyWarning>), String> {
+    let all_pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let mut checker = CopyChecker::new(strict);
+    checker.missing_pages(None, doc)?;
 
-    // 获取所有页面
-    let pages: Vec<_> = doc.get_pages().into_iter().collect();
+    let mut pdfs = Vec::new();
+    let mut missing = Vec::new();
+    for &page_idx in pages_to_extract {
+        let Some(&page_id) = all_pages.get(page_idx) else { continue };
 
-    for (idx, (_page_num, page_id)) in pages.into_iter().enumerate() {
-        // 为每一页创建新文档
+        // 为每一页创建新文档和单独的ID映射表
         let mut single_page_doc = Document::with_version("1.5");
         let pages_id = single_page_doc.new_object_id();
-        
-        // 为每一页创建单独的ID映射表
         let mut id_map: HashMap<ObjectId, ObjectId> = HashMap::new();
-        
-        // 深度复制页面对象及其所有引用的资源
-        if let Ok(page_obj) = doc.get_object(page_id) {
-            let new_page_obj = deep_copy_object_with_map(&doc, &mut single_page_doc, page_obj, &mut id_map);
-            let new_page_id = single_page_doc.add_object(new_page_obj);
-            
-            // 记录页面ID映射
-            id_map.insert(page_id, new_page_id);
-            
-            // 更新页面的父引用
-            if let Ok(Object::Dictionary(ref mut page_dict)) = single_page_doc.get_object_mut(new_page_id) {
-                page_dict.set("Parent", Object::Reference(pages_id));
-            }
-            
-            // 创建页面树
-            let mut pages_dict = Dictionary::new();
-            pages_dict.set("Type", Object::Name(b"Pages".to_vec()));
-            pages_dict.set("Count", Object::Integer(1));
-            pages_dict.set("Kids", Object::Array(vec![Object::Reference(new_page_id)]));
-            single_page_doc.objects.insert(pages_id, Object::Dictionary(pages_dict));
-            
-            // 创建目录
-            let mut catalog = Dictionary::new();
-            catalog.set("Type", Object::Name(b"Catalog".to_vec()));
-            catalog.set("Pages", Object::Reference(pages_id));
-            
-            let catalog_id = single_page_doc.add_object(Object::Dictionary(catalog));
-            single_page_doc.trailer.set("Root", Object::Reference(catalog_id));
-            
-            // 保存单页文档
-            let mut buffer = Vec::new();
-            single_page_doc.save_to(&mut buffer)
-                .map_err(|e| JsValue::from_str(&format!("无法保存第 {} 页: {}", idx + 1, e)))?;
-            
-            let uint8_array = js_sys::Uint8Array::from(&buffer[..]);
-            result.set(idx as u32, JsValue::from(uint8_array));
-        }
+        reserve_page_ids(doc, &[page_id], &mut single_page_doc, &mut id_map);
+
+        // 复制页面及其引用的资源，创建页面树和目录
+        let new_page_id = copy_page(doc, &mut single_page_doc, page_id, pages_id, &mut id_map, &mut missing);
+        checker.missing_objects(None, page_idx, &mut missing)?;
+        let Some(new_page_id) = new_page_id else { continue };
+        build_page_tree(&mut single_page_doc, pages_id, vec![Object::Reference(new_page_id)]);
+
+        // 保存单页文档
+        let mut buffer = Vec::new();
+        save_document(&mut single_page_doc, &mut buffer, linearize)
+            .map_err(|e| format!("无法保存第 {} 页: {}", page_idx + 1, e))?;
+        pdfs.push(buffer);
     }
 
-    Ok(result)
+    Ok((pdfs, checker.warnings))
 }
 
 /// 按页码范围分割 PDF
 /// page_ranges: 格式如 "1-3,5,7-9" 表示提取第1-3页、第5页和第7-9页
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn split_pdf_by_range(pdf_bytes: &[u8], page_ranges: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    split_pdf_by_range_bytes(pdf_bytes, page_ranges, false, linearize.unwrap_or(false))
+        .map(|(pdf, _)| pdf)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 按页码范围分割 PDF，同时返回缺失的页面和对象
+/// strict 为 true 时，页面或其引用的对象不存在直接报错；否则跳过页面、把引用替换为 null 并记录警告
 #[wasm_bindgen]
-pub fn split_pdf_by_range(pdf_bytes: &[u8], page_ranges: &str) -> Result<Vec<u8>, JsValue> {
+pub fn split_pdf_by_range_with_warnings(pdf_bytes: &[u8], page_ranges: &str, strict: bool, linearize: Option<bool>) -> Result<PdfWithWarnings, JsValue> {
+    split_pdf_by_range_from_bytes(pdf_bytes, page_ranges, strict, linearize.unwrap_or(false))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// split_pdf_by_range_with_warnings 的 Rust 接口，错误以字符串返回（JsValue 只能在 wasm 环境中构造）
+pub fn split_pdf_by_range_from_bytes(pdf_bytes: &[u8], page_ranges: &str, strict: bool, linearize: bool) -> Result<PdfWithWarnings, String> {
+    split_pdf_by_range_bytes(pdf_bytes, page_ranges, strict, linearize)
+        .and_then(|(pdf, warnings)| PdfWithWarnings::new(pdf, &warnings))
+}
+
+fn split_pdf_by_range_bytes(pdf_bytes: &[u8], page_ranges: &str, strict: bool, linearize: bool) -> Result<(Vec<u8>, Vec<CopyWarning>), String> {
     // 加载 PDF 文档
     let doc = Document::load_mem(pdf_bytes)
-        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
 
     // 解析页码范围
-    let pages_to_extract = parse_page_ranges(page_ranges, doc.get_pages().len())
-        .map_err(|e| JsValue::from_str(&e))?;
+    let pages_to_extract = parse_page_ranges(page_ranges, doc.get_pages().len())?;
 
     if pages_to_extract.is_empty() {
-        return Err(JsValue::from_str("没有可提取的页面"));
+        return Err("没有可提取的页面".to_string());
     }
 
     // 创建新文档
@@ -156,64 +469,296 @@ pub fn split_pdf_by_range(pdf_bytes: &[u8], page_ranges: &str) -> Result<Vec<u8>
     let pages_id = new_doc.new_object_id();
     let mut page_ids = Vec::new();
     
-    let all_pages: Vec<_> = doc.get_pages().into_iter().collect();
+    let all_pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let selected: Vec<(usize, ObjectId)> = pages_to_extract.iter().filter_map(|&idx| Some((idx, *all_pages.get(idx)?))).collect();
 
-    // 创建ID映射表
+    // 创建ID映射表，预先登记所有选中的页面，页面之间的链接得以保留
     let mut id_map: HashMap<ObjectId, ObjectId> = HashMap::new();
-    
+    let selected_ids: Vec<ObjectId> = selected.iter().map(|&(_, id)| id).collect();
+    reserve_page_ids(&doc, &selected_ids, &mut new_doc, &mut id_map);
+
     // 复制选定的页面及其资源
-    for page_idx in &pages_to_extract {
-        if *page_idx < all_pages.len() {
-            let (_page_num, page_id) = all_pages[*page_idx];
-            
-            // 递归复制页面对象及其所有引用的资源
-            if let Ok(page_obj) = doc.get_object(page_id) {
-                // 深度复制页面对象，使用ID映射
-                let new_page_obj = deep_copy_object_with_map(&doc, &mut new_doc, page_obj, &mut id_map);
-                let new_page_id = new_doc.add_object(new_page_obj);
-                
-                // 记录页面ID映射
-                id_map.insert(page_id, new_page_id);
-                
-                // 更新页面的父引用
-                if let Ok(Object::Dictionary(ref mut page_dict)) = new_doc.get_object_mut(new_page_id) {
-                    page_dict.set("Parent", Object::Reference(pages_id));
-                }
-                
+    let mut checker = CopyChecker::new(strict);
+    checker.missing_pages(None, &doc)?;
+    let mut missing = Vec::new();
+    for &(page_idx, page_id) in &selected {
+        if let Some(new_page_id) = copy_page(&doc, &mut new_doc, page_id, pages_id, &mut id_map, &mut missing) {
+            page_ids.push(Object::Reference(new_page_id));
+        }
+        checker.missing_objects(None, page_idx, &mut missing)?;
+    }
+
+    // 创建页面树和目录
+    build_page_tree(&mut new_doc, pages_id, page_ids);
+
+    // 保存文档
+    let mut buffer = Vec::new();
+    save_document(&mut new_doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+
+    Ok((buffer, checker.warnings))
+}
+
+/// 交错合并正反面扫描件
+/// 单面扫描仪分别扫描正面和背面时，将两个 PDF 逐页交替合并为一个文档
+/// reverse_back: 背面 PDF 是否为倒序（将整叠纸翻面后扫描时通常为倒序）
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn interleave_pdfs(front: &[u8], back: &[u8], reverse_back: bool, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    interleave_pdf_bytes(front, back, reverse_back, false, linearize.unwrap_or(false))
+        .map(|(pdf, _)| pdf)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 交错合并正反面扫描件，同时返回缺失的页面和对象（正面为第 1 个文件，背面为第 2 个）
+/// strict 为 true 时，页面或其引用的对象不存在直接报错；否则跳过页面、把引用替换为 null 并记录警告
+#[wasm_bindgen]
+pub fn interleave_pdfs_with_warnings(front: &[u8], back: &[u8], reverse_back: bool, strict: bool, linearize: Option<bool>) -> Result<PdfWithWarnings, JsValue> {
+    interleave_pdf_bytes(front, back, reverse_back, strict, linearize.unwrap_or(false))
+        .and_then(|(pdf, warnings)| PdfWithWarnings::new(pdf, &warnings))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+fn interleave_pdf_bytes(front: &[u8], back: &[u8], reverse_back: bool, strict: bool, linearize: bool) -> Result<(Vec<u8>, Vec<CopyWarning>), String> {
+    let front_doc = Document::load_mem(front)
+        .map_err(|e| format!("无法加载正面 PDF: {}", e))?;
+    let back_doc = Document::load_mem(back)
+        .map_err(|e| format!("无法加载背面 PDF: {}", e))?;
+
+    let front_pages: Vec<ObjectId> = front_doc.get_pages().into_values().collect();
+    let mut back_pages: Vec<ObjectId> = back_doc.get_pages().into_values().collect();
+    if front_pages.len().abs_diff(back_pages.len()) > 1 {
+        return Err(format!(
+            "正面页数 ({}) 与背面页数 ({}) 相差超过 1 页，请检查是否漏扫",
+            front_pages.len(),
+            back_pages.len()
+        ));
+    }
+    // 背面页面在原文件中的序号，倒序时用于警告信息
+    let mut back_numbers: Vec<usize> = (0..back_pages.len()).collect();
+    if reverse_back {
+        back_pages.reverse();
+        back_numbers.reverse();
+    }
+    let back_pages: Vec<(usize, ObjectId)> = back_numbers.into_iter().zip(back_pages).collect();
+
+    let mut new_doc = Document::with_version("1.5");
+    let pages_id = new_doc.new_object_id();
+
+    let mut front_map: HashMap<ObjectId, ObjectId> = HashMap::new();
+    let mut back_map: HashMap<ObjectId, ObjectId> = HashMap::new();
+    reserve_page_ids(&front_doc, &front_pages, &mut new_doc, &mut front_map);
+    let back_ids: Vec<ObjectId> = back_pages.iter().map(|&(_, id)| id).collect();
+    reserve_page_ids(&back_doc, &back_ids, &mut new_doc, &mut back_map);
+
+    let mut checker = CopyChecker::new(strict);
+    for (file, doc) in [(1, &front_doc), (2, &back_doc)] {
+        checker.missing_pages(Some(file), doc)?;
+    }
+
+    let mut page_ids = Vec::new();
+    let mut missing = Vec::new();
+    for idx in 0..front_pages.len().max(back_pages.len()) {
+        if let Some(&page_id) = front_pages.get(idx) {
+            if let Some(new_page_id) = copy_page(&front_doc, &mut new_doc, page_id, pages_id, &mut front_map, &mut missing) {
+                page_ids.push(Object::Reference(new_page_id));
+            }
+            checker.missing_objects(Some(1), idx, &mut missing)?;
+        }
+        if let Some(&(page_idx, page_id)) = back_pages.get(idx) {
+            if let Some(new_page_id) = copy_page(&back_doc, &mut new_doc, page_id, pages_id, &mut back_map, &mut missing) {
                 page_ids.push(Object::Reference(new_page_id));
             }
+            checker.missing_objects(Some(2), page_idx, &mut missing)?;
+        }
+    }
+
+    build_page_tree(&mut new_doc, pages_id, page_ids);
+
+    let mut buffer = Vec::new();
+    save_document(&mut new_doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+
+    Ok((buffer, checker.warnings))
+}
+
+/// 预先为要复制的页面分配新的对象 ID
+/// 注释的 /P、链接目标等对其他页面的引用会直接映射到新页面，而不是再复制一份；
+/// 只登记能读取到的页面，copy_page 无法复制的页面不会留下指向空 ID 的引用
+fn reserve_page_ids(src_doc: &Document, pages: &[ObjectId], dst_doc: &mut Document, id_map: &mut HashMap<ObjectId, ObjectId>) {
+    for &page_id in pages {
+        if src_doc.get_dictionary(page_id).is_ok() {
+            id_map.entry(page_id).or_insert_with(|| dst_doc.new_object_id());
+        }
+    }
+}
+
+/// 复制一页及其引用的资源到目标文档，并挂到 pages_id 页面树节点下
+/// 页面对象不存在时返回 None；源文档中不存在的引用对象记录到 missing
+fn copy_page(
+    src_doc: &Document,
+    dst_doc: &mut Document,
+    page_id: ObjectId,
+    pages_id: ObjectId,
+    id_map: &mut HashMap<ObjectId, ObjectId>,
+    missing: &mut Vec<ObjectId>,
+) -> Option<ObjectId> {
+    let mut page_dict = src_doc.get_dictionary(page_id).ok()?.clone();
+    // 不复制 Parent，否则会顺着父节点把整棵页面树都复制过来
+    page_dict.remove(b"Parent");
+    let annots = page_dict.remove(b"Annots");
+
+    let new_page_id = *id_map.entry(page_id).or_insert_with(|| dst_doc.new_object_id());
+
+    // 深度复制页面对象及其所有引用的资源
+    let mut new_page_obj = deep_copy_object_tracked(src_doc, dst_doc, &Object::Dictionary(page_dict), id_map, missing);
+    if let Some(annots) = annots {
+        let annots = copy_annotations(src_doc, dst_doc, &annots, new_page_id, id_map, missing);
+        if let (Ok(page_dict), false) = (new_page_obj.as_dict_mut(), annots.is_empty()) {
+            page_dict.set("Annots", Object::Array(annots));
+        }
+    }
+    dst_doc.objects.insert(new_page_id, new_page_obj);
+
+    // 从父节点继承的属性（MediaBox、Resources 等）写入页面本身
+    inherit_page_attributes(src_doc, dst_doc, page_id, new_page_id, id_map, missing);
+
+    // 更新页面的父引用
+    if let Ok(page_dict) = dst_doc.get_dictionary_mut(new_page_id) {
+        page_dict.set("Parent", Object::Reference(pages_id));
+    }
+
+    Some(new_page_id)
+}
+
+/// 复制页面的注释，返回新的 /Annots 数组
+/// 注释的 /P 指向新页面；父注释不在本页的弹出窗口、目标页面未被复制的链接不复制，
+/// 避免顺着 /Parent、/P 或链接目标把无关的对象带进新文档
+fn copy_annotations(
+    src_doc: &Document,
+    dst_doc: &mut Document,
+    annots: &Object,
+    new_page_id: ObjectId,
+    id_map: &mut HashMap<ObjectId, ObjectId>,
+    missing: &mut Vec<ObjectId>,
+) -> Vec<Object> {
+    let Ok((_, Object::Array(annots))) = src_doc.dereference(annots) else { return Vec::new() };
+    let on_page: HashSet<ObjectId> = annots.iter().filter_map(|a| a.as_reference().ok()).collect();
+
+    let mut kept = Vec::new();
+    for annot in annots {
+        let Ok((annot_id, Object::Dictionary(dict))) = src_doc.dereference(annot) else { continue };
+        let subtype = dict.get(b"Subtype").and_then(Object::as_name).unwrap_or(b"");
+        let parent = dict.get(b"Parent").and_then(Object::as_reference).ok();
+        if subtype == b"Popup" && !parent.is_some_and(|p| on_page.contains(&p)) {
+            continue;
+        }
+        if subtype == b"Link" && link_target_page(src_doc, dict).is_some_and(|p| !id_map.contains_key(&p)) {
+            continue;
         }
+        kept.push((annot_id, dict));
     }
 
+    // 先登记本页注释的新 ID，注释与弹出窗口之间的相互引用直接映射
+    for (annot_id, _) in &kept {
+        if let Some(annot_id) = annot_id {
+            id_map.entry(*annot_id).or_insert_with(|| dst_doc.new_object_id());
+        }
+    }
+
+    kept.into_iter().map(|(annot_id, dict)| {
+        let mut dict = dict.clone();
+        dict.remove(b"P");
+        if dict.get(b"Popup").and_then(Object::as_reference).is_ok_and(|p| !on_page.contains(&p)) {
+            dict.remove(b"Popup");
+        }
+        let mut copied = deep_copy_object_tracked(src_doc, dst_doc, &Object::Dictionary(dict), id_map, missing);
+        if let Ok(copied) = copied.as_dict_mut() {
+            copied.set("P", Object::Reference(new_page_id));
+        }
+        match annot_id.and_then(|id| id_map.get(&id).copied()) {
+            Some(new_id) => {
+                dst_doc.objects.insert(new_id, copied);
+                Object::Reference(new_id)
+            }
+            None => copied,
+        }
+    }).collect()
+}
+
+/// 链接注释直接指向的目标页面（/Dest 或 GoTo 动作的 /D 为数组时）
+fn link_target_page(doc: &Document, link: &Dictionary) -> Option<ObjectId> {
+    let dest = match link.get(b"Dest") {
+        Ok(dest) => dest,
+        Err(_) => {
+            let (_, action) = doc.dereference(link.get(b"A").ok()?).ok()?;
+            action.as_dict().ok()?.get(b"D").ok()?
+        }
+    };
+    let (_, dest) = doc.dereference(dest).ok()?;
+    dest.as_array().ok()?.first()?.as_reference().ok()
+}
+
+/// 创建页面树和目录，返回目录对象 ID
+pub(crate) fn build_page_tree(doc: &mut Document, pages_id: ObjectId, page_ids: Vec<Object>) -> ObjectId {
     // 创建页面树
-    let mut pages = Dictionary::new();
-    pages.set("Type", Object::Name(b"Pages".to_vec()));
-    pages.set("Kids", Object::Array(page_ids.clone()));
-    pages.set("Count", Object::Integer(page_ids.len() as i64));
-    new_doc.objects.insert(pages_id, Object::Dictionary(pages));
+    let mut pages_dict = Dictionary::new();
+    pages_dict.set("Type", Object::Name(b"Pages".to_vec()));
+    pages_dict.set("Count", Object::Integer(page_ids.len() as i64));
+    pages_dict.set("Kids", Object::Array(page_ids));
+    doc.objects.insert(pages_id, Object::Dictionary(pages_dict));
 
     // 创建目录
     let mut catalog = Dictionary::new();
     catalog.set("Type", Object::Name(b"Catalog".to_vec()));
     catalog.set("Pages", Object::Reference(pages_id));
-    let catalog_id = new_doc.add_object(Object::Dictionary(catalog));
+    let catalog_id = doc.add_object(Object::Dictionary(catalog));
 
-    new_doc.trailer.set("Root", Object::Reference(catalog_id));
-
-    // 保存文档
-    let mut buffer = Vec::new();
-    new_doc.save_to(&mut buffer)
-        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+    doc.trailer.set("Root", Object::Reference(catalog_id));
+    catalog_id
+}
 
-    Ok(buffer)
+/// 将页面从父节点继承的属性复制到新页面上
+/// 复制后的页面挂到新的页面树下，原父节点上的 Resources、MediaBox 等属性需要写到页面本身
+fn inherit_page_attributes(
+    src_doc: &Document,
+    dst_doc: &mut Document,
+    page_id: ObjectId,
+    new_page_id: ObjectId,
+    id_map: &mut HashMap<ObjectId, ObjectId>,
+    missing: &mut Vec<ObjectId>,
+) {
+    for key in [b"Resources".as_slice(), b"MediaBox", b"CropBox", b"Rotate"] {
+        let has_own = src_doc.get_dictionary(page_id).map(|p| p.has(key)).unwrap_or(true);
+        if has_own {
+            continue;
+        }
+        if let Some(value) = get_inherited_attribute(src_doc, page_id, key) {
+            let copied = deep_copy_object_tracked(src_doc, dst_doc, value, id_map, missing);
+            if let Ok(page_dict) = dst_doc.get_dictionary_mut(new_page_id) {
+                page_dict.set(key.to_vec(), copied);
+            }
+        }
+    }
 }
 
 /// 深度复制对象及其引用（带ID映射，防止循环引用）
-fn deep_copy_object_with_map(
+pub(crate) fn deep_copy_object_with_map(
+    src_doc: &Document,
+    dst_doc: &mut Document,
+    obj: &Object,
+    id_map: &mut HashMap<ObjectId, ObjectId>,
+) -> Object {
+    deep_copy_object_tracked(src_doc, dst_doc, obj, id_map, &mut Vec::new())
+}
+
+/// 深度复制对象及其引用，源文档中不存在的引用对象复制为 null 并记录到 missing
+fn deep_copy_object_tracked(
     src_doc: &Document,
     dst_doc: &mut Document,
     obj: &Object,
     id_map: &mut HashMap<ObjectId, ObjectId>,
+    missing: &mut Vec<ObjectId>,
 ) -> Object {
     match obj {
         Object::Reference(id) => {
@@ -224,12 +769,18 @@ fn deep_copy_object_with_map(
             
             // 复制引用的对象
             if let Ok(referenced_obj) = src_doc.get_object(*id) {
+                // 未被复制的页面和页面树节点（如注释的 /P、链接目标）不随引用带入
+                let node_type = referenced_obj.as_dict().ok().and_then(|d| d.get(b"Type").and_then(Object::as_name).ok());
+                if matches!(node_type, Some(b"Page" | b"Pages")) {
+                    return Object::Null;
+                }
+
                 // 先创建占位符，防止循环引用导致无限递归
                 let new_id = dst_doc.new_object_id();
                 id_map.insert(*id, new_id);
                 
                 // 然后复制对象内容
-                let copied_obj = deep_copy_object_with_map(src_doc, dst_doc, referenced_obj, id_map);
+                let copied_obj = deep_copy_object_tracked(src_doc, dst_doc, referenced_obj, id_map, missing);
                 
                 // 插入复制的对象
                 dst_doc.objects.insert(new_id, copied_obj);
@@ -237,27 +788,28 @@ fn deep_copy_object_with_map(
                 return Object::Reference(new_id);
             }
             
-            // 如果无法获取对象，返回null
+            // 如果无法获取对象，记录后返回null
+            missing.push(*id);
             Object::Null
         }
         Object::Dictionary(dict) => {
             let mut new_dict = Dictionary::new();
             for (key, value) in dict.iter() {
-                let copied_value = deep_copy_object_with_map(src_doc, dst_doc, value, id_map);
+                let copied_value = deep_copy_object_tracked(src_doc, dst_doc, value, id_map, missing);
                 new_dict.set(key.clone(), copied_value);
             }
             Object::Dictionary(new_dict)
         }
         Object::Array(arr) => {
             let new_arr: Vec<Object> = arr.iter()
-                .map(|item| deep_copy_object_with_map(src_doc, dst_doc, item, id_map))
+                .map(|item| deep_copy_object_tracked(src_doc, dst_doc, item, id_map, missing))
                 .collect();
             Object::Array(new_arr)
         }
         Object::Stream(stream) => {
             let mut new_dict = Dictionary::new();
             for (key, value) in stream.dict.iter() {
-                let copied_value = deep_copy_object_with_map(src_doc, dst_doc, value, id_map);
+                let copied_value = deep_copy_object_tracked(src_doc, dst_doc, value, id_map, missing);
                 new_dict.set(key.clone(), copied_value);
             }
             Object::Stream(Stream::new(new_dict, stream.content.clone()))
@@ -311,72 +863,36 @@ fn parse_page_ranges(ranges: &str, max_pages: usize) -> Result<Vec<usize>, Strin
 /// 按页码范围分割成多个 PDF 文件
 /// page_ranges: 格式如 "1,3,5" 或 "1-2,4-5" 
 /// 返回多个独立的 PDF 文件数组
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
 #[wasm_bindgen]
-pub fn split_pdf_by_pages(pdf_bytes: &[u8], page_ranges: &str) -> Result<js_sys::Array, JsValue> {
+pub fn split_pdf_by_pages(pdf_bytes: &[u8], page_ranges: &str, linearize: Option<bool>) -> Result<js_sys::Array, JsValue> {
+    split_pdf_by_pages_bytes(pdf_bytes, page_ranges, false, linearize.unwrap_or(false))
+        .map(|(pdfs, _)| pdf_array(&pdfs))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// 按页码范围分割成多个单页 PDF，同时返回缺失的页面和对象
+/// strict 为 true 时，页面或其引用的对象不存在直接报错；否则跳过页面、把引用替换为 null 并记录警告
+#[wasm_bindgen]
+pub fn split_pdf_by_pages_with_warnings(pdf_bytes: &[u8], page_ranges: &str, strict: bool, linearize: Option<bool>) -> Result<PdfsWithWarnings, JsValue> {
+    split_pdf_by_pages_bytes(pdf_bytes, page_ranges, strict, linearize.unwrap_or(false))
+        .and_then(|(pdfs, warnings)| PdfsWithWarnings::new(pdfs, &warnings))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+fn split_pdf_by_pages_bytes(pdf_bytes: &[u8], page_ranges: &str, strict: bool, linearize: bool) -> Result<(Vec<Vec<u8>>, Vec<CopyWarning>), String> {
     // 加载 PDF 文档
     let doc = Document::load_mem(pdf_bytes)
-        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
 
     // 解析页码范围
-    let pages_to_extract = parse_page_ranges(page_ranges, doc.get_pages().len())
-        .map_err(|e| JsValue::from_str(&e))?;
+    let pages_to_extract = parse_page_ranges(page_ranges, doc.get_pages().len())?;
 
     if pages_to_extract.is_empty() {
-        return Err(JsValue::from_str("没有可提取的页面"));
-    }
-
-    let result = js_sys::Array::new_with_length(pages_to_extract.len() as u32);
-    let all_pages: Vec<_> = doc.get_pages().into_iter().collect();
-
-    // 为每个页面创建独立的 PDF
-    for (output_idx, &page_idx) in pages_to_extract.iter().enumerate() {
-        if page_idx < all_pages.len() {
-            let (_page_num, page_id) = all_pages[page_idx];
-            
-            // 创建单页文档
-            let mut single_page_doc = Document::with_version("1.5");
-            let pages_id = single_page_doc.new_object_id();
-            let mut id_map: HashMap<ObjectId, ObjectId> = HashMap::new();
-            
-            // 深度复制页面对象
-            if let Ok(page_obj) = doc.get_object(page_id) {
-                let new_page_obj = deep_copy_object_with_map(&doc, &mut single_page_doc, page_obj, &mut id_map);
-                let new_page_id = single_page_doc.add_object(new_page_obj);
-                
-                id_map.insert(page_id, new_page_id);
-                
-                // 更新页面的父引用
-                if let Ok(Object::Dictionary(ref mut page_dict)) = single_page_doc.get_object_mut(new_page_id) {
-                    page_dict.set("Parent", Object::Reference(pages_id));
-                }
-                
-                // 创建页面树
-                let mut pages_dict = Dictionary::new();
-                pages_dict.set("Type", Object::Name(b"Pages".to_vec()));
-                pages_dict.set("Count", Object::Integer(1));
-                pages_dict.set("Kids", Object::Array(vec![Object::Reference(new_page_id)]));
-                single_page_doc.objects.insert(pages_id, Object::Dictionary(pages_dict));
-                
-                // 创建目录
-                let mut catalog = Dictionary::new();
-                catalog.set("Type", Object::Name(b"Catalog".to_vec()));
-                catalog.set("Pages", Object::Reference(pages_id));
-                
-                let catalog_id = single_page_doc.add_object(Object::Dictionary(catalog));
-                single_page_doc.trailer.set("Root", Object::Reference(catalog_id));
-                
-                // 保存单页文档
-                let mut buffer = Vec::new();
-                single_page_doc.save_to(&mut buffer)
-                    .map_err(|e| JsValue::from_str(&format!("无法保存第 {} 页: {}", page_idx + 1, e)))?;
-                
-                let uint8_array = js_sys::Uint8Array::from(&buffer[..]);
-                result.set(output_idx as u32, JsValue::from(uint8_array));
-            }
-        }
+        return Err("没有可提取的页面".to_string());
     }
 
-    Ok(result)
+    split_single_pages(&doc, &pages_to_extract, strict, linearize)
 }
 
 /// 获取 PDF 页数
@@ -388,3 +904,1337 @@ pub fn get_pdf_page_count(pdf_bytes: &[u8]) -> Result<usize, JsValue> {
     Ok(doc.get_pages().len())
 }
 
+/// 统一 PDF 页面尺寸
+/// target: 目标纸张，如 "A4"、"B5"、"Letter"、"Legal" 或自定义 "595x842"、"210x297mm"
+/// fit_mode: "fit" 等比完整显示、"fill" 等比填满（超出部分裁掉）、"stretch" 拉伸填满
+/// 页面内容通过变换矩阵缩放并居中，横向页面会使用横向的目标尺寸
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn normalize_page_size(pdf_bytes: &[u8], target: &str, fit_mode: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    let target = parse_paper_size(target).map_err(|e| JsValue::from_str(&e))?;
+    let mode = FitMode::parse(fit_mode).map_err(|e| JsValue::from_str(&e))?;
+
+    normalize_document_pages(&mut doc, target, mode)
+        .map_err(|e| JsValue::from_str(&e))?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize.unwrap_or(false))
+        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+
+    Ok(buffer)
+}
+
+/// 将文档中每一页缩放到目标尺寸
+fn normalize_document_pages(doc: &mut Document, target: (f64, f64), mode: FitMode) -> Result<(), String> {
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+
+    for page_id in page_ids {
+        // 可见区域优先使用 CropBox
+        let [x0, y0, x1, y1] = get_page_box(doc, page_id, b"CropBox")
+            .or_else(|| get_page_box(doc, page_id, b"MediaBox"))
+            .unwrap_or([0.0, 0.0, 612.0, 792.0]);
+        let (box_width, box_height) = (x1 - x0, y1 - y0);
+        if box_width <= 0.0 || box_height <= 0.0 {
+            continue;
+        }
+
+        let rotated = get_page_rotation(doc, page_id) % 180 != 0;
+
+        // 目标方向跟随页面的显示方向
+        let (display_width, display_height) = if rotated { (box_height, box_width) } else { (box_width, box_height) };
+        let (mut target_width, mut target_height) = target;
+        if (display_width > display_height) != (target_width > target_height) && display_width != display_height {
+            std::mem::swap(&mut target_width, &mut target_height);
+        }
+
+        // 旋转页面的用户空间宽高与显示宽高相反
+        let (new_width, new_height) = if rotated { (target_height, target_width) } else { (target_width, target_height) };
+
+        let (scale_x, scale_y, offset_x, offset_y) =
+            compute_placement(box_width, box_height, new_width, new_height, mode);
+        let e = offset_x - scale_x * x0;
+        let f = offset_y - scale_y * y0;
+
+        // 用 q/cm ... Q 包裹原有内容，并裁剪到原可见区域
+        let prefix = format!(
+            "q\n{} 0 0 {} {} {} cm\n{} {} {} {} re W n\n",
+            scale_x, scale_y, e, f, x0, y0, box_width, box_height
+        );
+        let content_ids = doc.get_page_contents(page_id);
+        if !content_ids.is_empty() {
+            let prefix_id = doc.add_object(Stream::new(Dictionary::new(), prefix.into_bytes()));
+            let suffix_id = doc.add_object(Stream::new(Dictionary::new(), b"\nQ\n".to_vec()));
+
+            let mut contents = vec![Object::Reference(prefix_id)];
+            contents.extend(content_ids.into_iter().map(Object::Reference));
+            contents.push(Object::Reference(suffix_id));
+
+            let page = doc.get_dictionary_mut(page_id)
+                .map_err(|e| format!("无法读取页面: {}", e))?;
+            page.set("Contents", Object::Array(contents));
+        }
+
+        let inherits_crop_box = get_inherited_attribute(doc, page_id, b"CropBox").is_some();
+        let page = doc.get_dictionary_mut(page_id)
+            .map_err(|e| format!("无法读取页面: {}", e))?;
+        let media_box = vec![
+            Object::Integer(0),
+            Object::Integer(0),
+            Object::Real(new_width as f32),
+            Object::Real(new_height as f32),
+        ];
+        for key in [b"CropBox".as_slice(), b"TrimBox", b"BleedBox", b"ArtBox"] {
+            page.remove(key);
+        }
+        // 父节点上的 CropBox 会被继承，需要显式覆盖
+        if inherits_crop_box {
+            page.set("CropBox", Object::Array(media_box.clone()));
+        }
+        page.set("MediaBox", Object::Array(media_box));
+
+        transform_annotation_rects(doc, page_id, (scale_x, scale_y, e, f));
+    }
+
+    Ok(())
+}
+
+/// 对页面上所有注释的 Rect 应用缩放和平移
+fn transform_annotation_rects(doc: &mut Document, page_id: ObjectId, (a, d, e, f): (f64, f64, f64, f64)) {
+    let transform = |annot: &mut Dictionary| {
+        let rect = annot.get(b"Rect").ok()
+            .and_then(|r| r.as_array().ok())
+            .and_then(|r| parse_rect(r));
+        if let Some([x0, y0, x1, y1]) = rect {
+            annot.set("Rect", Object::Array(vec![
+                Object::Real((a * x0 + e) as f32),
+                Object::Real((d * y0 + f) as f32),
+                Object::Real((a * x1 + e) as f32),
+                Object::Real((d * y1 + f) as f32),
+            ]));
+        }
+    };
+
+    let annots = match doc.get_dictionary(page_id).and_then(|p| p.get(b"Annots")) {
+        Ok(Object::Reference(id)) => doc.get_object(*id).and_then(Object::as_array).cloned().unwrap_or_default(),
+        Ok(Object::Array(arr)) => arr.clone(),
+        _ => return,
+    };
+
+    for (idx, annot) in annots.iter().enumerate() {
+        match annot {
+            Object::Reference(id) => {
+                if let Ok(annot_dict) = doc.get_dictionary_mut(*id) {
+                    transform(annot_dict);
+                }
+            }
+            Object::Dictionary(_) => {
+                // 内联注释字典直接修改页面中的数组
+                if let Ok(Object::Array(arr)) = doc.get_dictionary_mut(page_id).and_then(|p| p.get_mut(b"Annots")) {
+                    if let Some(Object::Dictionary(annot_dict)) = arr.get_mut(idx) {
+                        transform(annot_dict);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 读取页面属性，沿 Parent 链查找可继承的属性（MediaBox、Resources 等）
+pub(crate) fn get_inherited_attribute<'a>(doc: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = doc.get_dictionary(page_id).ok()?;
+    // 限制深度，防止错误的 Parent 引用形成循环
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return Some(value);
+        }
+        let parent_id = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = doc.get_dictionary(parent_id).ok()?;
+    }
+    None
+}
+
+/// 读取页面的矩形属性（MediaBox、CropBox 等），返回规范化后的 [x0, y0, x1, y1]
+pub(crate) fn get_page_box(doc: &Document, page_id: ObjectId, key: &[u8]) -> Option<[f64; 4]> {
+    let value = get_inherited_attribute(doc, page_id, key)?;
+    let (_, value) = doc.dereference(value).ok()?;
+    parse_rect(value.as_array().ok()?)
+}
+
+/// 读取页面旋转角度，返回 0、90、180 或 270
+pub(crate) fn get_page_rotation(doc: &Document, page_id: ObjectId) -> i64 {
+    get_inherited_attribute(doc, page_id, b"Rotate")
+        .and_then(|r| r.as_i64().ok())
+        .map(|r| r.rem_euclid(360) / 90 * 90)
+        .unwrap_or(0)
+}
+
+/// 将四个数字组成的数组解析为矩形，保证 x0 <= x1、y0 <= y1
+pub(crate) fn parse_rect(values: &[Object]) -> Option<[f64; 4]> {
+    if values.len() != 4 {
+        return None;
+    }
+    let mut nums = [0.0; 4];
+    for (num, value) in nums.iter_mut().zip(values) {
+        *num = object_to_f64(value)?;
+    }
+    Some([nums[0].min(nums[2]), nums[1].min(nums[3]), nums[0].max(nums[2]), nums[1].max(nums[3])])
+}
+
+pub(crate) fn object_to_f64(obj: &Object) -> Option<f64> {
+    match obj {
+        Object::Integer(i) => Some(*i as f64),
+        Object::Real(r) => Some(*r as f64),
+        _ => None,
+    }
+}
+
+/// 页面框设置（以 JSON 字符串传入），单位为点，格式 [x0, y0, x1, y1]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PageBoxes {
+    #[serde(alias = "MediaBox")]
+    media_box: Option<[f64; 4]>,
+    #[serde(alias = "CropBox")]
+    crop_box: Option<[f64; 4]>,
+    #[serde(alias = "TrimBox")]
+    trim_box: Option<[f64; 4]>,
+    #[serde(alias = "BleedBox")]
+    bleed_box: Option<[f64; 4]>,
+    #[serde(alias = "ArtBox")]
+    art_box: Option<[f64; 4]>,
+}
+
+/// 设置页面的 MediaBox/CropBox/TrimBox/BleedBox/ArtBox
+/// page_ranges: 格式如 "1-3,5"，为空表示所有页面
+/// boxes: JSON 字符串，例如 {"crop_box": [36, 36, 576, 756]}
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn set_page_boxes(pdf_bytes: &[u8], page_ranges: &str, boxes: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    let boxes: PageBoxes = serde_json::from_str(boxes)
+        .map_err(|e| JsValue::from_str(&format!("无效的页面框参数: {}", e)))?;
+
+    let entries = [
+        ("MediaBox", boxes.media_box),
+        ("CropBox", boxes.crop_box),
+        ("TrimBox", boxes.trim_box),
+        ("BleedBox", boxes.bleed_box),
+        ("ArtBox", boxes.art_box),
+    ];
+    for (key, rect) in entries.iter() {
+        if let Some([x0, y0, x1, y1]) = rect {
+            if x0 >= x1 || y0 >= y1 {
+                return Err(JsValue::from_str(&format!("无效的 {}: 宽度和高度必须大于 0", key)));
+            }
+        }
+    }
+
+    let all_pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let selected = parse_page_selection(page_ranges, all_pages.len())
+        .map_err(|e| JsValue::from_str(&e))?;
+
+    for page_idx in selected {
+        let page = doc.get_dictionary_mut(all_pages[page_idx])
+            .map_err(|e| JsValue::from_str(&format!("无法读取第 {} 页: {}", page_idx + 1, e)))?;
+        for (key, rect) in entries.iter() {
+            if let Some(rect) = rect {
+                page.set(*key, rect_to_object(rect));
+            }
+        }
+    }
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize.unwrap_or(false))
+        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+
+    Ok(buffer)
+}
+
+/// 自动裁剪页面空白
+/// 根据内容流中的路径、文字和图片计算内容区域，设置 CropBox
+/// margin_mm: 内容四周保留的边距（毫米），默认 5
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn auto_crop_pdf(pdf_bytes: &[u8], margin_mm: Option<f64>, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    let margin = margin_mm.unwrap_or(5.0) * MM_TO_PT;
+    if margin < 0.0 {
+        return Err(JsValue::from_str("边距不能为负数"));
+    }
+
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in page_ids {
+        let Some(media_box) = get_page_box(&doc, page_id, b"MediaBox") else { continue };
+        // 空白页保持不变
+        let Some(bounds) = page_content_bounds(&doc, page_id) else { continue };
+
+        let crop_box = [
+            (bounds[0] - margin).max(media_box[0]),
+            (bounds[1] - margin).max(media_box[1]),
+            (bounds[2] + margin).min(media_box[2]),
+            (bounds[3] + margin).min(media_box[3]),
+        ];
+        if crop_box[0] >= crop_box[2] || crop_box[1] >= crop_box[3] {
+            continue;
+        }
+
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("CropBox", rect_to_object(&crop_box));
+        }
+    }
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize.unwrap_or(false))
+        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+
+    Ok(buffer)
+}
+
+/// 为页面四周添加边距
+/// 页面尺寸向四周扩大 margin_mm 毫米，原内容保持居中
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn add_margins(pdf_bytes: &[u8], margin_mm: f64, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    if margin_mm < 0.0 {
+        return Err(JsValue::from_str("边距不能为负数"));
+    }
+
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    let margin = margin_mm * MM_TO_PT;
+    let grow = |[x0, y0, x1, y1]: [f64; 4]| [x0 - margin, y0 - margin, x1 + margin, y1 + margin];
+
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in page_ids {
+        let media_box = get_page_box(&doc, page_id, b"MediaBox").unwrap_or([0.0, 0.0, 612.0, 792.0]);
+        let crop_box = get_page_box(&doc, page_id, b"CropBox");
+
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("MediaBox", rect_to_object(&grow(media_box)));
+            // CropBox 不扩大的话新增的边距会被裁掉
+            if let Some(crop_box) = crop_box {
+                page.set("CropBox", rect_to_object(&grow(crop_box)));
+            }
+        }
+    }
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize.unwrap_or(false))
+        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+
+    Ok(buffer)
+}
+
+/// 解析页码范围，空字符串表示所有页面
+pub(crate) fn parse_page_selection(ranges: &str, max_pages: usize) -> Result<Vec<usize>, String> {
+    if ranges.trim().is_empty() {
+        Ok((0..max_pages).collect())
+    } else {
+        parse_page_ranges(ranges, max_pages)
+    }
+}
+
+pub(crate) fn rect_to_object(rect: &[f64; 4]) -> Object {
+    Object::Array(rect.iter().map(|&v| Object::Real(v as f32)).collect())
+}
+
+/// 空白页插入选项（以 JSON 字符串传入）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BlankPageOptions {
+    /// 在这些页之后插入空白页（页码从 1 开始，0 表示插入到第一页之前）
+    after_pages: Vec<usize>,
+    /// 每隔 N 页插入空白页（最后一页之后不插入）
+    every: Option<usize>,
+    /// 页数为奇数时在末尾补一页空白页，便于双面打印
+    pad_to_even: bool,
+    /// 空白页尺寸，如 "A4"；不设置时与相邻页面一致
+    page_size: Option<String>,
+    /// 输出线性化（快速 Web 查看）文件
+    linearize: bool,
+}
+
+/// 插入空白页
+/// options: JSON 字符串，例如 {"after_pages": [0, 3], "every": 2, "pad_to_even": true, "page_size": "A4"}
+#[wasm_bindgen]
+pub fn insert_blank_pages(pdf_bytes: &[u8], options: &str) -> Result<Vec<u8>, JsValue> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    let options: BlankPageOptions = serde_json::from_str(options)
+        .map_err(|e| JsValue::from_str(&format!("无效的空白页选项: {}", e)))?;
+    let page_size = options.page_size.as_deref()
+        .map(parse_paper_size)
+        .transpose()
+        .map_err(|e| JsValue::from_str(&e))?;
+
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    if pages.is_empty() {
+        return Err(JsValue::from_str("PDF 中没有页面"));
+    }
+    if let Some(&position) = options.after_pages.iter().find(|&&p| p > pages.len()) {
+        return Err(JsValue::from_str(&format!("页码超出范围: {}", position)));
+    }
+    if options.every == Some(0) {
+        return Err(JsValue::from_str("every 必须大于 0"));
+    }
+
+    let pages_id = get_root_pages_id(&doc).map_err(|e| JsValue::from_str(&e))?;
+    materialize_inherited_attributes(&mut doc, &pages);
+
+    // blanks_after[i] 表示第 i 页之后插入的空白页数（0 表示第一页之前）
+    let mut blanks_after = vec![0usize; pages.len() + 1];
+    for &position in &options.after_pages {
+        blanks_after[position] += 1;
+    }
+    if let Some(every) = options.every {
+        for position in (every..pages.len()).step_by(every) {
+            blanks_after[position] += 1;
+        }
+    }
+    let total: usize = pages.len() + blanks_after.iter().sum::<usize>();
+    if options.pad_to_even && total % 2 == 1 {
+        blanks_after[pages.len()] += 1;
+    }
+
+    let mut new_pages = Vec::new();
+    for (position, &count) in blanks_after.iter().enumerate() {
+        if position > 0 {
+            new_pages.push(pages[position - 1]);
+        }
+        // 与前一页尺寸一致，插入到开头时与第一页一致
+        let neighbor = pages[position.saturating_sub(1)];
+        for _ in 0..count {
+            let blank_id = match page_size {
+                Some((width, height)) => add_blank_page(&mut doc, pages_id, [0.0, 0.0, width, height], 0),
+                None => add_blank_page_like(&mut doc, pages_id, neighbor),
+            };
+            new_pages.push(blank_id);
+        }
+    }
+
+    set_page_tree_kids(&mut doc, pages_id, &new_pages).map_err(|e| JsValue::from_str(&e))?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, options.linearize)
+        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+
+    Ok(buffer)
+}
+
+/// 每页最多复制的份数
+const MAX_PAGE_COPIES: u32 = 100;
+
+/// 复制页面
+/// 将 page_ranges 中的每一页复制 copies 份（最多 100 份），副本紧跟在原页面之后
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn duplicate_pages(pdf_bytes: &[u8], page_ranges: &str, copies: u32, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    duplicate_pages_from_bytes(pdf_bytes, page_ranges, copies, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 复制页面的 Rust 接口，失败时返回错误信息而不是 JsValue
+pub fn duplicate_pages_from_bytes(pdf_bytes: &[u8], page_ranges: &str, copies: u32, linearize: bool) -> Result<Vec<u8>, String> {
+    if copies > MAX_PAGE_COPIES {
+        return Err(format!("每页最多复制 {} 份", MAX_PAGE_COPIES));
+    }
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let selected = parse_page_selection(page_ranges, pages.len())?;
+
+    let pages_id = get_root_pages_id(&doc)?;
+    materialize_inherited_attributes(&mut doc, &pages);
+
+    let mut new_pages = Vec::new();
+    for (idx, &page_id) in pages.iter().enumerate() {
+        new_pages.push(page_id);
+        if selected.binary_search(&idx).is_ok() {
+            for _ in 0..copies {
+                new_pages.push(clone_page(&mut doc, page_id));
+            }
+        }
+    }
+
+    set_page_tree_kids(&mut doc, pages_id, &new_pages)?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+
+    Ok(buffer)
+}
+
+/// 获取目录中的根页面树节点
+fn get_root_pages_id(doc: &Document) -> Result<ObjectId, String> {
+    doc.catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(Object::as_reference)
+        .map_err(|e| format!("无法读取页面树: {}", e))
+}
+
+/// 把页面从中间节点继承的属性写到页面本身，页面树重建后属性不会丢失
+pub(crate) fn materialize_inherited_attributes(doc: &mut Document, pages: &[ObjectId]) {
+    for &page_id in pages {
+        for key in [b"Resources".as_slice(), b"MediaBox", b"CropBox", b"Rotate"] {
+            let has_own = doc.get_dictionary(page_id).map(|p| p.has(key)).unwrap_or(true);
+            if has_own {
+                continue;
+            }
+            if let Some(value) = get_inherited_attribute(doc, page_id, key).cloned() {
+                if let Ok(page) = doc.get_dictionary_mut(page_id) {
+                    page.set(key.to_vec(), value);
+                }
+            }
+        }
+    }
+}
+
+/// 将根页面树节点替换为扁平的页面列表
+/// 原有的中间页面树节点会被删除，调用前需先处理继承属性
+pub(crate) fn set_page_tree_kids(doc: &mut Document, pages_id: ObjectId, pages: &[ObjectId]) -> Result<(), String> {
+    // 收集中间节点
+    let mut stack = vec![pages_id];
+    let mut intermediate = Vec::new();
+    while let Some(node_id) = stack.pop() {
+        let Ok(node) = doc.get_dictionary(node_id) else { continue };
+        if node_id != pages_id {
+            intermediate.push(node_id);
+        }
+        if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+            for kid in kids {
+                if let Ok(kid_id) = kid.as_reference() {
+                    let is_pages = doc.get_dictionary(kid_id).map(|k| k.type_is(b"Pages")).unwrap_or(false);
+                    if is_pages && !intermediate.contains(&kid_id) {
+                        stack.push(kid_id);
+                    }
+                }
+            }
+        }
+    }
+    for node_id in intermediate {
+        doc.objects.remove(&node_id);
+    }
+
+    for &page_id in pages {
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Parent", Object::Reference(pages_id));
+        }
+    }
+
+    let root = doc.get_dictionary_mut(pages_id)
+        .map_err(|e| format!("无法读取页面树: {}", e))?;
+    root.set("Kids", Object::Array(pages.iter().map(|&id| Object::Reference(id)).collect()));
+    root.set("Count", Object::Integer(pages.len() as i64));
+
+    Ok(())
+}
+
+/// 创建一页空白页
+fn add_blank_page(doc: &mut Document, pages_id: ObjectId, media_box: [f64; 4], rotate: i64) -> ObjectId {
+    let mut page = Dictionary::new();
+    page.set("Type", Object::Name(b"Page".to_vec()));
+    page.set("Parent", Object::Reference(pages_id));
+    page.set("MediaBox", rect_to_object(&media_box));
+    page.set("Resources", Object::Dictionary(Dictionary::new()));
+    if rotate != 0 {
+        page.set("Rotate", Object::Integer(rotate));
+    }
+    doc.add_object(Object::Dictionary(page))
+}
+
+/// 创建与指定页面尺寸、方向相同的空白页
+fn add_blank_page_like(doc: &mut Document, pages_id: ObjectId, page_id: ObjectId) -> ObjectId {
+    let media_box = get_page_box(doc, page_id, b"CropBox")
+        .or_else(|| get_page_box(doc, page_id, b"MediaBox"))
+        .unwrap_or([0.0, 0.0, 612.0, 792.0]);
+    let rotate = get_page_rotation(doc, page_id);
+    add_blank_page(doc, pages_id, media_box, rotate)
+}
+
+/// 复制页面对象，内容和资源与原页面共享，注释单独复制
+fn clone_page(doc: &mut Document, page_id: ObjectId) -> ObjectId {
+    let mut page = doc.get_dictionary(page_id).cloned().unwrap_or_default();
+    let new_page_id = doc.new_object_id();
+
+    // 注释通过 /P 指向所属页面，不能在两个页面间共享
+    if let Ok(annots) = page.get(b"Annots").and_then(|a| doc.dereference(a)).and_then(|(_, a)| a.as_array()) {
+        let annots = annots.clone();
+        let mut new_annots = Vec::new();
+        for annot in annots {
+            let annot_dict = doc.dereference(&annot).ok().and_then(|(_, a)| a.as_dict().ok().cloned());
+            if let Some(mut annot_dict) = annot_dict {
+                // Popup 注释随其父注释存在，单独复制会产生悬空引用
+                if annot_dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Popup".as_slice()) {
+                    continue;
+                }
+                annot_dict.remove(b"Popup");
+                annot_dict.set("P", Object::Reference(new_page_id));
+                new_annots.push(Object::Reference(doc.add_object(annot_dict)));
+            }
+        }
+        page.set("Annots", Object::Array(new_annots));
+    }
+
+    doc.objects.insert(new_page_id, Object::Dictionary(page));
+    new_page_id
+}
+
+/// 叠加选项（以 JSON 字符串传入）
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct OverlayOptions {
+    /// "over" 绘制在原内容之上（默认），"under" 绘制在原内容之下
+    position: String,
+    /// 叠加页与原页面的对应方式:
+    /// "repeat_last"（默认）按页对应，叠加页用完后重复最后一页；
+    /// "cycle" 循环使用叠加页；"first_page" 只叠加到第一页
+    mode: String,
+    /// 只处理这些页面，如 "1-3,5"；为空表示所有页面
+    pages: String,
+    /// 叠加页尺寸与原页面不同时等比缩放并居中
+    scale_to_fit: bool,
+    /// 输出线性化（快速 Web 查看）文件
+    linearize: bool,
+}
+
+impl Default for OverlayOptions {
+    fn default() -> Self {
+        OverlayOptions {
+            position: "over".to_string(),
+            mode: "repeat_last".to_string(),
+            pages: String::new(),
+            scale_to_fit: false,
+            linearize: false,
+        }
+    }
+}
+
+/// 将一个 PDF 叠加到另一个 PDF 上（如信纸模板、水印页）
+/// 叠加 PDF 的每一页被转换为表单 XObject，绘制在原页面内容之上或之下
+/// options: JSON 字符串，例如 {"position": "under", "mode": "first_page"}
+#[wasm_bindgen]
+pub fn overlay_pdf(base: &[u8], overlay: &[u8], options: &str) -> Result<Vec<u8>, JsValue> {
+    let mut doc = Document::load_mem(base)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+    let overlay_doc = Document::load_mem(overlay)
+        .map_err(|e| JsValue::from_str(&format!("无法加载叠加 PDF: {}", e)))?;
+
+    let options: OverlayOptions = serde_json::from_str(options)
+        .map_err(|e| JsValue::from_str(&format!("无效的叠加选项: {}", e)))?;
+    let under = match options.position.as_str() {
+        "over" => false,
+        "under" => true,
+        other => return Err(JsValue::from_str(&format!("不支持的叠加位置: {}", other))),
+    };
+
+    let overlay_pages: Vec<ObjectId> = overlay_doc.get_pages().into_values().collect();
+    if overlay_pages.is_empty() {
+        return Err(JsValue::from_str("叠加 PDF 中没有页面"));
+    }
+
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let selected = parse_page_selection(&options.pages, pages.len())
+        .map_err(|e| JsValue::from_str(&e))?;
+
+    let mut id_map: HashMap<ObjectId, ObjectId> = HashMap::new();
+    let mut forms: HashMap<usize, (ObjectId, [f64; 4])> = HashMap::new();
+
+    for (order, &page_idx) in selected.iter().enumerate() {
+        let overlay_idx = match options.mode.as_str() {
+            "repeat_last" => order.min(overlay_pages.len() - 1),
+            "cycle" => order % overlay_pages.len(),
+            "first_page" if order == 0 => 0,
+            "first_page" => break,
+            other => return Err(JsValue::from_str(&format!("不支持的叠加方式: {}", other))),
+        };
+
+        let (form_id, form_box) = match forms.get(&overlay_idx) {
+            Some(form) => *form,
+            None => {
+                let form = page_to_form_xobject(&overlay_doc, &mut doc, overlay_pages[overlay_idx], &mut id_map)
+                    .ok_or_else(|| JsValue::from_str(&format!("无法读取叠加 PDF 第 {} 页", overlay_idx + 1)))?;
+                forms.insert(overlay_idx, form);
+                form
+            }
+        };
+
+        let page_id = pages[page_idx];
+        let page_box = get_page_box(&doc, page_id, b"CropBox")
+            .or_else(|| get_page_box(&doc, page_id, b"MediaBox"))
+            .unwrap_or([0.0, 0.0, 612.0, 792.0]);
+
+        // 叠加页的左下角对齐原页面左下角，或等比缩放居中
+        let (scale_x, scale_y, offset_x, offset_y) = if options.scale_to_fit {
+            compute_placement(
+                form_box[2] - form_box[0],
+                form_box[3] - form_box[1],
+                page_box[2] - page_box[0],
+                page_box[3] - page_box[1],
+                FitMode::Fit,
+            )
+        } else {
+            (1.0, 1.0, 0.0, 0.0)
+        };
+        let e = page_box[0] + offset_x - scale_x * form_box[0];
+        let f = page_box[1] + offset_y - scale_y * form_box[1];
+
+        let name = add_xobject_to_page(&mut doc, page_id, "Overlay", form_id)
+            .map_err(|e| JsValue::from_str(&e))?;
+        let draw = format!(
+            "q\n{} 0 0 {} {} {} cm\n/{} Do\nQ\n",
+            scale_x, scale_y, e, f, name
+        );
+        wrap_page_contents(&mut doc, page_id, draw.into_bytes(), under)
+            .map_err(|e| JsValue::from_str(&e))?;
+    }
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, options.linearize)
+        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+
+    Ok(buffer)
+}
+
+/// 将源文档的一页转换为目标文档中的表单 XObject
+/// 返回 XObject 的对象 ID 和其 BBox（页面可见区域）
+fn page_to_form_xobject(
+    src_doc: &Document,
+    dst_doc: &mut Document,
+    page_id: ObjectId,
+    id_map: &mut HashMap<ObjectId, ObjectId>,
+) -> Option<(ObjectId, [f64; 4])> {
+    let bbox = get_page_box(src_doc, page_id, b"CropBox")
+        .or_else(|| get_page_box(src_doc, page_id, b"MediaBox"))
+        .unwrap_or([0.0, 0.0, 612.0, 792.0]);
+    let content = src_doc.get_page_content(page_id).ok()?;
+
+    let mut form_dict = Dictionary::new();
+    form_dict.set("Type", Object::Name(b"XObject".to_vec()));
+    form_dict.set("Subtype", Object::Name(b"Form".to_vec()));
+    form_dict.set("BBox", rect_to_object(&bbox));
+    if let Some(resources) = get_inherited_attribute(src_doc, page_id, b"Resources") {
+        let resources = deep_copy_object_with_map(src_doc, dst_doc, resources, id_map);
+        form_dict.set("Resources", resources);
+    }
+
+    let mut stream = Stream::new(form_dict, content);
+    // 压缩失败时保留未压缩的内容
+    let _ = stream.compress();
+    Some((dst_doc.add_object(Object::Stream(stream)), bbox))
+}
+
+/// 在页面资源中登记一个 XObject，返回使用的资源名称
+/// 页面的资源字典会被复制为页面自己的字典，避免影响共享同一资源字典的其他页面
+pub(crate) fn add_xobject_to_page(doc: &mut Document, page_id: ObjectId, prefix: &str, xobject_id: ObjectId) -> Result<String, String> {
+    let mut resources = get_inherited_attribute(doc, page_id, b"Resources")
+        .and_then(|r| doc.dereference(r).ok())
+        .and_then(|(_, r)| r.as_dict().ok().cloned())
+        .unwrap_or_default();
+    let mut xobjects = resources.get(b"XObject").ok()
+        .and_then(|x| doc.dereference(x).ok())
+        .and_then(|(_, x)| x.as_dict().ok().cloned())
+        .unwrap_or_default();
+
+    let name = (1..)
+        .map(|n| format!("{}{}", prefix, n))
+        .find(|name| !xobjects.has(name.as_bytes()))
+        .unwrap_or_default();
+    xobjects.set(name.clone(), Object::Reference(xobject_id));
+    resources.set("XObject", Object::Dictionary(xobjects));
+
+    let page = doc.get_dictionary_mut(page_id)
+        .map_err(|e| format!("无法读取页面: {}", e))?;
+    page.set("Resources", Object::Dictionary(resources));
+
+    Ok(name)
+}
+
+/// 在页面原有内容之前或之后添加一段内容
+/// 添加到之后时，原内容会被 q/Q 包裹，避免其未恢复的图形状态影响新内容
+pub(crate) fn wrap_page_contents(doc: &mut Document, page_id: ObjectId, content: Vec<u8>, before: bool) -> Result<(), String> {
+    let existing = doc.get_page_contents(page_id);
+    let content_id = doc.add_object(Stream::new(Dictionary::new(), content));
+
+    let mut contents = Vec::new();
+    if before {
+        contents.push(Object::Reference(content_id));
+        contents.extend(existing.into_iter().map(Object::Reference));
+    } else {
+        if !existing.is_empty() {
+            let save_id = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+            let restore_id = doc.add_object(Stream::new(Dictionary::new(), b"\nQ\n".to_vec()));
+            contents.push(Object::Reference(save_id));
+            contents.extend(existing.into_iter().map(Object::Reference));
+            contents.push(Object::Reference(restore_id));
+        }
+        contents.push(Object::Reference(content_id));
+    }
+
+    let page = doc.get_dictionary_mut(page_id)
+        .map_err(|e| format!("无法读取页面: {}", e))?;
+    page.set("Contents", Object::Array(contents));
+
+    Ok(())
+}
+
+/// 将 PDF 页面渲染为图片
+/// page_number: 页码（从 1 开始）；dpi: 分辨率，例如 72、150、300；format: "png" 或 "jpeg"
+/// 嵌入的字体按字形轮廓绘制，未嵌入的字体以占位色块代替；JPX/JBIG2 图片以灰色块代替
+#[wasm_bindgen]
+pub fn render_pdf_page(pdf_bytes: &[u8], page_number: usize, dpi: f64, format: &str) -> Result<Vec<u8>, JsValue> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+    check_dpi(dpi).map_err(|e| JsValue::from_str(&e))?;
+
+    let page_id = page_id_by_number(&doc, page_number).map_err(|e| JsValue::from_str(&e))?;
+    let pixmap = render_page(&doc, page_id, dpi / 72.0, &RenderOptions::default())
+        .map_err(|e| JsValue::from_str(&e))?;
+    encode_pixmap(&pixmap, format, 85).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 生成页面缩略图（PNG）
+/// 长边缩放到 max_size 像素，文字以占位色块绘制以加快速度
+#[wasm_bindgen]
+pub fn render_pdf_thumbnail(pdf_bytes: &[u8], page_number: usize, max_size: u32) -> Result<Vec<u8>, JsValue> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+    if max_size == 0 {
+        return Err(JsValue::from_str("缩略图尺寸必须大于 0"));
+    }
+
+    let page_id = page_id_by_number(&doc, page_number).map_err(|e| JsValue::from_str(&e))?;
+    let (width, height) = page_display_size(&doc, page_id);
+    let scale = max_size as f64 / width.max(height).max(1.0);
+    let options = RenderOptions { greek_text: true, ..RenderOptions::default() };
+    let pixmap = render_page(&doc, page_id, scale, &options).map_err(|e| JsValue::from_str(&e))?;
+    encode_pixmap(&pixmap, "png", 85).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 按页码（从 1 开始）查找页面
+fn page_id_by_number(doc: &Document, page_number: usize) -> Result<ObjectId, String> {
+    let pages = doc.get_pages();
+    u32::try_from(page_number).ok()
+        .and_then(|n| pages.get(&n).copied())
+        .ok_or_else(|| format!("页码超出范围: {}（共 {} 页）", page_number, pages.len()))
+}
+
+/// 将指定页面逐页渲染为图片
+/// page_ranges: 格式如 "1-3,5"，空字符串表示所有页面；dpi: 分辨率；format: "png" 或 "jpeg"
+/// 返回每页一张图片的数组
+#[wasm_bindgen]
+pub fn pdf_to_images(pdf_bytes: &[u8], page_ranges: &str, dpi: f64, format: &str) -> Result<js_sys::Array, JsValue> {
+    let images = render_pages_to_images(pdf_bytes, page_ranges, dpi, format)
+        .map_err(|e| JsValue::from_str(&e))?;
+
+    let result = js_sys::Array::new_with_length(images.len() as u32);
+    for (idx, image) in images.iter().enumerate() {
+        result.set(idx as u32, JsValue::from(js_sys::Uint8Array::from(&image[..])));
+    }
+    Ok(result)
+}
+
+fn render_pages_to_images(pdf_bytes: &[u8], page_ranges: &str, dpi: f64, format: &str) -> Result<Vec<Vec<u8>>, String> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+    check_dpi(dpi)?;
+
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let selected = parse_page_selection(page_ranges, pages.len())?;
+    if selected.is_empty() {
+        return Err("没有可渲染的页面".to_string());
+    }
+
+    selected.iter().map(|&page_idx| {
+        let pixmap = render_page(&doc, pages[page_idx], dpi / 72.0, &RenderOptions::default())
+            .map_err(|e| format!("无法渲染第 {} 页: {}", page_idx + 1, e))?;
+        encode_pixmap(&pixmap, format, 85)
+    }).collect()
+}
+
+/// 将 PDF 栅格化：每页渲染为一张 JPEG 图片，并用只包含该图片的页面替换原页面
+/// 输出中不保留任何文字、矢量图形、注释、表单或元数据，适合涂黑后发布或兼容老旧打印机
+/// dpi: 渲染分辨率；quality: JPEG 质量（1-100）
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn rasterize_pdf(pdf_bytes: &[u8], dpi: f64, quality: u8, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    rasterize_pdf_bytes(pdf_bytes, dpi, quality, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+fn rasterize_pdf_bytes(pdf_bytes: &[u8], dpi: f64, quality: u8, linearize: bool) -> Result<Vec<u8>, String> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+    check_dpi(dpi)?;
+    if quality == 0 || quality > 100 {
+        return Err("质量参数必须在 1-100 之间".to_string());
+    }
+
+    let mut output = Document::with_version("1.5");
+    let pages_id = output.new_object_id();
+    let mut page_ids = Vec::new();
+
+    for (page_number, page_id) in doc.get_pages() {
+        // 渲染结果已应用页面旋转，新页面按显示方向摆放
+        let (width, height) = page_display_size(&doc, page_id);
+        let pixmap = render_page(&doc, page_id, dpi / 72.0, &RenderOptions::default())
+            .map_err(|e| format!("无法渲染第 {} 页: {}", page_number, e))?;
+        let jpeg = encode_pixmap(&pixmap, "jpeg", quality)?;
+        let frame = decode_frames(&jpeg)?.into_iter().next()
+            .ok_or_else(|| format!("无法转换第 {} 页", page_number))?;
+        let image = embed_frame(&mut output, frame)
+            .map_err(|e| format!("无法转换第 {} 页: {}", page_number, e))?;
+
+        let mut xobject = Dictionary::new();
+        xobject.set("Im1", Object::Reference(image.id));
+        let mut resources = Dictionary::new();
+        resources.set("XObject", Object::Dictionary(xobject));
+
+        let content = format!("q\n{} 0 0 {} 0 0 cm\n/Im1 Do\nQ\n", width, height);
+        let content_id = output.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+
+        let mut page = Dictionary::new();
+        page.set("Type", Object::Name(b"Page".to_vec()));
+        page.set("Parent", Object::Reference(pages_id));
+        page.set("MediaBox", rect_to_object(&[0.0, 0.0, width, height]));
+        page.set("Resources", Object::Dictionary(resources));
+        page.set("Contents", Object::Reference(content_id));
+        page_ids.push(Object::Reference(output.add_object(page)));
+    }
+
+    build_page_tree(&mut output, pages_id, page_ids);
+
+    let mut buffer = Vec::new();
+    save_document(&mut output, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 将纯文本或 Markdown 排版为 PDF，使用标准字体（仅支持西文字符）
+/// options: JSON 字符串，如 {"page_size": "A4", "font_size": 11, "markdown": true}
+#[wasm_bindgen]
+pub fn text_to_pdf(text: &str, options: &str) -> Result<Vec<u8>, JsValue> {
+    let options: TextPdfOptions = serde_json::from_str(options)
+        .map_err(|e| JsValue::from_str(&format!("无效的排版选项: {}", e)))?;
+    text_to_pdf_bytes(text, &options, None).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 将纯文本或 Markdown 排版为 PDF，并嵌入 TrueType/OpenType 字体的子集以显示中文等字符
+/// font_bytes: TTF 或 OTF 字体文件的字节数组
+#[wasm_bindgen]
+pub fn text_to_pdf_with_font(text: &str, options: &str, font_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let options: TextPdfOptions = serde_json::from_str(options)
+        .map_err(|e| JsValue::from_str(&format!("无效的排版选项: {}", e)))?;
+    text_to_pdf_bytes(text, &options, Some(font_bytes)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 读取 PDF 的书签，返回 JSON 字符串
+/// 每项包含 title、page（从 1 开始，无法跳转时为 null）、fit、left/top/zoom、open、color、bold、italic、children
+#[wasm_bindgen]
+pub fn get_pdf_outline(pdf_bytes: &[u8]) -> Result<String, JsValue> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    let page_numbers: HashMap<ObjectId, usize> = doc.get_pages().into_iter().map(|(number, id)| (id, number as usize)).collect();
+    let entries = outline_to_entries(&read_outline(&doc), &page_numbers);
+    serde_json::to_string(&entries).map_err(|e| JsValue::from_str(&format!("无法生成书签 JSON: {}", e)))
+}
+
+/// 用新的书签树替换 PDF 原有的书签，outline 为 get_pdf_outline 格式的 JSON 数组；空数组表示删除书签
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn set_pdf_outline(pdf_bytes: &[u8], outline: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    let entries: Vec<OutlineEntry> = serde_json::from_str(outline)
+        .map_err(|e| JsValue::from_str(&format!("无效的书签: {}", e)))?;
+    set_pdf_outline_bytes(pdf_bytes, &entries, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+fn set_pdf_outline_bytes(pdf_bytes: &[u8], entries: &[OutlineEntry], linearize: bool) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let outline = entries_to_outline(entries, &page_ids)?;
+    let catalog_id = doc.trailer.get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|e| format!("无法获取文档目录: {}", e))?;
+    write_outline(&mut doc, catalog_id, &outline)?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 读取 PDF 的表单字段，返回 JSON 数组
+/// 每项包含 name、type、value、options、rect、page（从 1 开始）、read_only、required
+#[wasm_bindgen]
+pub fn get_form_fields(pdf_bytes: &[u8]) -> Result<String, JsValue> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    serde_json::to_string(&form_field_infos(&doc))
+        .map_err(|e| JsValue::from_str(&format!("无法生成表单 JSON: {}", e)))
+}
+
+/// 填写表单，values 为字段名到值的 JSON 对象，例如 {"name": "张三", "agree": true, "gender": "Male"}
+/// 文本和选择字段为字符串（多选列表可为数组），复选框为布尔值，单选按钮为选项名；填写后重新生成字段外观
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn fill_form(pdf_bytes: &[u8], values: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    fill_form_from_bytes(pdf_bytes, values, None, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 填写表单，表单字体无法显示的文字（如中文）使用 font_bytes 中 TrueType/OpenType 字体的子集
+#[wasm_bindgen]
+pub fn fill_form_with_font(pdf_bytes: &[u8], values: &str, font_bytes: &[u8], linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    fill_form_from_bytes(pdf_bytes, values, Some(font_bytes), linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 填写表单的 Rust 接口，values 同 fill_form；提供 font_bytes 时同 fill_form_with_font
+pub fn fill_form_from_bytes(pdf_bytes: &[u8], values: &str, font_bytes: Option<&[u8]>, linearize: bool) -> Result<Vec<u8>, String> {
+    let values: serde_json::Map<String, serde_json::Value> = serde_json::from_str(values)
+        .map_err(|e| format!("无效的表单数据: {}", e))?;
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    fill_form_fields(&mut doc, &values, font_bytes)?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 扁平化表单：将字段的外观固定到页面内容中，删除表单字段，结果不可再编辑
+/// 需要重新生成外观且表单字体无法显示其中的文字时返回错误，可改用 flatten_form_with_font
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn flatten_form(pdf_bytes: &[u8], linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    flatten_form_from_bytes(pdf_bytes, None, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 扁平化表单，表单字体无法显示的文字（如中文）使用 font_bytes 中 TrueType/OpenType 字体的子集
+#[wasm_bindgen]
+pub fn flatten_form_with_font(pdf_bytes: &[u8], font_bytes: &[u8], linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    flatten_form_from_bytes(pdf_bytes, Some(font_bytes), linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 扁平化表单的 Rust 接口；提供 font_bytes 时同 flatten_form_with_font
+pub fn flatten_form_from_bytes(pdf_bytes: &[u8], font_bytes: Option<&[u8]>, linearize: bool) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    flatten_form_fields(&mut doc, font_bytes)?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 列出 PDF 中的注释，返回 JSON 数组
+/// 每项包含 page（从 1 开始）、index（在该页的序号）、subtype、rect、contents、author、modified、color
+#[wasm_bindgen]
+pub fn list_annotations(pdf_bytes: &[u8]) -> Result<String, JsValue> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    serde_json::to_string(&list_page_annotations(&doc))
+        .map_err(|e| JsValue::from_str(&format!("无法生成注释 JSON: {}", e)))
+}
+
+/// 删除注释，filter 为 JSON 字符串，例如 {"subtypes": ["Highlight"], "pages": "1-3", "authors": ["Alice"]}
+/// 各条件同时满足的注释被删除，空对象 {} 删除全部注释；表单控件不会被删除，其弹出窗口随注释一起删除
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn remove_annotations(pdf_bytes: &[u8], filter: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    let filter: AnnotationFilter = serde_json::from_str(filter)
+        .map_err(|e| JsValue::from_str(&format!("无效的注释筛选条件: {}", e)))?;
+    remove_annotations_bytes(pdf_bytes, &filter, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+fn remove_annotations_bytes(pdf_bytes: &[u8], filter: &AnnotationFilter, linearize: bool) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    remove_matching_annotations(&mut doc, filter)?;
+    doc.prune_objects();
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 扁平化注释：将注释的外观固定到页面内容中并删除注释（表单控件、链接和没有外观的注释保持不变）
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn flatten_annotations(pdf_bytes: &[u8], linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    flatten_annotations_bytes(pdf_bytes, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+fn flatten_annotations_bytes(pdf_bytes: &[u8], linearize: bool) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    flatten_all_annotations(&mut doc)?;
+    doc.prune_objects();
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 添加注释，annotations 为 JSON 数组，每项的 type 为 "Highlight"、"Underline"、"StrikeOut"、"Text"、
+/// "FreeText"、"Square"、"Circle"、"Line"、"Ink" 或 "Link"，page 从 1 开始；坐标为 PDF 页面坐标（点）
+/// 除链接外都会生成外观流，不支持注释的阅读器和扁平化后也能正确显示
+/// FreeText 使用 Helvetica，文字包含 WinAnsi 以外的字符（如中文）时报错，请使用 add_annotations_with_font
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn add_annotations(pdf_bytes: &[u8], annotations: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    add_annotations_from_bytes(pdf_bytes, annotations, None, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 与 add_annotations 相同，FreeText 的文字使用 font_bytes（TTF/OTF）并嵌入用到的字形，可以显示中文等任意字符
+#[wasm_bindgen]
+pub fn add_annotations_with_font(pdf_bytes: &[u8], annotations: &str, font_bytes: &[u8], linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    add_annotations_from_bytes(pdf_bytes, annotations, Some(font_bytes), linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 添加注释的 Rust 接口，失败时返回错误信息
+/// font_bytes 为 None 时同 add_annotations，否则同 add_annotations_with_font
+pub fn add_annotations_from_bytes(pdf_bytes: &[u8], annotations: &str, font_bytes: Option<&[u8]>, linearize: bool) -> Result<Vec<u8>, String> {
+    let annotations: Vec<NewAnnotation> = serde_json::from_str(annotations)
+        .map_err(|e| format!("无效的注释: {}", e))?;
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    add_new_annotations(&mut doc, &annotations, font_bytes)?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 涂黑：删除区域内的文字和注释、遮盖图片像素并绘制遮盖色块，同时清除文档信息和 XMP 元数据
+/// redactions: JSON 字符串，例如 {"regions": [{"page": 1, "rect": [72, 700, 300, 720]}], "terms": ["机密"]}
+#[wasm_bindgen]
+pub fn redact_pdf(pdf_bytes: &[u8], redactions: &str) -> Result<Vec<u8>, JsValue> {
+    let options: RedactOptions = serde_json::from_str(redactions)
+        .map_err(|e| JsValue::from_str(&format!("无效的涂黑选项: {}", e)))?;
+    redact_pdf_bytes(pdf_bytes, &options).map_err(|e| JsValue::from_str(&e))
+}
+
+fn redact_pdf_bytes(pdf_bytes: &[u8], options: &RedactOptions) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    redact_document(&mut doc, options)?;
+    // 删除被替换的原内容流、图片等对象，避免涂黑的内容残留在文件中
+    doc.prune_objects();
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, options.linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 清理结果：清理后的 PDF 和删除内容的统计
+#[wasm_bindgen]
+pub struct SanitizeResult {
+    pdf: Vec<u8>,
+    report: String,
+}
+
+#[wasm_bindgen]
+impl SanitizeResult {
+    /// 清理后的 PDF 字节数组
+    #[wasm_bindgen(getter)]
+    pub fn pdf(&self) -> Vec<u8> {
+        self.pdf.clone()
+    }
+
+    /// 删除内容的统计（JSON 字符串）
+    #[wasm_bindgen(getter)]
+    pub fn report(&self) -> String {
+        self.report.clone()
+    }
+}
+
+/// 清理 PDF 中的主动内容：JavaScript、OpenAction、附加动作（AA）、Launch、SubmitForm 等危险动作、
+/// 嵌入文件、多媒体注释和 XFA 表单
+/// policy: JSON 字符串，例如 {"remove_uri": true, "remove_metadata": true}
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn sanitize_pdf(pdf_bytes: &[u8], policy: &str, linearize: Option<bool>) -> Result<SanitizeResult, JsValue> {
+    let policy: SanitizePolicy = serde_json::from_str(policy)
+        .map_err(|e| JsValue::from_str(&format!("无效的清理策略: {}", e)))?;
+    sanitize_pdf_bytes(pdf_bytes, &policy, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+fn sanitize_pdf_bytes(pdf_bytes: &[u8], policy: &SanitizePolicy, linearize: bool) -> Result<SanitizeResult, String> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    let report = sanitize_document(&mut doc, policy)?;
+    // 删除不再被引用的脚本、动作和嵌入文件对象
+    doc.prune_objects();
+    let report = serde_json::to_string(&report)
+        .map_err(|e| format!("无法生成清理报告: {}", e))?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(SanitizeResult { pdf: buffer, report })
+}
+
+/// 列出 PDF 中的附件，返回 JSON 数组
+/// 每项包含 name、file_name、description、mime、size、created、modified、page（页面附件的页码，文档级附件为 null）
+#[wasm_bindgen]
+pub fn list_attachments(pdf_bytes: &[u8]) -> Result<String, JsValue> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    serde_json::to_string(&list_document_attachments(&doc))
+        .map_err(|e| JsValue::from_str(&format!("无法生成附件 JSON: {}", e)))
+}
+
+/// 提取附件内容，name 为 list_attachments 返回的名称
+#[wasm_bindgen]
+pub fn extract_attachment(pdf_bytes: &[u8], name: &str) -> Result<Vec<u8>, JsValue> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+
+    attachment_data(&doc, name).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 嵌入文件作为文档级附件，已有同名附件时替换
+/// mime 如 "text/csv"，description 为附件说明，均可省略；大小和当前时间写入 /Params
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn add_attachment(
+    pdf_bytes: &[u8],
+    name: &str,
+    data: &[u8],
+    mime: Option<String>,
+    description: Option<String>,
+    linearize: Option<bool>,
+) -> Result<Vec<u8>, JsValue> {
+    add_attachment_bytes(pdf_bytes, name, data, mime.as_deref(), description.as_deref(), linearize.unwrap_or(false))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+fn add_attachment_bytes(
+    pdf_bytes: &[u8],
+    name: &str,
+    data: &[u8],
+    mime: Option<&str>,
+    description: Option<&str>,
+    linearize: bool,
+) -> Result<Vec<u8>, String> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    embed_attachment(&mut doc, name, data, mime, description)?;
+    // 删除被替换的同名附件和旧的名称树节点
+    doc.prune_objects();
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 检查 PDF 文件结构，返回 JSON 数组，没有问题时为空数组
+/// 每项包含 kind（问题类型）、object（相关对象，如 "12 0"）和 message；无法正常加载的文件也可以检查
+#[wasm_bindgen]
+pub fn validate_pdf(pdf_bytes: &[u8]) -> Result<String, JsValue> {
+    serde_json::to_string(&validate_document(pdf_bytes))
+        .map_err(|e| JsValue::from_str(&format!("无法生成检查结果 JSON: {}", e)))
+}
+
+/// 修复损坏的 PDF：扫描对象重建交叉引用表，修正流长度，并重建页面树
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn repair_pdf(pdf_bytes: &[u8], linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    repair_pdf_bytes(pdf_bytes, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+fn repair_pdf_bytes(pdf_bytes: &[u8], linearize: bool) -> Result<Vec<u8>, String> {
+    let mut doc = repair_document(pdf_bytes)?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 将 PDF 转换为 PDF/A，level 为 "1b"、"2b" 或 "3b"
+/// 嵌入 sRGB 输出意图和 XMP 元数据，删除加密、脚本和 PDF/A 不允许的动作与注释，1b 和 2b 还删除附件；
+/// 字体未嵌入（如 text_to_pdf 使用的标准字体）等无法自动修正的问题会报错并列出
+/// linearize: 为 true 时输出线性化（快速 Web 查看）文件，见 linearize_pdf；省略时为 false
+#[wasm_bindgen]
+pub fn convert_to_pdfa(pdf_bytes: &[u8], level: &str, linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    convert_to_pdfa_from_bytes(pdf_bytes, level, None, linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 与 convert_to_pdfa 相同，未嵌入的简单字体（如标准 14 字体）改用 font_bytes（TTF/OTF）的子集，
+/// 使用这些字体的文字按替换字体重新编码；替换字体缺少其中的字符时报错
+#[wasm_bindgen]
+pub fn convert_to_pdfa_with_font(pdf_bytes: &[u8], level: &str, font_bytes: &[u8], linearize: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    convert_to_pdfa_from_bytes(pdf_bytes, level, Some(font_bytes), linearize.unwrap_or(false)).map_err(|e| JsValue::from_str(&e))
+}
+
+/// 转换为 PDF/A 的 Rust 接口，失败时返回错误信息
+/// font_bytes 为 None 时同 convert_to_pdfa，否则同 convert_to_pdfa_with_font
+pub fn convert_to_pdfa_from_bytes(pdf_bytes: &[u8], level: &str, font_bytes: Option<&[u8]>, linearize: bool) -> Result<Vec<u8>, String> {
+    let level = PdfALevel::parse(level)?;
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    convert_document(&mut doc, level, font_bytes)?;
+
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, linearize)
+        .map_err(|e| format!("无法保存 PDF: {}", e))?;
+    Ok(buffer)
+}
+
+/// 检查 PDF/A 符合性，按 XMP 元数据中声明的级别检查（没有声明时按 PDF/A-2b），返回 JSON 数组，符合要求时为空数组
+/// 每项包含 kind（问题类型）、object（相关对象，如 "12 0"）和 message
+#[wasm_bindgen]
+pub fn check_pdfa(pdf_bytes: &[u8]) -> Result<String, JsValue> {
+    let violations = check_pdfa_document(pdf_bytes).map_err(|e| JsValue::from_str(&e))?;
+    serde_json::to_string(&violations)
+        .map_err(|e| JsValue::from_str(&format!("无法生成检查结果 JSON: {}", e)))
+}
+
+/// 将 PDF 重新保存为线性化（快速 Web 查看）文件
+/// 线性化文件把首页所需的对象放在文件开头，并带有提示表，浏览器边下载边显示
+#[wasm_bindgen]
+pub fn linearize_pdf(pdf_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let mut doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| JsValue::from_str(&format!("无法加载 PDF: {}", e)))?;
+    let mut buffer = Vec::new();
+    save_document(&mut doc, &mut buffer, true)
+        .map_err(|e| JsValue::from_str(&format!("无法保存 PDF: {}", e)))?;
+    Ok(buffer)
+}
+
+/// 判断 PDF 是否为线性化文件；线性化后又经过增量更新的文件不再视为线性化
+#[wasm_bindgen]
+pub fn is_linearized(pdf_bytes: &[u8]) -> bool {
+    is_linearized_pdf(pdf_bytes)
+}
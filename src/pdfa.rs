@@ -0,0 +1,1366 @@
+// PDF/A 转换与符合性检查（PDF/A-1b、2b、3b）
+// 转换时删除加密、脚本、不允许的动作和注释，修正注释标志、图形状态、图片和过滤器，
+// 嵌入 sRGB 输出意图和带 PDF/A 标识的 XMP 元数据；字体无法自动嵌入，转换后仍不符合要求时报错并列出问题
+
+use lopdf::content::Operation;
+use lopdf::xref::XrefType;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::annotations::process_page_annotations;
+use crate::attachments::{associate_embedded_files, current_pdf_date};
+use crate::content_stream::{encode_content, page_operations, parse_content};
+use crate::filters::{decode_stream, filter_list, ImageCodec};
+use crate::font_embed::EmbeddedFont;
+use crate::outline::{decode_text_string, text_string};
+use crate::pdf_utils::{get_inherited_attribute, object_to_f64, parse_rect};
+use crate::render_color::resolve;
+use crate::render_font::RenderFont;
+use crate::sanitize::{sanitize_document, SanitizePolicy};
+
+/// 遍历嵌套对象时允许的最大深度
+const MAX_DEPTH: usize = 64;
+
+/// 注释标志位：Invisible、Hidden、Print、NoView
+const INVISIBLE: i64 = 1;
+const HIDDEN: i64 = 2;
+const PRINT: i64 = 4;
+const NO_VIEW: i64 = 32;
+
+/// PDF/A 不允许的动作类型
+const FORBIDDEN_ACTIONS: &[&[u8]] = &[
+    b"Launch", b"Sound", b"Movie", b"ResetForm", b"ImportData", b"Hide",
+    b"SetOCGState", b"Rendition", b"Trans", b"GoTo3DView", b"JavaScript",
+];
+
+/// 允许的命名动作（/Named）
+const ALLOWED_NAMED_ACTIONS: &[&[u8]] = &[b"NextPage", b"PrevPage", b"FirstPage", b"LastPage"];
+
+/// 输出意图使用的 sRGB 配置文件名称
+const SRGB_IDENTIFIER: &str = "sRGB IEC61966-2.1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PdfALevel {
+    A1b,
+    A2b,
+    A3b,
+}
+
+impl PdfALevel {
+    /// 解析 "1b"、"2b"、"3b"，也接受 "PDF/A-2b" 的写法，不区分大小写
+    pub(crate) fn parse(level: &str) -> Result<PdfALevel, String> {
+        let normalized = level.trim().to_lowercase();
+        match normalized.strip_prefix("pdf/a-").unwrap_or(&normalized) {
+            "1b" => Ok(PdfALevel::A1b),
+            "2b" => Ok(PdfALevel::A2b),
+            "3b" => Ok(PdfALevel::A3b),
+            _ => Err(format!("不支持的 PDF/A 级别: {}（支持 1b、2b、3b）", level)),
+        }
+    }
+
+    fn part(self) -> u8 {
+        match self {
+            PdfALevel::A1b => 1,
+            PdfALevel::A2b => 2,
+            PdfALevel::A3b => 3,
+        }
+    }
+
+    fn name(self) -> String {
+        format!("PDF/A-{}b", self.part())
+    }
+}
+
+/// 不符合 PDF/A 要求的问题
+#[derive(Debug, Serialize)]
+pub(crate) struct Violation {
+    /// 问题类型："header"、"encrypted"、"missing_file_id"、"xref_stream"、"missing_metadata"、
+    /// "wrong_identification"、"metadata_filter"、"missing_output_intent"、"device_color"、
+    /// "font_not_embedded"、"forbidden_action"、"additional_actions"、"forbidden_annotation"、
+    /// "annotation_flags"、"missing_appearance"、"appearance"、"need_appearances"、"xfa"、
+    /// "transparency"、"transfer_function"、"image"、"lzw_filter"、"external_stream"、
+    /// "postscript"、"embedded_file"、"optional_content"
+    kind: &'static str,
+    /// 相关对象，如 "12 0"
+    object: Option<String>,
+    /// 问题说明
+    message: String,
+}
+
+impl Violation {
+    fn new(kind: &'static str, object: Option<ObjectId>, message: impl Into<String>) -> Violation {
+        Violation { kind, object: object.map(|(number, generation)| format!("{} {}", number, generation)), message: message.into() }
+    }
+}
+
+fn name<'a>(dict: &'a Dictionary, key: &[u8]) -> Option<&'a [u8]> {
+    dict.get(key).and_then(Object::as_name).ok()
+}
+
+fn is_annotation(dict: &Dictionary, is_stream: bool) -> bool {
+    !is_stream && (dict.type_is(b"Annot") || dict.has(b"Rect") && dict.has(b"Subtype"))
+}
+
+/// PDF/A 不允许的动作（供清理主动内容时使用）
+fn is_forbidden_action(dict: &Dictionary) -> bool {
+    match name(dict, b"S") {
+        Some(b"Named") => !name(dict, b"N").is_some_and(|n| ALLOWED_NAMED_ACTIONS.contains(&n)),
+        Some(action) => FORBIDDEN_ACTIONS.contains(&action) && !dict.type_is(b"StructElem"),
+        None => false,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 符合性检查
+// ---------------------------------------------------------------------------
+
+/// 检查 PDF 文件，按 XMP 元数据中声明的级别检查，没有声明时按 PDF/A-2b 检查
+pub(crate) fn check_pdfa_document(buffer: &[u8]) -> Result<Vec<Violation>, String> {
+    let doc = Document::load_mem(buffer).map_err(|e| format!("无法加载 PDF: {}", e))?;
+
+    let mut violations = Vec::new();
+    if !binary_header(buffer) {
+        violations.push(Violation::new("header", None, "文件头后缺少包含至少 4 个二进制字节的注释行"));
+    }
+    let level = declared_level(&doc).unwrap_or(PdfALevel::A2b);
+    violations.extend(check_document(&doc, level));
+    Ok(violations)
+}
+
+/// 文件以 "%PDF-" 开头，且第二行是包含至少 4 个大于 127 的字节的注释
+fn binary_header(buffer: &[u8]) -> bool {
+    if !buffer.starts_with(b"%PDF-") {
+        return false;
+    }
+    let is_eol = |b: &u8| *b == b'\r' || *b == b'\n';
+    let Some(end) = buffer.iter().position(is_eol) else { return false };
+    let rest = &buffer[end..];
+    let rest = rest.strip_prefix(b"\r").unwrap_or(rest);
+    let rest = rest.strip_prefix(b"\n").unwrap_or(rest);
+    let line = &rest[..rest.iter().position(is_eol).unwrap_or(rest.len())];
+    line.first() == Some(&b'%') && line.iter().filter(|&&b| b > 127).count() >= 4
+}
+
+/// 文档目录中的 XMP 元数据文本
+fn metadata_xml(doc: &Document) -> Option<String> {
+    let catalog = doc.catalog().ok()?;
+    let stream = resolve(doc, catalog.get(b"Metadata").ok()?).as_stream().ok()?;
+    let data = decode_stream(doc, stream).ok()?.data;
+    Some(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// XMP 属性的值，支持元素（<pdfaid:part>2</pdfaid:part>）和属性（pdfaid:part="2"）两种写法
+fn xmp_value<'a>(xml: &'a str, property: &str) -> Option<&'a str> {
+    let rest = xml[xml.find(property)? + property.len()..].trim_start();
+    let value = if let Some(rest) = rest.strip_prefix('>') {
+        &rest[..rest.find('<')?]
+    } else {
+        let rest = rest.strip_prefix('=')?.trim_start();
+        let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let rest = &rest[1..];
+        &rest[..rest.find(quote)?]
+    };
+    Some(value.trim())
+}
+
+/// XMP 中声明的 PDF/A 标识（部分和符合级别）
+fn identification(xml: &str) -> Option<(String, String)> {
+    Some((xmp_value(xml, "pdfaid:part")?.to_string(), xmp_value(xml, "pdfaid:conformance")?.to_uppercase()))
+}
+
+fn declared_level(doc: &Document) -> Option<PdfALevel> {
+    let (part, conformance) = identification(&metadata_xml(doc)?)?;
+    if !matches!(conformance.as_str(), "A" | "B" | "U") {
+        return None;
+    }
+    PdfALevel::parse(&format!("{}b", part)).ok()
+}
+
+/// PDF/A 输出意图的 ICC 配置文件通道数
+fn output_intent_components(doc: &Document) -> Option<i64> {
+    let catalog = doc.catalog().ok()?;
+    let intents = resolve(doc, catalog.get(b"OutputIntents").ok()?).as_array().ok()?;
+    intents.iter()
+        .filter_map(|intent| resolve(doc, intent).as_dict().ok())
+        .filter(|intent| name(intent, b"S") == Some(b"GTS_PDFA1"))
+        .find_map(|intent| {
+            let profile = resolve(doc, intent.get(b"DestOutputProfile").ok()?).as_stream().ok()?;
+            profile.dict.get(b"N").and_then(Object::as_i64).ok()
+        })
+}
+
+/// 与输出意图不一致、不能直接使用的设备颜色空间
+fn forbidden_device_spaces(components: i64) -> &'static [&'static [u8]] {
+    match components {
+        1 => &[b"DeviceRGB", b"DeviceCMYK"],
+        3 => &[b"DeviceCMYK"],
+        4 => &[b"DeviceRGB"],
+        _ => &[],
+    }
+}
+
+/// 颜色空间的完整名称（内联图片使用缩写）
+fn device_name(name: &[u8]) -> &[u8] {
+    match name {
+        b"RGB" => b"DeviceRGB",
+        b"CMYK" => b"DeviceCMYK",
+        b"G" => b"DeviceGray",
+        _ => name,
+    }
+}
+
+/// 颜色空间（或索引颜色空间的基础颜色空间）使用的设备颜色空间名称
+fn device_space<'a>(doc: &'a Document, space: &'a Object) -> Option<&'a [u8]> {
+    match resolve(doc, space) {
+        Object::Name(name) => Some(device_name(name)),
+        Object::Array(items) if items.first().and_then(|i| i.as_name().ok()) == Some(b"Indexed") => {
+            resolve(doc, items.get(1)?).as_name().ok().map(device_name)
+        }
+        _ => None,
+    }
+}
+
+/// 内容流中第一个使用到的不允许的设备颜色空间
+fn content_device_space(operations: &[Operation], forbidden: &[&[u8]]) -> Option<String> {
+    operations.iter()
+        .filter_map(|op| match op.operator.as_str() {
+            "rg" | "RG" => Some(b"DeviceRGB".as_slice()),
+            "k" | "K" => Some(b"DeviceCMYK".as_slice()),
+            "cs" | "CS" => op.operands.first().and_then(|o| o.as_name().ok()).map(device_name),
+            "BI" => op.operands.first()
+                .and_then(|d| d.as_dict().ok())
+                .and_then(|d| d.get(b"CS").or_else(|_| d.get(b"ColorSpace")).ok())
+                .and_then(|cs| cs.as_name().ok())
+                .map(device_name),
+            _ => None,
+        })
+        .find(|space| forbidden.contains(space))
+        .map(|space| String::from_utf8_lossy(space).into_owned())
+}
+
+/// 检查已加载的文档（不含文件头）
+fn check_document(doc: &Document, level: PdfALevel) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    // 文件尾和交叉引用
+    if doc.trailer.has(b"Encrypt") {
+        violations.push(Violation::new("encrypted", None, "文档不能加密"));
+    }
+    let has_id = doc.trailer.get(b"ID").ok()
+        .and_then(|id| resolve(doc, id).as_array().ok())
+        .is_some_and(|id| id.len() == 2 && id.iter().all(|part| part.as_str().is_ok()));
+    if !has_id {
+        violations.push(Violation::new("missing_file_id", None, "文件尾缺少文件标识（/ID）"));
+    }
+    let object_streams = doc.objects.values().any(|o| o.as_stream().is_ok_and(|s| s.dict.type_is(b"ObjStm")));
+    if level == PdfALevel::A1b && (matches!(doc.reference_table.cross_reference_type, XrefType::CrossReferenceStream) || object_streams) {
+        violations.push(Violation::new("xref_stream", None, "PDF/A-1 不能使用交叉引用流和对象流"));
+    }
+
+    check_catalog(doc, level, &mut violations);
+
+    let forbidden = output_intent_components(doc).map_or(&[][..], forbidden_device_spaces);
+    for (&id, object) in &doc.objects {
+        check_object(doc, level, id, object, forbidden, &mut violations, 0);
+    }
+    if !forbidden.is_empty() {
+        check_content_colors(doc, forbidden, &mut violations);
+    }
+    violations
+}
+
+fn check_catalog(doc: &Document, level: PdfALevel, violations: &mut Vec<Violation>) {
+    let Ok(catalog) = doc.catalog() else { return };
+
+    match metadata_xml(doc) {
+        None => violations.push(Violation::new("missing_metadata", None, "文档目录缺少 XMP 元数据流（/Metadata）")),
+        Some(xml) => match identification(&xml) {
+            None => violations.push(Violation::new("wrong_identification", None, "XMP 元数据缺少 PDF/A 标识（pdfaid:part 和 pdfaid:conformance）")),
+            Some((part, conformance)) if part != level.part().to_string() || !matches!(conformance.as_str(), "A" | "B" | "U") => {
+                violations.push(Violation::new(
+                    "wrong_identification",
+                    None,
+                    format!("XMP 元数据中的 PDF/A 标识（{}{}）与 {} 不符", part, conformance, level.name()),
+                ));
+            }
+            Some(_) => {}
+        },
+    }
+    let metadata_id = catalog.get(b"Metadata").and_then(Object::as_reference).ok();
+    let metadata_filtered = catalog.get(b"Metadata").ok()
+        .and_then(|m| resolve(doc, m).as_stream().ok())
+        .is_some_and(|m| m.dict.has(b"Filter"));
+    if level == PdfALevel::A1b && metadata_filtered {
+        violations.push(Violation::new("metadata_filter", metadata_id, "PDF/A-1 的元数据流不能压缩"));
+    }
+
+    if output_intent_components(doc).is_none() {
+        violations.push(Violation::new("missing_output_intent", None, "缺少带 ICC 配置文件的 PDF/A 输出意图（GTS_PDFA1）"));
+    }
+
+    let names = catalog.get(b"Names").ok().and_then(|n| resolve(doc, n).as_dict().ok());
+    if names.is_some_and(|n| n.has(b"JavaScript")) {
+        violations.push(Violation::new("forbidden_action", None, "不允许文档级 JavaScript"));
+    }
+
+    let form = catalog.get(b"AcroForm").ok().and_then(|f| resolve(doc, f).as_dict().ok());
+    if let Some(form) = form {
+        if form.get(b"NeedAppearances").and_then(Object::as_bool).unwrap_or(false) {
+            violations.push(Violation::new("need_appearances", None, "表单不能要求阅读器重新生成外观（NeedAppearances）"));
+        }
+        if form.has(b"XFA") {
+            violations.push(Violation::new("xfa", None, "不允许 XFA 表单"));
+        }
+    }
+
+    let Some(properties) = catalog.get(b"OCProperties").ok().and_then(|p| resolve(doc, p).as_dict().ok()) else { return };
+    if level == PdfALevel::A1b {
+        violations.push(Violation::new("optional_content", None, "PDF/A-1 不允许可选内容"));
+        return;
+    }
+    let mut configs: Vec<&Object> = properties.get(b"D").into_iter().collect();
+    if let Some(others) = properties.get(b"Configs").ok().and_then(|c| resolve(doc, c).as_array().ok()) {
+        configs.extend(others);
+    }
+    for config in configs {
+        let Ok(dict) = resolve(doc, config).as_dict() else { continue };
+        if !dict.has(b"Name") || dict.has(b"AS") {
+            violations.push(Violation::new("optional_content", config.as_reference().ok(), "可选内容配置必须有名称（/Name），且不能包含 /AS"));
+        }
+    }
+}
+
+fn check_object(
+    doc: &Document,
+    level: PdfALevel,
+    id: ObjectId,
+    object: &Object,
+    forbidden: &[&[u8]],
+    violations: &mut Vec<Violation>,
+    depth: usize,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let (dict, is_stream) = match object {
+        Object::Dictionary(dict) => (dict, false),
+        Object::Stream(stream) => (&stream.dict, true),
+        Object::Array(items) => {
+            for item in items {
+                check_object(doc, level, id, item, forbidden, violations, depth + 1);
+            }
+            return;
+        }
+        _ => return,
+    };
+    check_dictionary(doc, level, id, dict, is_stream, forbidden, violations);
+    for (_, value) in dict.iter() {
+        check_object(doc, level, id, value, forbidden, violations, depth + 1);
+    }
+}
+
+fn check_dictionary(
+    doc: &Document,
+    level: PdfALevel,
+    id: ObjectId,
+    dict: &Dictionary,
+    is_stream: bool,
+    forbidden: &[&[u8]],
+    violations: &mut Vec<Violation>,
+) {
+    let mut report = |kind, message: String| violations.push(Violation::new(kind, Some(id), message));
+    let subtype = name(dict, b"Subtype").unwrap_or(b"");
+
+    if dict.has(b"AA") {
+        report("additional_actions", "不允许附加动作（/AA）".to_string());
+    }
+    if is_forbidden_action(dict) {
+        let action = name(dict, b"N").filter(|_| name(dict, b"S") == Some(b"Named")).or(name(dict, b"S")).unwrap_or(b"");
+        report("forbidden_action", format!("不允许的动作: {}", String::from_utf8_lossy(action)));
+    }
+
+    if is_annotation(dict, is_stream) {
+        check_annotation(doc, level, dict, &mut report);
+    } else if dict.type_is(b"Font") && !matches!(subtype, b"Type0" | b"Type3") {
+        if !is_font_embedded(doc, dict) {
+            let base_font = name(dict, b"BaseFont").map(String::from_utf8_lossy).unwrap_or_default();
+            report("font_not_embedded", format!("字体 {} 未嵌入", base_font));
+        }
+    } else if !is_stream {
+        // 图形状态参数字典
+        if dict.has(b"TR") || dict.has(b"HTP") || dict.has(b"TR2") && name(dict, b"TR2") != Some(b"Default") {
+            report("transfer_function", "图形状态不能包含传递函数或半色调相位（TR、TR2、HTP）".to_string());
+        }
+        if level == PdfALevel::A1b {
+            let soft_mask = dict.has(b"SMask") && name(dict, b"SMask") != Some(b"None");
+            let alpha = [b"CA".as_slice(), b"ca"].iter().any(|key| dict.get(key).ok().and_then(object_to_f64).is_some_and(|a| a != 1.0));
+            let blend = name(dict, b"BM").is_some_and(|bm| bm != b"Normal" && bm != b"Compatible");
+            if soft_mask || alpha || blend {
+                report("transparency", "PDF/A-1 不允许透明度（软蒙版、不透明度或混合模式）".to_string());
+            }
+        }
+    }
+
+    if level == PdfALevel::A1b && is_transparency_group(doc, dict.get(b"Group").ok()) {
+        report("transparency", "PDF/A-1 不允许透明度组".to_string());
+    }
+
+    if dict.has(b"EF") {
+        let file_name = dict.get(b"UF").or_else(|_| dict.get(b"F")).ok()
+            .and_then(|f| resolve(doc, f).as_str().ok())
+            .map(decode_text_string)
+            .unwrap_or_default();
+        if level != PdfALevel::A3b {
+            report("embedded_file", format!("{} 不允许嵌入文件: {}", level.name(), file_name));
+        } else if !dict.has(b"AFRelationship") {
+            report("embedded_file", format!("嵌入文件 {} 缺少 /AFRelationship", file_name));
+        }
+    }
+
+    if let Ok(space) = dict.get(b"ColorSpace") {
+        // 图片和着色的颜色空间，或资源字典中的颜色空间表
+        let spaces: Vec<&Object> = match resolve(doc, space) {
+            Object::Dictionary(resources) => resources.iter().map(|(_, s)| s).collect(),
+            _ => vec![space],
+        };
+        if let Some(space) = spaces.into_iter().filter_map(|s| device_space(doc, s)).find(|s| forbidden.contains(s)) {
+            report("device_color", format!("使用了与输出意图不一致的设备颜色空间 {}", String::from_utf8_lossy(space)));
+        }
+    }
+
+    if !is_stream {
+        return;
+    }
+    if dict.has(b"F") || dict.has(b"FFilter") || dict.has(b"FDecodeParms") {
+        report("external_stream", "流不能引用外部文件（F、FFilter、FDecodeParms）".to_string());
+    }
+    let filters = filter_list(doc, dict, b"Filter", b"Filter");
+    if filters.iter().any(|f| f == b"LZWDecode") {
+        report("lzw_filter", "不允许使用 LZWDecode 过滤器".to_string());
+    }
+    if subtype == b"Image" {
+        if level == PdfALevel::A1b && filters.iter().any(|f| f == b"JPXDecode") {
+            report("image", "PDF/A-1 不允许 JPEG 2000 图片".to_string());
+        }
+        if dict.get(b"Interpolate").and_then(Object::as_bool).unwrap_or(false) {
+            report("image", "图片不能设置 /Interpolate".to_string());
+        }
+        if dict.has(b"Alternates") || dict.has(b"OPI") {
+            report("image", "图片不能包含替代图片（/Alternates）或 OPI 信息".to_string());
+        }
+        if level == PdfALevel::A1b && (dict.has(b"SMask") || dict.has(b"SMaskInData")) {
+            report("transparency", "PDF/A-1 不允许图片使用软蒙版".to_string());
+        }
+    }
+    if subtype == b"PS" || subtype == b"Form" && (dict.has(b"PS") || name(dict, b"Subtype2") == Some(b"PS")) {
+        report("postscript", "不允许 PostScript XObject".to_string());
+    }
+}
+
+fn check_annotation(doc: &Document, level: PdfALevel, annot: &Dictionary, report: &mut dyn FnMut(&'static str, String)) {
+    let subtype = name(annot, b"Subtype").unwrap_or(b"");
+    let forbidden = matches!(subtype, b"Sound" | b"Movie" | b"Screen" | b"3D" | b"RichMedia")
+        || subtype == b"FileAttachment" && level != PdfALevel::A3b;
+    if forbidden {
+        report("forbidden_annotation", format!("不允许的注释类型: {}", String::from_utf8_lossy(subtype)));
+    }
+
+    let flags = annot.get(b"F").and_then(Object::as_i64).ok();
+    let popup_exempt = subtype == b"Popup" && level != PdfALevel::A1b && flags.is_none();
+    let flags = flags.unwrap_or(0);
+    if !popup_exempt && (flags & PRINT == 0 || flags & (INVISIBLE | HIDDEN | NO_VIEW) != 0) {
+        report("annotation_flags", "注释必须设置打印标志，且不能隐藏".to_string());
+    }
+
+    match annot.get(b"AP").ok().and_then(|ap| resolve(doc, ap).as_dict().ok()) {
+        Some(appearance) if appearance.iter().any(|(key, _)| key != b"N") => {
+            report("appearance", "注释外观只能包含正常外观（/N）".to_string());
+        }
+        None if level != PdfALevel::A1b && needs_appearance(annot) => {
+            report("missing_appearance", format!("{} 注释缺少外观流", String::from_utf8_lossy(subtype)));
+        }
+        _ => {}
+    }
+
+    if level == PdfALevel::A1b && annot.get(b"CA").ok().and_then(object_to_f64).is_some_and(|a| a != 1.0) {
+        report("transparency", "PDF/A-1 不允许注释使用不透明度（/CA）".to_string());
+    }
+}
+
+/// 字体描述符中是否有字体程序
+fn is_font_embedded(doc: &Document, font: &Dictionary) -> bool {
+    font.get(b"FontDescriptor").ok()
+        .and_then(|d| resolve(doc, d).as_dict().ok())
+        .is_some_and(|d| d.has(b"FontFile") || d.has(b"FontFile2") || d.has(b"FontFile3"))
+}
+
+/// PDF/A-2 起除弹出窗口、链接和零尺寸注释外都必须有外观流
+fn needs_appearance(annot: &Dictionary) -> bool {
+    let subtype = name(annot, b"Subtype").unwrap_or(b"");
+    let has_area = annot.get(b"Rect").and_then(Object::as_array).ok()
+        .and_then(|r| parse_rect(r))
+        .is_some_and(|[left, bottom, right, top]| right > left || top > bottom);
+    !matches!(subtype, b"Popup" | b"Link") && has_area
+}
+
+/// 透明度组：/S 为 /Transparency 的组属性字典
+fn is_transparency_group(doc: &Document, group: Option<&Object>) -> bool {
+    group.and_then(|g| resolve(doc, g).as_dict().ok()).is_some_and(|g| name(g, b"S") == Some(b"Transparency"))
+}
+
+/// 检查页面和表单 XObject 内容流中使用的设备颜色
+fn check_content_colors(doc: &Document, forbidden: &[&[u8]], violations: &mut Vec<Violation>) {
+    for (number, page_id) in doc.get_pages() {
+        if let Some(space) = content_device_space(&page_operations(doc, page_id), forbidden) {
+            violations.push(Violation::new("device_color", Some(page_id), format!("第 {} 页使用了与输出意图不一致的设备颜色空间 {}", number, space)));
+        }
+    }
+    for (&id, object) in &doc.objects {
+        let Ok(stream) = object.as_stream() else { continue };
+        if name(&stream.dict, b"Subtype") != Some(b"Form") {
+            continue;
+        }
+        let Ok(decoded) = decode_stream(doc, stream) else { continue };
+        if let Some(space) = content_device_space(&parse_content(&decoded.data), forbidden) {
+            violations.push(Violation::new("device_color", Some(id), format!("表单 XObject 使用了与输出意图不一致的设备颜色空间 {}", space)));
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 转换
+// ---------------------------------------------------------------------------
+
+/// 将文档转换为指定级别的 PDF/A，转换后仍不符合要求时报错并列出问题
+/// 提供 font_data 时用它替换未嵌入的简单字体（如标准 14 字体），否则这些字体会使转换失败
+pub(crate) fn convert_document(doc: &mut Document, level: PdfALevel, font_data: Option<&[u8]>) -> Result<(), String> {
+    remove_encryption(doc)?;
+
+    // 脚本、不允许的动作、多媒体注释和 XFA；PDF/A-3 保留嵌入文件
+    let keep_embedded_files = level == PdfALevel::A3b;
+    sanitize_document(doc, &SanitizePolicy::for_pdfa(is_forbidden_action, keep_embedded_files))?;
+    if keep_embedded_files {
+        associate_embedded_files(doc)?;
+    }
+    if let Some(font_data) = font_data {
+        replace_missing_fonts(doc, font_data)?;
+    }
+
+    // PDF/A-2 起没有外观流的注释无法显示，直接删除（表单控件保留，由检查报告）
+    if level != PdfALevel::A1b {
+        let select = |_: &Document, _: Option<ObjectId>, annot: &Dictionary| {
+            name(annot, b"Subtype") != Some(b"Widget") && !annot.has(b"AP") && needs_appearance(annot)
+        };
+        let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        for page_id in page_ids {
+            process_page_annotations(doc, page_id, &select, false)?;
+        }
+    }
+
+    fix_objects(doc, level);
+    fix_optional_content(doc, level)?;
+    recompress_lzw_streams(doc);
+    let components = ensure_output_intent(doc)?;
+    convert_device_colors(doc, forbidden_device_spaces(components), components)?;
+    write_metadata(doc, level)?;
+    ensure_file_id(doc);
+
+    // 文件头后的二进制注释行由 save_document 写出
+    doc.version = if level == PdfALevel::A1b { "1.4" } else { "1.7" }.to_string();
+    if level == PdfALevel::A1b {
+        // PDF-1.4 没有交叉引用流和对象流
+        doc.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+        for key in [b"Type".as_slice(), b"W", b"Index", b"Length", b"Filter", b"DecodeParms", b"XRefStm", b"Prev"] {
+            doc.trailer.remove(key);
+        }
+    }
+    doc.prune_objects();
+
+    let violations = check_document(doc, level);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let messages: Vec<String> = violations.iter()
+        .map(|v| match &v.object {
+            Some(object) => format!("{}（对象 {}）", v.message, object),
+            None => v.message.clone(),
+        })
+        .collect();
+    Err(format!("无法转换为 {}: {}", level.name(), messages.join("；")))
+}
+
+/// 删除加密：可以用空密码解密的文档先解密
+fn remove_encryption(doc: &mut Document) -> Result<(), String> {
+    if !doc.trailer.has(b"Encrypt") {
+        return Ok(());
+    }
+    if doc.is_encrypted() {
+        doc.decrypt("").map_err(|e| format!("无法解密 PDF（需要密码）: {}", e))?;
+    }
+    if let Some(Object::Reference(id)) = doc.trailer.remove(b"Encrypt") {
+        doc.objects.remove(&id);
+    }
+    Ok(())
+}
+
+/// 修正注释标志和外观、图形状态、图片、外部流和透明度组
+fn fix_objects(doc: &mut Document, level: PdfALevel) {
+    // 间接引用的注释外观字典和透明度组
+    let mut appearances = HashSet::new();
+    let mut groups = HashSet::new();
+    for object in doc.objects.values() {
+        let Some(dict) = object.as_dict().ok().or_else(|| object.as_stream().ok().map(|s| &s.dict)) else { continue };
+        if let Ok(&Object::Reference(id)) = dict.get(b"AP") {
+            appearances.insert(id);
+        }
+        if let Ok(&Object::Reference(id)) = dict.get(b"Group") {
+            if is_transparency_group(doc, Some(&Object::Reference(id))) {
+                groups.insert(id);
+            }
+        }
+    }
+
+    for (id, object) in doc.objects.iter_mut() {
+        if appearances.contains(id) {
+            if let Object::Dictionary(appearance) = object {
+                keep_normal_appearance(appearance);
+            }
+        }
+        fix_object(object, level, &groups, 0);
+    }
+}
+
+fn keep_normal_appearance(appearance: &mut Dictionary) {
+    let keys: Vec<Vec<u8>> = appearance.iter().map(|(key, _)| key.clone()).filter(|key| key != b"N").collect();
+    for key in keys {
+        appearance.remove(&key);
+    }
+}
+
+fn fix_object(object: &mut Object, level: PdfALevel, groups: &HashSet<ObjectId>, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let (dict, is_stream) = match object {
+        Object::Dictionary(dict) => (dict, false),
+        Object::Stream(stream) => (&mut stream.dict, true),
+        Object::Array(items) => {
+            for item in items {
+                fix_object(item, level, groups, depth + 1);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    if is_annotation(dict, is_stream) {
+        let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+        dict.set("F", Object::Integer((flags | PRINT) & !(INVISIBLE | HIDDEN | NO_VIEW)));
+        if let Ok(Object::Dictionary(appearance)) = dict.get_mut(b"AP") {
+            keep_normal_appearance(appearance);
+        }
+        if level == PdfALevel::A1b {
+            dict.remove(b"CA");
+        }
+    } else if is_stream {
+        for key in [b"F".as_slice(), b"FFilter", b"FDecodeParms"] {
+            dict.remove(key);
+        }
+        if name(dict, b"Subtype") == Some(b"Image") {
+            for key in [b"Interpolate".as_slice(), b"Alternates", b"OPI"] {
+                dict.remove(key);
+            }
+            if level == PdfALevel::A1b {
+                dict.remove(b"SMask");
+                dict.remove(b"SMaskInData");
+            }
+        }
+    } else {
+        // 图形状态参数字典
+        dict.remove(b"TR");
+        dict.remove(b"HTP");
+        if dict.has(b"TR2") && name(dict, b"TR2") != Some(b"Default") {
+            dict.remove(b"TR2");
+        }
+        if level == PdfALevel::A1b {
+            if dict.has(b"SMask") {
+                dict.set("SMask", Object::Name(b"None".to_vec()));
+            }
+            for key in ["CA", "ca"] {
+                if dict.has(key.as_bytes()) {
+                    dict.set(key, Object::Real(1.0));
+                }
+            }
+            if dict.has(b"BM") {
+                dict.set("BM", Object::Name(b"Normal".to_vec()));
+            }
+        }
+        // 表单要求阅读器重新生成外观
+        if dict.has(b"Fields") {
+            dict.remove(b"NeedAppearances");
+        }
+        // PDF/A-3 的嵌入文件必须说明与文档的关系
+        if level == PdfALevel::A3b && dict.has(b"EF") && !dict.has(b"AFRelationship") {
+            dict.set("AFRelationship", Object::Name(b"Unspecified".to_vec()));
+        }
+    }
+
+    if level == PdfALevel::A1b {
+        let transparent = match dict.get(b"Group") {
+            Ok(Object::Reference(id)) => groups.contains(id),
+            Ok(Object::Dictionary(group)) => name(group, b"S") == Some(b"Transparency"),
+            _ => false,
+        };
+        if transparent {
+            dict.remove(b"Group");
+        }
+    }
+
+    for (_, value) in dict.iter_mut() {
+        fix_object(value, level, groups, depth + 1);
+    }
+}
+
+/// PDF/A-1 删除可选内容；PDF/A-2 起为每个配置补上名称并删除 /AS
+fn fix_optional_content(doc: &mut Document, level: PdfALevel) -> Result<(), String> {
+    let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    if level == PdfALevel::A1b {
+        catalog.remove(b"OCProperties");
+        return Ok(());
+    }
+    let Some(properties) = catalog.get(b"OCProperties").ok().cloned() else { return Ok(()) };
+    let properties_id = properties.as_reference().ok();
+    let Some(mut properties) = resolve(doc, &properties).as_dict().ok().cloned() else { return Ok(()) };
+
+    if let Ok(config) = properties.get_mut(b"D") {
+        fix_config(doc, config, "Default");
+    }
+    if let Ok(Object::Array(configs)) = properties.get_mut(b"Configs") {
+        for (index, config) in configs.iter_mut().enumerate() {
+            fix_config(doc, config, &format!("Config {}", index + 1));
+        }
+    }
+
+    match properties_id {
+        Some(id) => {
+            doc.objects.insert(id, Object::Dictionary(properties));
+        }
+        None => {
+            let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+            catalog.set("OCProperties", Object::Dictionary(properties));
+        }
+    }
+    Ok(())
+}
+
+fn fix_config(doc: &mut Document, config: &mut Object, default_name: &str) {
+    let dict = match config {
+        Object::Reference(id) => match doc.get_dictionary_mut(*id) {
+            Ok(dict) => dict,
+            Err(_) => return,
+        },
+        Object::Dictionary(dict) => dict,
+        _ => return,
+    };
+    dict.remove(b"AS");
+    if !dict.has(b"Name") {
+        dict.set("Name", text_string(default_name));
+    }
+}
+
+/// 使用 LZWDecode 的流改为 FlateDecode 压缩
+fn recompress_lzw_streams(doc: &mut Document) {
+    let ids: Vec<ObjectId> = doc.objects.iter()
+        .filter(|(_, object)| object.as_stream().is_ok_and(|s| {
+            filter_list(doc, &s.dict, b"Filter", b"Filter").iter().any(|f| f == b"LZWDecode")
+        }))
+        .map(|(&id, _)| id)
+        .collect();
+
+    for id in ids {
+        let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else { continue };
+        let Ok(decoded) = decode_stream(doc, stream) else { continue };
+        let mut dict = stream.dict.clone();
+        dict.remove(b"Filter");
+        dict.remove(b"DecodeParms");
+        let stream = match decoded.codec {
+            None => {
+                let mut stream = Stream::new(dict, decoded.data);
+                // 压缩失败时保留未压缩的内容
+                let _ = stream.compress();
+                stream
+            }
+            // JPEG 数据前的 LZW 解码后原样保留
+            Some(ImageCodec::Dct) => {
+                dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+                Stream::new(dict, decoded.data).with_compression(false)
+            }
+            Some(_) => continue,
+        };
+        doc.objects.insert(id, Object::Stream(stream));
+    }
+}
+
+/// 确保文档有 PDF/A 输出意图，没有时嵌入 sRGB 配置文件；返回配置文件的通道数
+fn ensure_output_intent(doc: &mut Document) -> Result<i64, String> {
+    if let Some(components) = output_intent_components(doc) {
+        return Ok(components);
+    }
+
+    let mut profile_dict = Dictionary::new();
+    profile_dict.set("N", Object::Integer(3));
+    let mut profile = Stream::new(profile_dict, srgb_icc_profile());
+    // 压缩失败时保留未压缩的内容
+    let _ = profile.compress();
+    let profile_id = doc.add_object(profile);
+
+    let mut intent = Dictionary::new();
+    intent.set("Type", Object::Name(b"OutputIntent".to_vec()));
+    intent.set("S", Object::Name(b"GTS_PDFA1".to_vec()));
+    intent.set("OutputConditionIdentifier", Object::string_literal(SRGB_IDENTIFIER));
+    intent.set("RegistryName", Object::string_literal("http://www.color.org"));
+    intent.set("Info", Object::string_literal(SRGB_IDENTIFIER));
+    intent.set("DestOutputProfile", Object::Reference(profile_id));
+
+    // 多个输出意图必须使用同一配置文件，其他用途的输出意图一并替换
+    let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    catalog.set("OutputIntents", Object::Array(vec![Object::Dictionary(intent)]));
+    Ok(3)
+}
+
+/// 将内容流中与输出意图不一致的设备颜色换算为输出意图的颜色空间
+/// 图片和着色的颜色无法换算，由检查报告
+fn convert_device_colors(doc: &mut Document, forbidden: &[&[u8]], components: i64) -> Result<(), String> {
+    if forbidden.is_empty() {
+        return Ok(());
+    }
+
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in page_ids {
+        let operations = page_operations(doc, page_id);
+        let Some(operations) = convert_operations(&operations, forbidden, components) else { continue };
+        let mut stream = Stream::new(Dictionary::new(), encode_content(&operations));
+        // 压缩失败时保留未压缩的内容
+        let _ = stream.compress();
+        let content_id = doc.add_object(stream);
+        let page = doc.get_dictionary_mut(page_id)
+            .map_err(|e| format!("无法读取页面: {}", e))?;
+        page.set("Contents", Object::Reference(content_id));
+    }
+
+    let form_ids: Vec<ObjectId> = doc.objects.iter()
+        .filter(|(_, object)| object.as_stream().is_ok_and(|s| name(&s.dict, b"Subtype") == Some(b"Form")))
+        .map(|(&id, _)| id)
+        .collect();
+    for id in form_ids {
+        let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else { continue };
+        let Ok(decoded) = decode_stream(doc, stream) else { continue };
+        let Some(operations) = convert_operations(&parse_content(&decoded.data), forbidden, components) else { continue };
+        let mut dict = stream.dict.clone();
+        dict.remove(b"Filter");
+        dict.remove(b"DecodeParms");
+        let mut stream = Stream::new(dict, encode_content(&operations));
+        let _ = stream.compress();
+        doc.objects.insert(id, Object::Stream(stream));
+    }
+    Ok(())
+}
+
+/// 换算颜色操作，没有需要换算的操作时返回 None
+fn convert_operations(operations: &[Operation], forbidden: &[&[u8]], components: i64) -> Option<Vec<Operation>> {
+    let (target, fill, stroke): (&[u8], &str, &str) = match components {
+        1 => (b"DeviceGray", "g", "G"),
+        3 => (b"DeviceRGB", "rg", "RG"),
+        _ => (b"DeviceCMYK", "k", "K"),
+    };
+    let device = |operator: &str| if operator.eq_ignore_ascii_case("rg") { b"DeviceRGB".as_slice() } else { b"DeviceCMYK" };
+    let values = |op: &Operation| -> Vec<f64> { op.operands.iter().filter_map(object_to_f64).collect() };
+    let converted = |values: Vec<f64>| -> Vec<Object> {
+        convert_color(&values, components).into_iter().map(|v| Object::Real(v as f32)).collect()
+    };
+
+    let mut changed = false;
+    // 当前填充和描边颜色空间是否已被替换，q/Q 时保存和恢复
+    let mut replaced = (false, false);
+    let mut saved = Vec::new();
+    let mut output = Vec::with_capacity(operations.len());
+    for op in operations {
+        let mut op = op.clone();
+        match op.operator.as_str() {
+            "q" => saved.push(replaced),
+            "Q" => replaced = saved.pop().unwrap_or_default(),
+            "rg" | "k" if forbidden.contains(&device(&op.operator)) => {
+                op = Operation::new(fill, converted(values(&op)));
+                changed = true;
+            }
+            "RG" | "K" if forbidden.contains(&device(&op.operator)) => {
+                op = Operation::new(stroke, converted(values(&op)));
+                changed = true;
+            }
+            "cs" | "CS" => {
+                let space = op.operands.first().and_then(|o| o.as_name().ok()).map(device_name);
+                let replace = space.is_some_and(|s| forbidden.contains(&s));
+                if op.operator == "cs" {
+                    replaced.0 = replace;
+                } else {
+                    replaced.1 = replace;
+                }
+                if replace {
+                    op.operands = vec![Object::Name(target.to_vec())];
+                    changed = true;
+                }
+            }
+            "sc" | "scn" if replaced.0 => op.operands = converted(values(&op)),
+            "SC" | "SCN" if replaced.1 => op.operands = converted(values(&op)),
+            _ => {}
+        }
+        output.push(op);
+    }
+    changed.then_some(output)
+}
+
+/// 在灰度、RGB 和 CMYK 之间换算颜色（不使用色彩管理的近似换算）
+fn convert_color(values: &[f64], components: i64) -> Vec<f64> {
+    let (r, g, b) = match *values {
+        [gray] => (gray, gray, gray),
+        [r, g, b] => (r, g, b),
+        [c, m, y, k] => ((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)),
+        _ => (0.0, 0.0, 0.0),
+    };
+    let (r, g, b) = (r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0));
+    match components {
+        1 => vec![0.299 * r + 0.587 * g + 0.114 * b],
+        3 => vec![r, g, b],
+        _ => {
+            let k = 1.0 - r.max(g).max(b);
+            if k >= 1.0 {
+                return vec![0.0, 0.0, 0.0, 1.0];
+            }
+            vec![(1.0 - r - k) / (1.0 - k), (1.0 - g - k) / (1.0 - k), (1.0 - b - k) / (1.0 - k), k]
+        }
+    }
+}
+
+/// 未嵌入的字体及其编码
+struct MissingFont {
+    base_font: String,
+    font: RenderFont,
+}
+
+/// 用提供的字体替换未嵌入的简单字体：使用这些字体的文字按 Unicode 重新编码为替换字体的 CID，
+/// 字宽以替换字体为准，文字位置可能与原来略有不同
+fn replace_missing_fonts(doc: &mut Document, font_data: &[u8]) -> Result<(), String> {
+    let mut missing: HashMap<ObjectId, MissingFont> = HashMap::new();
+    for (&id, object) in &doc.objects {
+        let Ok(dict) = object.as_dict() else { continue };
+        if !dict.type_is(b"Font") || !matches!(name(dict, b"Subtype"), Some(b"Type1" | b"MMType1" | b"TrueType")) || is_font_embedded(doc, dict) {
+            continue;
+        }
+        let base_font = name(dict, b"BaseFont").map(String::from_utf8_lossy).unwrap_or_default().into_owned();
+        // 符号字体的编码对应的不是文字，无法按 Unicode 替换
+        if base_font.contains("Symbol") || base_font.contains("Dingbats") {
+            return Err(format!("无法替换未嵌入的符号字体 {}", base_font));
+        }
+        missing.insert(id, MissingFont { base_font, font: RenderFont::load(doc, dict) });
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let mut font = EmbeddedFont::parse(font_data)?;
+
+    // 先处理页面，没有资源字典的表单使用调用它的页面（或表单）的资源
+    let mut forms: Vec<(ObjectId, Dictionary)> = Vec::new();
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in page_ids {
+        let resources = get_inherited_attribute(doc, page_id, b"Resources")
+            .and_then(|r| resolve(doc, r).as_dict().ok().cloned())
+            .unwrap_or_default();
+        let operations = page_operations(doc, page_id);
+        let Some(operations) = reencode_text(doc, &operations, &resources, &missing, &mut font, &mut forms)? else { continue };
+        let mut stream = Stream::new(Dictionary::new(), encode_content(&operations));
+        let _ = stream.compress();
+        let content_id = doc.add_object(stream);
+        let page = doc.get_dictionary_mut(page_id)
+            .map_err(|e| format!("无法读取页面: {}", e))?;
+        page.set("Contents", Object::Reference(content_id));
+    }
+    // 其余有资源字典的表单，如注释外观
+    let mut own_resources: Vec<(ObjectId, Dictionary)> = doc.objects.iter()
+        .filter_map(|(&id, object)| Some((id, object.as_stream().ok()?)))
+        .filter(|(_, stream)| name(&stream.dict, b"Subtype") == Some(b"Form"))
+        .filter_map(|(id, stream)| Some((id, resolve(doc, stream.dict.get(b"Resources").ok()?).as_dict().ok()?.clone())))
+        .collect();
+    own_resources.sort_by_key(|(id, _)| std::cmp::Reverse(*id));
+    forms.splice(0..0, own_resources);
+
+    let mut visited = HashSet::new();
+    while let Some((id, resources)) = forms.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else { continue };
+        let Ok(decoded) = decode_stream(doc, stream) else { continue };
+        let Some(operations) = reencode_text(doc, &parse_content(&decoded.data), &resources, &missing, &mut font, &mut forms)? else { continue };
+        let mut dict = stream.dict.clone();
+        dict.remove(b"Filter");
+        dict.remove(b"DecodeParms");
+        let mut stream = Stream::new(dict, encode_content(&operations));
+        let _ = stream.compress();
+        doc.objects.insert(id, Object::Stream(stream));
+    }
+
+    // 所有未嵌入的字体都指向同一个嵌入字体
+    let mut ids: Vec<ObjectId> = missing.into_keys().collect();
+    ids.sort();
+    font.write(doc, ids[0])?;
+    let replacement = doc.get_object(ids[0]).map_err(|e| format!("无法读取替换字体: {}", e))?.clone();
+    for &id in &ids[1..] {
+        doc.objects.insert(id, replacement.clone());
+    }
+    Ok(())
+}
+
+/// 重新编码使用未嵌入字体的文字，没有需要修改的文字时返回 None；
+/// 遇到的表单 XObject 及其使用的资源加入 forms
+fn reencode_text(
+    doc: &Document,
+    operations: &[Operation],
+    resources: &Dictionary,
+    missing: &HashMap<ObjectId, MissingFont>,
+    font: &mut EmbeddedFont,
+    forms: &mut Vec<(ObjectId, Dictionary)>,
+) -> Result<Option<Vec<Operation>>, String> {
+    let resource = |category: &[u8], key: &Object| -> Option<&Object> {
+        let key = key.as_name().ok()?;
+        resolve(doc, resources.get(category).ok()?).as_dict().ok()?.get(key).ok()
+    };
+
+    let mut changed = false;
+    // (当前字体, 字号, 字间距)，q/Q 时保存和恢复
+    let mut state: (Option<ObjectId>, f64, f64) = (None, 0.0, 0.0);
+    let mut saved = Vec::new();
+    let mut output = Vec::with_capacity(operations.len());
+    for op in operations {
+        let nums: Vec<f64> = op.operands.iter().filter_map(object_to_f64).collect();
+        match op.operator.as_str() {
+            "q" => saved.push(state),
+            "Q" => state = saved.pop().unwrap_or(state),
+            "Tf" => {
+                state.0 = op.operands.first()
+                    .and_then(|n| resource(b"Font", n))
+                    .and_then(|f| f.as_reference().ok())
+                    .filter(|id| missing.contains_key(id));
+                state.1 = nums.last().copied().unwrap_or(0.0);
+            }
+            "Tw" => state.2 = nums.first().copied().unwrap_or(0.0),
+            "Do" => {
+                let form = op.operands.first()
+                    .and_then(|n| resource(b"XObject", n))
+                    .and_then(|x| x.as_reference().ok())
+                    .and_then(|id| Some((id, doc.get_object(id).and_then(Object::as_stream).ok()?)))
+                    .filter(|(_, x)| name(&x.dict, b"Subtype") == Some(b"Form"));
+                if let Some((id, xobject)) = form {
+                    if !xobject.dict.has(b"Resources") {
+                        forms.push((id, resources.clone()));
+                    }
+                }
+            }
+            "Tj" | "'" | "\"" | "TJ" if state.0.is_some() => {
+                let missing_font = &missing[&state.0.unwrap()];
+                let items: Vec<Object> = match op.operands.last() {
+                    Some(Object::Array(items)) if op.operator == "TJ" => items.clone(),
+                    Some(s @ Object::String(..)) => vec![s.clone()],
+                    _ => {
+                        output.push(op.clone());
+                        continue;
+                    }
+                };
+                // ' 和 " 拆分为换行（及设置间距）和 TJ
+                if op.operator == "\"" && nums.len() >= 2 {
+                    state.2 = nums[0];
+                    output.push(Operation::new("Tw", vec![op.operands[0].clone()]));
+                    output.push(Operation::new("Tc", vec![op.operands[1].clone()]));
+                }
+                if op.operator == "'" || op.operator == "\"" {
+                    output.push(Operation::new("T*", vec![]));
+                }
+                // 双字节编码不应用字间距，改为在空格后调整位置
+                let word_adjust = (state.2 != 0.0 && state.1 != 0.0).then(|| Object::Real((-state.2 * 1000.0 / state.1) as f32));
+                let mut encoded = Vec::with_capacity(items.len());
+                for item in items {
+                    let Object::String(bytes, _) = item else {
+                        encoded.push(item);
+                        continue;
+                    };
+                    let mut text = String::new();
+                    for c in missing_font.font.decode(&bytes) {
+                        let unicode = missing_font.font.unicode(c.code)
+                            .ok_or_else(|| format!("无法确定字体 {} 中编码 {} 对应的文字", missing_font.base_font, c.code))?;
+                        text.push_str(&unicode);
+                    }
+                    if !font.covers(&text) {
+                        return Err(format!("替换字体缺少文字「{}」中的字符", text));
+                    }
+                    match &word_adjust {
+                        Some(adjust) => {
+                            for (index, part) in text.split(' ').enumerate() {
+                                if index > 0 {
+                                    encoded.push(Object::String(font.encode(" "), StringFormat::Hexadecimal));
+                                    encoded.push(adjust.clone());
+                                }
+                                if !part.is_empty() {
+                                    encoded.push(Object::String(font.encode(part), StringFormat::Hexadecimal));
+                                }
+                            }
+                        }
+                        None => encoded.push(Object::String(font.encode(&text), StringFormat::Hexadecimal)),
+                    }
+                }
+                output.push(Operation::new("TJ", vec![Object::Array(encoded)]));
+                changed = true;
+                continue;
+            }
+            _ => {}
+        }
+        output.push(op.clone());
+    }
+    Ok(changed.then_some(output))
+}
+
+/// 更新文档信息的日期，并写入与之一致的 XMP 元数据
+fn write_metadata(doc: &mut Document, level: PdfALevel) -> Result<(), String> {
+    let info_id = doc.trailer.get(b"Info").and_then(Object::as_reference).ok();
+    let mut info = doc.trailer.get(b"Info").ok()
+        .and_then(|i| resolve(doc, i).as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+
+    let text = |info: &Dictionary, key: &[u8]| {
+        info.get(key).ok()
+            .and_then(|v| v.as_str().ok())
+            .map(decode_text_string)
+            .filter(|v| !v.is_empty())
+    };
+    let now = current_pdf_date();
+    if text(&info, b"CreationDate").and_then(|d| xmp_date(&d)).is_none() {
+        info.set("CreationDate", Object::string_literal(now.as_str()));
+    }
+    info.set("ModDate", Object::string_literal(now.as_str()));
+    // XMP 中没有对应的 pdf:Trapped 时不能出现
+    info.remove(b"Trapped");
+
+    let mut properties = format!(
+        "   <pdfaid:part>{}</pdfaid:part>\n   <pdfaid:conformance>B</pdfaid:conformance>\n   <dc:format>application/pdf</dc:format>\n",
+        level.part()
+    );
+    let alternative = |value: &str| format!("<rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt>", xml_escape(value));
+    if let Some(title) = text(&info, b"Title") {
+        properties.push_str(&format!("   <dc:title>{}</dc:title>\n", alternative(&title)));
+    }
+    if let Some(author) = text(&info, b"Author") {
+        properties.push_str(&format!("   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n", xml_escape(&author)));
+    }
+    if let Some(subject) = text(&info, b"Subject") {
+        properties.push_str(&format!("   <dc:description>{}</dc:description>\n", alternative(&subject)));
+    }
+    if let Some(keywords) = text(&info, b"Keywords") {
+        properties.push_str(&format!("   <pdf:Keywords>{}</pdf:Keywords>\n", xml_escape(&keywords)));
+    }
+    if let Some(creator) = text(&info, b"Creator") {
+        properties.push_str(&format!("   <xmp:CreatorTool>{}</xmp:CreatorTool>\n", xml_escape(&creator)));
+    }
+    if let Some(producer) = text(&info, b"Producer") {
+        properties.push_str(&format!("   <pdf:Producer>{}</pdf:Producer>\n", xml_escape(&producer)));
+    }
+    if let Some(created) = text(&info, b"CreationDate").and_then(|d| xmp_date(&d)) {
+        properties.push_str(&format!("   <xmp:CreateDate>{}</xmp:CreateDate>\n", created));
+    }
+    if let Some(modified) = xmp_date(&now) {
+        properties.push_str(&format!("   <xmp:ModifyDate>{0}</xmp:ModifyDate>\n   <xmp:MetadataDate>{0}</xmp:MetadataDate>\n", modified));
+    }
+
+    let xmp = format!(
+        concat!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+            " <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+            "  <rdf:Description rdf:about=\"\"\n",
+            "    xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\"\n",
+            "    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n",
+            "    xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n",
+            "    xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n",
+            "{}",
+            "  </rdf:Description>\n",
+            " </rdf:RDF>\n",
+            "</x:xmpmeta>\n",
+            "<?xpacket end=\"w\"?>",
+        ),
+        properties
+    );
+
+    match info_id {
+        Some(id) => {
+            doc.objects.insert(id, Object::Dictionary(info));
+        }
+        None => {
+            let id = doc.add_object(Object::Dictionary(info));
+            doc.trailer.set("Info", Object::Reference(id));
+        }
+    }
+
+    let mut metadata_dict = Dictionary::new();
+    metadata_dict.set("Type", Object::Name(b"Metadata".to_vec()));
+    metadata_dict.set("Subtype", Object::Name(b"XML".to_vec()));
+    // PDF/A-1 要求元数据流不压缩，其他级别也保持明文以便读取
+    let metadata_id = doc.add_object(Stream::new(metadata_dict, xmp.into_bytes()).with_compression(false));
+    let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    catalog.set("Metadata", Object::Reference(metadata_id));
+    Ok(())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// PDF 日期（如 "D:20240101120000+08'00'"）转换为 XMP 日期（如 "2024-01-01T12:00:00+08:00"），
+/// 省略的月、日、时间按 PDF 规范取默认值
+fn xmp_date(date: &str) -> Option<String> {
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let digits = date.bytes().take_while(u8::is_ascii_digit).count();
+    if !(4..=14).contains(&digits) || digits % 2 != 0 {
+        return None;
+    }
+    let full = format!("{}{}", &date[..digits], &"00000101000000"[digits..]);
+    let zone = match &date.as_bytes()[digits..] {
+        [] => String::new(),
+        [b'Z', ..] => "Z".to_string(),
+        [sign @ (b'+' | b'-'), rest @ ..] => {
+            let numbers: Vec<u8> = rest.iter().copied().filter(u8::is_ascii_digit).collect();
+            let hours = numbers.get(..2)?;
+            let minutes = numbers.get(2..4).unwrap_or(b"00");
+            format!("{}{}:{}", *sign as char, String::from_utf8_lossy(hours), String::from_utf8_lossy(minutes))
+        }
+        _ => return None,
+    };
+    Some(format!(
+        "{}-{}-{}T{}:{}:{}{}",
+        &full[..4], &full[4..6], &full[6..8], &full[8..10], &full[10..12], &full[12..14], zone
+    ))
+}
+
+/// 文件尾缺少文件标识时按文档内容和当前时间生成
+fn ensure_file_id(doc: &mut Document) {
+    let valid = doc.trailer.get(b"ID").and_then(Object::as_array)
+        .is_ok_and(|id| id.len() == 2 && id.iter().all(|part| part.as_str().is_ok()));
+    if valid {
+        return;
+    }
+    let mut hasher = DefaultHasher::new();
+    current_pdf_date().hash(&mut hasher);
+    doc.max_id.hash(&mut hasher);
+    for (id, object) in &doc.objects {
+        id.hash(&mut hasher);
+        if let Ok(stream) = object.as_stream() {
+            stream.content.hash(&mut hasher);
+        }
+    }
+    let first = hasher.finish();
+    first.hash(&mut hasher);
+    let mut bytes = first.to_be_bytes().to_vec();
+    bytes.extend(hasher.finish().to_be_bytes());
+    let id = Object::String(bytes, StringFormat::Hexadecimal);
+    doc.trailer.set("ID", Object::Array(vec![id.clone(), id]));
+}
+
+/// 按 ICC v2 格式生成 sRGB IEC61966-2.1 配置文件（显示器类，RGB 到 XYZ）
+fn srgb_icc_profile() -> Vec<u8> {
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    let xyz = |x: f64, y: f64, z: f64| -> Vec<u8> {
+        let mut data = b"XYZ \0\0\0\0".to_vec();
+        for value in [x, y, z] {
+            data.extend(s15_fixed16(value));
+        }
+        data
+    };
+
+    let mut description = b"desc\0\0\0\0".to_vec();
+    description.extend((SRGB_IDENTIFIER.len() as u32 + 1).to_be_bytes());
+    description.extend(SRGB_IDENTIFIER.as_bytes());
+    // ASCII 描述的结尾 0，Unicode 和 ScriptCode 描述为空
+    description.extend([0u8; 1 + 4 + 4 + 2 + 1 + 67]);
+
+    let mut copyright = b"text\0\0\0\0".to_vec();
+    copyright.extend(b"No copyright, use freely\0");
+
+    // sRGB 色调曲线：线性段加 2.4 次幂
+    let mut curve = b"curv\0\0\0\0".to_vec();
+    curve.extend(1024u32.to_be_bytes());
+    for i in 0..1024 {
+        let x = i as f64 / 1023.0;
+        let y = if x <= 0.04045 { x / 12.92 } else { ((x + 0.055) / 1.055).powf(2.4) };
+        curve.extend(((y * 65535.0).round() as u16).to_be_bytes());
+    }
+
+    // 标签和数据序号，三条色调曲线共用一份数据
+    let data = [
+        description,
+        copyright,
+        xyz(0.9505, 1.0, 1.0891),
+        xyz(0.4361, 0.2225, 0.0139),
+        xyz(0.3851, 0.7169, 0.0971),
+        xyz(0.1431, 0.0606, 0.7141),
+        curve,
+    ];
+    let tags: [(&[u8; 4], usize); 9] = [
+        (b"desc", 0), (b"cprt", 1), (b"wtpt", 2),
+        (b"rXYZ", 3), (b"gXYZ", 4), (b"bXYZ", 5),
+        (b"rTRC", 6), (b"gTRC", 6), (b"bTRC", 6),
+    ];
+
+    let mut offsets = Vec::new();
+    let mut body = Vec::new();
+    let mut offset = 128 + 4 + 12 * tags.len();
+    for item in &data {
+        offsets.push(offset);
+        body.extend(item);
+        while body.len() % 4 != 0 {
+            body.push(0);
+        }
+        offset = 128 + 4 + 12 * tags.len() + body.len();
+    }
+    let size = 128 + 4 + 12 * tags.len() + body.len();
+
+    let mut profile = Vec::with_capacity(size);
+    profile.extend((size as u32).to_be_bytes());
+    profile.extend([0u8; 4]); // CMM
+    profile.extend([2, 0x10, 0, 0]); // 版本 2.1
+    profile.extend(b"mntrRGB XYZ ");
+    // 创建日期 1998-02-09 06:49:00
+    for value in [1998u16, 2, 9, 6, 49, 0] {
+        profile.extend(value.to_be_bytes());
+    }
+    profile.extend(b"acsp");
+    profile.extend([0u8; 4 + 4 + 4 + 4 + 8 + 4]); // 平台、标志、制造商、型号、属性、渲染意图
+    for value in [0.9642, 1.0, 0.8249] {
+        profile.extend(s15_fixed16(value)); // 连接空间光源 D50
+    }
+    profile.resize(128, 0);
+
+    profile.extend((tags.len() as u32).to_be_bytes());
+    for (signature, index) in tags {
+        profile.extend(signature);
+        profile.extend((offsets[index] as u32).to_be_bytes());
+        profile.extend((data[index].len() as u32).to_be_bytes());
+    }
+    profile.extend(body);
+    profile
+}
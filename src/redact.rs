@@ -0,0 +1,933 @@
+// 涂黑：从内容流中删除区域内的文字，遮盖图片像素，删除重叠的注释，再绘制遮盖色块
+// 与只画一个黑框不同，被涂黑的文字和像素不再存在于文件中，无法复制、搜索或移开色块后看到
+
+use lopdf::content::Operation;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::acroform::remove_form_widgets;
+use crate::annotations::process_page_annotations;
+use crate::content_stream::{encode_content, intersect_rect, page_operations, parse_content, union_rect, Matrix, Rect};
+use crate::filters::decode_stream;
+use crate::image_embed::flate_image_stream;
+use crate::page_size::parse_color;
+use crate::pdf_utils::{get_inherited_attribute, object_to_f64, parse_page_selection, parse_rect, wrap_page_contents};
+use crate::render_color::{decode_image, expand_inline_image_dict, resolve, DecodedImage};
+use crate::render_font::RenderFont;
+
+/// 表单 XObject 允许的最大嵌套深度
+const MAX_DEPTH: usize = 8;
+/// 标记内容属性和结构元素中可能重复被涂黑文字的替代文本
+const ALTERNATE_TEXT_KEYS: [&[u8]; 3] = [b"ActualText", b"Alt", b"E"];
+
+/// 涂黑选项（以 JSON 字符串传入）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct RedactOptions {
+    /// 要涂黑的区域
+    regions: Vec<RedactRegion>,
+    /// 要涂黑的文字，每一处出现都会被涂黑
+    terms: Vec<String>,
+    /// 搜索文字时区分大小写
+    case_sensitive: bool,
+    /// 搜索文字的页码范围，如 "1-3,5"，为空时搜索全部页面
+    pages: String,
+    /// 遮盖色，默认 "#000000"
+    fill: Option<String>,
+    /// 输出线性化（快速 Web 查看）文件
+    pub(crate) linearize: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RedactRegion {
+    /// 页码，从 1 开始
+    page: usize,
+    /// [left, bottom, right, top]，PDF 页面坐标（点）
+    rect: [f64; 4],
+}
+
+/// 页面上的一个字形
+struct Glyph {
+    text: Option<String>,
+    bounds: Rect,
+}
+
+/// 涂黑时跟踪的图形和文字状态
+#[derive(Clone)]
+struct State {
+    ctm: Matrix,
+    char_spacing: f64,
+    word_spacing: f64,
+    horizontal_scaling: f64,
+    leading: f64,
+    font: Option<Rc<RenderFont>>,
+    font_size: f64,
+    rise: f64,
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            ctm: Matrix::IDENTITY,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            font: None,
+            font_size: 0.0,
+            rise: 0.0,
+        }
+    }
+}
+
+/// 一段标记内容（BDC/BMC ... EMC）
+#[derive(Default)]
+struct MarkedContent {
+    /// 属性列表所在的 BDC/DP 操作在输出中的位置
+    properties: Vec<usize>,
+    mcid: Option<i64>,
+    /// 其中有内容被删除或替换
+    touched: bool,
+}
+
+/// 改写后的内容
+struct Rewritten {
+    operations: Vec<Operation>,
+    /// 需要登记到资源字典的新 XObject
+    added: Vec<(Vec<u8>, ObjectId)>,
+    /// 已被替换且不再使用的 XObject，从资源字典中删除，避免原内容随资源保留在文件中
+    removed: Vec<Vec<u8>>,
+    /// 已改为内联副本且不再使用的属性列表，同样从资源字典中删除
+    removed_properties: Vec<Vec<u8>>,
+}
+
+/// 逐页执行涂黑：先按页面上出现的顺序给字形编号（搜索文字时收集字形），再改写内容流
+struct Redactor<'a> {
+    doc: &'a Document,
+    /// 用户指定的区域，与之相交的字形被删除
+    regions: Vec<Rect>,
+    /// 所有涂黑区域（含搜索到的文字），与之相交的图片像素被遮盖
+    areas: Vec<Rect>,
+    /// 需要删除的字形编号
+    marked: HashSet<usize>,
+    /// 收集模式下记录的字形；为 None 时改写内容
+    glyphs: Option<Vec<Glyph>>,
+    glyph_index: usize,
+    fill: (f64, f64, f64),
+    fonts: HashMap<ObjectId, Rc<RenderFont>>,
+    default_font: Rc<RenderFont>,
+    /// 页面内容中有内容被删除的标记内容编号，对应的结构元素需清除替代文本
+    mcids: Vec<i64>,
+    /// 新建的对象，完成后加入文档
+    objects: Vec<(ObjectId, Object)>,
+    next_id: u32,
+    name_counter: usize,
+}
+
+pub(crate) fn redact_document(doc: &mut Document, options: &RedactOptions) -> Result<(), String> {
+    let terms: Vec<Vec<char>> = options.terms.iter()
+        .map(|term| normalize_text(term, options.case_sensitive))
+        .filter(|term| !term.is_empty())
+        .collect();
+    if options.regions.is_empty() && terms.is_empty() {
+        return Err("未指定涂黑区域或搜索文字".to_string());
+    }
+    let fill = parse_color(options.fill.as_deref().unwrap_or("#000000"))?;
+
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let mut regions: Vec<Vec<Rect>> = vec![Vec::new(); page_ids.len()];
+    for region in &options.regions {
+        let index = region.page.checked_sub(1)
+            .filter(|&i| i < page_ids.len())
+            .ok_or_else(|| format!("涂黑区域的页码 {} 超出范围（共 {} 页）", region.page, page_ids.len()))?;
+        let [x1, y1, x2, y2] = region.rect;
+        regions[index].push([x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)]);
+    }
+    let search_pages: HashSet<usize> = if terms.is_empty() {
+        HashSet::new()
+    } else {
+        parse_page_selection(&options.pages, page_ids.len())?.into_iter().collect()
+    };
+
+    let mut removed_widgets = HashSet::new();
+    let mut touched_mcids = HashSet::new();
+    for (index, &page_id) in page_ids.iter().enumerate() {
+        let mut redactor = Redactor::new(doc, fill);
+        redactor.regions = std::mem::take(&mut regions[index]);
+        redactor.areas = redactor.regions.clone();
+
+        let operations = page_operations(doc, page_id);
+        let resources = get_inherited_attribute(doc, page_id, b"Resources")
+            .map(|r| resolve(doc, r))
+            .and_then(|r| r.as_dict().ok().cloned())
+            .unwrap_or_default();
+
+        if search_pages.contains(&index) {
+            redactor.glyphs = Some(Vec::new());
+            redactor.run(&operations, &resources, State::new(), 0);
+            let glyphs = redactor.glyphs.take().unwrap_or_default();
+            let (marked, rects) = find_terms(&glyphs, &terms, options.case_sensitive);
+            redactor.marked = marked;
+            redactor.areas.extend(rects);
+        }
+        if redactor.areas.is_empty() {
+            continue;
+        }
+
+        redactor.glyph_index = 0;
+        let rewritten = redactor.run(&operations, &resources, State::new(), 0);
+        let Redactor { areas, objects, next_id, mcids, .. } = redactor;
+        touched_mcids.extend(mcids.into_iter().map(|mcid| (page_id, mcid)));
+        for (id, object) in objects {
+            doc.objects.insert(id, object);
+        }
+        doc.max_id = doc.max_id.max(next_id - 1);
+        if let Some(rewritten) = rewritten {
+            let mut stream = Stream::new(Dictionary::new(), encode_content(&rewritten.operations));
+            // 压缩失败时保留未压缩的内容
+            let _ = stream.compress();
+            let content_id = doc.add_object(stream);
+            let resources = rewrite_resources(doc, &resources, &rewritten);
+            let page = doc.get_dictionary_mut(page_id)
+                .map_err(|e| format!("无法读取页面: {}", e))?;
+            page.set("Contents", Object::Reference(content_id));
+            page.set("Resources", Object::Dictionary(resources));
+        }
+
+        removed_widgets.extend(redact_annotations(doc, page_id, &areas)?);
+
+        let (r, g, b) = fill;
+        let mut content = format!("q\n{} {} {} rg\n", r, g, b);
+        for [x0, y0, x1, y1] in &areas {
+            content.push_str(&format!("{} {} {} {} re\n", x0, y0, x1 - x0, y1 - y0));
+        }
+        content.push_str("f\nQ\n");
+        wrap_page_contents(doc, page_id, content.into_bytes(), false)?;
+
+        // 缩略图仍显示涂黑前的内容
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.remove(b"Thumb");
+            page.remove(b"PieceInfo");
+            page.remove(b"Metadata");
+        }
+    }
+
+    if !removed_widgets.is_empty() {
+        remove_form_widgets(doc, &removed_widgets)?;
+    }
+    if !touched_mcids.is_empty() {
+        clear_structure_text(doc, &touched_mcids);
+    }
+
+    // 文档信息和 XMP 元数据可能包含被涂黑的内容
+    doc.trailer.remove(b"Info");
+    let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    catalog.remove(b"Metadata");
+    catalog.remove(b"PieceInfo");
+    Ok(())
+}
+
+/// 删除与涂黑区域相交的注释（含表单控件），返回被删除的控件
+fn redact_annotations(doc: &mut Document, page_id: ObjectId, areas: &[Rect]) -> Result<HashSet<ObjectId>, String> {
+    let overlaps = |doc: &Document, annot: &Dictionary| {
+        annot.get(b"Rect").ok()
+            .and_then(|r| resolve(doc, r).as_array().ok())
+            .and_then(|r| parse_rect(r))
+            .is_some_and(|rect| areas.iter().any(|area| intersect_rect(area, &rect).is_some()))
+    };
+
+    let annots = doc.get_dictionary(page_id).ok()
+        .and_then(|p| p.get(b"Annots").ok())
+        .and_then(|a| resolve(doc, a).as_array().ok())
+        .cloned()
+        .unwrap_or_default();
+    let widgets = annots.iter()
+        .filter_map(|a| a.as_reference().ok())
+        .filter(|&id| doc.get_dictionary(id).is_ok_and(|annot| {
+            annot.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget".as_slice()) && overlaps(doc, annot)
+        }))
+        .collect();
+
+    process_page_annotations(doc, page_id, &|doc, _, annot| overlaps(doc, annot), false)?;
+    Ok(widgets)
+}
+
+/// 清除包含被涂黑内容的结构元素（及其上级元素）的 ActualText、Alt 和 E
+fn clear_structure_text(doc: &mut Document, touched: &HashSet<(ObjectId, i64)>) {
+    let root = doc.catalog().ok()
+        .and_then(|c| c.get(b"StructTreeRoot").ok())
+        .and_then(|r| resolve(doc, r).as_dict().ok());
+    let Some(kids) = root.and_then(|r| r.get(b"K").ok()) else { return };
+
+    let mut elements = HashSet::new();
+    let mut visited = HashSet::new();
+    for kid in struct_kids(kids) {
+        if let Object::Reference(id) = kid {
+            find_struct_elements(doc, *id, None, touched, &mut Vec::new(), &mut visited, &mut elements);
+        }
+    }
+    for id in elements {
+        if let Ok(element) = doc.get_dictionary_mut(id) {
+            for key in ALTERNATE_TEXT_KEYS {
+                element.remove(key);
+            }
+        }
+    }
+}
+
+fn struct_kids(kids: &Object) -> &[Object] {
+    match kids {
+        Object::Array(kids) => kids,
+        kid => std::slice::from_ref(kid),
+    }
+}
+
+/// 深度优先遍历结构树，记录引用了被涂黑标记内容的元素及其上级元素
+fn find_struct_elements(
+    doc: &Document,
+    id: ObjectId,
+    page: Option<ObjectId>,
+    touched: &HashSet<(ObjectId, i64)>,
+    path: &mut Vec<ObjectId>,
+    visited: &mut HashSet<ObjectId>,
+    elements: &mut HashSet<ObjectId>,
+) {
+    if !visited.insert(id) {
+        return;
+    }
+    let Ok(element) = doc.get_dictionary(id) else { return };
+    let page = element.get(b"Pg").and_then(Object::as_reference).ok().or(page);
+    let Ok(kids) = element.get(b"K") else { return };
+    let is_touched = |page: Option<ObjectId>, mcid: i64| page.is_some_and(|page| touched.contains(&(page, mcid)));
+
+    path.push(id);
+    for kid in struct_kids(resolve(doc, kids)) {
+        let hit = match kid {
+            Object::Integer(mcid) => is_touched(page, *mcid),
+            Object::Reference(kid_id) => match doc.get_dictionary(*kid_id) {
+                Ok(kid) if kid.get(b"Type").and_then(Object::as_name).ok() == Some(b"MCR") => marked_content_reference(kid, page, &is_touched),
+                Ok(kid) if kid.get(b"Type").and_then(Object::as_name).ok() == Some(b"OBJR") => false,
+                Ok(_) => {
+                    find_struct_elements(doc, *kid_id, page, touched, path, visited, elements);
+                    false
+                }
+                Err(_) => false,
+            },
+            Object::Dictionary(kid) => marked_content_reference(kid, page, &is_touched),
+            _ => false,
+        };
+        if hit {
+            elements.extend(path.iter().copied());
+        }
+    }
+    path.pop();
+}
+
+/// 标记内容引用（MCR）是否指向页面内容中被涂黑的标记内容；表单中的标记内容（带 /Stm）不作检查
+fn marked_content_reference(mcr: &Dictionary, page: Option<ObjectId>, is_touched: &impl Fn(Option<ObjectId>, i64) -> bool) -> bool {
+    if mcr.has(b"Stm") {
+        return false;
+    }
+    let page = mcr.get(b"Pg").and_then(Object::as_reference).ok().or(page);
+    mcr.get(b"MCID").and_then(Object::as_i64).is_ok_and(|mcid| is_touched(page, mcid))
+}
+
+/// 复制资源字典，登记新的 XObject 并删除被替换的 XObject
+fn rewrite_resources(doc: &Document, resources: &Dictionary, rewritten: &Rewritten) -> Dictionary {
+    let mut resources = resources.clone();
+    if !rewritten.removed_properties.is_empty() {
+        let mut dict = resources.get(b"Properties").ok()
+            .and_then(|p| resolve(doc, p).as_dict().ok())
+            .cloned()
+            .unwrap_or_default();
+        for name in &rewritten.removed_properties {
+            dict.remove(name);
+        }
+        resources.set("Properties", Object::Dictionary(dict));
+    }
+    if rewritten.added.is_empty() && rewritten.removed.is_empty() {
+        return resources;
+    }
+    let mut dict = resources.get(b"XObject").ok()
+        .and_then(|x| resolve(doc, x).as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    for name in &rewritten.removed {
+        dict.remove(name);
+    }
+    for (name, id) in &rewritten.added {
+        dict.set(name.clone(), Object::Reference(*id));
+    }
+    resources.set("XObject", Object::Dictionary(dict));
+    resources
+}
+
+impl<'a> Redactor<'a> {
+    fn new(doc: &'a Document, fill: (f64, f64, f64)) -> Self {
+        Redactor {
+            doc,
+            regions: Vec::new(),
+            areas: Vec::new(),
+            marked: HashSet::new(),
+            glyphs: None,
+            glyph_index: 0,
+            fill,
+            fonts: HashMap::new(),
+            default_font: Rc::new(RenderFont::load(doc, &Dictionary::new())),
+            mcids: Vec::new(),
+            objects: Vec::new(),
+            next_id: doc.max_id + 1,
+            name_counter: 0,
+        }
+    }
+
+    fn add_object(&mut self, object: Object) -> ObjectId {
+        let id = (self.next_id, 0);
+        self.next_id += 1;
+        self.objects.push((id, object));
+        id
+    }
+
+    /// 资源字典中未使用的 XObject 名称
+    fn new_name(&mut self, resources: &Dictionary) -> Vec<u8> {
+        let existing = resources.get(b"XObject").ok().and_then(|x| resolve(self.doc, x).as_dict().ok());
+        loop {
+            self.name_counter += 1;
+            let name = format!("Rd{}", self.name_counter).into_bytes();
+            if !existing.is_some_and(|x| x.has(&name)) {
+                return name;
+            }
+        }
+    }
+
+    fn load_font(&mut self, resources: &Dictionary, name: &[u8]) -> Rc<RenderFont> {
+        let doc = self.doc;
+        let entry = resources.get(b"Font").ok()
+            .and_then(|f| resolve(doc, f).as_dict().ok())
+            .and_then(|f| f.get(name).ok());
+        match entry {
+            Some(Object::Reference(id)) => {
+                if let Some(font) = self.fonts.get(id) {
+                    return font.clone();
+                }
+                let font = match doc.get_dictionary(*id) {
+                    Ok(dict) => Rc::new(RenderFont::load(doc, dict)),
+                    Err(_) => self.default_font.clone(),
+                };
+                self.fonts.insert(*id, font.clone());
+                font
+            }
+            Some(Object::Dictionary(dict)) => Rc::new(RenderFont::load(doc, dict)),
+            _ => self.default_font.clone(),
+        }
+    }
+
+    fn intersects_area(&self, bounds: &Rect) -> bool {
+        self.areas.iter().any(|area| intersect_rect(area, bounds).is_some())
+    }
+
+    /// 执行一段内容，内容有改动时返回改写后的操作和新登记的 XObject
+    fn run(&mut self, operations: &[Operation], resources: &Dictionary, mut state: State, depth: usize) -> Option<Rewritten> {
+        let doc = self.doc;
+        let mut stack: Vec<State> = Vec::new();
+        let mut text_matrix = Matrix::IDENTITY;
+        let mut line_matrix = Matrix::IDENTITY;
+        let mut output = Vec::with_capacity(operations.len());
+        let mut added = Vec::new();
+        let mut replaced = HashSet::new();
+        let mut replaced_properties = HashSet::new();
+        // 最外层为整段内容，不会被 EMC 弹出
+        let mut sequences = vec![MarkedContent::default()];
+        let touch = |sequences: &mut Vec<MarkedContent>| sequences.iter_mut().for_each(|s| s.touched = true);
+
+        for op in operations {
+            let nums: Vec<f64> = op.operands.iter().filter_map(object_to_f64).collect();
+            match op.operator.as_str() {
+                "q" => stack.push(state.clone()),
+                "Q" => {
+                    if let Some(saved) = stack.pop() {
+                        state = saved;
+                    }
+                }
+                "cm" => {
+                    if let Some(m) = Matrix::from_objects(&op.operands) {
+                        state.ctm = m.then(&state.ctm);
+                    }
+                }
+                "BT" => {
+                    text_matrix = Matrix::IDENTITY;
+                    line_matrix = Matrix::IDENTITY;
+                }
+                "Tc" => state.char_spacing = nums.first().copied().unwrap_or(0.0),
+                "Tw" => state.word_spacing = nums.first().copied().unwrap_or(0.0),
+                "Tz" => state.horizontal_scaling = nums.first().copied().unwrap_or(100.0) / 100.0,
+                "TL" => state.leading = nums.first().copied().unwrap_or(0.0),
+                "Ts" => state.rise = nums.first().copied().unwrap_or(0.0),
+                "Tf" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        state.font = Some(self.load_font(resources, name));
+                    }
+                    state.font_size = nums.last().copied().unwrap_or(0.0);
+                }
+                "Td" | "TD" if nums.len() >= 2 => {
+                    if op.operator == "TD" {
+                        state.leading = -nums[1];
+                    }
+                    line_matrix = Matrix::translate(nums[0], nums[1]).then(&line_matrix);
+                    text_matrix = line_matrix;
+                }
+                "Tm" => {
+                    if let Some(m) = Matrix::from_objects(&op.operands) {
+                        line_matrix = m;
+                        text_matrix = m;
+                    }
+                }
+                "T*" => {
+                    line_matrix = Matrix::translate(0.0, -state.leading).then(&line_matrix);
+                    text_matrix = line_matrix;
+                }
+                "Tj" | "'" | "\"" | "TJ" => {
+                    if op.operator == "'" || op.operator == "\"" {
+                        if op.operator == "\"" && nums.len() >= 2 {
+                            state.word_spacing = nums[0];
+                            state.char_spacing = nums[1];
+                        }
+                        line_matrix = Matrix::translate(0.0, -state.leading).then(&line_matrix);
+                        text_matrix = line_matrix;
+                    }
+                    let items: Vec<Object> = match op.operands.last() {
+                        Some(Object::Array(items)) if op.operator == "TJ" => items.clone(),
+                        Some(s @ Object::String(..)) => vec![s.clone()],
+                        _ => {
+                            output.push(op.clone());
+                            continue;
+                        }
+                    };
+                    if let Some(items) = self.show_text(&items, &state, &mut text_matrix) {
+                        // 改写为 TJ，用位移代替被删除的字形，其余文字位置不变
+                        touch(&mut sequences);
+                        if op.operator == "\"" && nums.len() >= 2 {
+                            output.push(Operation::new("Tw", vec![Object::Real(nums[0] as f32)]));
+                            output.push(Operation::new("Tc", vec![Object::Real(nums[1] as f32)]));
+                        }
+                        if op.operator != "Tj" && op.operator != "TJ" {
+                            output.push(Operation::new("T*", Vec::new()));
+                        }
+                        output.push(Operation::new("TJ", vec![Object::Array(items)]));
+                        continue;
+                    }
+                }
+                "BI" if self.glyphs.is_none() => {
+                    let (Some(Object::Dictionary(dict)), Some(Object::String(data, _))) = (op.operands.first(), op.operands.get(1)) else {
+                        output.push(op.clone());
+                        continue;
+                    };
+                    if self.intersects_area(&state.ctm.apply_rect(&[0.0, 0.0, 1.0, 1.0])) {
+                        // 内联图片改为遮盖后的图片 XObject；无法解码时直接删除
+                        touch(&mut sequences);
+                        let stream = Stream::new(expand_inline_image_dict(dict), data.clone());
+                        if let Some(id) = self.mask_image(&stream, resources, &state.ctm) {
+                            let name = self.new_name(resources);
+                            output.push(Operation::new("Do", vec![Object::Name(name.clone())]));
+                            added.push((name, id));
+                        }
+                        continue;
+                    }
+                }
+                "Do" => {
+                    let Some(Object::Name(name)) = op.operands.first() else {
+                        output.push(op.clone());
+                        continue;
+                    };
+                    let xobject = resources.get(b"XObject").ok()
+                        .and_then(|x| resolve(doc, x).as_dict().ok())
+                        .and_then(|x| x.get(name).ok())
+                        .and_then(|x| resolve(doc, x).as_stream().ok());
+                    let Some(xobject) = xobject else {
+                        output.push(op.clone());
+                        continue;
+                    };
+
+                    let replacement = match xobject.dict.get(b"Subtype").and_then(Object::as_name) {
+                        Ok(b"Image") if self.glyphs.is_none() && self.intersects_area(&state.ctm.apply_rect(&[0.0, 0.0, 1.0, 1.0])) => {
+                            // 无法解码的图片（如 JPX）无法遮盖部分像素，整张删除
+                            Some(self.mask_image(xobject, resources, &state.ctm))
+                        }
+                        Ok(b"Form") => self.redact_form(xobject, resources, &state, depth),
+                        _ => None,
+                    };
+                    match replacement {
+                        Some(replacement) => {
+                            if let Some(id) = replacement {
+                                let new_name = self.new_name(resources);
+                                output.push(Operation::new("Do", vec![Object::Name(new_name.clone())]));
+                                added.push((new_name, id));
+                            }
+                            replaced.insert(name.clone());
+                            touch(&mut sequences);
+                        }
+                        None => output.push(op.clone()),
+                    }
+                    continue;
+                }
+                "BDC" | "BMC" => {
+                    let properties = (op.operator == "BDC").then(|| self.marked_content_properties(op, resources)).flatten();
+                    let mcid = properties.and_then(|p| p.get(b"MCID").and_then(Object::as_i64).ok());
+                    let properties = if op.operator == "BDC" { vec![output.len()] } else { Vec::new() };
+                    sequences.push(MarkedContent { properties, mcid, touched: false });
+                }
+                "DP" => {
+                    if let Some(sequence) = sequences.last_mut() {
+                        sequence.properties.push(output.len());
+                    }
+                }
+                "EMC" if sequences.len() > 1 => {
+                    if let Some(sequence) = sequences.pop() {
+                        replaced_properties.extend(self.finish_marked_content(sequence, &mut output, resources, depth));
+                    }
+                }
+                _ => {}
+            }
+            output.push(op.clone());
+        }
+        // 未闭合的标记内容和最外层一并处理
+        let changed = sequences[0].touched;
+        while let Some(sequence) = sequences.pop() {
+            replaced_properties.extend(self.finish_marked_content(sequence, &mut output, resources, depth));
+        }
+
+        // 同一 XObject 在其他位置未被涂黑时仍需保留
+        let removed = replaced.into_iter()
+            .filter(|name| !output.iter().any(|op| op.operator == "Do" && op.operands.first().and_then(|n| n.as_name().ok()) == Some(name.as_slice())))
+            .collect();
+        let removed_properties = replaced_properties.into_iter()
+            .filter(|name| !output.iter().any(|op| matches!(op.operator.as_str(), "BDC" | "DP") && op.operands.get(1).and_then(|n| n.as_name().ok()) == Some(name.as_slice())))
+            .collect();
+        changed.then_some(Rewritten { operations: output, added, removed, removed_properties })
+    }
+
+    /// BDC/DP 的属性列表，可以直接写在内容中或引用资源字典中的 /Properties
+    fn marked_content_properties<'b>(&self, op: &'b Operation, resources: &'b Dictionary) -> Option<&'b Dictionary> where 'a: 'b {
+        match op.operands.get(1)? {
+            Object::Dictionary(dict) => Some(dict),
+            Object::Name(name) => resources.get(b"Properties").ok()
+                .and_then(|p| resolve(self.doc, p).as_dict().ok())
+                .and_then(|p| p.get(name).ok())
+                .and_then(|p| resolve(self.doc, p).as_dict().ok()),
+            _ => None,
+        }
+    }
+
+    /// 标记内容结束：其中有内容被删除时去掉属性列表中的替代文本，避免被涂黑的文字随 ActualText 等保留下来
+    /// 返回改为内联副本的属性列表名称
+    fn finish_marked_content(&mut self, sequence: MarkedContent, output: &mut [Operation], resources: &Dictionary, depth: usize) -> Vec<Vec<u8>> {
+        let mut replaced = Vec::new();
+        if !sequence.touched {
+            return replaced;
+        }
+        if depth == 0 {
+            self.mcids.extend(sequence.mcid);
+        }
+        for index in sequence.properties {
+            let Some(op) = output.get(index) else { continue };
+            let Some(properties) = self.marked_content_properties(op, resources) else { continue };
+            if !ALTERNATE_TEXT_KEYS.iter().any(|key| properties.has(key)) {
+                continue;
+            }
+            // 引用的属性列表可能被其他内容共用，改为写在内容中的副本
+            let mut properties = properties.clone();
+            for key in ALTERNATE_TEXT_KEYS {
+                properties.remove(key);
+            }
+            if let Object::Name(name) = &output[index].operands[1] {
+                replaced.push(name.clone());
+            }
+            output[index].operands[1] = Object::Dictionary(properties);
+        }
+        replaced
+    }
+
+    /// 涂黑表单 XObject 的内容，有改动时返回新的表单对象（原对象可能被其他页面共用，不作修改）
+    /// 嵌套过深或内容无法解码的表单无法检查其中是否有要涂黑的内容，返回 Some(None) 整个删除
+    fn redact_form(&mut self, form: &Stream, resources: &Dictionary, state: &State, depth: usize) -> Option<Option<ObjectId>> {
+        let doc = self.doc;
+        if depth >= MAX_DEPTH {
+            return Some(None);
+        }
+        let content = match decode_stream(doc, form) {
+            Ok(content) if content.codec.is_none() => content,
+            _ => return Some(None),
+        };
+        let matrix = form.dict.get(b"Matrix").ok()
+            .and_then(|m| resolve(doc, m).as_array().ok())
+            .and_then(|m| Matrix::from_objects(m))
+            .unwrap_or(Matrix::IDENTITY);
+        let form_resources = form.dict.get(b"Resources").ok()
+            .and_then(|r| resolve(doc, r).as_dict().ok())
+            .unwrap_or(resources)
+            .clone();
+        let form_state = State { ctm: matrix.then(&state.ctm), ..state.clone() };
+        let rewritten = self.run(&parse_content(&content.data), &form_resources, form_state, depth + 1)?;
+
+        let mut dict = form.dict.clone();
+        dict.remove(b"Filter");
+        dict.remove(b"DecodeParms");
+        dict.remove(b"Length");
+        dict.set("Resources", Object::Dictionary(rewrite_resources(doc, &form_resources, &rewritten)));
+        let mut stream = Stream::new(dict, encode_content(&rewritten.operations));
+        let _ = stream.compress();
+        Some(Some(self.add_object(Object::Stream(stream))))
+    }
+
+    /// 生成遮盖了涂黑区域像素的新图片，无法解码时返回 None
+    fn mask_image(&mut self, image: &Stream, resources: &Dictionary, ctm: &Matrix) -> Option<ObjectId> {
+        let image_mask = image.dict.get(b"ImageMask").ok()
+            .and_then(|v| resolve(self.doc, v).as_bool().ok())
+            .unwrap_or(false);
+        let DecodedImage::Pixmap(pixmap) = decode_image(self.doc, image, Some(resources), [0.0; 3])? else { return None };
+        let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+
+        // 像素空间（原点在左上角，单位为像素）到页面
+        let to_page = Matrix([1.0 / width as f64, 0.0, 0.0, -1.0 / height as f64, 0.0, 1.0]).then(ctm);
+        let to_pixel = to_page.inverse()?;
+        let mut masked = vec![false; width * height];
+        for area in &self.areas {
+            let [x0, y0, x1, y1] = to_pixel.apply_rect(area);
+            let cols = (x0.floor().max(0.0) as usize)..(x1.ceil().clamp(0.0, width as f64) as usize);
+            let rows = (y0.floor().max(0.0) as usize)..(y1.ceil().clamp(0.0, height as f64) as usize);
+            for row in rows {
+                for col in cols.clone() {
+                    let cell = to_page.apply_rect(&[col as f64, row as f64, col as f64 + 1.0, row as f64 + 1.0]);
+                    if intersect_rect(area, &cell).is_some() {
+                        masked[row * width + col] = true;
+                    }
+                }
+            }
+        }
+
+        let mut dict = Dictionary::new();
+        dict.set("Type", Object::Name(b"XObject".to_vec()));
+        dict.set("Subtype", Object::Name(b"Image".to_vec()));
+        dict.set("Width", Object::Integer(width as i64));
+        dict.set("Height", Object::Integer(height as i64));
+
+        let pixels = pixmap.pixels();
+        if image_mask {
+            // 模板蒙版：采样值 0 表示绘制，被遮盖的像素不再绘制
+            let row_bytes = width.div_ceil(8);
+            let mut samples = vec![0u8; row_bytes * height];
+            for (i, pixel) in pixels.iter().enumerate() {
+                if pixel.alpha() == 0 || masked[i] {
+                    let (row, col) = (i / width, i % width);
+                    samples[row * row_bytes + col / 8] |= 0x80 >> (col % 8);
+                }
+            }
+            dict.set("ImageMask", Object::Boolean(true));
+            dict.set("BitsPerComponent", Object::Integer(1));
+            let stream = flate_image_stream(dict, &samples).ok()?;
+            return Some(self.add_object(Object::Stream(stream)));
+        }
+
+        let fill = [self.fill.0, self.fill.1, self.fill.2].map(|c| (c * 255.0).round() as u8);
+        let mut samples = Vec::with_capacity(width * height * 3);
+        let mut alpha = Vec::with_capacity(width * height);
+        for (i, pixel) in pixels.iter().enumerate() {
+            if masked[i] {
+                samples.extend_from_slice(&fill);
+                alpha.push(255);
+            } else {
+                let color = pixel.demultiply();
+                samples.extend_from_slice(&[color.red(), color.green(), color.blue()]);
+                alpha.push(color.alpha());
+            }
+        }
+        dict.set("ColorSpace", Object::Name(b"DeviceRGB".to_vec()));
+        dict.set("BitsPerComponent", Object::Integer(8));
+        if alpha.iter().any(|&a| a != 255) {
+            let mut mask_dict = Dictionary::new();
+            mask_dict.set("Type", Object::Name(b"XObject".to_vec()));
+            mask_dict.set("Subtype", Object::Name(b"Image".to_vec()));
+            mask_dict.set("Width", Object::Integer(width as i64));
+            mask_dict.set("Height", Object::Integer(height as i64));
+            mask_dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+            mask_dict.set("BitsPerComponent", Object::Integer(8));
+            let mask = flate_image_stream(mask_dict, &alpha).ok()?;
+            let mask_id = self.add_object(Object::Stream(mask));
+            dict.set("SMask", Object::Reference(mask_id));
+        }
+        let stream = flate_image_stream(dict, &samples).ok()?;
+        Some(self.add_object(Object::Stream(stream)))
+    }
+
+    /// 处理一次文字绘制并推进文字矩阵；有字形被删除时返回改写后的 TJ 数组
+    fn show_text(&mut self, items: &[Object], state: &State, text_matrix: &mut Matrix) -> Option<Vec<Object>> {
+        let font = state.font.clone().unwrap_or_else(|| self.default_font.clone());
+        let size = state.font_size;
+        let scaling = state.horizontal_scaling;
+
+        let mut output = Vec::new();
+        let mut removed_any = false;
+        // 尚未写出的位移（TJ 数组中的数值，千分之一字号）
+        let mut pending = 0.0;
+
+        for item in items {
+            let (bytes, format) = match item {
+                Object::String(bytes, format) => (bytes, format),
+                other => {
+                    if let Some(adjust) = object_to_f64(other) {
+                        let offset = -adjust / 1000.0 * size;
+                        let shift = if font.vertical { Matrix::translate(0.0, offset) } else { Matrix::translate(offset * scaling, 0.0) };
+                        *text_matrix = shift.then(text_matrix);
+                        pending += adjust;
+                    }
+                    continue;
+                }
+            };
+
+            let mut kept = Vec::new();
+            let mut pos = 0;
+            for ch in font.decode(bytes) {
+                let code_bytes = &bytes[pos..(pos + ch.len).min(bytes.len())];
+                pos += ch.len;
+
+                let glyph_matrix = Matrix([size * scaling, 0.0, 0.0, size, 0.0, state.rise]).then(text_matrix).then(&state.ctm);
+                let glyph = if font.vertical {
+                    [-ch.width / 2.0, -1.0, ch.width / 2.0, 0.0]
+                } else {
+                    [0.0, -0.25, ch.width, 0.85]
+                };
+                let bounds = glyph_matrix.apply_rect(&glyph);
+
+                let index = self.glyph_index;
+                self.glyph_index += 1;
+                let remove = match &mut self.glyphs {
+                    Some(glyphs) => {
+                        glyphs.push(Glyph { text: font.unicode(ch.code), bounds });
+                        false
+                    }
+                    None => self.marked.contains(&index) || self.regions.iter().any(|r| intersect_rect(r, &bounds).is_some()),
+                };
+
+                let spacing = state.char_spacing + if ch.is_space { state.word_spacing } else { 0.0 };
+                let (shift, adjust) = if font.vertical {
+                    let dy = -size + spacing;
+                    (Matrix::translate(0.0, dy), -dy)
+                } else {
+                    let advance = ch.width * size + spacing;
+                    (Matrix::translate(advance * scaling, 0.0), -advance)
+                };
+                *text_matrix = shift.then(text_matrix);
+
+                if remove {
+                    removed_any = true;
+                    if !kept.is_empty() {
+                        output.push(Object::String(std::mem::take(&mut kept), *format));
+                    }
+                    if size != 0.0 {
+                        pending += adjust / size * 1000.0;
+                    }
+                } else {
+                    if pending != 0.0 {
+                        output.push(Object::Real(pending as f32));
+                        pending = 0.0;
+                    }
+                    kept.extend_from_slice(code_bytes);
+                }
+            }
+            if !kept.is_empty() {
+                output.push(Object::String(kept, *format));
+            }
+        }
+        if pending != 0.0 {
+            output.push(Object::Real(pending as f32));
+        }
+
+        removed_any.then_some(output)
+    }
+}
+
+/// 统一空白和大小写，用于搜索
+fn normalize_text(text: &str, case_sensitive: bool) -> Vec<char> {
+    let mut chars: Vec<char> = Vec::new();
+    for c in text.trim().chars() {
+        let c = if c.is_whitespace() {
+            ' '
+        } else if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        };
+        if c == ' ' && chars.last() == Some(&' ') {
+            continue;
+        }
+        chars.push(c);
+    }
+    chars
+}
+
+/// 两个字形是否在同一行
+fn same_line(a: &Rect, b: &Rect) -> bool {
+    let height = (a[3] - a[1]).min(b[3] - b[1]);
+    ((a[1] + a[3]) - (b[1] + b[3])).abs() / 2.0 < height / 2.0
+}
+
+/// 在页面文字中搜索，返回需要删除的字形编号和遮盖区域
+fn find_terms(glyphs: &[Glyph], terms: &[Vec<char>], case_sensitive: bool) -> (HashSet<usize>, Vec<Rect>) {
+    // 页面文字，每个字符对应产生它的字形；换行和较大的字距视为空格
+    let mut chars: Vec<(char, Option<usize>)> = Vec::new();
+    for (index, glyph) in glyphs.iter().enumerate() {
+        if let Some(previous) = index.checked_sub(1).map(|i| &glyphs[i]) {
+            let height = previous.bounds[3] - previous.bounds[1];
+            let gap = glyph.bounds[0] - previous.bounds[2];
+            let separated = !same_line(&previous.bounds, &glyph.bounds) || gap > height * 0.15 || gap < -height;
+            if separated && chars.last().is_some_and(|(c, _)| *c != ' ') {
+                chars.push((' ', None));
+            }
+        }
+        let text = glyph.text.as_deref().unwrap_or("\u{FFFD}");
+        for c in normalize_text(text, case_sensitive) {
+            if c == ' ' && chars.last().is_some_and(|(c, _)| *c == ' ') {
+                continue;
+            }
+            chars.push((c, Some(index)));
+        }
+        // 只含空白的字形（如空格）
+        if text.chars().all(char::is_whitespace) && !text.is_empty() && chars.last().is_some_and(|(c, _)| *c != ' ') {
+            chars.push((' ', Some(index)));
+        }
+    }
+
+    let mut marked = HashSet::new();
+    let mut rects: Vec<Rect> = Vec::new();
+    for term in terms {
+        let mut start = 0;
+        while start + term.len() <= chars.len() {
+            if !chars[start..start + term.len()].iter().map(|(c, _)| c).eq(term.iter()) {
+                start += 1;
+                continue;
+            }
+            // 同一行上连续的字形合并为一个遮盖区域
+            let mut indices: Vec<usize> = chars[start..start + term.len()].iter().filter_map(|(_, i)| *i).collect();
+            indices.dedup();
+            let mut current: Option<Rect> = None;
+            for index in indices {
+                marked.insert(index);
+                let bounds = glyphs[index].bounds;
+                current = match current {
+                    Some(rect) if same_line(&rect, &bounds) => Some(union_rect(&rect, &bounds)),
+                    Some(rect) => {
+                        rects.push(rect);
+                        Some(bounds)
+                    }
+                    None => Some(bounds),
+                };
+            }
+            rects.extend(current);
+            start += term.len();
+        }
+    }
+    (marked, rects)
+}
@@ -0,0 +1,988 @@
+// 页面渲染（纯 Rust 光栅化，可在 wasm 中运行）
+// 解释内容流中的路径、颜色、裁剪、图片、文字、表单和图案，绘制到 tiny-skia 画布
+
+use lopdf::content::Operation;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::rc::Rc;
+use tiny_skia::{
+    Color, FillRule, FilterQuality, LineCap, LineJoin, Mask, Paint, Path, PathBuilder, Pattern, Pixmap,
+    PixmapPaint, Rect as SkiaRect, Shader, SpreadMode, Stroke, StrokeDash, Transform,
+};
+
+use crate::content_stream::{intersect_rect, page_operations, parse_content, Matrix, Rect};
+use crate::filters::decode_stream;
+use crate::pdf_utils::{get_inherited_attribute, get_page_box, get_page_rotation, object_to_f64, parse_rect};
+use crate::render_color::{decode_image, expand_inline_image_dict, resolve, ColorSpace, DecodedImage, Shading};
+use crate::render_font::RenderFont;
+
+/// 画布允许的最大像素数
+const MAX_RENDER_PIXELS: u64 = 50_000_000;
+/// 允许的最高渲染分辨率
+const MAX_DPI: f64 = 2400.0;
+
+/// 校验渲染分辨率：必须是有限正数且不超过上限
+pub(crate) fn check_dpi(dpi: f64) -> Result<(), String> {
+    if !dpi.is_finite() || dpi <= 0.0 {
+        return Err("分辨率必须大于 0".to_string());
+    }
+    if dpi > MAX_DPI {
+        return Err(format!("分辨率不能超过 {}", MAX_DPI));
+    }
+    Ok(())
+}
+/// 表单、图案和 Type3 字形的最大嵌套深度
+const MAX_DEPTH: usize = 12;
+/// 平铺图案单元格的最大边长（像素）
+const MAX_TILE_SIZE: f64 = 1024.0;
+
+/// 渲染选项
+pub(crate) struct RenderOptions {
+    /// 所有文字绘制为占位色块（缩略图模式，速度更快）
+    pub greek_text: bool,
+    /// 绘制注释的外观流
+    pub annotations: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { greek_text: false, annotations: true }
+    }
+}
+
+/// 页面的可见区域（CropBox，缺省为 MediaBox）与旋转角度
+fn page_geometry(doc: &Document, page_id: ObjectId) -> (Rect, i64) {
+    let media_box = get_page_box(doc, page_id, b"MediaBox").unwrap_or([0.0, 0.0, 612.0, 792.0]);
+    let view_box = get_page_box(doc, page_id, b"CropBox")
+        .and_then(|crop| intersect_rect(&crop, &media_box))
+        .unwrap_or(media_box);
+    (view_box, get_page_rotation(doc, page_id))
+}
+
+/// 页面旋转后的显示尺寸（点）
+pub(crate) fn page_display_size(doc: &Document, page_id: ObjectId) -> (f64, f64) {
+    let (view_box, rotation) = page_geometry(doc, page_id);
+    let (width, height) = (view_box[2] - view_box[0], view_box[3] - view_box[1]);
+    if rotation % 180 == 0 {
+        (width, height)
+    } else {
+        (height, width)
+    }
+}
+
+/// 渲染页面，scale 为每点对应的像素数（dpi / 72）
+pub(crate) fn render_page(doc: &Document, page_id: ObjectId, scale: f64, options: &RenderOptions) -> Result<Pixmap, String> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err("缩放比例必须大于 0".to_string());
+    }
+    let ([x0, y0, x1, y1], rotation) = page_geometry(doc, page_id);
+    let (width, height) = page_display_size(doc, page_id);
+    let (pixel_width, pixel_height) = ((width * scale).round().max(1.0), (height * scale).round().max(1.0));
+    if !pixel_width.is_finite() || !pixel_height.is_finite() || pixel_width > u32::MAX as f64 || pixel_height > u32::MAX as f64 {
+        return Err("渲染尺寸过大，请降低分辨率".to_string());
+    }
+    let (pixel_width, pixel_height) = (pixel_width as u64, pixel_height as u64);
+    if pixel_width.checked_mul(pixel_height).is_none_or(|pixels| pixels > MAX_RENDER_PIXELS) {
+        return Err(format!("渲染尺寸过大: {}x{} 像素，请降低分辨率", pixel_width, pixel_height));
+    }
+
+    // 用户空间到设备空间：翻转 y 轴并按页面旋转角度顺时针旋转
+    let s = scale;
+    let base = match rotation {
+        90 => Matrix([0.0, s, s, 0.0, -y0 * s, -x0 * s]),
+        180 => Matrix([-s, 0.0, 0.0, s, x1 * s, -y0 * s]),
+        270 => Matrix([0.0, -s, -s, 0.0, y1 * s, x1 * s]),
+        _ => Matrix([s, 0.0, 0.0, -s, -x0 * s, y1 * s]),
+    };
+
+    let mut pixmap = Pixmap::new(pixel_width as u32, pixel_height as u32).ok_or("无法创建画布")?;
+    pixmap.fill(Color::WHITE);
+
+    let mut renderer = Renderer::new(doc, pixmap, options);
+    let resources = get_inherited_attribute(doc, page_id, b"Resources")
+        .and_then(|r| resolve(doc, r).as_dict().ok());
+    let operations = page_operations(doc, page_id);
+    renderer.execute(&operations, resources, State::new(base), 0);
+
+    if options.annotations {
+        renderer.draw_annotations(page_id, base);
+    }
+    Ok(renderer.pixmap)
+}
+
+/// 将画布编码为 PNG 或 JPEG
+pub(crate) fn encode_pixmap(pixmap: &Pixmap, format: &str, quality: u8) -> Result<Vec<u8>, String> {
+    // 画布背景不透明，直接去掉 alpha 通道
+    let rgb: Vec<u8> = pixmap.pixels().iter().flat_map(|p| [p.red(), p.green(), p.blue()]).collect();
+    let image = image::RgbImage::from_raw(pixmap.width(), pixmap.height(), rgb).ok_or("无法创建图片")?;
+
+    let mut buffer = Vec::new();
+    match format.to_lowercase().as_str() {
+        "png" | "" => image
+            .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
+            .map_err(|e| format!("无法编码 PNG: {}", e))?,
+        "jpeg" | "jpg" => image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality)
+            .encode_image(&image)
+            .map_err(|e| format!("无法编码 JPEG: {}", e))?,
+        other => return Err(format!("不支持的图片格式: {}", other)),
+    }
+    Ok(buffer)
+}
+
+fn to_transform(m: &Matrix) -> Transform {
+    let [a, b, c, d, e, f] = m.0.map(|v| v as f32);
+    Transform::from_row(a, b, c, d, e, f)
+}
+
+fn rect_path(rect: &Rect) -> Option<Path> {
+    let [x0, y0, x1, y1] = rect.map(|v| v as f32);
+    SkiaRect::from_ltrb(x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)).map(PathBuilder::from_rect)
+}
+
+fn numbers(operands: &[Object]) -> Vec<f64> {
+    operands.iter().filter_map(object_to_f64).collect()
+}
+
+// ---------------------------------------------------------------------------
+// 图形状态
+// ---------------------------------------------------------------------------
+
+/// 填充或描边颜色
+#[derive(Clone)]
+struct PaintColor {
+    space: Rc<ColorSpace>,
+    values: Vec<f64>,
+    rgb: [f64; 3],
+    /// 图案颜色空间下的图案名称
+    pattern: Option<Vec<u8>>,
+}
+
+impl PaintColor {
+    fn new(space: ColorSpace) -> PaintColor {
+        let values = space.initial_color();
+        let rgb = space.to_rgb(&values);
+        PaintColor { space: Rc::new(space), values, rgb, pattern: None }
+    }
+
+    fn set_values(&mut self, operands: &[Object]) {
+        self.values = numbers(operands);
+        self.pattern = match operands.last() {
+            Some(Object::Name(name)) => Some(name.clone()),
+            _ => None,
+        };
+        self.rgb = self.space.to_rgb(&self.values);
+    }
+}
+
+#[derive(Clone)]
+struct State {
+    ctm: Matrix,
+    clip: Option<Rc<Mask>>,
+    fill: PaintColor,
+    stroke: PaintColor,
+    fill_alpha: f64,
+    stroke_alpha: f64,
+    /// 无色图案和 Type3 d1 字形中忽略颜色设置
+    lock_color: bool,
+    line_width: f64,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f64,
+    dash: Option<(Vec<f64>, f64)>,
+    font: Option<Rc<RenderFont>>,
+    font_size: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    horizontal_scaling: f64,
+    leading: f64,
+    render_mode: i64,
+    rise: f64,
+}
+
+impl State {
+    fn new(ctm: Matrix) -> State {
+        State {
+            ctm,
+            clip: None,
+            fill: PaintColor::new(ColorSpace::Gray),
+            stroke: PaintColor::new(ColorSpace::Gray),
+            fill_alpha: 1.0,
+            stroke_alpha: 1.0,
+            lock_color: false,
+            line_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 10.0,
+            dash: None,
+            font: None,
+            font_size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            render_mode: 0,
+            rise: 0.0,
+        }
+    }
+}
+
+/// 绘制来源：纯色、渐变或平铺图案
+enum PaintSource {
+    Solid(Color),
+    Shader(Shader<'static>),
+    Tile(Rc<Pixmap>, Transform, f32),
+}
+
+/// 渐变和图案的变换以设备空间给出，而 tiny-skia 会再叠加一次路径变换，这里预先抵消
+fn with_paint<R>(source: &PaintSource, path_transform: Transform, draw: impl FnOnce(&Paint) -> R) -> R {
+    let mut paint = Paint { anti_alias: true, ..Paint::default() };
+    let inverse = path_transform.invert().unwrap_or_default();
+    match source {
+        PaintSource::Solid(color) => paint.set_color(*color),
+        PaintSource::Shader(shader) => {
+            paint.shader = shader.clone();
+            paint.shader.transform(inverse);
+        }
+        PaintSource::Tile(pixmap, transform, opacity) => {
+            let transform = transform.post_concat(inverse);
+            paint.shader = Pattern::new(pixmap.as_ref().as_ref(), SpreadMode::Repeat, FilterQuality::Bilinear, *opacity, transform);
+        }
+    }
+    draw(&paint)
+}
+
+fn solid_color(rgb: [f64; 3], alpha: f64) -> Color {
+    let [r, g, b] = rgb.map(|c| c.clamp(0.0, 1.0) as f32);
+    Color::from_rgba(r, g, b, alpha.clamp(0.0, 1.0) as f32).unwrap_or(Color::BLACK)
+}
+
+// ---------------------------------------------------------------------------
+// 渲染器
+// ---------------------------------------------------------------------------
+
+struct Renderer<'a> {
+    doc: &'a Document,
+    pixmap: Pixmap,
+    options: &'a RenderOptions,
+    fonts: HashMap<ObjectId, Rc<RenderFont>>,
+    tiles: HashMap<String, Rc<Pixmap>>,
+}
+
+/// 文字对象内的状态
+struct TextObject {
+    matrix: Matrix,
+    line_matrix: Matrix,
+    /// 裁剪模式文字的轮廓（设备空间），在 ET 时并入裁剪区域
+    clip: Option<PathBuilder>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(doc: &'a Document, pixmap: Pixmap, options: &'a RenderOptions) -> Self {
+        Renderer { doc, pixmap, options, fonts: HashMap::new(), tiles: HashMap::new() }
+    }
+
+    fn lookup<'r>(&self, resources: Option<&'r Dictionary>, category: &[u8], name: &[u8]) -> Option<&'r Object>
+    where
+        'a: 'r,
+    {
+        let doc: &'r Document = self.doc;
+        let category = resolve(doc, resources?.get(category).ok()?).as_dict().ok()?;
+        Some(resolve(doc, category.get(name).ok()?))
+    }
+
+    /// 执行内容流，state 为进入内容流时的图形状态
+    fn execute(&mut self, operations: &[Operation], resources: Option<&Dictionary>, mut state: State, depth: usize) {
+        let base_ctm = state.ctm;
+        let mut stack: Vec<State> = Vec::new();
+        let mut path = PathBuilder::new();
+        let mut pending_clip: Option<FillRule> = None;
+        let mut text = TextObject { matrix: Matrix::IDENTITY, line_matrix: Matrix::IDENTITY, clip: None };
+
+        for op in operations {
+            let nums = numbers(&op.operands);
+            let num = |i: usize| nums.get(i).copied().unwrap_or(0.0) as f32;
+
+            match op.operator.as_str() {
+                // 图形状态
+                "q" => stack.push(state.clone()),
+                "Q" => {
+                    if let Some(saved) = stack.pop() {
+                        state = saved;
+                    }
+                }
+                "cm" => {
+                    if let Some(m) = Matrix::from_objects(&op.operands) {
+                        state.ctm = m.then(&state.ctm);
+                    }
+                }
+                "w" if !nums.is_empty() => state.line_width = nums[0],
+                "J" if !nums.is_empty() => state.line_cap = line_cap(nums[0]),
+                "j" if !nums.is_empty() => state.line_join = line_join(nums[0]),
+                "M" if !nums.is_empty() => state.miter_limit = nums[0],
+                "d" => state.dash = parse_dash(self.doc, &op.operands),
+                "gs" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        if let Some(gs) = self.lookup(resources, b"ExtGState", name).and_then(|g| g.as_dict().ok()) {
+                            self.apply_ext_gstate(gs, &mut state);
+                        }
+                    }
+                }
+
+                // 路径构造
+                "m" if nums.len() >= 2 => path.move_to(num(0), num(1)),
+                "l" if nums.len() >= 2 => path.line_to(num(0), num(1)),
+                "c" if nums.len() >= 6 => path.cubic_to(num(0), num(1), num(2), num(3), num(4), num(5)),
+                "v" if nums.len() >= 4 => {
+                    let current = path.last_point().unwrap_or_default();
+                    path.cubic_to(current.x, current.y, num(0), num(1), num(2), num(3));
+                }
+                "y" if nums.len() >= 4 => path.cubic_to(num(0), num(1), num(2), num(3), num(2), num(3)),
+                "h" => path.close(),
+                "re" if nums.len() >= 4 => {
+                    let (x, y, w, h) = (num(0), num(1), num(2), num(3));
+                    path.move_to(x, y);
+                    path.line_to(x + w, y);
+                    path.line_to(x + w, y + h);
+                    path.line_to(x, y + h);
+                    path.close();
+                }
+                "W" => pending_clip = Some(FillRule::Winding),
+                "W*" => pending_clip = Some(FillRule::EvenOdd),
+
+                // 路径绘制
+                "S" | "s" | "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" | "n" => {
+                    let operator = op.operator.as_str();
+                    if matches!(operator, "s" | "b" | "b*") {
+                        path.close();
+                    }
+                    let finished = std::mem::replace(&mut path, PathBuilder::new()).finish();
+                    let rule = if operator.ends_with('*') { FillRule::EvenOdd } else { FillRule::Winding };
+
+                    if let Some(finished) = &finished {
+                        if matches!(operator, "f" | "F" | "f*" | "B" | "B*" | "b" | "b*") {
+                            self.fill_path(finished, rule, &state, resources, base_ctm, depth);
+                        }
+                        if matches!(operator, "S" | "s" | "B" | "B*" | "b" | "b*") {
+                            self.stroke_path(finished, &state, resources, base_ctm, depth);
+                        }
+                    }
+                    if let Some(clip_rule) = pending_clip.take() {
+                        state.clip = self.clip_with(&state.clip, finished.as_ref(), clip_rule, to_transform(&state.ctm));
+                    }
+                }
+
+                // 颜色
+                "g" | "G" | "rg" | "RG" | "k" | "K" if !state.lock_color => {
+                    let space = match op.operator.to_lowercase().as_str() {
+                        "g" => ColorSpace::Gray,
+                        "rg" => ColorSpace::Rgb,
+                        _ => ColorSpace::Cmyk,
+                    };
+                    let target = if op.operator.chars().all(|c| c.is_lowercase()) { &mut state.fill } else { &mut state.stroke };
+                    *target = PaintColor::new(space);
+                    target.set_values(&op.operands);
+                }
+                "cs" | "CS" if !state.lock_color => {
+                    let Some(name) = op.operands.first() else { continue };
+                    let space = ColorSpace::parse(self.doc, name, resources);
+                    if op.operator == "cs" {
+                        state.fill = PaintColor::new(space);
+                    } else {
+                        state.stroke = PaintColor::new(space);
+                    }
+                }
+                "sc" | "scn" if !state.lock_color => state.fill.set_values(&op.operands),
+                "SC" | "SCN" if !state.lock_color => state.stroke.set_values(&op.operands),
+
+                "sh" => {
+                    let Some(Object::Name(name)) = op.operands.first() else { continue };
+                    let Some(shading) = self.lookup(resources, b"Shading", name)
+                        .and_then(|s| Shading::parse(self.doc, s, resources)) else { continue };
+                    let source = match shading.shader(to_transform(&state.ctm), state.fill_alpha as f32) {
+                        Some(shader) => PaintSource::Shader(shader),
+                        None => match shading.fallback {
+                            Some(rgb) => PaintSource::Solid(solid_color(rgb, state.fill_alpha)),
+                            None => continue,
+                        },
+                    };
+                    self.fill_canvas(&source, &state);
+                }
+
+                // 图片与表单
+                "Do" => {
+                    let Some(Object::Name(name)) = op.operands.first() else { continue };
+                    let Some(Object::Stream(xobject)) = self.lookup(resources, b"XObject", name) else { continue };
+                    match xobject.dict.get(b"Subtype").and_then(Object::as_name) {
+                        Ok(b"Image") => self.draw_image(xobject, resources, &state),
+                        Ok(b"Form") => self.draw_form(xobject, resources, state.clone(), depth),
+                        _ => {}
+                    }
+                }
+                "BI" => {
+                    if let [Object::Dictionary(dict), Object::String(data, _)] = op.operands.as_slice() {
+                        let image = Stream::new(expand_inline_image_dict(dict), data.clone());
+                        self.draw_image(&image, resources, &state);
+                    }
+                }
+
+                // 文字
+                "BT" => {
+                    text = TextObject { matrix: Matrix::IDENTITY, line_matrix: Matrix::IDENTITY, clip: None };
+                }
+                "ET" => {
+                    if let Some(clip) = text.clip.take().and_then(PathBuilder::finish) {
+                        state.clip = self.clip_with(&state.clip, Some(&clip), FillRule::Winding, Transform::identity());
+                    }
+                }
+                "Tc" => state.char_spacing = nums.first().copied().unwrap_or(0.0),
+                "Tw" => state.word_spacing = nums.first().copied().unwrap_or(0.0),
+                "Tz" => state.horizontal_scaling = nums.first().copied().unwrap_or(100.0) / 100.0,
+                "TL" => state.leading = nums.first().copied().unwrap_or(0.0),
+                "Ts" => state.rise = nums.first().copied().unwrap_or(0.0),
+                "Tr" => state.render_mode = nums.first().copied().unwrap_or(0.0) as i64,
+                "Tf" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        state.font = Some(self.load_font(resources, name));
+                    }
+                    state.font_size = nums.last().copied().unwrap_or(0.0);
+                }
+                "Td" | "TD" if nums.len() >= 2 => {
+                    if op.operator == "TD" {
+                        state.leading = -nums[1];
+                    }
+                    text.line_matrix = Matrix::translate(nums[0], nums[1]).then(&text.line_matrix);
+                    text.matrix = text.line_matrix;
+                }
+                "Tm" => {
+                    if let Some(m) = Matrix::from_objects(&op.operands) {
+                        text.line_matrix = m;
+                        text.matrix = m;
+                    }
+                }
+                "T*" => {
+                    text.line_matrix = Matrix::translate(0.0, -state.leading).then(&text.line_matrix);
+                    text.matrix = text.line_matrix;
+                }
+                "Tj" | "'" | "\"" | "TJ" => {
+                    if op.operator == "'" || op.operator == "\"" {
+                        if op.operator == "\"" && nums.len() >= 2 {
+                            state.word_spacing = nums[0];
+                            state.char_spacing = nums[1];
+                        }
+                        text.line_matrix = Matrix::translate(0.0, -state.leading).then(&text.line_matrix);
+                        text.matrix = text.line_matrix;
+                    }
+                    let items: Vec<Object> = match op.operands.last() {
+                        Some(Object::Array(items)) if op.operator == "TJ" => items.clone(),
+                        Some(s @ Object::String(..)) => vec![s.clone()],
+                        _ => continue,
+                    };
+                    self.show_text(&items, &state, &mut text, resources, base_ctm, depth);
+                }
+
+                // Type3 字形：d1 表示字形不带颜色，使用当前填充色
+                "d1" => state.lock_color = true,
+                _ => {}
+            }
+        }
+    }
+
+    fn apply_ext_gstate(&mut self, gs: &Dictionary, state: &mut State) {
+        let doc = self.doc;
+        let number = |key: &[u8]| gs.get(key).ok().and_then(|v| object_to_f64(resolve(doc, v)));
+        if let Some(width) = number(b"LW") {
+            state.line_width = width;
+        }
+        if let Some(cap) = number(b"LC") {
+            state.line_cap = line_cap(cap);
+        }
+        if let Some(join) = number(b"LJ") {
+            state.line_join = line_join(join);
+        }
+        if let Some(limit) = number(b"ML") {
+            state.miter_limit = limit;
+        }
+        if let Some(alpha) = number(b"ca") {
+            state.fill_alpha = alpha;
+        }
+        if let Some(alpha) = number(b"CA") {
+            state.stroke_alpha = alpha;
+        }
+        if let Ok(Object::Array(dash)) = gs.get(b"D").map(|d| resolve(doc, d)) {
+            if let [pattern, phase] = dash.as_slice() {
+                state.dash = parse_dash(doc, &[resolve(doc, pattern).clone(), phase.clone()]);
+            }
+        }
+        if let Ok(Object::Array(font)) = gs.get(b"Font").map(|f| resolve(doc, f)) {
+            if let [Object::Reference(id), size] = font.as_slice() {
+                let font = match self.fonts.get(id) {
+                    Some(font) => font.clone(),
+                    None => {
+                        let dict = doc.get_dictionary(*id).cloned().unwrap_or_default();
+                        let font = Rc::new(RenderFont::load(doc, &dict));
+                        self.fonts.insert(*id, font.clone());
+                        font
+                    }
+                };
+                state.font = Some(font);
+                state.font_size = object_to_f64(size).unwrap_or(state.font_size);
+            }
+        }
+    }
+
+    fn load_font(&mut self, resources: Option<&Dictionary>, name: &[u8]) -> Rc<RenderFont> {
+        let entry = resources
+            .and_then(|r| r.get(b"Font").ok())
+            .and_then(|f| resolve(self.doc, f).as_dict().ok())
+            .and_then(|f| f.get(name).ok());
+
+        if let Some(Object::Reference(id)) = entry {
+            if let Some(font) = self.fonts.get(id) {
+                return font.clone();
+            }
+        }
+        let dict = entry.and_then(|f| resolve(self.doc, f).as_dict().ok()).cloned().unwrap_or_default();
+        let font = Rc::new(RenderFont::load(self.doc, &dict));
+        if let Some(Object::Reference(id)) = entry {
+            self.fonts.insert(*id, font.clone());
+        }
+        font
+    }
+
+    /// 在当前裁剪区域内与新路径求交
+    fn clip_with(&self, clip: &Option<Rc<Mask>>, path: Option<&Path>, rule: FillRule, transform: Transform) -> Option<Rc<Mask>> {
+        let mut mask = match clip {
+            Some(mask) => (**mask).clone(),
+            None => {
+                let mut mask = Mask::new(self.pixmap.width(), self.pixmap.height())?;
+                if let Some(path) = path {
+                    mask.fill_path(path, rule, true, transform);
+                }
+                return Some(Rc::new(mask));
+            }
+        };
+        match path {
+            Some(path) => mask.intersect_path(path, rule, true, transform),
+            // 空路径裁剪后没有可绘制区域
+            None => mask.clear(),
+        }
+        Some(Rc::new(mask))
+    }
+
+    fn paint_source(&mut self, color: &PaintColor, alpha: f64, resources: Option<&Dictionary>, base_ctm: Matrix, depth: usize) -> PaintSource {
+        if let Some(name) = &color.pattern {
+            if let Some(source) = self.pattern_source(name, color, alpha, resources, base_ctm, depth) {
+                return source;
+            }
+        }
+        PaintSource::Solid(solid_color(color.rgb, alpha))
+    }
+
+    fn pattern_source(
+        &mut self,
+        name: &[u8],
+        color: &PaintColor,
+        alpha: f64,
+        resources: Option<&Dictionary>,
+        base_ctm: Matrix,
+        depth: usize,
+    ) -> Option<PaintSource> {
+        let pattern_entry = resources
+            .and_then(|r| r.get(b"Pattern").ok())
+            .and_then(|p| resolve(self.doc, p).as_dict().ok())
+            .and_then(|p| p.get(name).ok())?;
+        let pattern = resolve(self.doc, pattern_entry);
+        let dict = match pattern {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &stream.dict,
+            _ => return None,
+        };
+        // 图案矩阵将图案空间映射到内容流的初始坐标空间
+        let matrix = dict.get(b"Matrix").ok()
+            .and_then(|m| resolve(self.doc, m).as_array().ok())
+            .and_then(|m| Matrix::from_objects(m))
+            .unwrap_or(Matrix::IDENTITY)
+            .then(&base_ctm);
+
+        match dict.get(b"PatternType").ok().and_then(|t| t.as_i64().ok())? {
+            2 => {
+                let shading = Shading::parse(self.doc, dict.get(b"Shading").ok()?, resources)?;
+                match shading.shader(to_transform(&matrix), alpha as f32) {
+                    Some(shader) => Some(PaintSource::Shader(shader)),
+                    None => shading.fallback.map(|rgb| PaintSource::Solid(solid_color(rgb, alpha))),
+                }
+            }
+            1 => {
+                let stream = pattern.as_stream().ok()?;
+                let key = format!("{:?}|{:?}|{:?}", pattern_entry, matrix.0, color.rgb);
+                let (tile, transform) = self.render_tile(stream, color, resources, &matrix, &key, depth)?;
+                Some(PaintSource::Tile(tile, transform, alpha as f32))
+            }
+            _ => None,
+        }
+    }
+
+    /// 将平铺图案的一个单元格渲染为位图，返回位图及其到设备空间的变换
+    fn render_tile(
+        &mut self,
+        stream: &Stream,
+        color: &PaintColor,
+        resources: Option<&Dictionary>,
+        matrix: &Matrix,
+        key: &str,
+        depth: usize,
+    ) -> Option<(Rc<Pixmap>, Transform)> {
+        if depth >= MAX_DEPTH {
+            return None;
+        }
+        let doc = self.doc;
+        let dict = &stream.dict;
+        let bbox = dict.get(b"BBox").ok().and_then(|b| resolve(doc, b).as_array().ok()).and_then(|b| parse_rect(b))?;
+        let step = |key: &[u8], default: f64| {
+            dict.get(key).ok().and_then(|v| object_to_f64(resolve(doc, v))).map(f64::abs).filter(|s| *s > 0.0).unwrap_or(default)
+        };
+        let x_step = step(b"XStep", bbox[2] - bbox[0]);
+        let y_step = step(b"YStep", bbox[3] - bbox[1]);
+        if x_step <= 0.0 || y_step <= 0.0 {
+            return None;
+        }
+
+        let [a, b, c, d, _, _] = matrix.0;
+        let tile_width = ((a * a + b * b).sqrt() * x_step).ceil().clamp(1.0, MAX_TILE_SIZE);
+        let tile_height = ((c * c + d * d).sqrt() * y_step).ceil().clamp(1.0, MAX_TILE_SIZE);
+        let (sx, sy) = (tile_width / x_step, tile_height / y_step);
+        let transform = to_transform(&Matrix([1.0 / sx, 0.0, 0.0, 1.0 / sy, bbox[0], bbox[1]]).then(matrix));
+
+        if let Some(tile) = self.tiles.get(key) {
+            return Some((tile.clone(), transform));
+        }
+
+        let pixmap = Pixmap::new(tile_width as u32, tile_height as u32)?;
+        let mut cell = Renderer::new(doc, pixmap, self.options);
+        let mut state = State::new(Matrix([sx, 0.0, 0.0, sy, -bbox[0] * sx, -bbox[1] * sy]));
+        // 无色图案（PaintType 2）使用设置图案时给出的颜色
+        if dict.get(b"PaintType").ok().and_then(|t| t.as_i64().ok()) == Some(2) {
+            let mut solid = PaintColor::new(ColorSpace::Rgb);
+            solid.rgb = color.rgb;
+            state.fill = solid.clone();
+            state.stroke = solid;
+            state.lock_color = true;
+        }
+        state.clip = cell.clip_with(&None, rect_path(&bbox).as_ref(), FillRule::Winding, to_transform(&state.ctm));
+
+        let tile_resources = dict.get(b"Resources").ok().and_then(|r| resolve(doc, r).as_dict().ok());
+        let content = decode_stream(doc, stream).ok()?.data;
+        cell.execute(&parse_content(&content), tile_resources.or(resources), state, depth + 1);
+
+        let tile = Rc::new(cell.pixmap);
+        self.tiles.insert(key.to_string(), tile.clone());
+        Some((tile, transform))
+    }
+
+    fn fill_path(&mut self, path: &Path, rule: FillRule, state: &State, resources: Option<&Dictionary>, base_ctm: Matrix, depth: usize) {
+        let source = self.paint_source(&state.fill, state.fill_alpha, resources, base_ctm, depth);
+        let transform = to_transform(&state.ctm);
+        let pixmap = &mut self.pixmap;
+        with_paint(&source, transform, |paint| pixmap.fill_path(path, paint, rule, transform, state.clip.as_deref()));
+    }
+
+    fn stroke_path(&mut self, path: &Path, state: &State, resources: Option<&Dictionary>, base_ctm: Matrix, depth: usize) {
+        let source = self.paint_source(&state.stroke, state.stroke_alpha, resources, base_ctm, depth);
+        let transform = to_transform(&state.ctm);
+
+        // 设备空间中不足 1 像素的线条按细线绘制
+        let device_width = state.line_width * state.ctm.scale_factor();
+        let mut stroke = Stroke {
+            width: if device_width < 1.0 { 0.0 } else { state.line_width as f32 },
+            miter_limit: state.miter_limit.max(1.0) as f32,
+            line_cap: state.line_cap,
+            line_join: state.line_join,
+            dash: None,
+        };
+        if let Some((array, phase)) = &state.dash {
+            let mut array: Vec<f32> = array.iter().map(|&v| v.max(0.0) as f32).collect();
+            if array.len() % 2 == 1 {
+                array.extend(array.clone());
+            }
+            stroke.dash = StrokeDash::new(array, *phase as f32);
+        }
+
+        let pixmap = &mut self.pixmap;
+        with_paint(&source, transform, |paint| pixmap.stroke_path(path, paint, &stroke, transform, state.clip.as_deref()));
+    }
+
+    /// 在裁剪区域内铺满整个画布（sh 运算符）
+    fn fill_canvas(&mut self, source: &PaintSource, state: &State) {
+        let Some(rect) = SkiaRect::from_xywh(0.0, 0.0, self.pixmap.width() as f32, self.pixmap.height() as f32) else { return };
+        let pixmap = &mut self.pixmap;
+        with_paint(source, Transform::identity(), |paint| pixmap.fill_rect(rect, paint, Transform::identity(), state.clip.as_deref()));
+    }
+
+    fn draw_image(&mut self, stream: &Stream, resources: Option<&Dictionary>, state: &State) {
+        let Some(decoded) = decode_image(self.doc, stream, resources, state.fill.rgb) else { return };
+        let image = match decoded {
+            DecodedImage::Pixmap(image) => image,
+            // 无法解码的图片绘制为灰色占位块
+            DecodedImage::Unsupported => {
+                if let Some(unit) = rect_path(&[0.0, 0.0, 1.0, 1.0]) {
+                    let source = PaintSource::Solid(solid_color([0.8; 3], state.fill_alpha));
+                    let (transform, pixmap) = (to_transform(&state.ctm), &mut self.pixmap);
+                    with_paint(&source, transform, |paint| pixmap.fill_path(&unit, paint, FillRule::Winding, transform, state.clip.as_deref()));
+                }
+                return;
+            }
+        };
+
+        // 图片远大于显示尺寸时先缩小，避免采样失真
+        let [a, b, c, d, _, _] = state.ctm.0;
+        let device_width = (a * a + b * b).sqrt();
+        let device_height = (c * c + d * d).sqrt();
+        let factor = (image.width() as f64 / device_width.max(1.0))
+            .min(image.height() as f64 / device_height.max(1.0))
+            .floor();
+        let image = if factor >= 2.0 { downsample(&image, factor.min(64.0) as u32).unwrap_or(image) } else { image };
+
+        // 图片空间：单位正方形，第一行像素在上方
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        let transform = to_transform(&Matrix([1.0 / width, 0.0, 0.0, -1.0 / height, 0.0, 1.0]).then(&state.ctm));
+        // 放大显示且未要求插值的小图片保持像素边缘清晰
+        let interpolate = stream.dict.get(b"Interpolate").or_else(|_| stream.dict.get(b"I")).and_then(Object::as_bool).unwrap_or(false);
+        let upscaled = device_width > width * 2.0 && device_height > height * 2.0;
+        let paint = PixmapPaint {
+            opacity: state.fill_alpha.clamp(0.0, 1.0) as f32,
+            quality: if upscaled && !interpolate { FilterQuality::Nearest } else { FilterQuality::Bilinear },
+            ..PixmapPaint::default()
+        };
+        self.pixmap.draw_pixmap(0, 0, image.as_ref(), &paint, transform, state.clip.as_deref());
+    }
+
+    fn draw_form(&mut self, stream: &Stream, resources: Option<&Dictionary>, mut state: State, depth: usize) {
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        let doc = self.doc;
+        if let Some(matrix) = stream.dict.get(b"Matrix").ok()
+            .and_then(|m| resolve(doc, m).as_array().ok())
+            .and_then(|m| Matrix::from_objects(m))
+        {
+            state.ctm = matrix.then(&state.ctm);
+        }
+        if let Some(bbox) = stream.dict.get(b"BBox").ok().and_then(|b| resolve(doc, b).as_array().ok()).and_then(|b| parse_rect(b)) {
+            state.clip = self.clip_with(&state.clip, rect_path(&bbox).as_ref(), FillRule::Winding, to_transform(&state.ctm));
+        }
+
+        let form_resources = stream.dict.get(b"Resources").ok().and_then(|r| resolve(doc, r).as_dict().ok());
+        let Ok(content) = decode_stream(doc, stream) else { return };
+        self.execute(&parse_content(&content.data), form_resources.or(resources), state, depth + 1);
+    }
+
+    fn show_text(&mut self, items: &[Object], state: &State, text: &mut TextObject, resources: Option<&Dictionary>, base_ctm: Matrix, depth: usize) {
+        let font = match &state.font {
+            Some(font) => font.clone(),
+            None => Rc::new(RenderFont::load(self.doc, &Dictionary::new())),
+        };
+        let size = state.font_size;
+        let scaling = state.horizontal_scaling;
+        let mode = state.render_mode;
+        let fills = matches!(mode, 0 | 2 | 4 | 6);
+        let strokes = matches!(mode, 1 | 2 | 5 | 6);
+        let clips = mode >= 4;
+        let greek = self.options.greek_text || !font.has_outlines();
+
+        for item in items {
+            let bytes = match item {
+                Object::String(bytes, _) => bytes,
+                other => {
+                    if let Some(adjust) = object_to_f64(other) {
+                        let offset = -adjust / 1000.0 * size;
+                        let shift = if font.vertical { Matrix::translate(0.0, offset) } else { Matrix::translate(offset * scaling, 0.0) };
+                        text.matrix = shift.then(&text.matrix);
+                    }
+                    continue;
+                }
+            };
+
+            for ch in font.decode(bytes) {
+                // 字形空间（1 单位等于字号）到用户空间
+                let origin = if font.vertical { Matrix::translate(-ch.width / 2.0, -0.88) } else { Matrix::IDENTITY };
+                let glyph_matrix = origin
+                    .then(&Matrix([size * scaling, 0.0, 0.0, size, 0.0, state.rise]))
+                    .then(&text.matrix);
+
+                if fills || strokes || clips {
+                    if !greek {
+                        if let Some(type3) = &font.type3 {
+                            if let Some(proc_stream) = font.type3_proc(self.doc, ch.code) {
+                                self.draw_type3_glyph(proc_stream, &type3.matrix, type3.resources.as_ref().or(resources), &glyph_matrix, state, depth);
+                            }
+                        } else if let Some(outline) = font.outline(ch.code) {
+                            if let Some(user_path) = outline.as_ref().clone().transform(to_transform(&glyph_matrix)) {
+                                if fills {
+                                    self.fill_path(&user_path, FillRule::Winding, state, resources, base_ctm, depth);
+                                }
+                                if strokes {
+                                    self.stroke_path(&user_path, state, resources, base_ctm, depth);
+                                }
+                                if clips {
+                                    if let Some(device_path) = user_path.transform(to_transform(&state.ctm)) {
+                                        text.clip.get_or_insert_with(PathBuilder::new).push_path(&device_path);
+                                    }
+                                }
+                            }
+                        }
+                    } else if (fills || strokes) && !ch.is_space && ch.width > 0.0 {
+                        self.draw_greeked_glyph(ch.width, &glyph_matrix, state);
+                    }
+                }
+
+                let mut advance = ch.width * size + state.char_spacing;
+                if ch.is_space {
+                    advance += state.word_spacing;
+                }
+                let shift = if font.vertical {
+                    Matrix::translate(0.0, -size + state.char_spacing + if ch.is_space { state.word_spacing } else { 0.0 })
+                } else {
+                    Matrix::translate(advance * scaling, 0.0)
+                };
+                text.matrix = shift.then(&text.matrix);
+            }
+        }
+    }
+
+    fn draw_type3_glyph(
+        &mut self,
+        stream: &Stream,
+        font_matrix: &Matrix,
+        resources: Option<&Dictionary>,
+        glyph_matrix: &Matrix,
+        state: &State,
+        depth: usize,
+    ) {
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        let Ok(content) = decode_stream(self.doc, stream) else { return };
+        let mut glyph_state = state.clone();
+        glyph_state.ctm = font_matrix.then(glyph_matrix).then(&state.ctm);
+        self.execute(&parse_content(&content.data), resources, glyph_state, depth + 1);
+    }
+
+    /// 无法取得字形轮廓时，用浅色矩形表示文字位置
+    fn draw_greeked_glyph(&mut self, width: f64, glyph_matrix: &Matrix, state: &State) {
+        let Some(block) = rect_path(&[width * 0.05, 0.0, width * 0.95, 0.5]) else { return };
+        let source = PaintSource::Solid(solid_color(state.fill.rgb, state.fill_alpha * 0.35));
+        let transform = to_transform(&glyph_matrix.then(&state.ctm));
+        let pixmap = &mut self.pixmap;
+        with_paint(&source, transform, |paint| pixmap.fill_path(&block, paint, FillRule::Winding, transform, state.clip.as_deref()));
+    }
+
+    /// 绘制注释的外观流（/AP /N），跳过隐藏和弹出注释
+    fn draw_annotations(&mut self, page_id: ObjectId, base: Matrix) {
+        let doc = self.doc;
+        let Some(annots) = doc.get_dictionary(page_id).ok()
+            .and_then(|p| p.get(b"Annots").ok())
+            .and_then(|a| resolve(doc, a).as_array().ok()) else { return };
+
+        for annot in annots {
+            let Ok(annot) = resolve(doc, annot).as_dict() else { continue };
+            if annot.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Popup".as_slice()) {
+                continue;
+            }
+            // Hidden（位 2）和 NoView（位 6）
+            let flags = annot.get(b"F").ok().and_then(|f| f.as_i64().ok()).unwrap_or(0);
+            if flags & (2 | 32) != 0 {
+                continue;
+            }
+
+            let Some(normal) = annot.get(b"AP").ok()
+                .and_then(|ap| resolve(doc, ap).as_dict().ok())
+                .and_then(|ap| ap.get(b"N").ok())
+                .map(|n| resolve(doc, n)) else { continue };
+            let appearance = match normal {
+                Object::Stream(stream) => stream,
+                Object::Dictionary(states) => {
+                    let Some(state) = annot.get(b"AS").and_then(Object::as_name).ok() else { continue };
+                    let Some(Object::Stream(stream)) = states.get(state).ok().map(|s| resolve(doc, s)) else { continue };
+                    stream
+                }
+                _ => continue,
+            };
+
+            let Some(rect) = annot.get(b"Rect").ok().and_then(|r| resolve(doc, r).as_array().ok()).and_then(|r| parse_rect(r)) else { continue };
+            let Some(bbox) = appearance.dict.get(b"BBox").ok().and_then(|b| resolve(doc, b).as_array().ok()).and_then(|b| parse_rect(b)) else { continue };
+            let matrix = appearance.dict.get(b"Matrix").ok()
+                .and_then(|m| resolve(doc, m).as_array().ok())
+                .and_then(|m| Matrix::from_objects(m))
+                .unwrap_or(Matrix::IDENTITY);
+
+            // 外观流变换后的边框映射到注释矩形
+            let bounds = matrix.apply_rect(&bbox);
+            let (bw, bh) = (bounds[2] - bounds[0], bounds[3] - bounds[1]);
+            if bw <= 0.0 || bh <= 0.0 {
+                continue;
+            }
+            let (sx, sy) = ((rect[2] - rect[0]) / bw, (rect[3] - rect[1]) / bh);
+            let fit = Matrix([sx, 0.0, 0.0, sy, rect[0] - bounds[0] * sx, rect[1] - bounds[1] * sy]);
+            self.draw_form(appearance, None, State::new(fit.then(&base)), 0);
+        }
+    }
+}
+
+fn line_cap(value: f64) -> LineCap {
+    match value as i64 {
+        1 => LineCap::Round,
+        2 => LineCap::Square,
+        _ => LineCap::Butt,
+    }
+}
+
+fn line_join(value: f64) -> LineJoin {
+    match value as i64 {
+        1 => LineJoin::Round,
+        2 => LineJoin::Bevel,
+        _ => LineJoin::Miter,
+    }
+}
+
+/// 解析 d 运算符的操作数 [数组 相位]，空数组表示实线
+fn parse_dash(doc: &Document, operands: &[Object]) -> Option<(Vec<f64>, f64)> {
+    let array: Vec<f64> = resolve(doc, operands.first()?).as_array().ok()?
+        .iter()
+        .filter_map(|v| object_to_f64(resolve(doc, v)))
+        .collect();
+    if array.is_empty() || array.iter().all(|&v| v <= 0.0) {
+        return None;
+    }
+    let phase = operands.get(1).and_then(object_to_f64).unwrap_or(0.0);
+    Some((array, phase))
+}
+
+/// 按整数倍缩小图片（预乘颜色取平均）
+fn downsample(image: &Pixmap, factor: u32) -> Option<Pixmap> {
+    let width = (image.width() / factor).max(1);
+    let height = (image.height() / factor).max(1);
+    let mut output = Pixmap::new(width, height)?;
+    let source = image.pixels();
+    let source_width = image.width() as usize;
+
+    for (index, pixel) in output.pixels_mut().iter_mut().enumerate() {
+        let (x, y) = (index as u32 % width, index as u32 / width);
+        let mut sum = [0u32; 4];
+        let mut count = 0;
+        for sy in y * factor..((y + 1) * factor).min(image.height()) {
+            for sx in x * factor..((x + 1) * factor).min(image.width()) {
+                let p = source[sy as usize * source_width + sx as usize];
+                for (total, value) in sum.iter_mut().zip([p.red(), p.green(), p.blue(), p.alpha()]) {
+                    *total += value as u32;
+                }
+                count += 1;
+            }
+        }
+        let [r, g, b, a] = sum.map(|total| (total / count.max(1)) as u8);
+        if let Some(color) = tiny_skia::PremultipliedColorU8::from_rgba(r, g, b, a) {
+            *pixel = color;
+        }
+    }
+    Some(output)
+}
@@ -0,0 +1,940 @@
+// 渲染用的颜色空间、函数、图片解码与渐变
+// 颜色统一转换为 sRGB 近似值，不做 ICC 色彩管理
+
+use lopdf::{Dictionary, Document, Object, Stream};
+use tiny_skia::{Color, GradientStop, LinearGradient, Pixmap, Point, RadialGradient, Shader, SpreadMode, Transform};
+
+use crate::filters::{decode_stream, ImageCodec};
+use crate::pdf_utils::object_to_f64;
+
+/// 解引用对象，失败时返回原对象
+pub(crate) fn resolve<'a>(doc: &'a Document, obj: &'a Object) -> &'a Object {
+    doc.dereference(obj).map(|(_, o)| o).unwrap_or(obj)
+}
+
+fn number_array(doc: &Document, obj: Option<&Object>) -> Option<Vec<f64>> {
+    let array = resolve(doc, obj?).as_array().ok()?;
+    array.iter().map(|v| object_to_f64(resolve(doc, v))).collect()
+}
+
+// ---------------------------------------------------------------------------
+// 颜色空间
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug)]
+pub(crate) enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+    Lab { white_point: [f64; 3], range: [f64; 4] },
+    Indexed { base: Box<ColorSpace>, hival: usize, lookup: Vec<u8> },
+    /// Separation 和 DeviceN：通过色调转换函数映射到替代颜色空间
+    Tint { components: usize, alternate: Box<ColorSpace>, function: Option<Function> },
+    Pattern { base: Option<Box<ColorSpace>> },
+}
+
+impl ColorSpace {
+    /// 解析颜色空间，名称会先在资源字典的 /ColorSpace 中查找
+    pub(crate) fn parse(doc: &Document, obj: &Object, resources: Option<&Dictionary>) -> ColorSpace {
+        Self::parse_depth(doc, obj, resources, 0).unwrap_or(ColorSpace::Gray)
+    }
+
+    fn parse_depth(doc: &Document, obj: &Object, resources: Option<&Dictionary>, depth: usize) -> Option<ColorSpace> {
+        if depth > 8 {
+            return None;
+        }
+        match resolve(doc, obj) {
+            Object::Name(name) => match name.as_slice() {
+                b"DeviceGray" | b"G" | b"CalGray" => Some(ColorSpace::Gray),
+                b"DeviceRGB" | b"RGB" | b"CalRGB" => Some(ColorSpace::Rgb),
+                b"DeviceCMYK" | b"CMYK" => Some(ColorSpace::Cmyk),
+                b"Pattern" => Some(ColorSpace::Pattern { base: None }),
+                _ => {
+                    let named = resources
+                        .and_then(|r| r.get(b"ColorSpace").ok())
+                        .and_then(|cs| resolve(doc, cs).as_dict().ok())
+                        .and_then(|cs| cs.get(name).ok())?;
+                    Self::parse_depth(doc, named, None, depth + 1)
+                }
+            },
+            Object::Array(items) => {
+                let family = resolve(doc, items.first()?).as_name().ok()?;
+                let arg = |i: usize| items.get(i).map(|o| resolve(doc, o));
+                match family {
+                    b"DeviceGray" | b"G" | b"CalGray" => Some(ColorSpace::Gray),
+                    b"DeviceRGB" | b"RGB" | b"CalRGB" => Some(ColorSpace::Rgb),
+                    b"DeviceCMYK" | b"CMYK" => Some(ColorSpace::Cmyk),
+                    b"ICCBased" => {
+                        let dict = &arg(1)?.as_stream().ok()?.dict;
+                        if let Ok(alternate) = dict.get(b"Alternate") {
+                            if let Some(cs) = Self::parse_depth(doc, alternate, resources, depth + 1) {
+                                return Some(cs);
+                            }
+                        }
+                        match dict.get(b"N").ok().and_then(|n| n.as_i64().ok()) {
+                            Some(1) => Some(ColorSpace::Gray),
+                            Some(4) => Some(ColorSpace::Cmyk),
+                            _ => Some(ColorSpace::Rgb),
+                        }
+                    }
+                    b"Lab" => {
+                        let dict = arg(1).and_then(|d| d.as_dict().ok());
+                        let white_point = number_array(doc, dict.and_then(|d| d.get(b"WhitePoint").ok()))
+                            .filter(|w| w.len() == 3)
+                            .map(|w| [w[0], w[1], w[2]])
+                            .unwrap_or([0.9505, 1.0, 1.089]);
+                        let range = number_array(doc, dict.and_then(|d| d.get(b"Range").ok()))
+                            .filter(|r| r.len() == 4)
+                            .map(|r| [r[0], r[1], r[2], r[3]])
+                            .unwrap_or([-100.0, 100.0, -100.0, 100.0]);
+                        Some(ColorSpace::Lab { white_point, range })
+                    }
+                    b"Indexed" | b"I" => {
+                        let base = Self::parse_depth(doc, items.get(1)?, resources, depth + 1)?;
+                        let hival = arg(2).and_then(object_to_f64).unwrap_or(0.0).clamp(0.0, 255.0) as usize;
+                        let lookup = match arg(3)? {
+                            Object::String(bytes, _) => bytes.clone(),
+                            Object::Stream(stream) => decode_stream(doc, stream).ok()?.data,
+                            _ => return None,
+                        };
+                        Some(ColorSpace::Indexed { base: Box::new(base), hival, lookup })
+                    }
+                    b"Separation" | b"DeviceN" => {
+                        let components = if family == b"Separation" {
+                            1
+                        } else {
+                            arg(1)?.as_array().ok()?.len()
+                        };
+                        let alternate = Self::parse_depth(doc, items.get(2)?, resources, depth + 1)?;
+                        let function = items.get(3).and_then(|f| Function::parse(doc, f));
+                        Some(ColorSpace::Tint { components, alternate: Box::new(alternate), function })
+                    }
+                    b"Pattern" => {
+                        let base = items.get(1).and_then(|b| Self::parse_depth(doc, b, resources, depth + 1));
+                        Some(ColorSpace::Pattern { base: base.map(Box::new) })
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn components(&self) -> usize {
+        match self {
+            ColorSpace::Gray | ColorSpace::Indexed { .. } => 1,
+            ColorSpace::Rgb | ColorSpace::Lab { .. } => 3,
+            ColorSpace::Cmyk => 4,
+            ColorSpace::Tint { components, .. } => *components,
+            ColorSpace::Pattern { base } => base.as_ref().map(|b| b.components()).unwrap_or(0),
+        }
+    }
+
+    /// 设置颜色空间后的初始颜色
+    pub(crate) fn initial_color(&self) -> Vec<f64> {
+        match self {
+            ColorSpace::Cmyk => vec![0.0, 0.0, 0.0, 1.0],
+            ColorSpace::Lab { .. } => vec![0.0, 0.0, 0.0],
+            ColorSpace::Tint { components, .. } => vec![1.0; *components],
+            other => vec![0.0; other.components()],
+        }
+    }
+
+    /// 图片的默认 Decode 数组
+    pub(crate) fn default_decode(&self, bits: u32) -> Vec<(f64, f64)> {
+        match self {
+            ColorSpace::Indexed { .. } => vec![(0.0, ((1u32 << bits) - 1) as f64)],
+            ColorSpace::Lab { range, .. } => vec![(0.0, 100.0), (range[0], range[1]), (range[2], range[3])],
+            other => vec![(0.0, 1.0); other.components()],
+        }
+    }
+
+    /// 转换为 0-1 范围的 RGB
+    pub(crate) fn to_rgb(&self, values: &[f64]) -> [f64; 3] {
+        let v = |i: usize| values.get(i).copied().unwrap_or(0.0).clamp(0.0, 1.0);
+        match self {
+            ColorSpace::Gray => [v(0); 3],
+            ColorSpace::Rgb => [v(0), v(1), v(2)],
+            ColorSpace::Cmyk => cmyk_to_rgb(v(0), v(1), v(2), v(3)),
+            ColorSpace::Lab { white_point, .. } => {
+                let get = |i: usize| values.get(i).copied().unwrap_or(0.0);
+                lab_to_rgb(get(0), get(1), get(2), white_point)
+            }
+            ColorSpace::Indexed { base, hival, lookup } => {
+                let index = values.first().copied().unwrap_or(0.0).round().clamp(0.0, *hival as f64) as usize;
+                let n = base.components();
+                let entry: Vec<f64> = (0..n)
+                    .map(|c| lookup.get(index * n + c).copied().unwrap_or(0) as f64 / 255.0)
+                    .collect();
+                // Lab 的查找表按 Range 缩放
+                let entry = match base.as_ref() {
+                    ColorSpace::Lab { range, .. } => vec![
+                        entry[0] * 100.0,
+                        range[0] + entry[1] * (range[1] - range[0]),
+                        range[2] + entry[2] * (range[3] - range[2]),
+                    ],
+                    _ => entry,
+                };
+                base.to_rgb(&entry)
+            }
+            ColorSpace::Tint { alternate, function, components } => match function {
+                Some(function) => alternate.to_rgb(&function.eval(&values[..values.len().min(*components)])),
+                // 无法计算色调时按灰度近似
+                None => [1.0 - v(0); 3],
+            },
+            ColorSpace::Pattern { base } => base.as_ref().map(|b| b.to_rgb(values)).unwrap_or([0.5; 3]),
+        }
+    }
+}
+
+fn cmyk_to_rgb(c: f64, m: f64, y: f64, k: f64) -> [f64; 3] {
+    [(1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)]
+}
+
+fn lab_to_rgb(l: f64, a: f64, b: f64, white_point: &[f64; 3]) -> [f64; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+    let g = |t: f64| if t > 6.0 / 29.0 { t * t * t } else { 108.0 / 841.0 * (t - 4.0 / 29.0) };
+    let (x, y, z) = (white_point[0] * g(fx), white_point[1] * g(fy), white_point[2] * g(fz));
+
+    let linear = [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ];
+    linear.map(|c| {
+        let c = c.clamp(0.0, 1.0);
+        if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// PDF 函数
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug)]
+pub(crate) enum Function {
+    /// 类型 0：采样函数
+    Sampled {
+        domain: Vec<f64>,
+        range: Vec<f64>,
+        size: Vec<usize>,
+        encode: Vec<f64>,
+        decode: Vec<f64>,
+        /// 归一化到 0-1 的采样值
+        samples: Vec<f64>,
+    },
+    /// 类型 2：指数插值
+    Exponential { domain: Vec<f64>, c0: Vec<f64>, c1: Vec<f64>, n: f64 },
+    /// 类型 3：拼接函数
+    Stitching { domain: Vec<f64>, functions: Vec<Function>, bounds: Vec<f64>, encode: Vec<f64> },
+    /// 类型 4：PostScript 计算函数
+    PostScript { domain: Vec<f64>, range: Vec<f64>, program: Vec<PsOp> },
+    /// 多个单输出函数组成的数组
+    Array(Vec<Function>),
+}
+
+impl Function {
+    pub(crate) fn parse(doc: &Document, obj: &Object) -> Option<Function> {
+        Self::parse_depth(doc, obj, 0)
+    }
+
+    fn parse_depth(doc: &Document, obj: &Object, depth: usize) -> Option<Function> {
+        if depth > 8 {
+            return None;
+        }
+        let obj = resolve(doc, obj);
+        if let Object::Array(items) = obj {
+            let functions: Option<Vec<Function>> = items.iter().map(|f| Self::parse_depth(doc, f, depth + 1)).collect();
+            return functions.map(Function::Array);
+        }
+
+        let (dict, stream) = match obj {
+            Object::Dictionary(dict) => (dict, None),
+            Object::Stream(stream) => (&stream.dict, Some(stream)),
+            _ => return None,
+        };
+        let numbers = |key: &[u8]| number_array(doc, dict.get(key).ok());
+        let domain = numbers(b"Domain").unwrap_or_else(|| vec![0.0, 1.0]);
+
+        match dict.get(b"FunctionType").ok().and_then(|t| t.as_i64().ok())? {
+            0 => {
+                let stream = stream?;
+                let size: Vec<usize> = numbers(b"Size")?.iter().map(|s| *s as usize).collect();
+                let range = numbers(b"Range")?;
+                let bits = dict.get(b"BitsPerSample").ok().and_then(|b| b.as_i64().ok())? as u32;
+                if !matches!(bits, 1 | 2 | 4 | 8 | 12 | 16 | 24 | 32) || size.contains(&0) {
+                    return None;
+                }
+                let encode = numbers(b"Encode")
+                    .unwrap_or_else(|| size.iter().flat_map(|&s| [0.0, (s - 1) as f64]).collect());
+                let decode = numbers(b"Decode").unwrap_or_else(|| range.clone());
+                let data = decode_stream(doc, stream).ok()?.data;
+                let outputs = range.len() / 2;
+                let count = size.iter().product::<usize>().checked_mul(outputs)?;
+                let max = ((1u64 << bits) - 1) as f64;
+                let mut reader = BitReader::new(&data);
+                let samples = (0..count)
+                    .map(|_| reader.read(bits).unwrap_or(0) as f64 / max)
+                    .collect();
+                Some(Function::Sampled { domain, range, size, encode, decode, samples })
+            }
+            2 => Some(Function::Exponential {
+                domain,
+                c0: numbers(b"C0").unwrap_or_else(|| vec![0.0]),
+                c1: numbers(b"C1").unwrap_or_else(|| vec![1.0]),
+                n: dict.get(b"N").ok().and_then(object_to_f64).unwrap_or(1.0),
+            }),
+            3 => {
+                let functions = resolve(doc, dict.get(b"Functions").ok()?).as_array().ok()?
+                    .iter()
+                    .map(|f| Self::parse_depth(doc, f, depth + 1))
+                    .collect::<Option<Vec<_>>>()?;
+                let bounds = numbers(b"Bounds").unwrap_or_default();
+                let encode = numbers(b"Encode")
+                    .unwrap_or_else(|| functions.iter().flat_map(|_| [0.0, 1.0]).collect());
+                Some(Function::Stitching { domain, functions, bounds, encode })
+            }
+            4 => {
+                let data = decode_stream(doc, stream?).ok()?.data;
+                let program = parse_postscript(&data)?;
+                Some(Function::PostScript { domain, range: numbers(b"Range")?, program })
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn eval(&self, input: &[f64]) -> Vec<f64> {
+        let clip_input = |domain: &[f64], i: usize| {
+            let x = input.get(i).copied().unwrap_or(0.0);
+            match (domain.get(2 * i), domain.get(2 * i + 1)) {
+                (Some(&lo), Some(&hi)) => x.clamp(lo.min(hi), hi.max(lo)),
+                _ => x,
+            }
+        };
+
+        match self {
+            Function::Sampled { domain, range, size, encode, decode, samples } => {
+                let outputs = range.len() / 2;
+                // 第一维线性插值，其余维取最近的采样点
+                let mut index = 0;
+                let mut stride = outputs;
+                let mut first = (0, 0, 0.0);
+                for (dim, &dim_size) in size.iter().enumerate() {
+                    let x = clip_input(domain, dim);
+                    let (d0, d1) = (domain[2 * dim], domain[2 * dim + 1]);
+                    let (e0, e1) = (encode[2 * dim], encode[2 * dim + 1]);
+                    let e = interpolate(x, d0, d1, e0, e1).clamp(0.0, (dim_size - 1) as f64);
+                    if dim == 0 {
+                        let lo = e.floor() as usize;
+                        let hi = (lo + 1).min(dim_size - 1);
+                        first = (lo, hi, e - lo as f64);
+                    } else {
+                        index += e.round() as usize * stride;
+                    }
+                    stride *= dim_size;
+                }
+                (0..outputs)
+                    .map(|o| {
+                        let (lo, hi, t) = first;
+                        let a = samples.get(index + lo * outputs + o).copied().unwrap_or(0.0);
+                        let b = samples.get(index + hi * outputs + o).copied().unwrap_or(0.0);
+                        let value = a + (b - a) * t;
+                        let out = decode[2 * o] + value * (decode[2 * o + 1] - decode[2 * o]);
+                        out.clamp(range[2 * o].min(range[2 * o + 1]), range[2 * o].max(range[2 * o + 1]))
+                    })
+                    .collect()
+            }
+            Function::Exponential { domain, c0, c1, n } => {
+                let x = clip_input(domain, 0);
+                let t = x.powf(*n);
+                c0.iter().zip(c1).map(|(a, b)| a + t * (b - a)).collect()
+            }
+            Function::Stitching { domain, functions, bounds, encode } => {
+                let x = clip_input(domain, 0);
+                let k = bounds.iter().take_while(|&&b| x >= b).count().min(functions.len().saturating_sub(1));
+                let lo = if k == 0 { domain[0] } else { bounds[k - 1] };
+                let hi = bounds.get(k).copied().unwrap_or(domain[1]);
+                let (e0, e1) = (encode.get(2 * k).copied().unwrap_or(0.0), encode.get(2 * k + 1).copied().unwrap_or(1.0));
+                let t = if hi > lo { interpolate(x, lo, hi, e0, e1) } else { e0 };
+                functions.get(k).map(|f| f.eval(&[t])).unwrap_or_default()
+            }
+            Function::PostScript { domain, range, program } => {
+                let mut stack: Vec<f64> = (0..domain.len() / 2).map(|i| clip_input(domain, i)).collect();
+                run_postscript(program, &mut stack, 0);
+                let outputs = range.len() / 2;
+                let start = stack.len().saturating_sub(outputs);
+                (0..outputs)
+                    .map(|o| {
+                        let v = stack.get(start + o).copied().unwrap_or(0.0);
+                        v.clamp(range[2 * o].min(range[2 * o + 1]), range[2 * o].max(range[2 * o + 1]))
+                    })
+                    .collect()
+            }
+            Function::Array(functions) => functions.iter().flat_map(|f| f.eval(input)).collect(),
+        }
+    }
+}
+
+fn interpolate(x: f64, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
+    if x1 == x0 {
+        y0
+    } else {
+        y0 + (x - x0) * (y1 - y0) / (x1 - x0)
+    }
+}
+
+/// PostScript 计算函数的指令
+#[derive(Clone, Debug)]
+pub(crate) enum PsOp {
+    Number(f64),
+    Operator(String),
+    If(Vec<PsOp>),
+    IfElse(Vec<PsOp>, Vec<PsOp>),
+}
+
+fn parse_postscript(data: &[u8]) -> Option<Vec<PsOp>> {
+    let text = String::from_utf8_lossy(data);
+    let spaced = text.replace('{', " { ").replace('}', " } ");
+    let mut tokens = spaced.split_whitespace();
+    if tokens.next()? != "{" {
+        return None;
+    }
+    parse_ps_block(&mut tokens, 0)
+}
+
+fn parse_ps_block<'a>(tokens: &mut impl Iterator<Item = &'a str>, depth: usize) -> Option<Vec<PsOp>> {
+    if depth > 32 {
+        return None;
+    }
+    let mut ops = Vec::new();
+    let mut pending: Vec<Vec<PsOp>> = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "{" => pending.push(parse_ps_block(tokens, depth + 1)?),
+            "}" => return Some(ops),
+            "if" => ops.push(PsOp::If(pending.pop()?)),
+            "ifelse" => {
+                let otherwise = pending.pop()?;
+                ops.push(PsOp::IfElse(pending.pop()?, otherwise));
+            }
+            _ => ops.push(match token.parse::<f64>() {
+                Ok(n) => PsOp::Number(n),
+                Err(_) => PsOp::Operator(token.to_string()),
+            }),
+        }
+    }
+    Some(ops)
+}
+
+fn run_postscript(program: &[PsOp], stack: &mut Vec<f64>, depth: usize) {
+    if depth > 32 {
+        return;
+    }
+    for op in program {
+        if stack.len() > 1000 {
+            return;
+        }
+        match op {
+            PsOp::Number(n) => stack.push(*n),
+            PsOp::If(body) => {
+                if stack.pop().unwrap_or(0.0) != 0.0 {
+                    run_postscript(body, stack, depth + 1);
+                }
+            }
+            PsOp::IfElse(then, otherwise) => {
+                let branch = if stack.pop().unwrap_or(0.0) != 0.0 { then } else { otherwise };
+                run_postscript(branch, stack, depth + 1);
+            }
+            PsOp::Operator(name) => run_ps_operator(name, stack),
+        }
+    }
+}
+
+fn run_ps_operator(name: &str, stack: &mut Vec<f64>) {
+    let bool_value = |b: bool| if b { 1.0 } else { 0.0 };
+    let pop = |stack: &mut Vec<f64>| stack.pop().unwrap_or(0.0);
+
+    match name {
+        "true" => stack.push(1.0),
+        "false" => stack.push(0.0),
+        "pop" => {
+            stack.pop();
+        }
+        "dup" => {
+            let v = stack.last().copied().unwrap_or(0.0);
+            stack.push(v);
+        }
+        "exch" => {
+            let (b, a) = (pop(stack), pop(stack));
+            stack.extend([b, a]);
+        }
+        "copy" => {
+            let n = pop(stack).max(0.0) as usize;
+            let start = stack.len().saturating_sub(n);
+            let copied: Vec<f64> = stack[start..].to_vec();
+            stack.extend(copied);
+        }
+        "index" => {
+            let n = pop(stack).max(0.0) as usize;
+            let v = stack.len().checked_sub(n + 1).map(|i| stack[i]).unwrap_or(0.0);
+            stack.push(v);
+        }
+        "roll" => {
+            let j = pop(stack) as i64;
+            let n = pop(stack).max(0.0) as usize;
+            if n > 0 && n <= stack.len() {
+                let start = stack.len() - n;
+                let shift = j.rem_euclid(n as i64) as usize;
+                stack[start..].rotate_right(shift);
+            }
+        }
+        "neg" | "abs" | "ceiling" | "floor" | "round" | "truncate" | "sqrt" | "sin" | "cos" | "exp" | "ln" | "log"
+        | "cvi" | "cvr" | "not" => {
+            let a = pop(stack);
+            stack.push(match name {
+                "neg" => -a,
+                "abs" => a.abs(),
+                "ceiling" => a.ceil(),
+                "floor" => a.floor(),
+                "round" => (a + 0.5).floor(),
+                "truncate" | "cvi" => a.trunc(),
+                "sqrt" => a.max(0.0).sqrt(),
+                "sin" => a.to_radians().sin(),
+                "cos" => a.to_radians().cos(),
+                "exp" => a.exp(),
+                "ln" => a.ln(),
+                "log" => a.log10(),
+                "not" => bool_value(a == 0.0),
+                _ => a,
+            });
+        }
+        _ => {
+            let b = pop(stack);
+            let a = pop(stack);
+            stack.push(match name {
+                "add" => a + b,
+                "sub" => a - b,
+                "mul" => a * b,
+                "div" => if b == 0.0 { 0.0 } else { a / b },
+                "idiv" => if b as i64 == 0 { 0.0 } else { (a as i64 / b as i64) as f64 },
+                "mod" => if b as i64 == 0 { 0.0 } else { (a as i64 % b as i64) as f64 },
+                "exp" => a.powf(b),
+                "atan" => {
+                    let angle = a.atan2(b).to_degrees();
+                    if angle < 0.0 { angle + 360.0 } else { angle }
+                }
+                "eq" => bool_value(a == b),
+                "ne" => bool_value(a != b),
+                "gt" => bool_value(a > b),
+                "ge" => bool_value(a >= b),
+                "lt" => bool_value(a < b),
+                "le" => bool_value(a <= b),
+                "and" => ((a as i64) & (b as i64)) as f64,
+                "or" => ((a as i64) | (b as i64)) as f64,
+                "xor" => ((a as i64) ^ (b as i64)) as f64,
+                "bitshift" => {
+                    let (value, shift) = (a as i64, b as i64);
+                    if shift >= 0 { (value << shift.min(63)) as f64 } else { (value >> (-shift).min(63)) as f64 }
+                }
+                _ => {
+                    // 未知运算符：恢复栈
+                    stack.extend([a, b]);
+                    return;
+                }
+            });
+        }
+    }
+}
+
+/// 按位读取采样数据
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    bit_pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        BitReader { data, bit_pos: 0 }
+    }
+
+    pub(crate) fn read(&mut self, bits: u32) -> Option<u32> {
+        if bits == 8 && self.bit_pos.is_multiple_of(8) {
+            let v = *self.data.get(self.bit_pos / 8)?;
+            self.bit_pos += 8;
+            return Some(v as u32);
+        }
+        let mut value = 0u32;
+        for _ in 0..bits {
+            let byte = *self.data.get(self.bit_pos / 8)?;
+            let bit = (byte >> (7 - self.bit_pos % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.bit_pos += 1;
+        }
+        Some(value)
+    }
+
+    /// 跳到下一个字节边界（图片每行按字节对齐）
+    pub(crate) fn align(&mut self) {
+        self.bit_pos = self.bit_pos.div_ceil(8) * 8;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 图片解码
+// ---------------------------------------------------------------------------
+
+/// 单张图片允许的最大像素数
+const MAX_IMAGE_PIXELS: usize = 64 * 1024 * 1024;
+
+/// 将内联图片的缩写键和缩写值展开为完整名称
+pub(crate) fn expand_inline_image_dict(dict: &Dictionary) -> Dictionary {
+    let mut expanded = Dictionary::new();
+    for (key, value) in dict.iter() {
+        let key: &[u8] = match key.as_slice() {
+            b"BPC" => b"BitsPerComponent",
+            b"CS" => b"ColorSpace",
+            b"D" => b"Decode",
+            b"DP" => b"DecodeParms",
+            b"F" => b"Filter",
+            b"H" => b"Height",
+            b"IM" => b"ImageMask",
+            b"I" => b"Interpolate",
+            b"W" => b"Width",
+            other => other,
+        };
+        let expand_name = |name: &[u8]| -> Vec<u8> {
+            match name {
+                b"G" => b"DeviceGray".to_vec(),
+                b"RGB" => b"DeviceRGB".to_vec(),
+                b"CMYK" => b"DeviceCMYK".to_vec(),
+                b"I" => b"Indexed".to_vec(),
+                b"AHx" => b"ASCIIHexDecode".to_vec(),
+                b"A85" => b"ASCII85Decode".to_vec(),
+                b"LZW" => b"LZWDecode".to_vec(),
+                b"Fl" => b"FlateDecode".to_vec(),
+                b"RL" => b"RunLengthDecode".to_vec(),
+                b"CCF" => b"CCITTFaxDecode".to_vec(),
+                b"DCT" => b"DCTDecode".to_vec(),
+                other => other.to_vec(),
+            }
+        };
+        let value = match value {
+            Object::Name(name) => Object::Name(expand_name(name)),
+            Object::Array(items) if key == b"Filter" || key == b"ColorSpace" => Object::Array(
+                items
+                    .iter()
+                    .map(|item| match item {
+                        Object::Name(name) => Object::Name(expand_name(name)),
+                        other => other.clone(),
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        };
+        expanded.set(key.to_vec(), value);
+    }
+    expanded
+}
+
+/// 解码后的图片
+pub(crate) enum DecodedImage {
+    Pixmap(Pixmap),
+    /// 不支持的编码（JPX、JBIG2 等），按尺寸绘制占位色块
+    Unsupported,
+}
+
+/// 解码图片 XObject 为预乘 RGBA 像素
+/// fill_color 用于模板蒙版（ImageMask）图片
+pub(crate) fn decode_image(doc: &Document, stream: &Stream, resources: Option<&Dictionary>, fill_color: [f64; 3]) -> Option<DecodedImage> {
+    let dict = &stream.dict;
+    let get = |key: &[u8]| dict.get(key).ok().map(|v| resolve(doc, v));
+    let width = get(b"Width").and_then(object_to_f64)? as usize;
+    let height = get(b"Height").and_then(object_to_f64)? as usize;
+    if width == 0 || height == 0 || width.checked_mul(height)? > MAX_IMAGE_PIXELS {
+        return None;
+    }
+    let image_mask = get(b"ImageMask").and_then(|v| v.as_bool().ok()).unwrap_or(false);
+    let decode = number_array(doc, dict.get(b"Decode").ok());
+
+    let decoded = decode_stream(doc, stream).ok()?;
+    let (data, bits, color_space) = match decoded.codec {
+        None => {
+            let bits = if image_mask { 1 } else { get(b"BitsPerComponent").and_then(object_to_f64).unwrap_or(8.0) as u32 };
+            let color_space = match get(b"ColorSpace") {
+                Some(cs) if !image_mask => ColorSpace::parse(doc, cs, resources),
+                _ => ColorSpace::Gray,
+            };
+            (decoded.data, bits, color_space)
+        }
+        Some(ImageCodec::Dct) => {
+            let img = image::load_from_memory_with_format(&decoded.data, image::ImageFormat::Jpeg).ok()?;
+            let mut pixmap = Pixmap::new(img.width(), img.height())?;
+            let invert = decode.as_ref().is_some_and(|d| d.first() == Some(&1.0));
+            for (pixel, rgb) in pixmap.pixels_mut().iter_mut().zip(img.to_rgb8().pixels()) {
+                let [r, g, b] = if invert { rgb.0.map(|c| 255 - c) } else { rgb.0 };
+                *pixel = tiny_skia::PremultipliedColorU8::from_rgba(r, g, b, 255)?;
+            }
+            apply_soft_mask(doc, dict, resources, &mut pixmap);
+            return Some(DecodedImage::Pixmap(pixmap));
+        }
+        Some(ImageCodec::CcittFax(params)) => (decode_ccitt(&decoded.data, &params, width, height)?, 1, ColorSpace::Gray),
+        Some(ImageCodec::Jpx) | Some(ImageCodec::Jbig2) => return Some(DecodedImage::Unsupported),
+    };
+
+    if !matches!(bits, 1 | 2 | 4 | 8 | 16) {
+        return None;
+    }
+    let components = color_space.components().max(1);
+    let ranges: Vec<(f64, f64)> = match &decode {
+        Some(d) if d.len() >= components * 2 => d.chunks(2).map(|p| (p[0], p[1])).collect(),
+        _ => color_space.default_decode(bits),
+    };
+    let max_value = ((1u64 << bits) - 1) as f64;
+    let color_key: Option<Vec<f64>> = get(b"Mask").and_then(|m| m.as_array().ok())
+        .map(|m| m.iter().filter_map(|v| object_to_f64(resolve(doc, v))).collect());
+
+    let mut pixmap = Pixmap::new(width as u32, height as u32)?;
+    let pixels = pixmap.pixels_mut();
+    let mut reader = BitReader::new(&data);
+    let mut raw = vec![0u32; components];
+    let mut values = vec![0.0; components];
+
+    // 单分量且位数不超过 8 时预先计算查找表
+    let lookup: Option<Vec<[u8; 3]>> = (components == 1 && bits <= 8 && !image_mask).then(|| {
+        (0..=max_value as u32)
+            .map(|s| {
+                let (d0, d1) = ranges[0];
+                let v = d0 + s as f64 * (d1 - d0) / max_value;
+                color_space.to_rgb(&[v]).map(|c| (c * 255.0).round() as u8)
+            })
+            .collect()
+    });
+
+    for row in 0..height {
+        for col in 0..width {
+            for value in raw.iter_mut() {
+                *value = reader.read(bits).unwrap_or(0);
+            }
+
+            let pixel = &mut pixels[row * width + col];
+            if image_mask {
+                // 采样值 0 表示绘制（Decode [1 0] 时相反）
+                let paint = (raw[0] == 0) != (ranges[0].0 > ranges[0].1);
+                *pixel = if paint {
+                    let [r, g, b] = fill_color.map(|c| (c * 255.0).round() as u8);
+                    tiny_skia::PremultipliedColorU8::from_rgba(r, g, b, 255)?
+                } else {
+                    tiny_skia::PremultipliedColorU8::TRANSPARENT
+                };
+                continue;
+            }
+
+            if let Some(key) = &color_key {
+                let masked = raw.iter().enumerate().all(|(i, &v)| {
+                    let (lo, hi) = (key.get(2 * i).copied().unwrap_or(0.0), key.get(2 * i + 1).copied().unwrap_or(-1.0));
+                    v as f64 >= lo && v as f64 <= hi
+                });
+                if masked {
+                    *pixel = tiny_skia::PremultipliedColorU8::TRANSPARENT;
+                    continue;
+                }
+            }
+
+            let [r, g, b] = match &lookup {
+                Some(lookup) => lookup[raw[0] as usize],
+                None => {
+                    for (i, value) in values.iter_mut().enumerate() {
+                        let (d0, d1) = ranges.get(i).copied().unwrap_or((0.0, 1.0));
+                        *value = d0 + raw[i] as f64 * (d1 - d0) / max_value;
+                    }
+                    color_space.to_rgb(&values).map(|c| (c * 255.0).round() as u8)
+                }
+            };
+            *pixel = tiny_skia::PremultipliedColorU8::from_rgba(r, g, b, 255)?;
+        }
+        reader.align();
+    }
+
+    if !image_mask {
+        apply_soft_mask(doc, dict, resources, &mut pixmap);
+    }
+    Some(DecodedImage::Pixmap(pixmap))
+}
+
+/// 解码 CCITT 传真编码为每行字节对齐的 1 位数据
+fn decode_ccitt(data: &[u8], params: &Dictionary, width: usize, height: usize) -> Option<Vec<u8>> {
+    let get = |key: &[u8]| params.get(key).ok().and_then(|v| v.as_i64().ok());
+    let k = get(b"K").unwrap_or(0);
+    let columns = get(b"Columns").unwrap_or(1728).max(1) as usize;
+    let black_is_1 = params.get(b"BlackIs1").ok().and_then(|v| v.as_bool().ok()).unwrap_or(false);
+    let columns = if columns == width { columns } else { width };
+    let row_bytes = columns.div_ceil(8);
+
+    let mut output = Vec::with_capacity(row_bytes * height);
+    let mut push_line = |transitions: &[u16]| {
+        if output.len() >= row_bytes * height {
+            return;
+        }
+        let mut row = vec![0u8; row_bytes];
+        for (i, color) in fax::decoder::pels(transitions, columns as u16).enumerate() {
+            // BlackIs1 为 false 时黑色输出 0
+            let bit = (color == fax::Color::Black) == black_is_1;
+            if bit {
+                row[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        output.extend_from_slice(&row);
+    };
+
+    if k < 0 {
+        let _ = fax::decoder::decode_g4(data.iter().copied(), columns as u16, Some(height as u16), &mut push_line);
+    } else {
+        let _ = fax::decoder::decode_g3(data.iter().copied(), &mut push_line);
+    }
+
+    // 数据损坏或不足时用白色补齐
+    let white = if black_is_1 { 0x00 } else { 0xFF };
+    output.resize(row_bytes * height, white);
+    Some(output)
+}
+
+/// 应用 /SMask 软蒙版或 /Mask 模板蒙版
+fn apply_soft_mask(doc: &Document, dict: &Dictionary, resources: Option<&Dictionary>, pixmap: &mut Pixmap) {
+    let (mask_stream, inverted) = match dict.get(b"SMask").ok().map(|m| resolve(doc, m)) {
+        Some(Object::Stream(stream)) => (stream, false),
+        _ => match dict.get(b"Mask").ok().map(|m| resolve(doc, m)) {
+            // 模板蒙版中采样值 1 表示遮住
+            Some(Object::Stream(stream)) => (stream, true),
+            _ => return,
+        },
+    };
+
+    let mut mask_dict = mask_stream.dict.clone();
+    if inverted {
+        mask_dict.set("ImageMask", Object::Boolean(false));
+        mask_dict.set("BitsPerComponent", Object::Integer(1));
+        mask_dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+        let invert = number_array(doc, mask_stream.dict.get(b"Decode").ok()).is_some_and(|d| d.first() == Some(&1.0));
+        if !invert {
+            mask_dict.set("Decode", Object::Array(vec![Object::Integer(1), Object::Integer(0)]));
+        }
+    } else {
+        mask_dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+    }
+    mask_dict.remove(b"SMask");
+    mask_dict.remove(b"Mask");
+    let mask_stream = Stream::new(mask_dict, mask_stream.content.clone());
+
+    let Some(DecodedImage::Pixmap(mask)) = decode_image(doc, &mask_stream, resources, [0.0; 3]) else { return };
+    let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+    let (mask_width, mask_height) = (mask.width() as usize, mask.height() as usize);
+    let mask_pixels = mask.pixels();
+
+    for (index, pixel) in pixmap.pixels_mut().iter_mut().enumerate() {
+        let (x, y) = (index % width, index / width);
+        let mx = (x * mask_width / width).min(mask_width - 1);
+        let my = (y * mask_height / height).min(mask_height - 1);
+        let alpha = mask_pixels[my * mask_width + mx].red() as u32;
+        let scale = |c: u8| ((c as u32 * alpha + 127) / 255) as u8;
+        if let Some(masked) = tiny_skia::PremultipliedColorU8::from_rgba(
+            scale(pixel.red()),
+            scale(pixel.green()),
+            scale(pixel.blue()),
+            scale(pixel.alpha()),
+        ) {
+            *pixel = masked;
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 渐变
+// ---------------------------------------------------------------------------
+
+/// 色标采样数
+const GRADIENT_STOPS: usize = 32;
+
+/// 轴向（类型 2）和径向（类型 3）渐变
+pub(crate) struct Shading {
+    kind: i64,
+    coords: Vec<f64>,
+    domain: [f64; 2],
+    function: Option<Function>,
+    color_space: ColorSpace,
+    /// 不支持的渐变类型使用的近似颜色
+    pub fallback: Option<[f64; 3]>,
+}
+
+impl Shading {
+    pub(crate) fn parse(doc: &Document, obj: &Object, resources: Option<&Dictionary>) -> Option<Shading> {
+        let dict = match resolve(doc, obj) {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &stream.dict,
+            _ => return None,
+        };
+        let kind = dict.get(b"ShadingType").ok().and_then(|t| t.as_i64().ok())?;
+        let color_space = ColorSpace::parse(doc, dict.get(b"ColorSpace").ok()?, resources);
+        let function = dict.get(b"Function").ok().and_then(|f| Function::parse(doc, f));
+        let domain = number_array(doc, dict.get(b"Domain").ok())
+            .filter(|d| d.len() >= 2)
+            .map(|d| [d[0], d[1]])
+            .unwrap_or([0.0, 1.0]);
+        let coords = number_array(doc, dict.get(b"Coords").ok()).unwrap_or_default();
+
+        // 其他类型的渐变取背景色或函数中点颜色近似
+        let fallback = number_array(doc, dict.get(b"Background").ok())
+            .map(|bg| color_space.to_rgb(&bg))
+            .or_else(|| function.as_ref().map(|f| color_space.to_rgb(&f.eval(&[(domain[0] + domain[1]) / 2.0]))));
+
+        Some(Shading { kind, coords, domain, function, color_space, fallback })
+    }
+
+    /// 生成 tiny-skia 着色器，transform 为渐变坐标到设备坐标的变换
+    pub(crate) fn shader(&self, transform: Transform, alpha: f32) -> Option<Shader<'static>> {
+        let function = self.function.as_ref()?;
+        let [t0, t1] = self.domain;
+        // 从函数中均匀采样色标，reverse 时从 t1 向 t0 采样
+        let stops = |reverse: bool| -> Vec<GradientStop> {
+            (0..=GRADIENT_STOPS)
+                .map(|i| {
+                    let position = i as f32 / GRADIENT_STOPS as f32;
+                    let fraction = if reverse { 1.0 - position as f64 } else { position as f64 };
+                    let [r, g, b] = self.color_space.to_rgb(&function.eval(&[t0 + (t1 - t0) * fraction]));
+                    let color = Color::from_rgba(r as f32, g as f32, b as f32, alpha).unwrap_or(Color::BLACK);
+                    GradientStop::new(position, color)
+                })
+                .collect()
+        };
+
+        // tiny-skia 不支持只延伸一端，统一按 Pad 处理
+        match (self.kind, self.coords.as_slice()) {
+            (2, [x0, y0, x1, y1, ..]) => LinearGradient::new(
+                Point::from_xy(*x0 as f32, *y0 as f32),
+                Point::from_xy(*x1 as f32, *y1 as f32),
+                stops(false),
+                SpreadMode::Pad,
+                transform,
+            ),
+            (3, [x0, y0, r0, x1, y1, r1, ..]) => {
+                // tiny-skia 的双圆径向渐变要求起始半径为 0，以较大的圆为终点近似
+                let reverse = r0 > r1;
+                let ((sx, sy), (ex, ey), radius) = if reverse {
+                    ((*x1, *y1), (*x0, *y0), *r0)
+                } else {
+                    ((*x0, *y0), (*x1, *y1), *r1)
+                };
+                RadialGradient::new(
+                    Point::from_xy(sx as f32, sy as f32),
+                    Point::from_xy(ex as f32, ey as f32),
+                    radius as f32,
+                    stops(reverse),
+                    SpreadMode::Pad,
+                    transform,
+                )
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,681 @@
+// 渲染用的字体加载与字形轮廓
+// 支持嵌入的 TrueType/OpenType、CFF（Type1C/CIDFontType0C）与 Type1 字体程序，
+// Type3 字体由渲染器执行字形过程；未嵌入的字体无法取得轮廓，渲染为占位色块
+// 同时根据 ToUnicode 与编码取得字符对应的文字，用于搜索
+
+use lopdf::{Dictionary, Document, Object, Stream};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use tiny_skia::{Path, PathBuilder, Transform};
+
+use crate::content_stream::{FontMetrics, Matrix};
+use crate::filters::decode_stream;
+use crate::render_color::resolve;
+use crate::standard_font::win_ansi_to_unicode;
+use crate::type1::{transform_path, Type1Font};
+
+/// WinAnsiEncoding 中 0x20-0x7E 的字形名称（与 StandardEncoding 仅 0x27、0x60 不同）
+const ASCII_NAMES: [&str; 95] = [
+    "space", "exclam", "quotedbl", "numbersign", "dollar", "percent", "ampersand", "quotesingle",
+    "parenleft", "parenright", "asterisk", "plus", "comma", "hyphen", "period", "slash",
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    "colon", "semicolon", "less", "equal", "greater", "question", "at",
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M",
+    "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z",
+    "bracketleft", "backslash", "bracketright", "asciicircum", "underscore", "grave",
+    "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m",
+    "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z",
+    "braceleft", "bar", "braceright", "asciitilde",
+];
+
+/// WinAnsiEncoding 中 0x80-0xFF 的字形名称
+const WIN_ANSI_HIGH_NAMES: [&str; 128] = [
+    "Euro", "", "quotesinglbase", "florin", "quotedblbase", "ellipsis", "dagger", "daggerdbl",
+    "circumflex", "perthousand", "Scaron", "guilsinglleft", "OE", "", "Zcaron", "",
+    "", "quoteleft", "quoteright", "quotedblleft", "quotedblright", "bullet", "endash", "emdash",
+    "tilde", "trademark", "scaron", "guilsinglright", "oe", "", "zcaron", "Ydieresis",
+    "space", "exclamdown", "cent", "sterling", "currency", "yen", "brokenbar", "section",
+    "dieresis", "copyright", "ordfeminine", "guillemotleft", "logicalnot", "hyphen", "registered", "macron",
+    "degree", "plusminus", "twosuperior", "threesuperior", "acute", "mu", "paragraph", "periodcentered",
+    "cedilla", "onesuperior", "ordmasculine", "guillemotright", "onequarter", "onehalf", "threequarters", "questiondown",
+    "Agrave", "Aacute", "Acircumflex", "Atilde", "Adieresis", "Aring", "AE", "Ccedilla",
+    "Egrave", "Eacute", "Ecircumflex", "Edieresis", "Igrave", "Iacute", "Icircumflex", "Idieresis",
+    "Eth", "Ntilde", "Ograve", "Oacute", "Ocircumflex", "Otilde", "Odieresis", "multiply",
+    "Oslash", "Ugrave", "Uacute", "Ucircumflex", "Udieresis", "Yacute", "Thorn", "germandbls",
+    "agrave", "aacute", "acircumflex", "atilde", "adieresis", "aring", "ae", "ccedilla",
+    "egrave", "eacute", "ecircumflex", "edieresis", "igrave", "iacute", "icircumflex", "idieresis",
+    "eth", "ntilde", "ograve", "oacute", "ocircumflex", "otilde", "odieresis", "divide",
+    "oslash", "ugrave", "uacute", "ucircumflex", "udieresis", "yacute", "thorn", "ydieresis",
+];
+
+/// StandardEncoding 中 0x80 以上的字形名称
+const STANDARD_HIGH_NAMES: [(u8, &str); 54] = [
+    (0xA1, "exclamdown"), (0xA2, "cent"), (0xA3, "sterling"), (0xA4, "fraction"), (0xA5, "yen"),
+    (0xA6, "florin"), (0xA7, "section"), (0xA8, "currency"), (0xA9, "quotesingle"), (0xAA, "quotedblleft"),
+    (0xAB, "guillemotleft"), (0xAC, "guilsinglleft"), (0xAD, "guilsinglright"), (0xAE, "fi"), (0xAF, "fl"),
+    (0xB1, "endash"), (0xB2, "dagger"), (0xB3, "daggerdbl"), (0xB4, "periodcentered"), (0xB6, "paragraph"),
+    (0xB7, "bullet"), (0xB8, "quotesinglbase"), (0xB9, "quotedblbase"), (0xBA, "quotedblright"), (0xBB, "guillemotright"),
+    (0xBC, "ellipsis"), (0xBD, "perthousand"), (0xBF, "questiondown"), (0xC1, "grave"), (0xC2, "acute"),
+    (0xC3, "circumflex"), (0xC4, "tilde"), (0xC5, "macron"), (0xC6, "breve"), (0xC7, "dotaccent"),
+    (0xC8, "dieresis"), (0xCA, "ring"), (0xCB, "cedilla"), (0xCD, "hungarumlaut"), (0xCE, "ogonek"),
+    (0xCF, "caron"), (0xD0, "emdash"), (0xE1, "AE"), (0xE3, "ordfeminine"), (0xE8, "Lslash"),
+    (0xE9, "Oslash"), (0xEA, "OE"), (0xEB, "ordmasculine"), (0xF1, "ae"), (0xF5, "dotlessi"),
+    (0xF8, "lslash"), (0xF9, "oslash"), (0xFA, "oe"), (0xFB, "germandbls"),
+];
+
+/// 不在 WinAnsiEncoding 中的常用字形名称
+const EXTRA_GLYPH_NAMES: [(&str, char); 16] = [
+    ("fi", '\u{FB01}'), ("fl", '\u{FB02}'), ("ff", '\u{FB00}'), ("ffi", '\u{FB03}'), ("ffl", '\u{FB04}'),
+    ("fraction", '\u{2044}'), ("dotlessi", '\u{0131}'), ("Lslash", '\u{0141}'), ("lslash", '\u{0142}'),
+    ("breve", '\u{02D8}'), ("dotaccent", '\u{02D9}'), ("ring", '\u{02DA}'), ("ogonek", '\u{02DB}'),
+    ("caron", '\u{02C7}'), ("hungarumlaut", '\u{02DD}'), ("minus", '\u{2212}'),
+];
+
+/// StandardEncoding 中编码对应的字形名称
+pub(crate) fn standard_encoding_name(code: u8) -> Option<&'static str> {
+    match code {
+        0x27 => Some("quoteright"),
+        0x60 => Some("quoteleft"),
+        0x20..=0x7E => Some(ASCII_NAMES[(code - 0x20) as usize]),
+        _ => STANDARD_HIGH_NAMES.iter().find(|(c, _)| *c == code).map(|(_, n)| *n),
+    }
+}
+
+fn win_ansi_name(code: u8) -> Option<&'static str> {
+    let name = match code {
+        0x20..=0x7E => ASCII_NAMES[(code - 0x20) as usize],
+        0x80..=0xFF => WIN_ANSI_HIGH_NAMES[(code - 0x80) as usize],
+        _ => "",
+    };
+    (!name.is_empty()).then_some(name)
+}
+
+/// 字形名称对应的 Unicode 字符
+fn glyph_name_to_unicode(name: &str) -> Option<char> {
+    if let Some(code) = (0x20..=0xFFu8).find(|&c| win_ansi_name(c) == Some(name)) {
+        return win_ansi_to_unicode(code);
+    }
+    if let Some((_, c)) = EXTRA_GLYPH_NAMES.iter().find(|(n, _)| *n == name) {
+        return Some(*c);
+    }
+    match name {
+        "quoteright" => return Some('\u{2019}'),
+        "quoteleft" => return Some('\u{2018}'),
+        "nbspace" => return Some('\u{00A0}'),
+        "sfthyphen" => return Some('\u{00AD}'),
+        _ => {}
+    }
+    // uniXXXX 和 uXXXX[XX] 形式的名称
+    let hex = match name.strip_prefix("uni") {
+        Some(hex) => hex.get(..4)?,
+        None => name.strip_prefix('u').filter(|h| (4..=6).contains(&h.len()))?,
+    };
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+}
+
+/// 将 ttf-parser 的轮廓回调写入 tiny-skia 路径
+struct OutlinePath(PathBuilder);
+
+impl ttf_parser::OutlineBuilder for OutlinePath {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+/// 嵌入的字体程序
+enum FontProgram {
+    /// TrueType 或 OpenType（含 CFF 轮廓的 OpenType 也由 ttf-parser 处理）
+    OpenType(Vec<u8>),
+    /// 裸 CFF 数据（FontFile3 /Type1C 或 /CIDFontType0C）
+    Cff(Vec<u8>),
+    Type1(Box<Type1Font>),
+    /// 未嵌入或无法解析
+    Missing,
+}
+
+/// Type0 字体的字符编码映射（CMap）
+struct CMap {
+    /// 编码空间 (字节数, 下界, 上界)
+    codespace: Vec<(usize, u32, u32)>,
+    /// 区间映射 (下界, 上界, 起始 CID)
+    ranges: Vec<(u32, u32, u32)>,
+    /// Identity 映射：编码即 CID
+    identity: bool,
+}
+
+impl CMap {
+    fn identity() -> CMap {
+        CMap { codespace: vec![(2, 0, 0xFFFF)], ranges: Vec::new(), identity: true }
+    }
+
+    /// 解析嵌入的 CMap 流中的 codespacerange、cidrange 和 cidchar
+    fn parse(data: &[u8]) -> CMap {
+        let text = String::from_utf8_lossy(data);
+        let mut cmap = CMap { codespace: Vec::new(), ranges: Vec::new(), identity: false };
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let hex = |t: &str| -> Option<(usize, u32)> {
+            let digits = t.strip_prefix('<')?.strip_suffix('>')?;
+            Some((digits.len().div_ceil(2), u32::from_str_radix(digits, 16).ok()?))
+        };
+
+        let mut i = 0;
+        while i < tokens.len() {
+            match tokens[i] {
+                "begincodespacerange" => {
+                    i += 1;
+                    while i + 1 < tokens.len() && tokens[i] != "endcodespacerange" {
+                        if let (Some((bytes, lo)), Some((_, hi))) = (hex(tokens[i]), hex(tokens[i + 1])) {
+                            cmap.codespace.push((bytes, lo, hi));
+                        }
+                        i += 2;
+                    }
+                }
+                "begincidrange" => {
+                    i += 1;
+                    while i + 2 < tokens.len() && tokens[i] != "endcidrange" {
+                        if let (Some((_, lo)), Some((_, hi)), Ok(cid)) = (hex(tokens[i]), hex(tokens[i + 1]), tokens[i + 2].parse()) {
+                            cmap.ranges.push((lo, hi, cid));
+                        }
+                        i += 3;
+                    }
+                }
+                "begincidchar" => {
+                    i += 1;
+                    while i + 1 < tokens.len() && tokens[i] != "endcidchar" {
+                        if let (Some((_, code)), Ok(cid)) = (hex(tokens[i]), tokens[i + 1].parse()) {
+                            cmap.ranges.push((code, code, cid));
+                        }
+                        i += 2;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        if cmap.codespace.is_empty() {
+            cmap.codespace.push((2, 0, 0xFFFF));
+        }
+        cmap
+    }
+
+    /// 从字节串开头读取一个字符编码，返回 (编码, 字节数)
+    fn next_code(&self, bytes: &[u8]) -> (u32, usize) {
+        for length in 1..=4.min(bytes.len()) {
+            let code = bytes[..length].iter().fold(0u32, |acc, &b| (acc << 8) | b as u32);
+            if self.codespace.iter().any(|&(n, lo, hi)| n == length && code >= lo && code <= hi) {
+                return (code, length);
+            }
+        }
+        // 不在编码空间内时按最短的编码长度跳过
+        let length = self.codespace.iter().map(|c| c.0).min().unwrap_or(1).clamp(1, bytes.len());
+        (bytes[..length].iter().fold(0u32, |acc, &b| (acc << 8) | b as u32), length)
+    }
+
+    fn cid(&self, code: u32) -> u32 {
+        if self.identity {
+            return code;
+        }
+        self.ranges
+            .iter()
+            .find(|&&(lo, hi, _)| code >= lo && code <= hi)
+            .map(|&(lo, _, cid)| cid + code - lo)
+            .unwrap_or(0)
+    }
+}
+
+/// 解析 ToUnicode CMap 中的 bfchar 和 bfrange，目标文字为 UTF-16BE
+pub(crate) fn parse_to_unicode(data: &[u8]) -> HashMap<u32, String> {
+    enum Token {
+        Hex(Vec<u8>),
+        Word(String),
+        ArrayStart,
+        ArrayEnd,
+    }
+
+    // <0003><0020> 这样不带空白的写法很常见，不能按空白拆分
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        match data[pos] {
+            b'<' => {
+                let end = data[pos..].iter().position(|&c| c == b'>').map_or(data.len(), |i| pos + i);
+                let digits: Vec<u8> = data[pos + 1..end].iter().copied().filter(u8::is_ascii_hexdigit).collect();
+                let bytes = digits.chunks(2)
+                    .map(|pair| {
+                        let text = std::str::from_utf8(pair).unwrap_or("0");
+                        // 奇数个数字时最后一位补 0
+                        u8::from_str_radix(&format!("{:0<2}", text), 16).unwrap_or(0)
+                    })
+                    .collect();
+                tokens.push(Token::Hex(bytes));
+                pos = end + 1;
+            }
+            b'[' => {
+                tokens.push(Token::ArrayStart);
+                pos += 1;
+            }
+            b']' => {
+                tokens.push(Token::ArrayEnd);
+                pos += 1;
+            }
+            c if c.is_ascii_whitespace() => pos += 1,
+            _ => {
+                let end = data[pos..].iter()
+                    .position(|&c| c.is_ascii_whitespace() || matches!(c, b'<' | b'[' | b']'))
+                    .map_or(data.len(), |i| pos + i);
+                tokens.push(Token::Word(String::from_utf8_lossy(&data[pos..end]).into_owned()));
+                pos = end;
+            }
+        }
+    }
+
+    let code = |bytes: &[u8]| bytes.iter().take(4).fold(0u32, |acc, &b| (acc << 8) | b as u32);
+    let text = |bytes: &[u8]| {
+        let units: Vec<u16> = bytes.chunks(2).map(|c| ((c[0] as u16) << 8) | *c.get(1).unwrap_or(&0) as u16).collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    let mut map = HashMap::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Word(word) if word == "beginbfchar" => {
+                i += 1;
+                while let (Some(Token::Hex(src)), Some(Token::Hex(dst))) = (tokens.get(i), tokens.get(i + 1)) {
+                    map.insert(code(src), text(dst));
+                    i += 2;
+                }
+            }
+            Token::Word(word) if word == "beginbfrange" => {
+                i += 1;
+                while let (Some(Token::Hex(lo)), Some(Token::Hex(hi))) = (tokens.get(i), tokens.get(i + 1)) {
+                    let (lo, hi) = (code(lo), code(hi));
+                    // 防止错误的区间产生过多映射
+                    let hi = hi.min(lo.saturating_add(0xFFFF));
+                    match tokens.get(i + 2) {
+                        Some(Token::Hex(dst)) if !dst.is_empty() => {
+                            // 目标文字的最后一个 UTF-16 单元随编码递增
+                            let mut units: Vec<u16> = dst.chunks(2).map(|c| ((c[0] as u16) << 8) | *c.get(1).unwrap_or(&0) as u16).collect();
+                            for source in lo..=hi {
+                                map.insert(source, String::from_utf16_lossy(&units));
+                                if let Some(last) = units.last_mut() {
+                                    *last = last.wrapping_add(1);
+                                }
+                            }
+                            i += 3;
+                        }
+                        Some(Token::ArrayStart) => {
+                            i += 3;
+                            let mut source = lo;
+                            while let Some(Token::Hex(dst)) = tokens.get(i) {
+                                if source <= hi {
+                                    map.insert(source, text(dst));
+                                }
+                                source += 1;
+                                i += 1;
+                            }
+                            if matches!(tokens.get(i), Some(Token::ArrayEnd)) {
+                                i += 1;
+                            }
+                        }
+                        _ => i += 3,
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    map
+}
+
+/// Type3 字体：字形由内容流绘制
+pub(crate) struct Type3Font {
+    pub matrix: Matrix,
+    pub resources: Option<Dictionary>,
+    char_procs: Dictionary,
+}
+
+/// 文字中的一个字符
+pub(crate) struct TextChar {
+    pub code: u32,
+    /// 水平排版时的前进宽度（文字空间单位，未乘字号）
+    pub width: f64,
+    /// 单字节编码 32，应用字间距 Tw
+    pub is_space: bool,
+    /// 编码占用的字节数
+    pub len: usize,
+}
+
+/// 渲染用字体
+pub(crate) struct RenderFont {
+    metrics: FontMetrics,
+    program: FontProgram,
+    /// 简单字体中编码对应的字形名称
+    names: Vec<Option<String>>,
+    /// 是否指定了 /Encoding（否则使用字体内置编码）
+    has_encoding: bool,
+    cmap: Option<CMap>,
+    cid_to_gid: Option<Vec<u16>>,
+    /// ToUnicode 中编码对应的文字
+    to_unicode: HashMap<u32, String>,
+    pub vertical: bool,
+    pub type3: Option<Type3Font>,
+    glyphs: RefCell<HashMap<u32, Option<Rc<Path>>>>,
+}
+
+impl RenderFont {
+    pub(crate) fn load(doc: &Document, font: &Dictionary) -> RenderFont {
+        let get = |dict: &Dictionary, key: &[u8]| dict.get(key).ok().map(|v| resolve(doc, v).clone());
+        let subtype = font.get(b"Subtype").and_then(Object::as_name).unwrap_or(b"").to_vec();
+        let metrics = FontMetrics::from_dict(doc, font);
+
+        let mut render_font = RenderFont {
+            metrics,
+            program: FontProgram::Missing,
+            names: vec![None; 256],
+            has_encoding: false,
+            cmap: None,
+            cid_to_gid: None,
+            to_unicode: HashMap::new(),
+            vertical: false,
+            type3: None,
+            glyphs: RefCell::new(HashMap::new()),
+        };
+
+        if let Some(Object::Stream(stream)) = get(font, b"ToUnicode") {
+            if let Ok(decoded) = decode_stream(doc, &stream) {
+                render_font.to_unicode = parse_to_unicode(&decoded.data);
+            }
+        }
+
+        if subtype == b"Type0" {
+            let encoding = get(font, b"Encoding");
+            render_font.cmap = Some(match &encoding {
+                Some(Object::Stream(stream)) => {
+                    render_font.vertical = stream.dict.get(b"WMode").ok().and_then(|w| w.as_i64().ok()) == Some(1);
+                    decode_stream(doc, stream).map(|d| CMap::parse(&d.data)).unwrap_or_else(|_| CMap::identity())
+                }
+                // 预定义的 CMap 只支持 Identity，其他按双字节编码近似
+                Some(Object::Name(name)) => {
+                    render_font.vertical = name.ends_with(b"-V");
+                    CMap::identity()
+                }
+                _ => CMap::identity(),
+            });
+
+            let descendant = get(font, b"DescendantFonts")
+                .and_then(|d| d.as_array().ok().and_then(|a| a.first().cloned()))
+                .map(|d| resolve(doc, &d).clone())
+                .and_then(|d| d.as_dict().ok().cloned());
+            if let Some(descendant) = descendant {
+                render_font.program = load_program(doc, &descendant);
+                render_font.cid_to_gid = match get(&descendant, b"CIDToGIDMap") {
+                    Some(Object::Stream(stream)) => decode_stream(doc, &stream).ok().map(|d| {
+                        d.data.chunks(2).map(|c| ((c[0] as u16) << 8) | *c.get(1).unwrap_or(&0) as u16).collect()
+                    }),
+                    _ => None,
+                };
+            }
+            return render_font;
+        }
+
+        // 简单字体的编码
+        match get(font, b"Encoding") {
+            Some(Object::Name(name)) => {
+                render_font.has_encoding = true;
+                render_font.set_base_encoding(&name);
+            }
+            Some(Object::Dictionary(dict)) => {
+                render_font.has_encoding = true;
+                match get(&dict, b"BaseEncoding") {
+                    Some(Object::Name(name)) => render_font.set_base_encoding(&name),
+                    _ if subtype == b"TrueType" => render_font.set_base_encoding(b"StandardEncoding"),
+                    _ => {}
+                }
+                if let Some(Object::Array(differences)) = get(&dict, b"Differences") {
+                    let mut code = 0usize;
+                    for item in differences {
+                        match resolve(doc, &item) {
+                            Object::Integer(n) => code = (*n).clamp(0, 255) as usize,
+                            Object::Name(name) => {
+                                if code < 256 {
+                                    render_font.names[code] = Some(String::from_utf8_lossy(name).into_owned());
+                                }
+                                code += 1;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if subtype == b"Type3" {
+            let matrix = get(font, b"FontMatrix")
+                .and_then(|m| m.as_array().ok().and_then(|m| Matrix::from_objects(m)))
+                .unwrap_or(Matrix([0.001, 0.0, 0.0, 0.001, 0.0, 0.0]));
+            render_font.type3 = Some(Type3Font {
+                matrix,
+                resources: get(font, b"Resources").and_then(|r| r.as_dict().ok().cloned()),
+                char_procs: get(font, b"CharProcs").and_then(|c| c.as_dict().ok().cloned()).unwrap_or_default(),
+            });
+            return render_font;
+        }
+
+        render_font.program = load_program(doc, font);
+        render_font
+    }
+
+    fn set_base_encoding(&mut self, name: &[u8]) {
+        for code in 0..=255u8 {
+            let glyph = match name {
+                b"WinAnsiEncoding" | b"MacRomanEncoding" => win_ansi_name(code),
+                b"StandardEncoding" => standard_encoding_name(code),
+                _ => None,
+            };
+            self.names[code as usize] = glyph.map(str::to_string);
+        }
+    }
+
+    /// 是否有可用的字形轮廓
+    pub(crate) fn has_outlines(&self) -> bool {
+        !matches!(self.program, FontProgram::Missing) || self.type3.is_some()
+    }
+
+    /// 拆分字符串为字符并计算前进宽度
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Vec<TextChar> {
+        let mut chars = Vec::new();
+        let width_scale = self.type3.as_ref().map(|t| t.matrix.0[0]).unwrap_or(0.001);
+        match &self.cmap {
+            Some(cmap) => {
+                let mut pos = 0;
+                while pos < bytes.len() {
+                    let (code, length) = cmap.next_code(&bytes[pos..]);
+                    pos += length;
+                    chars.push(TextChar {
+                        code,
+                        width: self.metrics.width(cmap.cid(code)) * width_scale,
+                        is_space: length == 1 && code == 32,
+                        len: length,
+                    });
+                }
+            }
+            None => {
+                for &b in bytes {
+                    chars.push(TextChar {
+                        code: b as u32,
+                        width: self.metrics.width(b as u32) * width_scale,
+                        is_space: b == 32,
+                        len: 1,
+                    });
+                }
+            }
+        }
+        chars
+    }
+
+    /// 编码对应的文字：优先使用 ToUnicode，简单字体其次按编码中的字形名称推断
+    pub(crate) fn unicode(&self, code: u32) -> Option<String> {
+        if let Some(text) = self.to_unicode.get(&code) {
+            return Some(text.clone());
+        }
+        if self.cmap.is_some() || code > 255 {
+            return None;
+        }
+        match &self.names[code as usize] {
+            Some(name) => glyph_name_to_unicode(name).map(String::from),
+            None => win_ansi_to_unicode(code as u8).map(String::from),
+        }
+    }
+
+    /// Type3 字体中编码对应的字形过程
+    pub(crate) fn type3_proc<'a>(&'a self, doc: &'a Document, code: u32) -> Option<&'a Stream> {
+        let type3 = self.type3.as_ref()?;
+        let name = self.names.get(code as usize)?.as_ref()?;
+        resolve(doc, type3.char_procs.get(name.as_bytes()).ok()?).as_stream().ok()
+    }
+
+    /// 字形轮廓（文字空间，1 单位等于字号）
+    pub(crate) fn outline(&self, code: u32) -> Option<Rc<Path>> {
+        if let Some(cached) = self.glyphs.borrow().get(&code) {
+            return cached.clone();
+        }
+        let path = self.build_outline(code).map(Rc::new);
+        self.glyphs.borrow_mut().insert(code, path.clone());
+        path
+    }
+
+    fn build_outline(&self, code: u32) -> Option<Path> {
+        let name = self.names.get(code as usize).and_then(|n| n.as_deref());
+        match &self.program {
+            FontProgram::Missing => None,
+            FontProgram::Type1(font) => {
+                let builtin = font.encoding.as_ref()
+                    .and_then(|e| e.get(&(code as u8)).map(String::as_str))
+                    .or_else(|| standard_encoding_name(code as u8));
+                let name = name.filter(|n| font.has_glyph(n)).or(builtin)?;
+                transform_path(font.outline(name)?, &font.matrix)
+            }
+            FontProgram::Cff(data) => {
+                let table = ttf_parser::cff::Table::parse(data)?;
+                let gid = match &self.cmap {
+                    Some(cmap) => {
+                        let cid = cmap.cid(code);
+                        // CID 字体需要由 CID 反查字形编号
+                        (0..table.number_of_glyphs())
+                            .map(ttf_parser::GlyphId)
+                            .find(|&g| table.glyph_cid(g) == Some(cid as u16))
+                            .unwrap_or(ttf_parser::GlyphId(cid as u16))
+                    }
+                    None => name
+                        .and_then(|n| table.glyph_index_by_name(n))
+                        .or_else(|| table.glyph_index(code as u8))?,
+                };
+                let mut builder = OutlinePath(PathBuilder::new());
+                table.outline(gid, &mut builder).ok()?;
+                let m = table.matrix();
+                builder.0.finish()?.transform(Transform::from_row(m.sx, m.ky, m.kx, m.sy, m.tx, m.ty))
+            }
+            FontProgram::OpenType(data) => {
+                let face = ttf_parser::Face::parse(data, 0).ok()?;
+                let gid = match &self.cmap {
+                    Some(cmap) => {
+                        let cid = cmap.cid(code);
+                        let gid = match &self.cid_to_gid {
+                            Some(map) => map.get(cid as usize).copied().unwrap_or(0),
+                            None => cid as u16,
+                        };
+                        ttf_parser::GlyphId(gid)
+                    }
+                    None => self.simple_glyph_id(&face, code, name)?,
+                };
+                let mut builder = OutlinePath(PathBuilder::new());
+                face.outline_glyph(gid, &mut builder)?;
+                let scale = 1.0 / face.units_per_em() as f32;
+                builder.0.finish()?.transform(Transform::from_scale(scale, scale))
+            }
+        }
+    }
+
+    /// 简单 TrueType 字体中编码到字形编号的映射
+    fn simple_glyph_id(&self, face: &ttf_parser::Face, code: u32, name: Option<&str>) -> Option<ttf_parser::GlyphId> {
+        let subtables: Vec<ttf_parser::cmap::Subtable> = face.tables().cmap
+            .map(|cmap| cmap.subtables.into_iter().collect())
+            .unwrap_or_default();
+        let find = |platform: ttf_parser::PlatformId, encoding: u16| {
+            subtables.iter().find(|s| s.platform_id == platform && s.encoding_id == encoding)
+        };
+
+        // 有编码时通过字形名称查 Unicode 表
+        if self.has_encoding {
+            if let Some(name) = name {
+                let by_unicode = glyph_name_to_unicode(name)
+                    .and_then(|c| find(ttf_parser::PlatformId::Windows, 1).and_then(|s| s.glyph_index(c as u32)));
+                if let Some(gid) = by_unicode.or_else(|| face.glyph_index_by_name(name)) {
+                    return Some(gid);
+                }
+            }
+        }
+        // 符号字体的 (3,0) 表通常映射到 0xF000 区
+        if let Some(symbol) = find(ttf_parser::PlatformId::Windows, 0) {
+            for prefix in [0, 0xF000, 0xF100, 0xF200] {
+                if let Some(gid) = symbol.glyph_index(prefix | code) {
+                    return Some(gid);
+                }
+            }
+        }
+        if let Some(gid) = find(ttf_parser::PlatformId::Macintosh, 0).and_then(|s| s.glyph_index(code)) {
+            return Some(gid);
+        }
+        let unicode = win_ansi_to_unicode(code as u8).map(|c| c as u32).unwrap_or(code);
+        if let Some(gid) = subtables.iter().filter(|s| s.is_unicode()).find_map(|s| s.glyph_index(unicode)) {
+            return Some(gid);
+        }
+        // 没有 cmap 的子集字体直接以编码作为字形编号
+        subtables.is_empty().then_some(ttf_parser::GlyphId(code as u16))
+    }
+}
+
+/// 读取字体描述符中嵌入的字体程序
+fn load_program(doc: &Document, font: &Dictionary) -> FontProgram {
+    let Some(descriptor) = font.get(b"FontDescriptor").ok()
+        .and_then(|d| resolve(doc, d).as_dict().ok()) else { return FontProgram::Missing };
+
+    for key in [b"FontFile2".as_slice(), b"FontFile3", b"FontFile"] {
+        let Some(stream) = descriptor.get(key).ok().and_then(|s| resolve(doc, s).as_stream().ok()) else { continue };
+        let Ok(decoded) = decode_stream(doc, stream) else { continue };
+        let data = decoded.data;
+
+        let program = match key {
+            b"FontFile" => Type1Font::parse(&data).map(|f| FontProgram::Type1(Box::new(f))),
+            b"FontFile3" => match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                Ok(b"OpenType") => ttf_parser::Face::parse(&data, 0).is_ok().then_some(FontProgram::OpenType(data)),
+                _ => ttf_parser::cff::Table::parse(&data).is_some().then_some(FontProgram::Cff(data)),
+            },
+            _ => ttf_parser::Face::parse(&data, 0).is_ok().then_some(FontProgram::OpenType(data)),
+        };
+        if let Some(program) = program {
+            return program;
+        }
+    }
+    FontProgram::Missing
+}
@@ -0,0 +1,615 @@
+// 结构检查与修复
+// 检查交叉引用表、缺失对象、页面树和流长度；修复时扫描文件中的 "N G obj" 标记重建交叉引用表，
+// 按 endstream 的位置修正流长度，并将页面树重建为扁平结构
+
+use lopdf::xref::{Xref, XrefEntry, XrefType};
+use lopdf::{Dictionary, Document, Object, ObjectId, ObjectStream, Reader};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::pdf_utils::{materialize_inherited_attributes, set_page_tree_kids};
+
+/// 遍历页面树时允许的最大深度
+const MAX_DEPTH: usize = 64;
+
+/// 结构问题
+#[derive(Debug, Serialize)]
+pub(crate) struct Issue {
+    /// 问题类型："header"、"broken_xref"、"missing_object"、"unreadable_object"、
+    /// "invalid_stream_length"、"bad_page_tree"、"wrong_count"
+    kind: &'static str,
+    /// 相关对象，如 "12 0"
+    object: Option<String>,
+    /// 问题说明
+    message: String,
+}
+
+impl Issue {
+    fn new(kind: &'static str, object: Option<ObjectId>, message: String) -> Issue {
+        Issue { kind, object: object.map(object_name), message }
+    }
+}
+
+fn object_name((number, generation): ObjectId) -> String {
+    format!("{} {}", number, generation)
+}
+
+/// 扫描到的间接对象
+struct ScannedObject {
+    id: ObjectId,
+    /// "N G obj" 标记在文件中的位置
+    offset: usize,
+    stream: Option<StreamSpan>,
+}
+
+/// 流在文件中的位置
+struct StreamSpan {
+    /// /Length 键和值在文件中的范围
+    length_entry: Option<(usize, usize)>,
+    /// 声明的长度：整数或间接引用
+    declared: Option<Object>,
+    data_start: usize,
+    /// 数据结束位置（不含 endstream 前的换行）
+    data_end: usize,
+    /// endstream 前换行的长度
+    eol: usize,
+}
+
+pub(crate) fn find(buffer: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    buffer.get(from..)?.windows(needle.len()).position(|w| w == needle).map(|p| p + from)
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | b'\x0C' | b'\0')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    b"()<>[]{}/%".contains(&byte)
+}
+
+/// 文件头中的版本号，如 "1.7"
+fn header_version(buffer: &[u8]) -> Option<String> {
+    let start = find(&buffer[..buffer.len().min(1024)], b"%PDF-", 0)? + 5;
+    let version: String = buffer[start..].iter()
+        .take_while(|b| b.is_ascii_digit() || **b == b'.')
+        .map(|&b| b as char)
+        .collect();
+    Some(version).filter(|v| !v.is_empty())
+}
+
+/// "obj" 关键字前的对象号和代号，返回对象标识和标记的起始位置
+pub(crate) fn marker_id(buffer: &[u8], obj_pos: usize) -> Option<(ObjectId, usize)> {
+    if buffer.get(obj_pos + 3).is_some_and(u8::is_ascii_alphanumeric) {
+        return None;
+    }
+    let mut i = obj_pos;
+    while i > 0 && is_whitespace(buffer[i - 1]) {
+        i -= 1;
+    }
+    let generation_end = i;
+    while i > 0 && buffer[i - 1].is_ascii_digit() {
+        i -= 1;
+    }
+    let generation_start = i;
+    while i > 0 && is_whitespace(buffer[i - 1]) {
+        i -= 1;
+    }
+    let number_end = i;
+    while i > 0 && buffer[i - 1].is_ascii_digit() {
+        i -= 1;
+    }
+    if generation_start == generation_end || number_end == generation_start || i == number_end {
+        return None;
+    }
+    if i > 0 && !is_whitespace(buffer[i - 1]) && !is_delimiter(buffer[i - 1]) {
+        return None;
+    }
+    let number = std::str::from_utf8(&buffer[i..number_end]).ok()?.parse().ok()?;
+    let generation = std::str::from_utf8(&buffer[generation_start..generation_end]).ok()?.parse().ok()?;
+    Some(((number, generation), i))
+}
+
+/// 对象中 stream 关键字的位置（必须后跟换行）
+fn stream_keyword(buffer: &[u8], from: usize, to: usize) -> Option<usize> {
+    let mut pos = from;
+    while let Some(at) = find(&buffer[..to], b"stream", pos) {
+        let before = at.checked_sub(1).map(|i| buffer[i]);
+        let after = buffer.get(at + 6).copied();
+        if before.is_some_and(|b| is_whitespace(b) || b == b'>') && matches!(after, Some(b'\r' | b'\n')) {
+            return Some(at);
+        }
+        pos = at + 6;
+    }
+    None
+}
+
+/// 字典中 /Length 键和值的位置及声明的长度
+fn length_entry(buffer: &[u8], from: usize, to: usize) -> (Option<(usize, usize)>, Option<Object>) {
+    let mut pos = from;
+    let key = loop {
+        let Some(at) = find(&buffer[..to], b"/Length", pos) else { return (None, None) };
+        if buffer.get(at + 7).is_some_and(|&b| is_whitespace(b) || is_delimiter(b)) {
+            break at;
+        }
+        pos = at + 7;
+    };
+
+    let integer = |start: usize| {
+        let mut i = start;
+        while i < to && is_whitespace(buffer[i]) {
+            i += 1;
+        }
+        let digits = i;
+        if i < to && buffer[i] == b'-' {
+            i += 1;
+        }
+        while i < to && buffer[i].is_ascii_digit() {
+            i += 1;
+        }
+        let value: i64 = std::str::from_utf8(&buffer[digits..i]).ok()?.parse().ok()?;
+        Some((value, i))
+    };
+    let Some((value, end)) = integer(key + 7) else { return (Some((key, key + 7)), None) };
+    // 间接引用 "N G R"
+    if let Some((generation, generation_end)) = integer(end) {
+        let mut i = generation_end;
+        while i < to && is_whitespace(buffer[i]) {
+            i += 1;
+        }
+        if buffer.get(i) == Some(&b'R') && value >= 0 && generation >= 0 {
+            return (Some((key, i + 1)), Some(Object::Reference((value as u32, generation as u16))));
+        }
+    }
+    (Some((key, end)), Some(Object::Integer(value)))
+}
+
+fn stream_span(buffer: &[u8], dict_start: usize, keyword: usize) -> StreamSpan {
+    let mut data_start = keyword + 6;
+    if buffer.get(data_start) == Some(&b'\r') {
+        data_start += 1;
+    }
+    if buffer.get(data_start) == Some(&b'\n') {
+        data_start += 1;
+    }
+    let (length_entry, declared) = length_entry(buffer, dict_start, keyword);
+
+    // 先按声明的长度查找 endstream，不符时使用第一个 endstream
+    let by_declared = match declared {
+        Some(Object::Integer(length)) if length >= 0 => {
+            let end = data_start.saturating_add(length as usize);
+            [0, 1, 2].into_iter()
+                .find(|&eol| {
+                    let gap = buffer.get(end..end + eol).is_some_and(|g| g.iter().all(|&b| b == b'\r' || b == b'\n'));
+                    gap && buffer.get(end + eol..).is_some_and(|rest| rest.starts_with(b"endstream"))
+                })
+                .map(|eol| (end, eol))
+        }
+        _ => None,
+    };
+    let (data_end, eol) = by_declared.unwrap_or_else(|| {
+        let end = find(buffer, b"endstream", data_start).unwrap_or(buffer.len());
+        let eol = if end >= data_start + 2 && &buffer[end - 2..end] == b"\r\n" {
+            2
+        } else {
+            usize::from(end > data_start && matches!(buffer[end - 1], b'\r' | b'\n'))
+        };
+        (end - eol, eol)
+    });
+    StreamSpan { length_entry, declared, data_start, data_end, eol }
+}
+
+/// 按文件顺序扫描所有 "N G obj" 标记，跳过流数据
+fn scan_objects(buffer: &[u8]) -> Vec<ScannedObject> {
+    let mut objects = Vec::new();
+    let mut pos = 0;
+    while let Some(at) = find(buffer, b"obj", pos) {
+        pos = at + 3;
+        let Some((id, offset)) = marker_id(buffer, at) else { continue };
+        let end = find(buffer, b"endobj", pos);
+        let stream = stream_keyword(buffer, pos, end.unwrap_or(buffer.len()))
+            .map(|keyword| stream_span(buffer, pos, keyword));
+        match (&stream, end) {
+            (Some(span), _) => pos = span.data_end + span.eol + b"endstream".len(),
+            (None, Some(end)) => pos = end + b"endobj".len(),
+            (None, None) => {}
+        }
+        objects.push(ScannedObject { id, offset, stream });
+    }
+    objects
+}
+
+/// 同一对象号出现多次时（增量更新）保留文件中靠后的版本
+fn latest_objects(scanned: &[ScannedObject]) -> Vec<&ScannedObject> {
+    let mut latest: BTreeMap<u32, &ScannedObject> = BTreeMap::new();
+    for object in scanned {
+        latest.insert(object.id.0, object);
+    }
+    latest.into_values().collect()
+}
+
+pub(crate) fn reader_for(buffer: &[u8], entries: impl IntoIterator<Item = (ObjectId, usize)>) -> Reader<'_> {
+    let mut xref = Xref::new(0, XrefType::CrossReferenceTable);
+    for ((number, generation), offset) in entries {
+        xref.insert(number, XrefEntry::Normal { offset: offset as u32, generation });
+    }
+    xref.size = xref.max_id() + 1;
+    let mut document = Document::new();
+    document.reference_table = xref;
+    Reader { buffer, document }
+}
+
+/// 没有有效长度的流按扫描到的位置截取数据
+fn with_stream_data(mut object: Object, buffer: &[u8], scanned: &ScannedObject) -> Object {
+    if let (Object::Stream(stream), Some(span)) = (&mut object, &scanned.stream) {
+        if stream.start_position.is_some() {
+            stream.set_content(buffer[span.data_start..span.data_end.max(span.data_start)].to_vec());
+            stream.start_position = None;
+        }
+    }
+    object
+}
+
+/// 按扫描结果读取对象，包括对象流中的压缩对象
+fn read_scanned_objects(buffer: &[u8], scanned: &[ScannedObject]) -> BTreeMap<ObjectId, Object> {
+    let latest = latest_objects(scanned);
+    let mut objects = BTreeMap::new();
+    let mut failed = Vec::new();
+
+    let reader = reader_for(buffer, latest.iter().map(|o| (o.id, o.offset)));
+    for &scanned in &latest {
+        // 长度错误时 lopdf 会退而把流当作字典解析，同样视为失败
+        match reader.get_object(scanned.id) {
+            Ok(object) if scanned.stream.is_none() || object.as_stream().is_ok() => {
+                objects.insert(scanned.id, with_stream_data(object, buffer, scanned));
+            }
+            _ => failed.push(scanned),
+        }
+    }
+
+    // 长度错误的流：去掉 /Length 后重新解析，数据截取到 endstream 之前
+    let mut patched = buffer.to_vec();
+    let mut retry = false;
+    for scanned in &failed {
+        if let Some((start, end)) = scanned.stream.as_ref().and_then(|s| s.length_entry) {
+            patched[start..end].fill(b' ');
+            retry = true;
+        }
+    }
+    if retry {
+        let reader = reader_for(&patched, latest.iter().map(|o| (o.id, o.offset)));
+        for scanned in failed {
+            if let Ok(object) = reader.get_object(scanned.id) {
+                objects.insert(scanned.id, with_stream_data(object, buffer, scanned));
+            }
+        }
+    }
+
+    let compressed: Vec<(ObjectId, Object)> = objects.values()
+        .filter_map(|o| o.as_stream().ok())
+        .filter(|s| s.dict.type_is(b"ObjStm"))
+        .filter_map(|s| ObjectStream::new(&mut s.clone()).ok())
+        .flat_map(|s| s.objects)
+        .collect();
+    for (id, object) in compressed {
+        objects.entry(id).or_insert(object);
+    }
+    objects
+}
+
+/// 文件中最后一个 trailer 字典或交叉引用流字典
+fn find_trailer(buffer: &[u8], scanned: &[ScannedObject], objects: &BTreeMap<ObjectId, Object>) -> Dictionary {
+    let mut candidates: Vec<(usize, Dictionary)> = Vec::new();
+
+    if let Some(at) = buffer.windows(7).rposition(|w| w == b"trailer") {
+        // 包装成间接对象交给 lopdf 解析
+        let mut wrapped = b"0 0 obj\n".to_vec();
+        wrapped.extend_from_slice(&buffer[at + 7..]);
+        let reader = reader_for(&wrapped, [((0, 0), 0)]);
+        if let Ok(Object::Dictionary(dict)) = reader.get_object((0, 0)) {
+            candidates.push((at, dict));
+        }
+    }
+    for object in scanned {
+        let dict = objects.get(&object.id)
+            .and_then(|o| o.as_stream().ok())
+            .filter(|s| s.dict.type_is(b"XRef"))
+            .map(|s| s.dict.clone());
+        if let Some(dict) = dict {
+            candidates.push((object.offset, dict));
+        }
+    }
+    candidates.into_iter().max_by_key(|(offset, _)| *offset).map(|(_, dict)| dict).unwrap_or_default()
+}
+
+/// 仅靠扫描恢复的文档
+fn recover_document(buffer: &[u8], scanned: &[ScannedObject]) -> Document {
+    let mut doc = Document::with_version(header_version(buffer).unwrap_or_else(|| "1.4".to_string()));
+    doc.objects = read_scanned_objects(buffer, scanned);
+    doc.trailer = find_trailer(buffer, scanned, &doc.objects);
+    doc.max_id = doc.objects.keys().map(|id| id.0).max().unwrap_or(0);
+    doc
+}
+
+/// 检查文件结构，返回发现的问题
+pub(crate) fn validate_document(buffer: &[u8]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if header_version(buffer).is_none() {
+        issues.push(Issue::new("header", None, "缺少 PDF 文件头（%PDF-）".to_string()));
+    }
+
+    let scanned = scan_objects(buffer);
+    let recovered = recover_document(buffer, &scanned);
+    let mut reported = HashSet::new();
+    check_stream_lengths(&scanned, &recovered, &mut reported, &mut issues);
+
+    let doc = match Document::load_mem(buffer) {
+        Ok(doc) => {
+            check_xref(&doc, &scanned, &mut reported, &mut issues);
+            doc
+        }
+        Err(e) => {
+            issues.push(Issue::new("broken_xref", None, format!("无法按交叉引用表读取文件: {}", e)));
+            recovered.clone()
+        }
+    };
+    check_references(&doc, &recovered, &mut reported, &mut issues);
+    check_page_tree(&doc, &mut issues);
+    issues
+}
+
+fn check_stream_lengths(scanned: &[ScannedObject], recovered: &Document, reported: &mut HashSet<ObjectId>, issues: &mut Vec<Issue>) {
+    for object in latest_objects(scanned) {
+        let Some(span) = &object.stream else { continue };
+        let actual = span.data_end.saturating_sub(span.data_start);
+        let declared = match &span.declared {
+            Some(Object::Reference(id)) => recovered.get_object(*id).and_then(Object::as_i64).ok(),
+            Some(value) => value.as_i64().ok(),
+            None => None,
+        };
+        let message = match declared {
+            None if span.declared.is_none() => format!("对象 {} 的流缺少 /Length", object_name(object.id)),
+            None => format!("对象 {} 的流长度引用了无效的对象", object_name(object.id)),
+            Some(length) if length == actual as i64 || length == (actual + span.eol) as i64 => continue,
+            Some(length) => format!("对象 {} 的流长度为 {}，实际为 {} 字节", object_name(object.id), length, actual),
+        };
+        reported.insert(object.id);
+        issues.push(Issue::new("invalid_stream_length", Some(object.id), message));
+    }
+}
+
+/// 交叉引用表中的偏移量必须指向对应的对象
+fn check_xref(doc: &Document, scanned: &[ScannedObject], reported: &mut HashSet<ObjectId>, issues: &mut Vec<Issue>) {
+    let markers: HashMap<usize, ObjectId> = scanned.iter().map(|o| (o.offset, o.id)).collect();
+    for (&number, entry) in &doc.reference_table.entries {
+        let XrefEntry::Normal { offset, generation } = *entry else { continue };
+        let id = (number, generation);
+        if markers.get(&(offset as usize)) != Some(&id) {
+            issues.push(Issue::new("broken_xref", Some(id), format!("交叉引用表中对象 {} 的偏移量 {} 处不是该对象", object_name(id), offset)));
+            reported.insert(id);
+        } else if !doc.objects.contains_key(&id) && reported.insert(id) {
+            issues.push(Issue::new("unreadable_object", Some(id), format!("对象 {} 无法解析", object_name(id))));
+        }
+    }
+}
+
+pub(crate) fn collect_references(object: &Object, references: &mut Vec<ObjectId>) {
+    match object {
+        Object::Reference(id) => references.push(*id),
+        Object::Array(items) => items.iter().for_each(|item| collect_references(item, references)),
+        Object::Dictionary(dict) => dict.iter().for_each(|(_, value)| collect_references(value, references)),
+        Object::Stream(stream) => stream.dict.iter().for_each(|(_, value)| collect_references(value, references)),
+        _ => {}
+    }
+}
+
+/// 被引用但不存在的对象；能通过扫描找到的属于交叉引用表的问题
+fn check_references(doc: &Document, recovered: &Document, reported: &mut HashSet<ObjectId>, issues: &mut Vec<Issue>) {
+    let mut references = Vec::new();
+    collect_references(&Object::Dictionary(doc.trailer.clone()), &mut references);
+    let sources: Vec<(Option<ObjectId>, usize)> = std::iter::once((None, references.len()))
+        .chain(doc.objects.iter().map(|(&id, object)| {
+            collect_references(object, &mut references);
+            (Some(id), references.len())
+        }))
+        .collect();
+
+    let mut start = 0;
+    for (source, end) in sources {
+        for &id in &references[start..end] {
+            if doc.objects.contains_key(&id) || !reported.insert(id) {
+                continue;
+            }
+            let from = source.map_or("文件尾（trailer）".to_string(), |s| format!("对象 {}", object_name(s)));
+            if recovered.objects.contains_key(&id) {
+                issues.push(Issue::new("broken_xref", Some(id), format!("{} 引用的对象 {} 不在交叉引用表中", from, object_name(id))));
+            } else {
+                issues.push(Issue::new("missing_object", Some(id), format!("{} 引用了不存在的对象 {}", from, object_name(id))));
+            }
+        }
+        start = end;
+    }
+}
+
+fn is_page_tree_node(node: &Dictionary) -> bool {
+    node.type_is(b"Pages") || (node.has(b"Kids") && !node.type_is(b"Page"))
+}
+
+fn kids<'a>(doc: &'a Document, node: &'a Dictionary) -> Option<&'a Vec<Object>> {
+    let kids = node.get(b"Kids").ok()?;
+    doc.dereference(kids).ok()?.1.as_array().ok()
+}
+
+fn check_page_tree(doc: &Document, issues: &mut Vec<Issue>) {
+    let Ok(catalog) = doc.catalog() else {
+        issues.push(Issue::new("bad_page_tree", None, "缺少文档目录（/Root）".to_string()));
+        return;
+    };
+    let root = catalog.get(b"Pages").and_then(Object::as_reference).ok()
+        .filter(|&id| doc.get_dictionary(id).is_ok_and(is_page_tree_node));
+    let Some(root) = root else {
+        issues.push(Issue::new("bad_page_tree", None, "文档目录缺少有效的页面树（/Pages）".to_string()));
+        return;
+    };
+    if check_page_node(doc, root, None, &mut HashSet::new(), issues, 0) == 0 {
+        issues.push(Issue::new("bad_page_tree", None, "文档没有页面".to_string()));
+    }
+}
+
+/// 检查页面树节点，返回其下的页数
+fn check_page_node(
+    doc: &Document,
+    id: ObjectId,
+    parent: Option<ObjectId>,
+    visited: &mut HashSet<ObjectId>,
+    issues: &mut Vec<Issue>,
+    depth: usize,
+) -> usize {
+    if depth > MAX_DEPTH || !visited.insert(id) {
+        issues.push(Issue::new("bad_page_tree", Some(id), format!("对象 {} 在页面树中重复出现或形成循环", object_name(id))));
+        return 0;
+    }
+    let Ok(node) = doc.get_dictionary(id) else {
+        issues.push(Issue::new("bad_page_tree", Some(id), format!("页面树中的对象 {} 不存在或不是字典", object_name(id))));
+        return 0;
+    };
+    if let Some(parent) = parent {
+        if node.get(b"Parent").and_then(Object::as_reference).ok() != Some(parent) {
+            issues.push(Issue::new("bad_page_tree", Some(id), format!("对象 {} 的 /Parent 不指向所在的页面树节点", object_name(id))));
+        }
+    }
+
+    if !is_page_tree_node(node) {
+        if !node.type_is(b"Page") {
+            issues.push(Issue::new("bad_page_tree", Some(id), format!("对象 {} 不是页面（缺少 /Type /Page）", object_name(id))));
+        }
+        return 1;
+    }
+
+    let Some(kids) = kids(doc, node) else {
+        issues.push(Issue::new("bad_page_tree", Some(id), format!("页面树节点 {} 缺少 /Kids", object_name(id))));
+        return 0;
+    };
+    let mut count = 0;
+    for kid in kids {
+        match kid.as_reference() {
+            Ok(kid_id) => count += check_page_node(doc, kid_id, Some(id), visited, issues, depth + 1),
+            Err(_) => issues.push(Issue::new("bad_page_tree", Some(id), format!("页面树节点 {} 的 /Kids 中有非引用项", object_name(id)))),
+        }
+    }
+    let declared = node.get(b"Count").and_then(Object::as_i64).ok();
+    if declared != Some(count as i64) {
+        let declared = declared.map_or("缺失".to_string(), |c| c.to_string());
+        issues.push(Issue::new("wrong_count", Some(id), format!("页面树节点 {} 的 /Count 为 {}，实际页数为 {}", object_name(id), declared, count)));
+    }
+    count
+}
+
+/// 修复文件结构：补回交叉引用表漏掉或偏移量错误的对象，修正流长度，重建页面树
+pub(crate) fn repair_document(buffer: &[u8]) -> Result<Document, String> {
+    let scanned = scan_objects(buffer);
+    let recovered = recover_document(buffer, &scanned);
+    let mut doc = match Document::load_mem(buffer) {
+        Ok(mut doc) => {
+            // 交叉引用表漏掉的对象，以及因长度错误被读成字典的流
+            for (id, object) in recovered.objects {
+                let lost_stream = object.as_stream().is_ok() && doc.objects.get(&id).is_some_and(|o| o.as_stream().is_err());
+                if lost_stream || !doc.objects.contains_key(&id) {
+                    doc.objects.insert(id, object);
+                }
+            }
+            if doc.catalog().is_err() {
+                doc.trailer = recovered.trailer;
+            }
+            doc
+        }
+        Err(_) => recovered,
+    };
+    if doc.objects.is_empty() {
+        return Err("无法修复 PDF: 未找到任何对象".to_string());
+    }
+    doc.max_id = doc.objects.keys().map(|id| id.0).max().unwrap_or(0);
+
+    // 交叉引用流和增量更新相关的条目在重新保存时失效
+    for key in [b"Prev".as_slice(), b"XRefStm", b"Type", b"W", b"Index", b"Filter", b"DecodeParms", b"Length"] {
+        doc.trailer.remove(key);
+    }
+    if doc.trailer.get(b"Info").and_then(Object::as_reference).is_ok_and(|id| doc.get_dictionary(id).is_err()) {
+        doc.trailer.remove(b"Info");
+    }
+
+    rebuild_page_tree(&mut doc)?;
+    doc.prune_objects();
+    Ok(doc)
+}
+
+/// 容错地收集页面树中的页面，跳过缺失、重复和循环引用的节点
+fn collect_pages(doc: &Document, id: ObjectId, visited: &mut HashSet<ObjectId>, pages: &mut Vec<ObjectId>, depth: usize) {
+    if depth > MAX_DEPTH || !visited.insert(id) {
+        return;
+    }
+    let Ok(node) = doc.get_dictionary(id) else { return };
+    if is_page_tree_node(node) {
+        for kid in kids(doc, node).into_iter().flatten() {
+            if let Ok(kid_id) = kid.as_reference() {
+                collect_pages(doc, kid_id, visited, pages, depth + 1);
+            }
+        }
+    } else if node.type_is(b"Page") || !node.has(b"Type") {
+        pages.push(id);
+    }
+}
+
+fn rebuild_page_tree(doc: &mut Document) -> Result<(), String> {
+    let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).ok()
+        .filter(|&id| doc.get_dictionary(id).is_ok_and(|c| c.type_is(b"Catalog") || c.has(b"Pages")))
+        .or_else(|| {
+            doc.objects.iter().rev()
+                .find(|(_, o)| o.as_dict().is_ok_and(|d| d.type_is(b"Catalog")))
+                .map(|(&id, _)| id)
+        })
+        .unwrap_or_else(|| doc.add_object(Dictionary::new()));
+    doc.trailer.set("Root", Object::Reference(catalog_id));
+
+    let root = doc.get_dictionary(catalog_id).ok()
+        .and_then(|c| c.get(b"Pages").and_then(Object::as_reference).ok())
+        .filter(|&id| doc.get_dictionary(id).is_ok_and(|n| !n.type_is(b"Page")));
+    let mut pages = Vec::new();
+    if let Some(root) = root {
+        collect_pages(doc, root, &mut HashSet::new(), &mut pages, 0);
+    }
+    // 页面树完全损坏时按对象号顺序使用所有页面对象
+    if pages.is_empty() {
+        pages = doc.objects.iter()
+            .filter(|(_, o)| o.as_dict().is_ok_and(|d| d.type_is(b"Page")))
+            .map(|(&id, _)| id)
+            .collect();
+    }
+    if pages.is_empty() {
+        return Err("无法修复 PDF: 未找到任何页面".to_string());
+    }
+
+    materialize_inherited_attributes(doc, &pages);
+    for &page_id in &pages {
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Type", Object::Name(b"Page".to_vec()));
+            if !page.has(b"MediaBox") {
+                page.set("MediaBox", vec![0.into(), 0.into(), 612.into(), 792.into()]);
+            }
+        }
+    }
+
+    let pages_id = root.unwrap_or_else(|| doc.add_object(Dictionary::new()));
+    doc.get_dictionary_mut(pages_id)
+        .map_err(|e| format!("无法读取页面树: {}", e))?
+        .set("Type", Object::Name(b"Pages".to_vec()));
+    set_page_tree_kids(doc, pages_id, &pages)?;
+    if let Ok(pages_node) = doc.get_dictionary_mut(pages_id) {
+        pages_node.remove(b"Parent");
+    }
+
+    let catalog = doc.get_dictionary_mut(catalog_id)
+        .map_err(|e| format!("无法获取文档目录: {}", e))?;
+    catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+    catalog.set("Pages", Object::Reference(pages_id));
+    Ok(())
+}
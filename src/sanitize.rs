@@ -0,0 +1,282 @@
+// 清理 PDF 中的主动内容：JavaScript、自动执行的动作、危险动作、嵌入文件、多媒体和 XFA，
+// 可选删除链接动作和元数据，并统计删除的内容
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::annotations::process_page_annotations;
+
+/// 清理策略（以 JSON 字符串传入）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct SanitizePolicy {
+    /// 删除打开网址的链接动作（URI）
+    remove_uri: bool,
+    /// 删除文档信息（Info）和 XMP 元数据
+    remove_metadata: bool,
+    /// 其他需要删除的动作（转换为 PDF/A 时使用）
+    #[serde(skip)]
+    extra_actions: Option<fn(&Dictionary) -> bool>,
+    /// 保留附件和关联文件（转换为 PDF/A-3 时使用）
+    #[serde(skip)]
+    keep_embedded_files: bool,
+}
+
+impl SanitizePolicy {
+    /// 转换为 PDF/A 时的策略：默认策略之外，还删除 extra_actions 选中的动作；keep_embedded_files 时保留附件
+    pub(crate) fn for_pdfa(extra_actions: fn(&Dictionary) -> bool, keep_embedded_files: bool) -> SanitizePolicy {
+        SanitizePolicy { extra_actions: Some(extra_actions), keep_embedded_files, ..SanitizePolicy::default() }
+    }
+}
+
+/// 各类被删除内容的数量
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct SanitizeReport {
+    /// JavaScript 动作和文档级脚本
+    pub javascript: usize,
+    /// 打开文档时执行的动作（/OpenAction）
+    pub open_action: usize,
+    /// 附加动作（/AA），如页面打开、字段计算和格式化脚本
+    pub additional_actions: usize,
+    /// 启动外部程序的动作（/Launch）
+    pub launch: usize,
+    /// 提交表单数据的动作（/SubmitForm）
+    pub submit_form: usize,
+    /// 打开网址的动作（/URI），仅在 remove_uri 时删除
+    pub uri: usize,
+    /// 其他危险动作：ImportData、GoToR、GoToE、Rendition、RichMediaExecute
+    pub other_actions: usize,
+    /// 嵌入文件（附件）
+    pub embedded_files: usize,
+    /// 多媒体注释（RichMedia、Screen、Movie、Sound、3D）
+    pub rich_media: usize,
+    /// XFA 表单
+    pub xfa: usize,
+    /// 文档信息和 XMP 元数据流，仅在 remove_metadata 时删除
+    pub metadata: usize,
+}
+
+/// 需要删除的动作类型
+#[derive(Clone, Copy)]
+enum DangerousAction {
+    JavaScript,
+    Launch,
+    SubmitForm,
+    Uri,
+    Other,
+}
+
+/// 遍历嵌套对象时允许的最大深度
+const MAX_DEPTH: usize = 64;
+
+pub(crate) fn sanitize_document(doc: &mut Document, policy: &SanitizePolicy) -> Result<SanitizeReport, String> {
+    let mut report = SanitizeReport::default();
+
+    // 附件和多媒体注释整体删除
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let mut media_count = 0;
+    for page_id in page_ids {
+        let select = |_: &Document, _: Option<ObjectId>, annot: &Dictionary| {
+            match annot.get(b"Subtype").and_then(Object::as_name).unwrap_or(b"") {
+                b"FileAttachment" => !policy.keep_embedded_files,
+                subtype => matches!(subtype, b"RichMedia" | b"Screen" | b"Movie" | b"Sound" | b"3D"),
+            }
+        };
+        let media = |annot: &Object| {
+            doc.dereference(annot).ok()
+                .and_then(|(_, a)| a.as_dict().ok())
+                .is_some_and(|a| a.get(b"Subtype").and_then(Object::as_name).is_ok_and(|s| s != b"FileAttachment" && select(doc, None, a)))
+        };
+        media_count += doc.get_dictionary(page_id).ok()
+            .and_then(|p| p.get(b"Annots").ok())
+            .and_then(|a| doc.dereference(a).ok())
+            .and_then(|(_, a)| a.as_array().ok())
+            .map_or(0, |annots| annots.iter().filter(|a| media(a)).count());
+        process_page_annotations(doc, page_id, &select, false)?;
+    }
+    report.rich_media = media_count;
+
+    if !policy.keep_embedded_files {
+        report.embedded_files = doc.objects.values()
+            .filter_map(|o| o.as_stream().ok())
+            .filter(|s| s.dict.get(b"Type").and_then(Object::as_name).ok() == Some(b"EmbeddedFile".as_slice()))
+            .count();
+    }
+
+    // 文档级脚本和附件名称树
+    let catalog = doc.catalog().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    let names = catalog.get(b"Names").ok().cloned();
+    let names_id = names.as_ref().and_then(|n| n.as_reference().ok());
+    let names_dict = names.as_ref()
+        .and_then(|n| doc.dereference(n).ok())
+        .and_then(|(_, n)| n.as_dict().ok().cloned());
+    if let Some(mut names_dict) = names_dict {
+        if let Ok(tree) = names_dict.get(b"JavaScript") {
+            report.javascript += name_tree_len(doc, tree, 0);
+        }
+        names_dict.remove(b"JavaScript");
+        if !policy.keep_embedded_files {
+            names_dict.remove(b"EmbeddedFiles");
+        }
+        let names_object = Object::Dictionary(names_dict);
+        match names_id {
+            Some(id) => {
+                doc.objects.insert(id, names_object);
+            }
+            None => {
+                let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+                catalog.set("Names", names_object);
+            }
+        }
+    }
+
+    let catalog = doc.catalog_mut().map_err(|e| format!("无法获取文档目录: {}", e))?;
+    if catalog.remove(b"OpenAction").is_some() {
+        report.open_action += 1;
+    }
+    // XFA 被删除后不再需要由阅读器渲染
+    catalog.remove(b"NeedsRendering");
+
+    // 危险的动作对象，引用它们的 /A 和 /Next 被删除
+    let dangerous: HashMap<ObjectId, DangerousAction> = doc.objects.iter()
+        .filter_map(|(&id, object)| Some((id, dangerous_action(object.as_dict().ok()?, policy)?)))
+        .collect();
+
+    let ids: Vec<ObjectId> = doc.objects.keys().copied().collect();
+    for id in ids {
+        if let Some(object) = doc.objects.get_mut(&id) {
+            sanitize_object(object, &dangerous, policy, &mut report, 0);
+        }
+    }
+
+    if policy.remove_metadata && doc.trailer.remove(b"Info").is_some() {
+        report.metadata += 1;
+    }
+    Ok(report)
+}
+
+/// 名称树中的条目数
+fn name_tree_len(doc: &Document, node: &Object, depth: usize) -> usize {
+    let Some(node) = doc.dereference(node).ok().and_then(|(_, n)| n.as_dict().ok()) else { return 0 };
+    if depth > MAX_DEPTH {
+        return 0;
+    }
+    let names = node.get(b"Names").ok()
+        .and_then(|n| doc.dereference(n).ok())
+        .and_then(|(_, n)| n.as_array().ok())
+        .map_or(0, |n| n.len() / 2);
+    let kids = node.get(b"Kids").ok()
+        .and_then(|k| doc.dereference(k).ok())
+        .and_then(|(_, k)| k.as_array().ok())
+        .map_or(0, |kids| kids.iter().map(|kid| name_tree_len(doc, kid, depth + 1)).sum());
+    names + kids
+}
+
+fn dangerous_action(dict: &Dictionary, policy: &SanitizePolicy) -> Option<DangerousAction> {
+    let action = match dict.get(b"S").and_then(Object::as_name).ok()? {
+        b"JavaScript" => DangerousAction::JavaScript,
+        b"Launch" => DangerousAction::Launch,
+        b"SubmitForm" => DangerousAction::SubmitForm,
+        b"URI" if policy.remove_uri => DangerousAction::Uri,
+        b"ImportData" | b"GoToR" | b"GoToE" | b"Rendition" | b"RichMediaExecute" => DangerousAction::Other,
+        _ if policy.extra_actions.is_some_and(|extra| extra(dict)) => DangerousAction::Other,
+        _ => return None,
+    };
+    Some(action)
+}
+
+impl SanitizeReport {
+    fn count(&mut self, action: DangerousAction) {
+        match action {
+            DangerousAction::JavaScript => self.javascript += 1,
+            DangerousAction::Launch => self.launch += 1,
+            DangerousAction::SubmitForm => self.submit_form += 1,
+            DangerousAction::Uri => self.uri += 1,
+            DangerousAction::Other => self.other_actions += 1,
+        }
+    }
+}
+
+/// 动作引用（或直接的动作字典）是否需要删除，需要时计数
+fn check_action(
+    value: &Object,
+    dangerous: &HashMap<ObjectId, DangerousAction>,
+    policy: &SanitizePolicy,
+    report: &mut SanitizeReport,
+) -> bool {
+    let action = match value {
+        Object::Reference(id) => dangerous.get(id).copied(),
+        Object::Dictionary(dict) => dangerous_action(dict, policy),
+        _ => None,
+    };
+    if let Some(action) = action {
+        report.count(action);
+    }
+    action.is_some()
+}
+
+fn sanitize_object(
+    object: &mut Object,
+    dangerous: &HashMap<ObjectId, DangerousAction>,
+    policy: &SanitizePolicy,
+    report: &mut SanitizeReport,
+    depth: usize,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &mut stream.dict,
+        Object::Array(items) => {
+            for item in items {
+                sanitize_object(item, dangerous, policy, report, depth + 1);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    if dict.remove(b"AA").is_some() {
+        report.additional_actions += 1;
+    }
+    if dict.get(b"A").is_ok_and(|a| check_action(a, dangerous, policy, report)) {
+        dict.remove(b"A");
+    }
+    // 动作链中的后续动作
+    let next = dict.get(b"Next").ok().cloned();
+    match next {
+        Some(Object::Array(actions)) => {
+            let kept: Vec<Object> = actions.into_iter()
+                .filter(|a| !check_action(a, dangerous, policy, report))
+                .collect();
+            dict.set("Next", Object::Array(kept));
+        }
+        Some(action) if check_action(&action, dangerous, policy, report) => {
+            dict.remove(b"Next");
+        }
+        _ => {}
+    }
+
+    // 文件规范中的嵌入文件和关联文件（PDF/A-3）
+    if !policy.keep_embedded_files {
+        dict.remove(b"EF");
+        dict.remove(b"RF");
+        dict.remove(b"AF");
+    }
+    // AcroForm 中的 XFA
+    if dict.has(b"Fields") && dict.remove(b"XFA").is_some() {
+        report.xfa += 1;
+    }
+    if policy.remove_metadata {
+        if dict.remove(b"Metadata").is_some() {
+            report.metadata += 1;
+        }
+        dict.remove(b"PieceInfo");
+    }
+
+    for (_, value) in dict.iter_mut() {
+        sanitize_object(value, dangerous, policy, report, depth + 1);
+    }
+}
@@ -0,0 +1,272 @@
+// PDF 标准 14 字体（无需嵌入）的编码与字宽
+// 标准字体只能显示 WinAnsiEncoding 中的字符，其他字符以 "?" 代替
+
+use lopdf::{Dictionary, Object};
+
+/// Helvetica 中 ASCII 32-126 的字宽（1/1000 字号）
+const HELVETICA_ASCII_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // 空格 - /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // 0 - ?
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // @ - O
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // P - _
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // ` - o
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // p - ~
+];
+
+/// Helvetica-Bold 中 ASCII 32-126 的字宽
+const HELVETICA_BOLD_ASCII_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Times-Roman 中 ASCII 32-126 的字宽
+const TIMES_ROMAN_ASCII_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444,
+    921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722,
+    556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500,
+    333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500,
+    500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541,
+];
+
+/// Times-Bold 中 ASCII 32-126 的字宽
+const TIMES_BOLD_ASCII_WIDTHS: [u16; 95] = [
+    250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+    930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778,
+    611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667, 333, 278, 333, 581, 500,
+    333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500,
+    556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394, 220, 394, 520,
+];
+
+/// Times-Italic 中 ASCII 32-126 的字宽
+const TIMES_ITALIC_ASCII_WIDTHS: [u16; 95] = [
+    250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500,
+    920, 611, 611, 667, 722, 611, 611, 722, 722, 333, 444, 667, 556, 833, 667, 722,
+    611, 722, 611, 500, 556, 722, 611, 833, 611, 556, 556, 389, 278, 389, 422, 500,
+    333, 500, 500, 444, 500, 444, 278, 500, 500, 278, 278, 444, 278, 722, 500, 500,
+    500, 500, 389, 389, 278, 500, 444, 667, 444, 444, 389, 400, 275, 400, 541,
+];
+
+/// Times-BoldItalic 中 ASCII 32-126 的字宽
+const TIMES_BOLD_ITALIC_ASCII_WIDTHS: [u16; 95] = [
+    250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+    832, 667, 667, 667, 722, 667, 667, 722, 778, 389, 500, 667, 611, 889, 722, 722,
+    611, 722, 667, 556, 611, 722, 667, 889, 667, 611, 611, 333, 278, 333, 570, 500,
+    333, 500, 500, 444, 500, 444, 333, 500, 556, 278, 278, 500, 278, 778, 556, 500,
+    500, 500, 389, 389, 278, 556, 444, 667, 500, 444, 389, 348, 220, 348, 570,
+];
+
+/// WinAnsiEncoding 中 0x80-0x9F 对应的 Unicode 字符（0 表示未定义）
+const WIN_ANSI_HIGH: [u16; 32] = [
+    0x20AC, 0, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021,
+    0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0, 0x017D, 0,
+    0, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0, 0x017E, 0x0178,
+];
+
+/// 标准字体的字体字典
+pub(crate) fn font_dictionary(base_font: &str) -> Dictionary {
+    let mut font = Dictionary::new();
+    font.set("Type", Object::Name(b"Font".to_vec()));
+    font.set("Subtype", Object::Name(b"Type1".to_vec()));
+    font.set("BaseFont", Object::Name(base_font.as_bytes().to_vec()));
+    font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+    font
+}
+
+/// 将文本编码为 WinAnsiEncoding，无法表示的字符替换为 "?"
+pub(crate) fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars().map(|c| win_ansi_code(c).unwrap_or(b'?')).collect()
+}
+
+/// 字符在 WinAnsiEncoding 中的编码，无法表示时返回 None
+pub(crate) fn win_ansi_code(c: char) -> Option<u8> {
+    match c as u32 {
+        0x20..=0x7E | 0xA0..=0xFF => Some(c as u8),
+        code => WIN_ANSI_HIGH
+            .iter()
+            .position(|&u| u != 0 && u as u32 == code)
+            .map(|i| 0x80 + i as u8),
+    }
+}
+
+/// WinAnsiEncoding 编码对应的 Unicode 字符
+pub(crate) fn win_ansi_to_unicode(code: u8) -> Option<char> {
+    match code {
+        0x80..=0x9F => char::from_u32(WIN_ANSI_HIGH[(code - 0x80) as usize] as u32).filter(|&c| c != '\0'),
+        _ => Some(code as char),
+    }
+}
+
+/// 可用于排版文字的标准字体（Symbol 和 ZapfDingbats 使用专用编码，不在此列）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum StandardFont {
+    Helvetica,
+    HelveticaBold,
+    HelveticaOblique,
+    HelveticaBoldOblique,
+    TimesRoman,
+    TimesBold,
+    TimesItalic,
+    TimesBoldItalic,
+    Courier,
+    CourierBold,
+    CourierOblique,
+    CourierBoldOblique,
+}
+
+impl StandardFont {
+    const ALL: [StandardFont; 12] = [
+        StandardFont::Helvetica,
+        StandardFont::HelveticaBold,
+        StandardFont::HelveticaOblique,
+        StandardFont::HelveticaBoldOblique,
+        StandardFont::TimesRoman,
+        StandardFont::TimesBold,
+        StandardFont::TimesItalic,
+        StandardFont::TimesBoldItalic,
+        StandardFont::Courier,
+        StandardFont::CourierBold,
+        StandardFont::CourierOblique,
+        StandardFont::CourierBoldOblique,
+    ];
+
+    /// 按字体名称查找，不区分大小写；"Times" 等同于 "Times-Roman"
+    pub(crate) fn parse(name: &str) -> Result<StandardFont, String> {
+        let normalized = name.trim().to_lowercase();
+        if normalized == "times" {
+            return Ok(StandardFont::TimesRoman);
+        }
+        StandardFont::ALL
+            .into_iter()
+            .find(|font| font.base_font().to_lowercase() == normalized)
+            .ok_or_else(|| format!("不支持的标准字体: {}", name))
+    }
+
+    pub(crate) fn base_font(self) -> &'static str {
+        match self {
+            StandardFont::Helvetica => "Helvetica",
+            StandardFont::HelveticaBold => "Helvetica-Bold",
+            StandardFont::HelveticaOblique => "Helvetica-Oblique",
+            StandardFont::HelveticaBoldOblique => "Helvetica-BoldOblique",
+            StandardFont::TimesRoman => "Times-Roman",
+            StandardFont::TimesBold => "Times-Bold",
+            StandardFont::TimesItalic => "Times-Italic",
+            StandardFont::TimesBoldItalic => "Times-BoldItalic",
+            StandardFont::Courier => "Courier",
+            StandardFont::CourierBold => "Courier-Bold",
+            StandardFont::CourierOblique => "Courier-Oblique",
+            StandardFont::CourierBoldOblique => "Courier-BoldOblique",
+        }
+    }
+
+    /// 同一字族中指定粗细和倾斜的字体
+    pub(crate) fn styled(self, bold: bool, italic: bool) -> StandardFont {
+        let family = StandardFont::ALL.iter().position(|&f| f == self).unwrap_or(0) / 4 * 4;
+        StandardFont::ALL[family + bold as usize + 2 * italic as usize]
+    }
+
+    /// WinAnsi 编码字符的字宽（1/1000 字号），ASCII 以外的字符使用平均字宽
+    pub(crate) fn char_width(self, code: u8) -> u16 {
+        let table = match self {
+            StandardFont::Helvetica | StandardFont::HelveticaOblique => &HELVETICA_ASCII_WIDTHS,
+            StandardFont::HelveticaBold | StandardFont::HelveticaBoldOblique => &HELVETICA_BOLD_ASCII_WIDTHS,
+            StandardFont::TimesRoman => &TIMES_ROMAN_ASCII_WIDTHS,
+            StandardFont::TimesBold => &TIMES_BOLD_ASCII_WIDTHS,
+            StandardFont::TimesItalic => &TIMES_ITALIC_ASCII_WIDTHS,
+            StandardFont::TimesBoldItalic => &TIMES_BOLD_ITALIC_ASCII_WIDTHS,
+            // Courier 为等宽字体
+            _ => return 600,
+        };
+        match code {
+            0x20..=0x7E => table[(code - 0x20) as usize],
+            _ => table[(b'n' - 0x20) as usize],
+        }
+    }
+
+    /// WinAnsi 编码文本的宽度（点）
+    pub(crate) fn text_width(self, text: &[u8], font_size: f64) -> f64 {
+        let units: u32 = text.iter().map(|&b| self.char_width(b) as u32).sum();
+        units as f64 * font_size / 1000.0
+    }
+}
+
+/// Helvetica 下 WinAnsi 编码文本的宽度（点）
+pub(crate) fn helvetica_width(text: &[u8], font_size: f64) -> f64 {
+    StandardFont::Helvetica.text_width(text, font_size)
+}
+
+/// 按最大宽度将文本折行，优先在空格处断开，过长的单词（以及不用空格分词的中文）按字符断开
+/// 文本中的换行符会强制换行；width 给出一段文本的宽度（点）
+pub(crate) fn wrap_text(text: &str, max_width: f64, width: impl Fn(&str) -> f64) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let mut candidate = line.clone();
+            if !candidate.is_empty() {
+                candidate.push(' ');
+            }
+            candidate.push_str(word);
+            if width(&candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // 单词本身超出宽度时逐字符断开
+            for c in word.chars() {
+                line.push(c);
+                if line.chars().nth(1).is_some() && width(&line) > max_width {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// 将文本截断到最大宽度以内，被截断时末尾加 "..."
+pub(crate) fn truncate_text(text: &str, max_width: f64, width: impl Fn(&str) -> f64) -> String {
+    if width(text) <= max_width {
+        return text.to_string();
+    }
+    let mut truncated = text.to_string();
+    let ellipsis_width = width("...");
+    while !truncated.is_empty() && width(&truncated) + ellipsis_width > max_width {
+        truncated.pop();
+    }
+    truncated.push_str("...");
+    truncated
+}
+
+/// 将字节写为 PDF 字面量字符串，如 "(Hello \(1\))"
+pub(crate) fn literal_string(text: &[u8]) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for &b in text {
+        match b {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            0x20..=0x7E => out.push(b as char),
+            _ => out.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    out.push(')');
+    out
+}
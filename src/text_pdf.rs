@@ -0,0 +1,772 @@
+// 文本和 Markdown 生成 PDF
+// 按纸张和边距排版：自动折行、分页；Markdown 模式支持标题、粗体/斜体、列表、代码块和分隔线，
+// 并由标题生成书签。文字使用标准字体，或嵌入用户提供的 TrueType/OpenType 字体子集以显示中文等字符
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::Deserialize;
+
+use crate::font_embed::{hex_string, EmbeddedFont};
+use crate::outline::{text_string, write_outline, DestView, OutlineItem};
+use crate::page_size::{parse_paper_size, MM_TO_PT};
+use crate::linearize::save_document;
+use crate::pdf_utils::build_page_tree;
+use crate::standard_font::{encode_win_ansi, font_dictionary, literal_string, win_ansi_code, StandardFont};
+
+/// 嵌入字体在资源字典中的名称
+const EMBEDDED_FONT_NAME: &str = "FE";
+
+/// text_to_pdf 的选项
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct TextPdfOptions {
+    /// 页面尺寸: "A4"（默认）、"Letter" 等纸张名称或自定义 "595x842"
+    page_size: String,
+    /// 页面方向: "portrait"（默认）或 "landscape"
+    orientation: String,
+    /// 四周边距（毫米）
+    margin_mm: f64,
+    /// 分别指定上、右、下、左边距（毫米），设置后忽略 margin_mm
+    margins_mm: Option<[f64; 4]>,
+    /// 标准字体字族: "Helvetica"（默认）、"Times-Roman"、"Courier"；嵌入字体时只用于代码
+    font: String,
+    /// 正文字号（点）
+    font_size: f64,
+    /// 行高与字号的比例
+    line_height: f64,
+    /// 按 Markdown 解析文本
+    markdown: bool,
+    /// 文档标题，写入文档信息
+    title: Option<String>,
+    /// 制表符展开的空格数
+    tab_width: usize,
+    /// 输出线性化（快速 Web 查看）文件
+    linearize: bool,
+}
+
+impl Default for TextPdfOptions {
+    fn default() -> Self {
+        TextPdfOptions {
+            page_size: "A4".to_string(),
+            orientation: "portrait".to_string(),
+            margin_mm: 20.0,
+            margins_mm: None,
+            font: "Helvetica".to_string(),
+            font_size: 11.0,
+            line_height: 1.4,
+            markdown: false,
+            title: None,
+            tab_width: 4,
+            linearize: false,
+        }
+    }
+}
+
+/// 生成 PDF，font_data 为要嵌入的 TrueType/OpenType 字体
+pub(crate) fn text_to_pdf_bytes(text: &str, options: &TextPdfOptions, font_data: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    if options.font_size <= 0.0 {
+        return Err("font_size 必须大于 0".to_string());
+    }
+    if options.line_height <= 0.0 {
+        return Err("line_height 必须大于 0".to_string());
+    }
+
+    let (mut width, mut height) = parse_paper_size(&options.page_size)?;
+    match options.orientation.trim().to_lowercase().as_str() {
+        "portrait" | "" => {
+            if width > height {
+                std::mem::swap(&mut width, &mut height);
+            }
+        }
+        "landscape" => {
+            if width < height {
+                std::mem::swap(&mut width, &mut height);
+            }
+        }
+        other => return Err(format!("不支持的页面方向: {}", other)),
+    }
+    let margins = options.margins_mm.unwrap_or([options.margin_mm; 4]);
+    if margins.iter().any(|m| *m < 0.0) {
+        return Err("边距不能为负数".to_string());
+    }
+    let margins = margins.map(|m| m * MM_TO_PT);
+    if width - margins[1] - margins[3] < options.font_size * 4.0 || height - margins[0] - margins[2] < options.font_size * 4.0 {
+        return Err("边距过大，页面上没有足够的排版空间".to_string());
+    }
+
+    let fonts = Fonts {
+        family: StandardFont::parse(&options.font)?,
+        embedded: font_data.map(EmbeddedFont::parse).transpose()?,
+        standard_used: Vec::new(),
+    };
+
+    let text = expand_tabs(text, options.tab_width);
+    let blocks = if options.markdown { parse_markdown(&text) } else { parse_plain(&text) };
+
+    let mut layout = Layout {
+        fonts,
+        page_size: (width, height),
+        margins,
+        font_size: options.font_size,
+        line_height: options.line_height,
+        pages: Vec::new(),
+        content: String::new(),
+        y: height - margins[0],
+        headings: Vec::new(),
+    };
+    for block in &blocks {
+        layout.block(block);
+    }
+    layout.finish_page();
+
+    layout.write_document(options.title.as_deref(), options.linearize)
+}
+
+// ---------------------------------------------------------------------------
+// 文档结构
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+}
+
+/// 同一样式的一段文字
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    style: Style,
+    text: String,
+}
+
+impl Span {
+    fn plain(text: &str) -> Span {
+        Span { style: Style::default(), text: text.to_string() }
+    }
+}
+
+enum Block {
+    /// 纯文本模式下的一行
+    Line(String),
+    Paragraph(Vec<Span>),
+    Heading(usize, Vec<Span>),
+    ListItem { depth: usize, marker: String, spans: Vec<Span> },
+    Code(Vec<String>),
+    Rule,
+}
+
+fn expand_tabs(text: &str, tab_width: usize) -> String {
+    let mut output = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let mut column = 0;
+        for c in line.chars() {
+            if c == '\t' {
+                let spaces = tab_width.max(1) - column % tab_width.max(1);
+                output.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            } else {
+                output.push(c);
+                column += 1;
+            }
+        }
+    }
+    output
+}
+
+fn parse_plain(text: &str) -> Vec<Block> {
+    text.lines().map(|line| Block::Line(line.trim_end().to_string())).collect()
+}
+
+fn parse_markdown(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<String>> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(parse_inline(&paragraph.join(" "))));
+            paragraph.clear();
+        }
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(lines) = code.as_mut() {
+            if trimmed.starts_with("```") {
+                blocks.push(Block::Code(std::mem::take(lines)));
+                code = None;
+            } else {
+                lines.push(line.trim_end().to_string());
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") {
+            flush(&mut paragraph, &mut blocks);
+            code = Some(Vec::new());
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+        } else if let Some((level, title)) = parse_heading(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Heading(level, parse_inline(title)));
+        } else if is_rule(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Rule);
+        } else if let Some((marker, rest)) = parse_list_marker(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            let indent = line.len() - line.trim_start().len();
+            blocks.push(Block::ListItem { depth: indent / 2, marker, spans: parse_inline(rest) });
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+
+    flush(&mut paragraph, &mut blocks);
+    // 未闭合的代码块保留到文末
+    if let Some(lines) = code {
+        blocks.push(Block::Code(lines));
+    }
+    blocks
+}
+
+/// "## 标题" → (2, "标题")
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// "---"、"***"、"___"（可含空格）
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|&c| c == marks[0])
+}
+
+/// 无序列表使用圆点，有序列表保留原编号
+fn parse_list_marker(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            return Some(("\u{2022}".to_string(), rest.trim_start()));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if (1..=9).contains(&digits) {
+        let rest = &line[digits..];
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((format!("{}.", &line[..digits]), rest.trim_start()));
+        }
+    }
+    None
+}
+
+/// 解析行内格式：**粗体**、*斜体*、***粗斜体***、`代码`、[链接](地址)，反斜杠转义
+fn parse_inline(text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut style = Style::default();
+    let mut current = String::new();
+
+    let push = |spans: &mut Vec<Span>, style: Style, text: &mut String| {
+        if !text.is_empty() {
+            spans.push(Span { style, text: std::mem::take(text) });
+        }
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                current.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '`' => {
+                if let Some(end) = chars[i + 1..].iter().position(|&n| n == '`') {
+                    push(&mut spans, style, &mut current);
+                    let code: String = chars[i + 1..i + 1 + end].iter().collect();
+                    spans.push(Span { style: Style { code: true, ..style }, text: code });
+                    i += end + 2;
+                    continue;
+                }
+            }
+            '[' | '!' => {
+                let start = if c == '!' && chars.get(i + 1) == Some(&'[') { i + 1 } else { i };
+                if chars[start] == '[' {
+                    if let Some((label, end)) = parse_link(&chars, start) {
+                        current.push_str(&label);
+                        i = end;
+                        continue;
+                    }
+                }
+            }
+            '*' | '_' => {
+                let run = chars[i..].iter().take_while(|&&n| n == c).count().min(3);
+                let before = i.checked_sub(1).map(|p| chars[p]);
+                let after = chars.get(i + run).copied();
+                // 下划线只在单词边界生效，避免 snake_case 被当作斜体
+                let word_inside = c == '_'
+                    && before.is_some_and(char::is_alphanumeric)
+                    && after.is_some_and(char::is_alphanumeric);
+                let (bold, italic) = (run >= 2, run != 2);
+                let closing = (!bold || style.bold) && (!italic || style.italic);
+                let valid = if closing {
+                    before.is_some_and(|b| !b.is_whitespace())
+                } else {
+                    after.is_some_and(|a| !a.is_whitespace()) && has_delimiter(&chars[i + run..], c, run)
+                };
+                if valid && !word_inside {
+                    push(&mut spans, style, &mut current);
+                    style.bold ^= bold;
+                    style.italic ^= italic;
+                    i += run;
+                    continue;
+                }
+                current.extend(&chars[i..i + run]);
+                i += run;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        i += 1;
+    }
+    push(&mut spans, style, &mut current);
+    spans
+}
+
+fn has_delimiter(rest: &[char], c: char, run: usize) -> bool {
+    rest.windows(run).enumerate().any(|(index, window)| {
+        index > 0 && window.iter().all(|&n| n == c) && !rest[index - 1].is_whitespace()
+    })
+}
+
+/// 解析 [文字](地址)，返回文字和结束位置
+fn parse_link(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let close = start + chars[start..].iter().position(|&c| c == ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = close + 1 + chars[close + 1..].iter().position(|&c| c == ')')?;
+    Some((chars[start + 1..close].iter().collect(), end + 1))
+}
+
+// ---------------------------------------------------------------------------
+// 字体
+// ---------------------------------------------------------------------------
+
+struct Fonts {
+    family: StandardFont,
+    embedded: Option<EmbeddedFont>,
+    /// 用到的标准字体，资源名依次为 F1、F2……
+    standard_used: Vec<StandardFont>,
+}
+
+impl Fonts {
+    /// 选择绘制文字的标准字体，返回 None 表示使用嵌入字体
+    /// 代码优先使用 Courier；嵌入字体时其余文字都使用嵌入字体
+    fn choose(&self, style: Style, text: &str) -> Option<StandardFont> {
+        let courier = StandardFont::Courier.styled(style.bold, style.italic);
+        match &self.embedded {
+            Some(_) if style.code && text.chars().all(|c| win_ansi_code(c).is_some()) => Some(courier),
+            Some(_) => None,
+            None if style.code => Some(courier),
+            None => Some(self.family.styled(style.bold, style.italic)),
+        }
+    }
+
+    fn width(&self, style: Style, text: &str, size: f64) -> f64 {
+        match (self.choose(style, text), &self.embedded) {
+            (None, Some(font)) => font.text_width(text, size),
+            (standard, _) => standard.unwrap_or(self.family).text_width(&encode_win_ansi(text), size),
+        }
+    }
+
+    /// 在 (x, y) 处绘制一段文字的内容流；嵌入字体没有粗体和斜体，分别用描边加粗和倾斜模拟
+    fn show(&mut self, style: Style, text: &str, size: f64, x: f64, y: f64) -> String {
+        match (self.choose(style, text), self.embedded.as_mut()) {
+            (None, Some(font)) => {
+                let encoded = hex_string(&font.encode(text));
+                let skew = if style.italic { 0.2 } else { 0.0 };
+                let bold = if style.bold { format!("2 Tr\n{} w\n", size * 0.03) } else { String::new() };
+                format!(
+                    "q\n{}BT\n/{} {} Tf\n1 0 {} 1 {} {} Tm\n{} Tj\nET\nQ\n",
+                    bold, EMBEDDED_FONT_NAME, size, skew, x, y, encoded
+                )
+            }
+            (standard, _) => {
+                let font = standard.unwrap_or(self.family);
+                let index = match self.standard_used.iter().position(|&f| f == font) {
+                    Some(index) => index,
+                    None => {
+                        self.standard_used.push(font);
+                        self.standard_used.len() - 1
+                    }
+                };
+                format!(
+                    "BT\n/F{} {} Tf\n1 0 0 1 {} {} Tm\n{} Tj\nET\n",
+                    index + 1, size, x, y, literal_string(&encode_win_ansi(text))
+                )
+            }
+        }
+    }
+
+    /// 写入用到的字体，返回字体资源字典
+    fn write(&self, doc: &mut Document) -> Result<Dictionary, String> {
+        let mut resources = Dictionary::new();
+        for (index, font) in self.standard_used.iter().enumerate() {
+            let font_id = doc.add_object(Object::Dictionary(font_dictionary(font.base_font())));
+            resources.set(format!("F{}", index + 1), Object::Reference(font_id));
+        }
+        if let Some(font) = &self.embedded {
+            let font_id = doc.new_object_id();
+            font.write(doc, font_id)?;
+            resources.set(EMBEDDED_FONT_NAME, Object::Reference(font_id));
+        }
+        Ok(resources)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 排版
+// ---------------------------------------------------------------------------
+
+/// 折行的最小单位：单词、空格或单个中日韩字符
+struct Piece {
+    style: Style,
+    text: String,
+    width: f64,
+    space: bool,
+}
+
+struct Heading {
+    level: usize,
+    title: String,
+    page_index: usize,
+    top: f64,
+}
+
+struct Layout {
+    fonts: Fonts,
+    page_size: (f64, f64),
+    /// 上、右、下、左边距（点）
+    margins: [f64; 4],
+    font_size: f64,
+    line_height: f64,
+    /// 已完成页面的内容流
+    pages: Vec<String>,
+    content: String,
+    /// 下一行的顶部位置
+    y: f64,
+    headings: Vec<Heading>,
+}
+
+impl Layout {
+    fn top(&self) -> f64 {
+        self.page_size.1 - self.margins[0]
+    }
+
+    fn left(&self) -> f64 {
+        self.margins[3]
+    }
+
+    fn content_width(&self) -> f64 {
+        self.page_size.0 - self.margins[1] - self.margins[3]
+    }
+
+    fn at_page_top(&self) -> bool {
+        self.y >= self.top()
+    }
+
+    fn finish_page(&mut self) {
+        if !self.content.is_empty() || self.pages.is_empty() {
+            self.pages.push(std::mem::take(&mut self.content));
+        }
+        self.y = self.top();
+    }
+
+    /// 剩余空间不足 height 时换页
+    fn ensure_space(&mut self, height: f64) {
+        if !self.at_page_top() && self.y - height < self.margins[2] {
+            self.finish_page();
+        }
+    }
+
+    /// 段前间距，位于页面顶部时省略
+    fn space_before(&mut self, amount: f64) {
+        if !self.at_page_top() {
+            self.y -= amount;
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        let size = self.font_size;
+        let leading = size * self.line_height;
+        match block {
+            Block::Line(text) => {
+                self.paragraph(&[Span::plain(text)], self.left(), self.content_width(), size);
+            }
+            Block::Paragraph(spans) => {
+                self.paragraph(spans, self.left(), self.content_width(), size);
+                self.y -= size * 0.5;
+            }
+            Block::Heading(level, spans) => {
+                let scale = [2.0, 1.6, 1.35, 1.15, 1.0, 0.9][(*level).clamp(1, 6) - 1];
+                let heading_size = size * scale;
+                let spans: Vec<Span> = spans
+                    .iter()
+                    .map(|span| Span { style: Style { bold: true, ..span.style }, text: span.text.clone() })
+                    .collect();
+                self.space_before(heading_size * 0.6);
+                // 标题后至少留一行正文的空间，避免标题落在页尾
+                self.ensure_space(heading_size * self.line_height + leading);
+                self.headings.push(Heading {
+                    level: *level,
+                    title: spans.iter().map(|span| span.text.as_str()).collect(),
+                    page_index: self.pages.len(),
+                    top: self.y,
+                });
+                self.paragraph(&spans, self.left(), self.content_width(), heading_size);
+                self.y -= heading_size * 0.3;
+            }
+            Block::ListItem { depth, marker, spans } => {
+                let indent = self.left() + size * 1.5 * *depth as f64;
+                let marker_width = self.fonts.width(Style::default(), marker, size);
+                let text_x = indent + (marker_width + size * 0.5).max(size * 1.5);
+                let width = (self.left() + self.content_width() - text_x).max(size * 4.0);
+                self.ensure_space(leading);
+                let baseline = self.baseline(size, leading);
+                let marker_content = self.fonts.show(Style::default(), marker, size, indent, baseline);
+                self.content.push_str(&marker_content);
+                self.paragraph(spans, text_x, width, size);
+                self.y -= size * 0.2;
+            }
+            Block::Code(lines) => {
+                let code_size = size * 0.9;
+                let code_leading = code_size * 1.35;
+                let padding = code_size * 0.5;
+                let width = self.content_width() - padding * 2.0;
+                self.space_before(size * 0.2);
+                self.ensure_space(padding + code_leading);
+                self.code_background(padding);
+                self.y -= padding;
+                for line in lines {
+                    let spans = [Span { style: Style { code: true, ..Style::default() }, text: line.clone() }];
+                    for line in self.wrap(&spans, code_size, width) {
+                        self.ensure_space(code_leading);
+                        self.code_background(code_leading);
+                        self.draw_line(&line, self.left() + padding, code_size, code_leading);
+                    }
+                }
+                self.code_background(padding);
+                self.y -= padding + size * 0.5;
+            }
+            Block::Rule => {
+                self.ensure_space(size);
+                let y = self.y - size / 2.0;
+                self.content.push_str(&format!(
+                    "q\n0.6 G\n0.5 w\n{} {} m\n{} {} l\nS\nQ\n",
+                    self.left(), y, self.left() + self.content_width(), y
+                ));
+                self.y -= size;
+            }
+        }
+    }
+
+    /// 在当前位置下方绘制高为 height 的代码块浅灰色背景
+    fn code_background(&mut self, height: f64) {
+        self.content.push_str(&format!(
+            "q\n0.95 g\n{} {} {} {} re\nf\nQ\n",
+            self.left(), self.y - height, self.content_width(), height
+        ));
+    }
+
+    fn baseline(&self, size: f64, leading: f64) -> f64 {
+        self.y - (leading - size) / 2.0 - size * 0.8
+    }
+
+    /// 折行并逐行绘制
+    fn paragraph(&mut self, spans: &[Span], x: f64, width: f64, size: f64) {
+        let leading = size * self.line_height;
+        for line in self.wrap(spans, size, width) {
+            self.ensure_space(leading);
+            self.draw_line(&line, x, size, leading);
+        }
+    }
+
+    fn draw_line(&mut self, line: &[Piece], x: f64, size: f64, leading: f64) {
+        let baseline = self.baseline(size, leading);
+        let mut cursor = x;
+        // 相邻的同样式片段合并绘制
+        let mut index = 0;
+        while index < line.len() {
+            let style = line[index].style;
+            let mut text = String::new();
+            let mut width = 0.0;
+            while index < line.len() && line[index].style == style {
+                text.push_str(&line[index].text);
+                width += line[index].width;
+                index += 1;
+            }
+            if !text.trim().is_empty() {
+                let run = self.fonts.show(style, &text, size, cursor, baseline);
+                self.content.push_str(&run);
+            }
+            cursor += width;
+        }
+        self.y -= leading;
+    }
+
+    /// 按宽度折行：在空格和中日韩字符之间断开，过长的单词按字符断开
+    fn wrap(&self, spans: &[Span], size: f64, max_width: f64) -> Vec<Vec<Piece>> {
+        let mut lines = Vec::new();
+        let mut line: Vec<Piece> = Vec::new();
+        let mut line_width = 0.0;
+
+        for piece in self.pieces(spans, size) {
+            if piece.space {
+                // 续行开头的空格不保留
+                if !line.is_empty() || lines.is_empty() {
+                    line_width += piece.width;
+                    line.push(piece);
+                }
+                continue;
+            }
+            if line_width + piece.width > max_width && line.iter().any(|p| !p.space) {
+                while line.last().is_some_and(|p| p.space) {
+                    line.pop();
+                }
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+            }
+            if piece.width > max_width {
+                // 单独一个单词超出宽度时逐字符断开
+                for c in piece.text.chars() {
+                    let text = c.to_string();
+                    let width = self.fonts.width(piece.style, &text, size);
+                    if line_width + width > max_width && !line.is_empty() {
+                        lines.push(std::mem::take(&mut line));
+                        line_width = 0.0;
+                    }
+                    line_width += width;
+                    line.push(Piece { style: piece.style, text, width, space: false });
+                }
+                continue;
+            }
+            line_width += piece.width;
+            line.push(piece);
+        }
+
+        if !line.is_empty() || lines.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn pieces(&self, spans: &[Span], size: f64) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        let mut push = |style: Style, text: &mut String, space: bool| {
+            if !text.is_empty() {
+                let width = self.fonts.width(style, text, size);
+                pieces.push(Piece { style, text: std::mem::take(text), width, space });
+            }
+        };
+
+        for span in spans {
+            let mut word = String::new();
+            for c in span.text.chars() {
+                if c == ' ' || is_cjk(c) {
+                    push(span.style, &mut word, false);
+                    push(span.style, &mut c.to_string(), c == ' ');
+                } else {
+                    word.push(c);
+                }
+            }
+            push(span.style, &mut word, false);
+        }
+        pieces
+    }
+
+    fn write_document(mut self, title: Option<&str>, linearize: bool) -> Result<Vec<u8>, String> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+
+        let mut resources = Dictionary::new();
+        resources.set("Font", Object::Dictionary(self.fonts.write(&mut doc)?));
+        let resources_id = doc.add_object(Object::Dictionary(resources));
+
+        let (width, height) = self.page_size;
+        let mut page_ids = Vec::new();
+        for content in std::mem::take(&mut self.pages) {
+            let content_id = doc.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+            let mut page = Dictionary::new();
+            page.set("Type", Object::Name(b"Page".to_vec()));
+            page.set("Parent", Object::Reference(pages_id));
+            page.set("MediaBox", Object::Array(vec![0.into(), 0.into(), Object::Real(width as f32), Object::Real(height as f32)]));
+            page.set("Resources", Object::Reference(resources_id));
+            page.set("Contents", Object::Reference(content_id));
+            page_ids.push(doc.add_object(page));
+        }
+
+        let catalog_id = build_page_tree(&mut doc, pages_id, page_ids.iter().map(|&id| Object::Reference(id)).collect());
+
+        let outline = build_outline(&self.headings, &page_ids);
+        if !outline.is_empty() {
+            write_outline(&mut doc, catalog_id, &outline)?;
+        }
+
+        if let Some(title) = title {
+            let mut info = Dictionary::new();
+            info.set("Title", text_string(title));
+            let info_id = doc.add_object(info);
+            doc.trailer.set("Info", Object::Reference(info_id));
+        }
+
+        let mut buffer = Vec::new();
+        save_document(&mut doc, &mut buffer, linearize)
+            .map_err(|e| format!("无法保存 PDF: {}", e))?;
+        Ok(buffer)
+    }
+}
+
+/// 按标题级别生成层级书签
+fn build_outline(headings: &[Heading], page_ids: &[ObjectId]) -> Vec<OutlineItem> {
+    fn attach(stack: &mut [(usize, OutlineItem)], roots: &mut Vec<OutlineItem>, item: OutlineItem) {
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(item),
+            None => roots.push(item),
+        }
+    }
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<(usize, OutlineItem)> = Vec::new();
+    for heading in headings {
+        let Some(&page_id) = page_ids.get(heading.page_index) else { continue };
+        while stack.last().is_some_and(|(level, _)| *level >= heading.level) {
+            if let Some((_, item)) = stack.pop() {
+                attach(&mut stack, &mut roots, item);
+            }
+        }
+        let title = if heading.title.trim().is_empty() { "无标题".to_string() } else { heading.title.trim().to_string() };
+        let view = DestView::Xyz { left: None, top: Some(heading.top), zoom: None };
+        stack.push((heading.level, OutlineItem::new(title, page_id, view)));
+    }
+    while let Some((_, item)) = stack.pop() {
+        attach(&mut stack, &mut roots, item);
+    }
+    roots
+}
+
+/// 中日韩文字可以在任意两个字符之间断行
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11FF | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFFEF | 0x20000..=0x3FFFF
+    )
+}
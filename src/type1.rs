@@ -0,0 +1,510 @@
+// Type1 字体程序解析（FontFile）
+// 解密 eexec 段，读取 Subrs 与 CharStrings，并解释 Type1 字形程序生成轮廓
+
+use std::collections::HashMap;
+use tiny_skia::{Path, PathBuilder, Transform};
+
+use crate::render_font::standard_encoding_name;
+
+const EEXEC_KEY: u16 = 55665;
+const CHARSTRING_KEY: u16 = 4330;
+
+/// 已解析的 Type1 字体程序
+pub(crate) struct Type1Font {
+    /// 字体矩阵，字形空间到文字空间
+    pub matrix: [f64; 6],
+    /// 字体内置编码，None 表示 StandardEncoding
+    pub encoding: Option<HashMap<u8, String>>,
+    subrs: Vec<Vec<u8>>,
+    char_strings: HashMap<String, Vec<u8>>,
+}
+
+impl Type1Font {
+    pub(crate) fn parse(data: &[u8]) -> Option<Type1Font> {
+        let data = strip_pfb(data);
+        let eexec = find(&data, b"eexec")?;
+        let clear = &data[..eexec];
+
+        let mut encrypted = &data[eexec + 5..];
+        while let Some((&c, rest)) = encrypted.split_first() {
+            if !matches!(c, b'\r' | b'\n' | b' ' | b'\t') {
+                break;
+            }
+            encrypted = rest;
+        }
+        // 部分字体的加密段以十六进制文本存储
+        let binary;
+        let encrypted = if encrypted.len() >= 4 && encrypted[..4].iter().all(u8::is_ascii_hexdigit) {
+            binary = hex_to_bytes(encrypted);
+            &binary[..]
+        } else {
+            encrypted
+        };
+        let private = decrypt(encrypted, EEXEC_KEY, 4);
+
+        let mut font = Type1Font {
+            matrix: parse_font_matrix(clear).unwrap_or([0.001, 0.0, 0.0, 0.001, 0.0, 0.0]),
+            encoding: parse_encoding(clear),
+            subrs: Vec::new(),
+            char_strings: HashMap::new(),
+        };
+        font.parse_private(&private);
+        (!font.char_strings.is_empty()).then_some(font)
+    }
+
+    /// 读取私有字典中的 Subrs 和 CharStrings
+    fn parse_private(&mut self, data: &[u8]) {
+        let len_iv = find(data, b"/lenIV")
+            .and_then(|pos| next_number(&data[pos + 6..]))
+            .unwrap_or(4);
+
+        let mut pos = 0;
+        let mut in_char_strings = false;
+        // 最近读到的名称与两个整数，用于识别 "dup 5 23 RD" 和 "/A 23 RD"
+        let mut last_name: Option<String> = None;
+        let mut numbers: Vec<i64> = Vec::new();
+
+        while pos < data.len() {
+            let c = data[pos];
+            if c.is_ascii_whitespace() {
+                pos += 1;
+                continue;
+            }
+            let start = pos;
+            if c == b'/' {
+                pos += 1;
+            }
+            while pos < data.len() && !data[pos].is_ascii_whitespace() && !(pos > start && matches!(data[pos], b'/' | b'[' | b'{')) {
+                pos += 1;
+            }
+            let token = &data[start..pos];
+
+            if token == b"/CharStrings" {
+                in_char_strings = true;
+            } else if let Some(name) = token.strip_prefix(b"/") {
+                last_name = Some(String::from_utf8_lossy(name).into_owned());
+                numbers.clear();
+            } else if token == b"RD" || token == b"-|" {
+                let length = numbers.last().copied().unwrap_or(0).max(0) as usize;
+                let begin = pos + 1;
+                let end = (begin + length).min(data.len());
+                let program = decrypt(&data[begin..end], CHARSTRING_KEY, len_iv as usize);
+                if in_char_strings {
+                    if let Some(name) = last_name.take() {
+                        self.char_strings.insert(name, program);
+                    }
+                } else if numbers.len() >= 2 {
+                    let index = numbers[numbers.len() - 2].max(0) as usize;
+                    if index < 65536 {
+                        if self.subrs.len() <= index {
+                            self.subrs.resize(index + 1, Vec::new());
+                        }
+                        self.subrs[index] = program;
+                    }
+                }
+                numbers.clear();
+                pos = end;
+            } else if let Ok(n) = std::str::from_utf8(token).unwrap_or("").parse::<i64>() {
+                numbers.push(n);
+            } else {
+                numbers.clear();
+            }
+        }
+    }
+
+    pub(crate) fn has_glyph(&self, name: &str) -> bool {
+        self.char_strings.contains_key(name)
+    }
+
+    /// 生成字形轮廓（字形空间）
+    pub(crate) fn outline(&self, name: &str) -> Option<Path> {
+        let mut interpreter = CharStringInterpreter::new(self);
+        interpreter.run_glyph(name, 0.0, 0.0, 0)?;
+        interpreter.builder.finish()
+    }
+}
+
+/// 去除 PFB 格式的分段头
+fn strip_pfb(data: &[u8]) -> Vec<u8> {
+    if data.first() != Some(&0x80) {
+        return data.to_vec();
+    }
+    let mut output = Vec::with_capacity(data.len());
+    let mut pos = 0;
+    while pos + 6 <= data.len() && data[pos] == 0x80 && data[pos + 1] != 3 {
+        let length = u32::from_le_bytes([data[pos + 2], data[pos + 3], data[pos + 4], data[pos + 5]]) as usize;
+        let end = (pos + 6 + length).min(data.len());
+        output.extend_from_slice(&data[pos + 6..end]);
+        pos = end;
+    }
+    output
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+fn next_number(data: &[u8]) -> Option<i64> {
+    let text = String::from_utf8_lossy(&data[..data.len().min(32)]);
+    text.split_whitespace().next()?.parse().ok()
+}
+
+fn hex_to_bytes(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data.iter().filter_map(|&c| (c as char).to_digit(16).map(|d| d as u8)).collect();
+    digits.chunks(2).map(|p| (p[0] << 4) | p.get(1).copied().unwrap_or(0)).collect()
+}
+
+/// Type1 加密算法，丢弃前 skip 个随机字节
+fn decrypt(data: &[u8], key: u16, skip: usize) -> Vec<u8> {
+    let mut r = key;
+    let mut output = Vec::with_capacity(data.len());
+    for &c in data {
+        output.push(c ^ (r >> 8) as u8);
+        r = (c as u16).wrapping_add(r).wrapping_mul(52845).wrapping_add(22719);
+    }
+    output.split_off(skip.min(output.len()))
+}
+
+fn parse_font_matrix(clear: &[u8]) -> Option<[f64; 6]> {
+    let pos = find(clear, b"/FontMatrix")?;
+    let rest = &clear[pos + 11..];
+    let open = rest.iter().position(|&c| c == b'[' || c == b'{')?;
+    let close = rest.iter().position(|&c| c == b']' || c == b'}')?;
+    let values: Vec<f64> = String::from_utf8_lossy(&rest[open + 1..close])
+        .split_whitespace()
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    (values.len() == 6).then(|| [values[0], values[1], values[2], values[3], values[4], values[5]])
+}
+
+/// 读取明文段中的 "dup 65 /A put" 形式的自定义编码
+fn parse_encoding(clear: &[u8]) -> Option<HashMap<u8, String>> {
+    let pos = find(clear, b"/Encoding")?;
+    let rest = String::from_utf8_lossy(&clear[pos + 9..]);
+    if rest.trim_start().starts_with("StandardEncoding") {
+        return None;
+    }
+    let end = rest.find("readonly def").or_else(|| rest.find(" def")).unwrap_or(rest.len());
+    let tokens: Vec<&str> = rest[..end].split_whitespace().collect();
+    let mut encoding = HashMap::new();
+    for window in tokens.windows(4) {
+        if let ["dup", code, name, "put"] = window {
+            if let (Ok(code), Some(name)) = (code.parse::<u8>(), name.strip_prefix('/')) {
+                encoding.insert(code, name.to_string());
+            }
+        }
+    }
+    Some(encoding)
+}
+
+// ---------------------------------------------------------------------------
+// Type1 字形程序解释器
+// ---------------------------------------------------------------------------
+
+enum Flow {
+    Continue,
+    Return,
+    EndChar,
+}
+
+struct CharStringInterpreter<'a> {
+    font: &'a Type1Font,
+    builder: PathBuilder,
+    stack: Vec<f64>,
+    ps_stack: Vec<f64>,
+    x: f64,
+    y: f64,
+    /// 当前字形的左侧边距点
+    sbx: f64,
+    /// 整体偏移（seac 组合字形时的重音符位置）
+    offset: (f64, f64),
+    open: bool,
+    flex: Option<Vec<(f64, f64)>>,
+}
+
+impl<'a> CharStringInterpreter<'a> {
+    fn new(font: &'a Type1Font) -> Self {
+        CharStringInterpreter {
+            font,
+            builder: PathBuilder::new(),
+            stack: Vec::new(),
+            ps_stack: Vec::new(),
+            x: 0.0,
+            y: 0.0,
+            sbx: 0.0,
+            offset: (0.0, 0.0),
+            open: false,
+            flex: None,
+        }
+    }
+
+    fn run_glyph(&mut self, name: &str, dx: f64, dy: f64, depth: usize) -> Option<()> {
+        let font = self.font;
+        let program = font.char_strings.get(name)?;
+        self.offset = (dx, dy);
+        self.stack.clear();
+        self.run(program, depth)?;
+        if self.open {
+            self.builder.close();
+            self.open = false;
+        }
+        Some(())
+    }
+
+    fn move_to(&mut self) {
+        if self.open {
+            self.builder.close();
+        }
+        self.builder.move_to((self.x + self.offset.0) as f32, (self.y + self.offset.1) as f32);
+        self.open = true;
+    }
+
+    fn line_to(&mut self) {
+        if !self.open {
+            self.move_to();
+        }
+        self.builder.line_to((self.x + self.offset.0) as f32, (self.y + self.offset.1) as f32);
+    }
+
+    fn curve_to(&mut self, d: [f64; 6]) {
+        if !self.open {
+            self.move_to();
+        }
+        let (ox, oy) = self.offset;
+        let (x1, y1) = (self.x + d[0], self.y + d[1]);
+        let (x2, y2) = (x1 + d[2], y1 + d[3]);
+        self.x = x2 + d[4];
+        self.y = y2 + d[5];
+        self.builder.cubic_to(
+            (x1 + ox) as f32, (y1 + oy) as f32,
+            (x2 + ox) as f32, (y2 + oy) as f32,
+            (self.x + ox) as f32, (self.y + oy) as f32,
+        );
+    }
+
+    /// 返回 None 表示程序出错；遇到 endchar 时返回 Some 并停止
+    fn run(&mut self, program: &[u8], depth: usize) -> Option<bool> {
+        if depth > 16 {
+            return None;
+        }
+        let mut pos = 0;
+        while pos < program.len() {
+            let v = program[pos];
+            pos += 1;
+            match v {
+                32..=246 => self.stack.push(v as f64 - 139.0),
+                247..=250 => {
+                    let w = *program.get(pos)? as f64;
+                    pos += 1;
+                    self.stack.push((v as f64 - 247.0) * 256.0 + w + 108.0);
+                }
+                251..=254 => {
+                    let w = *program.get(pos)? as f64;
+                    pos += 1;
+                    self.stack.push(-(v as f64 - 251.0) * 256.0 - w - 108.0);
+                }
+                255 => {
+                    let bytes = program.get(pos..pos + 4)?;
+                    pos += 4;
+                    self.stack.push(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64);
+                }
+                12 => {
+                    let op = *program.get(pos)?;
+                    pos += 1;
+                    if self.escape(op, depth)? {
+                        return Some(true);
+                    }
+                }
+                _ => {
+                    match self.operator(v, depth)? {
+                        Flow::EndChar => return Some(true),
+                        Flow::Return => return Some(false),
+                        Flow::Continue => {}
+                    }
+                }
+            }
+        }
+        Some(false)
+    }
+
+    fn arg(&self, i: usize) -> f64 {
+        self.stack.get(i).copied().unwrap_or(0.0)
+    }
+
+    fn operator(&mut self, op: u8, depth: usize) -> Option<Flow> {
+        match op {
+            // hstem、vstem：忽略提示
+            1 | 3 => {}
+            // vmoveto
+            4 => {
+                self.y += self.arg(0);
+                if !self.add_flex_point() {
+                    self.move_to();
+                }
+            }
+            // rlineto
+            5 => {
+                self.x += self.arg(0);
+                self.y += self.arg(1);
+                self.line_to();
+            }
+            // hlineto
+            6 => {
+                self.x += self.arg(0);
+                self.line_to();
+            }
+            // vlineto
+            7 => {
+                self.y += self.arg(0);
+                self.line_to();
+            }
+            // rrcurveto
+            8 => self.curve_to([self.arg(0), self.arg(1), self.arg(2), self.arg(3), self.arg(4), self.arg(5)]),
+            // closepath
+            9 => {
+                if self.open {
+                    self.builder.close();
+                    self.open = false;
+                }
+            }
+            // callsubr
+            10 => {
+                let index = self.stack.pop()? as usize;
+                let font = self.font;
+                // 子程序结束后操作数栈保留，供后续运算符使用
+                let end = self.run(font.subrs.get(index)?, depth + 1)?;
+                return Some(if end { Flow::EndChar } else { Flow::Continue });
+            }
+            // return
+            11 => return Some(Flow::Return),
+            // hsbw
+            13 => {
+                self.sbx = self.arg(0);
+                self.x = self.sbx;
+                self.y = 0.0;
+            }
+            // endchar
+            14 => return Some(Flow::EndChar),
+            // rmoveto
+            21 => {
+                self.x += self.arg(0);
+                self.y += self.arg(1);
+                if !self.add_flex_point() {
+                    self.move_to();
+                }
+            }
+            // hmoveto
+            22 => {
+                self.x += self.arg(0);
+                if !self.add_flex_point() {
+                    self.move_to();
+                }
+            }
+            // vhcurveto
+            30 => self.curve_to([0.0, self.arg(0), self.arg(1), self.arg(2), self.arg(3), 0.0]),
+            // hvcurveto
+            31 => self.curve_to([self.arg(0), 0.0, self.arg(1), self.arg(2), 0.0, self.arg(3)]),
+            _ => {}
+        }
+        // callsubr 和 return 之外的运算符都会清空操作数栈
+        self.stack.clear();
+        Some(Flow::Continue)
+    }
+
+    fn escape(&mut self, op: u8, depth: usize) -> Option<bool> {
+        match op {
+            // seac：组合基础字形与重音符
+            6 => {
+                let (asb, adx, ady) = (self.arg(0), self.arg(1), self.arg(2));
+                let base = standard_encoding_name(self.arg(3) as u8)?;
+                let accent = standard_encoding_name(self.arg(4) as u8)?;
+                let base_sbx = self.sbx;
+                self.run_glyph(base, 0.0, 0.0, depth + 1)?;
+                self.run_glyph(accent, base_sbx + adx - asb, ady, depth + 1)?;
+                return Some(true);
+            }
+            // sbw
+            7 => {
+                self.sbx = self.arg(0);
+                self.x = self.sbx;
+                self.y = self.arg(1);
+            }
+            // div
+            12 => {
+                let b = self.stack.pop()?;
+                let a = self.stack.pop()?;
+                self.stack.push(if b == 0.0 { 0.0 } else { a / b });
+                return Some(false);
+            }
+            // callothersubr
+            16 => {
+                let number = self.stack.pop()? as i64;
+                let count = self.stack.pop()?.max(0.0) as usize;
+                let start = self.stack.len().saturating_sub(count);
+                let args: Vec<f64> = self.stack.split_off(start);
+                match number {
+                    // 结束 flex：由 7 个点生成两段曲线
+                    0 => {
+                        let points = self.flex.take().unwrap_or_default();
+                        if points.len() >= 7 {
+                            let (ox, oy) = self.offset;
+                            if !self.open {
+                                self.move_to();
+                            }
+                            for curve in [&points[1..4], &points[4..7]] {
+                                self.builder.cubic_to(
+                                    (curve[0].0 + ox) as f32, (curve[0].1 + oy) as f32,
+                                    (curve[1].0 + ox) as f32, (curve[1].1 + oy) as f32,
+                                    (curve[2].0 + ox) as f32, (curve[2].1 + oy) as f32,
+                                );
+                            }
+                            let (x, y) = points[6];
+                            self.x = x;
+                            self.y = y;
+                        }
+                        // 后续的 pop pop setcurrentpoint 读取终点
+                        self.ps_stack.extend([self.y, self.x]);
+                    }
+                    1 => self.flex = Some(Vec::new()),
+                    2 => {}
+                    // 提示替换：下一条 pop 返回子程序编号 3
+                    3 => self.ps_stack.push(3.0),
+                    _ => self.ps_stack.extend(args.iter().rev()),
+                }
+                return Some(false);
+            }
+            // pop
+            17 => {
+                let value = self.ps_stack.pop().unwrap_or(0.0);
+                self.stack.push(value);
+                return Some(false);
+            }
+            // setcurrentpoint
+            33 => {
+                self.x = self.arg(0);
+                self.y = self.arg(1);
+            }
+            // dotsection、vstem3、hstem3 等提示运算符
+            _ => {}
+        }
+        self.stack.clear();
+        Some(false)
+    }
+
+    /// flex 过程中的 moveto 只记录控制点
+    fn add_flex_point(&mut self) -> bool {
+        match &mut self.flex {
+            Some(points) => {
+                points.push((self.x, self.y));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 将字形空间的轮廓按字体矩阵变换到文字空间
+pub(crate) fn transform_path(path: Path, matrix: &[f64; 6]) -> Option<Path> {
+    let [a, b, c, d, e, f] = matrix.map(|v| v as f32);
+    path.transform(Transform::from_row(a, b, c, d, e, f))
+}
@@ -3,6 +3,8 @@
 
 #[cfg(test)]
 mod tests {
+    use lopdf::{Dictionary, Document, Object, Stream};
+
     // 这里可以添加集成测试
     // 由于 WASM 环境的特殊性，实际测试建议在浏览器环境中进行
     
@@ -11,5 +13,1587 @@ mod tests {
         // 占位测试
         assert_eq!(2 + 2, 4);
     }
-}
 
+    /// 生成测试用 PDF，每个尺寸对应一页
+    fn make_pdf(page_sizes: &[(i64, i64)]) -> Vec<u8> {
+        let pages: Vec<_> = page_sizes.iter().map(|&size| (size, b"0 0 m 10 10 l S".as_slice())).collect();
+        make_pdf_with_contents(&pages)
+    }
+
+    /// 生成测试用 PDF，指定每一页的尺寸和内容流
+    fn make_pdf_with_contents(pages: &[((i64, i64), &[u8])]) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let mut kids = Vec::new();
+
+        for &((width, height), content) in pages {
+            let content_id = doc.add_object(Stream::new(Dictionary::new(), content.to_vec()));
+            let mut page = Dictionary::new();
+            page.set("Type", Object::Name(b"Page".to_vec()));
+            page.set("Parent", Object::Reference(pages_id));
+            page.set("MediaBox", vec![0.into(), 0.into(), width.into(), height.into()]);
+            page.set("Contents", Object::Reference(content_id));
+            kids.push(Object::Reference(doc.add_object(page)));
+        }
+
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name(b"Pages".to_vec()));
+        pages.set("Count", Object::Integer(kids.len() as i64));
+        pages.set("Kids", Object::Array(kids));
+        doc.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        let catalog_id = doc.add_object(catalog);
+        doc.trailer.set("Root", Object::Reference(catalog_id));
+
+        let mut buffer = Vec::new();
+        doc.save_to(&mut buffer).unwrap();
+        buffer
+    }
+
+    /// 读取每一页的 MediaBox 宽高
+    fn page_sizes(pdf_bytes: &[u8]) -> Vec<(f32, f32)> {
+        page_boxes(pdf_bytes, b"MediaBox").iter()
+            .map(|b| (b[2] - b[0], b[3] - b[1]))
+            .collect()
+    }
+
+    /// 读取每一页指定的页面框
+    fn page_boxes(pdf_bytes: &[u8], key: &[u8]) -> Vec<Vec<f32>> {
+        let doc = Document::load_mem(pdf_bytes).unwrap();
+        doc.get_pages().values().map(|&page_id| {
+            let page = doc.get_dictionary(page_id).unwrap();
+            page.get(key).unwrap().as_array().unwrap()
+                .iter().map(|v| v.as_float().unwrap()).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn test_normalize_page_size() {
+        let pdf = make_pdf(&[(612, 792), (842, 595), (300, 300)]);
+        let output = pdf_utils_rust::normalize_page_size(&pdf, "A4", "fit", None).unwrap();
+
+        let sizes = page_sizes(&output);
+        assert_eq!(sizes.len(), 3);
+        // 竖版页面使用竖版 A4，横版页面使用横版 A4
+        assert!((sizes[0].0 - 595.28).abs() < 0.01 && (sizes[0].1 - 841.89).abs() < 0.01);
+        assert!((sizes[1].0 - 841.89).abs() < 0.01 && (sizes[1].1 - 595.28).abs() < 0.01);
+
+        let doc = Document::load_mem(&output).unwrap();
+        let first_page = *doc.get_pages().values().next().unwrap();
+        let content = String::from_utf8(doc.get_page_content(first_page).unwrap()).unwrap();
+        assert!(content.starts_with("q\n"));
+        assert!(content.contains(" cm\n"));
+        assert!(content.trim_end().ends_with('Q'));
+    }
+
+    #[test]
+    fn test_auto_crop_and_margins() {
+        // 第二页包含注释和内联图片，确保解析不会在 BI/ID/EI 处中断
+        let pdf = make_pdf_with_contents(&[
+            ((600, 800), b"100 200 300 100 re f"),
+            ((600, 800), b"% comment\nq 50 0 0 50 400 500 cm BI /W 1 /H 1 /CS /G /BPC 8 ID \xff EI Q\nBT /F1 12 Tf 3 Tr 10 10 Td (hidden) Tj ET"),
+        ]);
+
+        let cropped = pdf_utils_rust::auto_crop_pdf(&pdf, Some(0.0), None).unwrap();
+        let crop_boxes = page_boxes(&cropped, b"CropBox");
+        assert_eq!(crop_boxes[0], vec![100.0, 200.0, 400.0, 300.0]);
+        // 不可见文字（Tr 3）不计入内容区域
+        assert_eq!(crop_boxes[1], vec![400.0, 500.0, 450.0, 550.0]);
+
+        let with_margins = pdf_utils_rust::add_margins(&cropped, 25.4, None).unwrap();
+        let crop_boxes = page_boxes(&with_margins, b"CropBox");
+        assert_eq!(crop_boxes[0], vec![28.0, 128.0, 472.0, 372.0]);
+        assert_eq!(page_sizes(&with_margins)[0], (744.0, 944.0));
+
+        // 深度嵌套的数组不会耗尽栈空间
+        let mut content = vec![b'['; 100_000];
+        content.extend_from_slice(b"\n100 200 300 100 re f");
+        let pdf = make_pdf_with_contents(&[((600, 800), content.as_slice())]);
+        let cropped = pdf_utils_rust::auto_crop_pdf(&pdf, Some(0.0), None).unwrap();
+        assert_eq!(page_sizes(&cropped).len(), 1);
+
+        // 表单 XObject 中的同名字体（/F1）是另一个字体，按各自的字宽计算
+        let pdf = make_pdf_with_contents(&[((600, 800), b"BT /F1 10 Tf 100 100 Td (AB) Tj ET /Fm Do")]);
+        let mut doc = Document::load_mem(&pdf).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let courier = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Courier".to_vec())),
+        ]));
+        let descendant = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"CIDFontType2".to_vec())),
+            ("DW", Object::Integer(1000)),
+        ]);
+        let cid_font = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type0".to_vec())),
+            ("Encoding", Object::Name(b"Identity-H".to_vec())),
+            ("DescendantFonts", Object::Array(vec![Object::Dictionary(descendant)])),
+        ]));
+        let form_resources = Dictionary::from_iter(vec![("Font", Object::Dictionary(Dictionary::from_iter(vec![("F1", Object::Reference(cid_font))])))]);
+        let form = doc.add_object(Stream::new(
+            Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"XObject".to_vec())),
+                ("Subtype", Object::Name(b"Form".to_vec())),
+                ("BBox", vec![0.into(), 0.into(), 600.into(), 800.into()].into()),
+                ("Resources", Object::Dictionary(form_resources)),
+            ]),
+            b"BT /F1 10 Tf 300 300 Td <0001> Tj ET".to_vec(),
+        ));
+        doc.get_dictionary_mut(page_id).unwrap().set("Resources", Dictionary::from_iter(vec![
+            ("Font", Object::Dictionary(Dictionary::from_iter(vec![("F1", Object::Reference(courier))]))),
+            ("XObject", Object::Dictionary(Dictionary::from_iter(vec![("Fm", Object::Reference(form))]))),
+        ]));
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).unwrap();
+        let cropped = pdf_utils_rust::auto_crop_pdf(&pdf, Some(0.0), None).unwrap();
+        assert_eq!(page_boxes(&cropped, b"CropBox")[0], vec![100.0, 97.5, 310.0, 308.5]);
+    }
+
+    #[test]
+    fn test_insert_blank_and_duplicate_pages() {
+        let pdf = make_pdf(&[(612, 792), (842, 595), (612, 792)]);
+
+        let output = pdf_utils_rust::insert_blank_pages(&pdf, r#"{"after_pages": [1], "pad_to_even": true}"#).unwrap();
+        let sizes = page_sizes(&output);
+        // 第 1 页之后插入一页，共 4 页已是偶数，不再补页
+        assert_eq!(sizes, vec![(612.0, 792.0), (612.0, 792.0), (842.0, 595.0), (612.0, 792.0)]);
+
+        let output = pdf_utils_rust::insert_blank_pages(&pdf, r#"{"every": 1, "page_size": "100x200"}"#).unwrap();
+        assert_eq!(page_sizes(&output).len(), 5);
+        assert_eq!(page_sizes(&output)[1], (100.0, 200.0));
+
+        let output = pdf_utils_rust::duplicate_pages(&pdf, "2", 2, None).unwrap();
+        let sizes = page_sizes(&output);
+        assert_eq!(sizes.len(), 5);
+        assert_eq!(&sizes[1..4], &[(842.0, 595.0); 3]);
+
+        // 份数超过上限时报错，而不是生成巨大的文件
+        assert_eq!(page_sizes(&pdf_utils_rust::duplicate_pages_from_bytes(&pdf, "1", 100, false).unwrap()).len(), 103);
+        let error = pdf_utils_rust::duplicate_pages_from_bytes(&pdf, "1", 101, false).unwrap_err();
+        assert!(error.contains("100"), "{}", error);
+        assert!(pdf_utils_rust::duplicate_pages_from_bytes(&pdf, "1", u32::MAX, false).is_err());
+        assert!(pdf_utils_rust::duplicate_pages_from_bytes(&pdf, "5", 1, false).is_err());
+    }
+
+    #[test]
+    fn test_interleave_pdfs() {
+        let front = make_pdf(&[(101, 100), (102, 100), (103, 100)]);
+        // 背面倒序扫描：最后一张纸的背面在最前
+        let back = make_pdf(&[(202, 100), (201, 100)]);
+
+        let output = pdf_utils_rust::interleave_pdfs(&front, &back, true, None).unwrap();
+        let widths: Vec<f32> = page_sizes(&output).iter().map(|s| s.0).collect();
+        assert_eq!(widths, vec![101.0, 201.0, 102.0, 202.0, 103.0]);
+    }
+
+    #[test]
+    fn test_overlay_pdf() {
+        let base = make_pdf(&[(612, 792), (612, 792), (612, 792)]);
+        let overlay = make_pdf_with_contents(&[
+            ((612, 792), b"0 0 1 rg 0 700 612 92 re f"),
+            ((612, 792), b"1 0 0 rg 0 0 612 50 re f"),
+        ]);
+
+        let output = pdf_utils_rust::overlay_pdf(&base, &overlay, r#"{"position": "under", "mode": "cycle"}"#).unwrap();
+        let doc = Document::load_mem(&output).unwrap();
+        let pages: Vec<_> = doc.get_pages().into_values().collect();
+
+        let form_contents: Vec<Vec<u8>> = pages.iter().map(|&page_id| {
+            let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+            // 叠加在原内容之下
+            assert!(content.find("/Overlay1 Do").unwrap() < content.find("0 0 m 10 10 l S").unwrap());
+
+            let (resources, _) = doc.get_page_resources(page_id);
+            let xobjects = resources.unwrap().get(b"XObject").unwrap().as_dict().unwrap();
+            let form_id = xobjects.get(b"Overlay1").unwrap().as_reference().unwrap();
+            let form = doc.get_object(form_id).unwrap().as_stream().unwrap();
+            assert_eq!(form.dict.get(b"Subtype").unwrap().as_name().unwrap(), b"Form");
+            form.decompressed_content().unwrap_or_else(|_| form.content.clone())
+        }).collect();
+
+        // 循环使用叠加页: 1, 2, 1
+        assert_eq!(form_contents[0], b"0 0 1 rg 0 700 612 92 re f");
+        assert_eq!(form_contents[1], b"1 0 0 rg 0 0 612 50 re f");
+        assert_eq!(form_contents[2], form_contents[0]);
+    }
+
+    #[test]
+    fn test_render_pdf_page() {
+        let pdf = make_pdf_with_contents(&[((200, 100), b"1 0 0 rg 0 0 100 100 re f")]);
+
+        let png = pdf_utils_rust::render_pdf_page(&pdf, 1, 144.0, "png").unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (400, 200));
+        // 左半部分为红色，右半部分保持白色背景
+        assert_eq!(image.get_pixel(100, 100).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(300, 100).0, [255, 255, 255]);
+
+        let thumbnail = pdf_utils_rust::render_pdf_thumbnail(&pdf, 1, 50).unwrap();
+        let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (50, 25));
+    }
+
+    #[test]
+    fn test_rasterize_pdf() {
+        let pdf = make_pdf_with_contents(&[
+            ((200, 100), b"BT /F1 12 Tf 10 10 Td (secret) Tj ET"),
+            ((300, 400), b"0 0 1 rg 0 0 300 400 re f"),
+        ]);
+
+        let output = pdf_utils_rust::rasterize_pdf(&pdf, 72.0, 90, None).unwrap();
+        let doc = Document::load_mem(&output).unwrap();
+        assert_eq!(page_sizes(&output), vec![(200.0, 100.0), (300.0, 400.0)]);
+
+        for page_id in doc.get_pages().into_values() {
+            // 每页只剩一张图片，原内容不再保留
+            let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+            assert!(content.contains("/Im1 Do"));
+            assert!(!content.contains("secret"));
+
+            let (resources, _) = doc.get_page_resources(page_id);
+            let xobjects = resources.unwrap().get(b"XObject").unwrap().as_dict().unwrap();
+            let image_id = xobjects.get(b"Im1").unwrap().as_reference().unwrap();
+            let image = doc.get_object(image_id).unwrap().as_stream().unwrap();
+            assert_eq!(image.dict.get(b"Filter").unwrap().as_name().unwrap(), b"DCTDecode");
+        }
+    }
+
+    #[test]
+    fn test_render_image_with_invalid_predictor() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&[0u8; 64]).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // Columns 乘以 Colors 和 BitsPerComponent 后溢出，行长度回绕为 0
+        for (colors, columns) in [(8, 1i64 << 58), (1, i64::MAX), (64, 8)] {
+            let pdf = make_pdf_with_contents(&[((100, 100), b"q 100 0 0 100 0 0 cm /Im1 Do Q")]);
+            let mut doc = Document::load_mem(&pdf).unwrap();
+            let mut params = Dictionary::new();
+            params.set("Predictor", 12);
+            params.set("Colors", colors);
+            params.set("BitsPerComponent", 8);
+            params.set("Columns", columns);
+            let mut dict = Dictionary::new();
+            dict.set("Type", Object::Name(b"XObject".to_vec()));
+            dict.set("Subtype", Object::Name(b"Image".to_vec()));
+            dict.set("Width", 8);
+            dict.set("Height", 8);
+            dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+            dict.set("BitsPerComponent", 8);
+            dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+            dict.set("DecodeParms", params);
+            let image_id = doc.add_object(Stream::new(dict, compressed.clone()));
+            let page_id = *doc.get_pages().get(&1).unwrap();
+            let mut xobjects = Dictionary::new();
+            xobjects.set("Im1", Object::Reference(image_id));
+            let mut resources = Dictionary::new();
+            resources.set("XObject", xobjects);
+            doc.get_dictionary_mut(page_id).unwrap().set("Resources", resources);
+            let mut buffer = Vec::new();
+            doc.save_to(&mut buffer).unwrap();
+
+            let png = pdf_utils_rust::render_pdf_page(&buffer, 1, 72.0, "png").unwrap();
+            assert_eq!(image::load_from_memory(&png).unwrap().width(), 100);
+        }
+    }
+
+    #[test]
+    fn test_text_to_pdf_markdown() {
+        let mut markdown = String::from("# Report\n\nSome **bold** and *italic* text.\n\n## Details\n\n- item one\n- item two\n\n");
+        for i in 0..80 {
+            markdown.push_str(&format!("Paragraph number {} with enough words to fill a line.\n\n", i));
+        }
+        markdown.push_str("## Summary\n\n```\nlet x = 1;\n```\n");
+
+        let output = pdf_utils_rust::text_to_pdf(&markdown, r#"{"markdown": true, "page_size": "A5"}"#).unwrap();
+        let doc = Document::load_mem(&output).unwrap();
+        assert!(doc.get_pages().len() > 1);
+        assert!(page_sizes(&output).iter().all(|&(w, h)| (w - 419.53).abs() < 0.1 && (h - 595.28).abs() < 0.1));
+
+        // 标题生成层级书签：Report 下有 Details 和 Summary
+        let catalog = doc.catalog().unwrap();
+        let outlines = doc.get_dictionary(catalog.get(b"Outlines").unwrap().as_reference().unwrap()).unwrap();
+        let report = doc.get_dictionary(outlines.get(b"First").unwrap().as_reference().unwrap()).unwrap();
+        assert_eq!(report.get(b"Title").unwrap().as_str().unwrap(), b"Report");
+        let details = doc.get_dictionary(report.get(b"First").unwrap().as_reference().unwrap()).unwrap();
+        let summary = doc.get_dictionary(report.get(b"Last").unwrap().as_reference().unwrap()).unwrap();
+        assert_eq!(details.get(b"Title").unwrap().as_str().unwrap(), b"Details");
+        assert_eq!(summary.get(b"Title").unwrap().as_str().unwrap(), b"Summary");
+
+        // 粗体、斜体和代码分别使用对应的标准字体
+        let first_page = doc.get_pages()[&1];
+        // 所有页面共用同一个资源字典对象
+        let (_, resource_ids) = doc.get_page_resources(first_page);
+        let resources = doc.get_dictionary(resource_ids[0]).unwrap();
+        let fonts = resources.get(b"Font").unwrap().as_dict().unwrap();
+        let base_fonts: Vec<Vec<u8>> = fonts.iter()
+            .map(|(_, font)| doc.get_dictionary(font.as_reference().unwrap()).unwrap().get(b"BaseFont").unwrap().as_name().unwrap().to_vec())
+            .collect();
+        for name in [b"Helvetica".as_slice(), b"Helvetica-Bold", b"Helvetica-Oblique", b"Courier"] {
+            assert!(base_fonts.iter().any(|f| f == name), "缺少字体 {}", String::from_utf8_lossy(name));
+        }
+    }
+
+    #[test]
+    fn test_set_and_get_pdf_outline() {
+        let pdf = make_pdf(&[(612, 792), (612, 792), (612, 792)]);
+        let outline = r##"[
+            {"title": "第一章", "page": 1, "open": false, "color": "#ff0000", "bold": true, "children": [
+                {"title": "1.1", "page": 2, "fit": "XYZ", "top": 500},
+                {"title": "1.2", "page": 3, "fit": "FitH", "top": 300}
+            ]},
+            {"title": "Appendix", "page": 3}
+        ]"##;
+        let output = pdf_utils_rust::set_pdf_outline(&pdf, outline, None).unwrap();
+
+        // 折叠的书签 /Count 为负数，根节点只计可见项
+        let doc = Document::load_mem(&output).unwrap();
+        let outlines = doc.get_dictionary(doc.catalog().unwrap().get(b"Outlines").unwrap().as_reference().unwrap()).unwrap();
+        assert_eq!(outlines.get(b"Count").unwrap().as_i64().unwrap(), 2);
+        let chapter = doc.get_dictionary(outlines.get(b"First").unwrap().as_reference().unwrap()).unwrap();
+        assert_eq!(chapter.get(b"Count").unwrap().as_i64().unwrap(), -2);
+        assert_eq!(chapter.get(b"F").unwrap().as_i64().unwrap(), 2);
+        let appendix_id = chapter.get(b"Next").unwrap().as_reference().unwrap();
+        assert_eq!(outlines.get(b"Last").unwrap().as_reference().unwrap(), appendix_id);
+
+        let json: serde_json::Value = serde_json::from_str(&pdf_utils_rust::get_pdf_outline(&output).unwrap()).unwrap();
+        assert_eq!(json[0]["title"], "第一章");
+        assert_eq!(json[0]["open"], false);
+        assert_eq!(json[0]["color"], "#ff0000");
+        assert_eq!(json[0]["children"][0]["page"], 2);
+        assert_eq!(json[0]["children"][0]["fit"], "XYZ");
+        assert_eq!(json[0]["children"][0]["top"], 500.0);
+        assert_eq!(json[0]["children"][1]["fit"], "FitH");
+        assert_eq!(json[1]["page"], 3);
+
+        // 再次设置时替换原有书签，空数组删除书签
+        let replaced = pdf_utils_rust::set_pdf_outline(&output, r#"[{"title": "Only", "page": 2}]"#, None).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&pdf_utils_rust::get_pdf_outline(&replaced).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        let removed = pdf_utils_rust::set_pdf_outline(&replaced, "[]", None).unwrap();
+        assert_eq!(pdf_utils_rust::get_pdf_outline(&removed).unwrap(), "[]");
+        assert!(Document::load_mem(&removed).unwrap().catalog().unwrap().get(b"Outlines").is_err());
+
+        // 错误的书签树：/Next 指向页面，/First 指回自身形成循环；替换时页面保留，循环能结束
+        let mut doc = Document::load_mem(&pdf).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let outlines_id = doc.new_object_id();
+        let item_id = doc.add_object(Dictionary::from_iter(vec![
+            ("Title", Object::string_literal("Broken")),
+            ("Parent", Object::Reference(outlines_id)),
+            ("Next", Object::Reference(page_id)),
+        ]));
+        doc.get_dictionary_mut(item_id).unwrap().set("First", Object::Reference(item_id));
+        doc.objects.insert(outlines_id, Object::Dictionary(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Outlines".to_vec())),
+            ("First", Object::Reference(item_id)),
+        ])));
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        doc.get_dictionary_mut(catalog_id).unwrap().set("Outlines", Object::Reference(outlines_id));
+        let mut broken = Vec::new();
+        doc.save_to(&mut broken).unwrap();
+        let replaced = pdf_utils_rust::set_pdf_outline(&broken, r#"[{"title": "Only", "page": 1}]"#, None).unwrap();
+        assert_eq!(page_sizes(&replaced).len(), 3);
+        let json: serde_json::Value = serde_json::from_str(&pdf_utils_rust::get_pdf_outline(&replaced).unwrap()).unwrap();
+        assert_eq!(json[0]["title"], "Only");
+    }
+
+    /// 生成带表单的测试 PDF：文本字段 name、复选框 agree（无外观）、单选按钮组 size（S/L 两个控件）
+    fn make_form_pdf() -> Vec<u8> {
+        let mut doc = Document::load_mem(&make_pdf(&[(612, 792)])).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let rect = |r: [i64; 4]| Object::Array(r.iter().map(|&v| v.into()).collect());
+        let widget = |field_type: Option<&str>, name: Option<&str>, r: [i64; 4]| {
+            let mut dict = Dictionary::new();
+            dict.set("Type", Object::Name(b"Annot".to_vec()));
+            dict.set("Subtype", Object::Name(b"Widget".to_vec()));
+            dict.set("Rect", rect(r));
+            if let Some(field_type) = field_type {
+                dict.set("FT", Object::Name(field_type.as_bytes().to_vec()));
+            }
+            if let Some(name) = name {
+                dict.set("T", Object::string_literal(name));
+            }
+            dict
+        };
+
+        let name_id = doc.add_object(widget(Some("Tx"), Some("name"), [50, 700, 250, 720]));
+        let agree_id = doc.add_object(widget(Some("Btn"), Some("agree"), [50, 660, 70, 680]));
+
+        let size_id = doc.new_object_id();
+        let mut kids = Vec::new();
+        for (i, state) in ["S", "L"].iter().enumerate() {
+            let mut on = Dictionary::new();
+            on.set("Subtype", Object::Name(b"Form".to_vec()));
+            on.set("BBox", rect([0, 0, 20, 20]));
+            let on_id = doc.add_object(Stream::new(on, b"0 g 5 5 10 10 re f".to_vec()));
+            let mut states = Dictionary::new();
+            states.set(*state, Object::Reference(on_id));
+            let mut ap = Dictionary::new();
+            ap.set("N", Object::Dictionary(states));
+            let x = 100 + 30 * i as i64;
+            let mut kid = widget(None, None, [x, 660, x + 20, 680]);
+            kid.set("Parent", Object::Reference(size_id));
+            kid.set("AP", Object::Dictionary(ap));
+            kids.push(Object::Reference(doc.add_object(kid)));
+        }
+        let mut size = Dictionary::new();
+        size.set("FT", Object::Name(b"Btn".to_vec()));
+        size.set("Ff", Object::Integer(1 << 15));
+        size.set("T", Object::string_literal("size"));
+        size.set("Kids", Object::Array(kids.clone()));
+        doc.objects.insert(size_id, Object::Dictionary(size));
+
+        let mut annots = vec![Object::Reference(name_id), Object::Reference(agree_id)];
+        annots.extend(kids);
+        doc.get_dictionary_mut(page_id).unwrap().set("Annots", Object::Array(annots));
+
+        let mut form = Dictionary::new();
+        form.set("Fields", vec![Object::Reference(name_id), Object::Reference(agree_id), Object::Reference(size_id)]);
+        form.set("DA", Object::string_literal("/Helv 0 Tf 0 g"));
+        doc.catalog_mut().unwrap().set("AcroForm", Object::Dictionary(form));
+
+        let mut buffer = Vec::new();
+        doc.save_to(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_fill_and_flatten_form() {
+        let pdf = make_form_pdf();
+        let fields: serde_json::Value = serde_json::from_str(&pdf_utils_rust::get_form_fields(&pdf).unwrap()).unwrap();
+        assert_eq!(fields.as_array().unwrap().len(), 3);
+        assert_eq!(fields[0]["type"], "text");
+        assert_eq!(fields[1]["type"], "checkbox");
+        assert_eq!(fields[1]["value"], false);
+        assert_eq!(fields[2]["type"], "radio");
+        assert_eq!(fields[2]["options"], serde_json::json!(["S", "L"]));
+        assert_eq!(fields[2]["page"], 1);
+
+        let filled = pdf_utils_rust::fill_form(&pdf, r#"{"name": "Alice", "agree": true, "size": "L"}"#, None).unwrap();
+        let fields: serde_json::Value = serde_json::from_str(&pdf_utils_rust::get_form_fields(&filled).unwrap()).unwrap();
+        assert_eq!(fields[0]["value"], "Alice");
+        assert_eq!(fields[1]["value"], true);
+        assert_eq!(fields[2]["value"], "L");
+
+        // 文本字段生成了外观流，复选框补上了 Yes/Off 外观
+        let doc = Document::load_mem(&filled).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let annots = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap().clone();
+        let name = doc.get_dictionary(annots[0].as_reference().unwrap()).unwrap();
+        let appearance = doc.get_object(name.get(b"AP").unwrap().as_dict().unwrap().get(b"N").unwrap().as_reference().unwrap()).unwrap();
+        assert!(String::from_utf8_lossy(&appearance.as_stream().unwrap().content).contains("(Alice) Tj"));
+        let agree = doc.get_dictionary(annots[1].as_reference().unwrap()).unwrap();
+        assert_eq!(agree.get(b"AS").unwrap().as_name().unwrap(), b"Yes");
+        let small = doc.get_dictionary(annots[2].as_reference().unwrap()).unwrap();
+        assert_eq!(small.get(b"AS").unwrap().as_name().unwrap(), b"Off");
+
+        // 扁平化后外观写入页面内容，控件和表单被删除
+        let flattened = pdf_utils_rust::flatten_form(&filled, None).unwrap();
+        assert_eq!(pdf_utils_rust::get_form_fields(&flattened).unwrap(), "[]");
+        let doc = Document::load_mem(&flattened).unwrap();
+        assert!(doc.catalog().unwrap().get(b"AcroForm").is_err());
+        let page_id = *doc.get_pages().values().next().unwrap();
+        assert!(doc.get_dictionary(page_id).unwrap().get(b"Annots").is_err());
+        let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).into_owned();
+        assert_eq!(content.matches(" Do").count(), 3);
+    }
+
+    /// 生成两页的测试 PDF，第 1 页带高亮（含外观流和弹出窗口）、便签和指向第 2 页的链接
+    fn make_annotated_pdf() -> Vec<u8> {
+        let mut doc = Document::load_mem(&make_pdf(&[(612, 792), (612, 792)])).unwrap();
+        let pages: Vec<_> = doc.get_pages().into_values().collect();
+        let rect = |r: [i64; 4]| Object::Array(r.iter().map(|&v| v.into()).collect());
+        let annot = |subtype: &str, r: [i64; 4]| {
+            let mut dict = Dictionary::new();
+            dict.set("Type", Object::Name(b"Annot".to_vec()));
+            dict.set("Subtype", Object::Name(subtype.as_bytes().to_vec()));
+            dict.set("Rect", rect(r));
+            dict
+        };
+
+        let mut form = Dictionary::new();
+        form.set("BBox", rect([0, 0, 100, 20]));
+        let appearance_id = doc.add_object(Stream::new(form, b"1 1 0 rg 0 0 100 20 re f".to_vec()));
+        let highlight_id = doc.new_object_id();
+        let mut popup = annot("Popup", [300, 700, 400, 750]);
+        popup.set("Parent", Object::Reference(highlight_id));
+        let popup_id = doc.add_object(popup);
+
+        let mut highlight = annot("Highlight", [50, 700, 150, 720]);
+        highlight.set("T", Object::string_literal("Alice"));
+        highlight.set("Contents", Object::string_literal("important"));
+        highlight.set("C", vec![1.into(), 1.into(), 0.into()]);
+        let mut ap = Dictionary::new();
+        ap.set("N", Object::Reference(appearance_id));
+        highlight.set("AP", Object::Dictionary(ap));
+        highlight.set("Popup", Object::Reference(popup_id));
+        doc.objects.insert(highlight_id, Object::Dictionary(highlight));
+
+        let mut note = annot("Text", [50, 600, 70, 620]);
+        note.set("T", Object::string_literal("Bob"));
+        let note_id = doc.add_object(note);
+        let mut link = annot("Link", [50, 500, 150, 520]);
+        link.set("Dest", vec![Object::Reference(pages[1]), Object::Name(b"Fit".to_vec())]);
+        let link_id = doc.add_object(link);
+
+        let annots = [highlight_id, popup_id, note_id, link_id].map(Object::Reference).to_vec();
+        doc.get_dictionary_mut(pages[0]).unwrap().set("Annots", annots);
+        let mut buffer = Vec::new();
+        doc.save_to(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn annotation_subtypes(pdf_bytes: &[u8]) -> Vec<String> {
+        let json: serde_json::Value = serde_json::from_str(&pdf_utils_rust::list_annotations(pdf_bytes).unwrap()).unwrap();
+        json.as_array().unwrap().iter().map(|a| a["subtype"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn test_list_remove_and_flatten_annotations() {
+        let pdf = make_annotated_pdf();
+        let json: serde_json::Value = serde_json::from_str(&pdf_utils_rust::list_annotations(&pdf).unwrap()).unwrap();
+        assert_eq!(json[0]["subtype"], "Highlight");
+        assert_eq!(json[0]["author"], "Alice");
+        assert_eq!(json[0]["contents"], "important");
+        assert_eq!(json[0]["color"], "#ffff00");
+        assert_eq!(json[0]["page"], 1);
+        assert_eq!(json[2]["index"], 2);
+
+        // 按作者删除；删除高亮时其弹出窗口一并删除
+        let removed = pdf_utils_rust::remove_annotations(&pdf, r#"{"authors": ["Bob"]}"#, None).unwrap();
+        assert_eq!(annotation_subtypes(&removed), ["Highlight", "Popup", "Link"]);
+        let removed = pdf_utils_rust::remove_annotations(&pdf, r#"{"subtypes": ["Highlight"]}"#, None).unwrap();
+        assert_eq!(annotation_subtypes(&removed), ["Text", "Link"]);
+
+        // 扁平化：有外观的高亮写入页面内容，没有外观的便签和链接保留
+        let flattened = pdf_utils_rust::flatten_annotations(&pdf, None).unwrap();
+        assert_eq!(annotation_subtypes(&flattened), ["Text", "Link"]);
+        let doc = Document::load_mem(&flattened).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).into_owned();
+        assert!(content.contains("1 0 0 1 50 700 cm"));
+
+        // 只提取第 1 页时，指向第 2 页的链接被丢弃，第 2 页也不会被带入
+        let first = pdf_utils_rust::split_pdf_by_range(&pdf, "1", None).unwrap();
+        assert_eq!(annotation_subtypes(&first), ["Highlight", "Popup", "Text"]);
+        let doc = Document::load_mem(&first).unwrap();
+        let page_objects = doc.objects.values()
+            .filter(|o| o.as_dict().ok().and_then(|d| d.get(b"Type").ok()).and_then(|t| t.as_name().ok()) == Some(b"Page".as_slice()))
+            .count();
+        assert_eq!(page_objects, 1);
+    }
+
+    #[test]
+    fn test_add_annotations() {
+        let pdf = make_pdf(&[(612, 792), (612, 792)]);
+        let annotations = r##"[
+            {"type": "highlight", "page": 1, "rects": [[72, 700, 300, 714]], "contents": "check", "author": "Alice"},
+            {"type": "FreeText", "page": 1, "rect": [300, 500, 500, 580], "contents": "Approved", "fill": "#eeeeff"},
+            {"type": "Ink", "page": 2, "paths": [[80, 200, 120, 260, 160, 200]], "width": 3, "color": "#8800ff"},
+            {"type": "Link", "page": 2, "rect": [80, 100, 200, 120], "dest_page": 1}
+        ]"##;
+        let result = pdf_utils_rust::add_annotations(&pdf, annotations, None).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&pdf_utils_rust::list_annotations(&result).unwrap()).unwrap();
+        assert_eq!(annotation_subtypes(&result), ["Highlight", "FreeText", "Ink", "Link"]);
+        assert_eq!(json[0]["author"], "Alice");
+        assert_eq!(json[0]["color"], "#ffff00");
+        assert_eq!(json[0]["rect"], serde_json::json!([72.0, 700.0, 300.0, 714.0]));
+        assert_eq!(json[2]["page"], 2);
+        assert_eq!(json[2]["rect"], serde_json::json!([77.0, 197.0, 163.0, 263.0]));
+
+        // 链接没有外观，其他注释扁平化后写入页面内容
+        let flattened = pdf_utils_rust::flatten_annotations(&result, None).unwrap();
+        assert_eq!(annotation_subtypes(&flattened), ["Link"]);
+        let doc = Document::load_mem(&flattened).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).into_owned();
+        assert_eq!(content.matches(" Do").count(), 2);
+
+        // Helvetica 无法显示中文时报错，提供字体时嵌入字体子集
+        let annotations = r#"[{"type": "FreeText", "page": 1, "rect": [300, 500, 500, 580], "contents": "中A"}]"#;
+        let error = pdf_utils_rust::add_annotations_from_bytes(&pdf, annotations, None, false).unwrap_err();
+        assert!(error.contains("add_annotations_with_font"), "{}", error);
+        let result = pdf_utils_rust::add_annotations_from_bytes(&pdf, annotations, Some(&make_otf()), false).unwrap();
+        let doc = Document::load_mem(&result).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let annots = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap();
+        let annot = doc.get_dictionary(annots[0].as_reference().unwrap()).unwrap();
+        let appearance = annot.get(b"AP").unwrap().as_dict().unwrap().get(b"N").unwrap().as_reference().unwrap();
+        let appearance = doc.get_object(appearance).unwrap().as_stream().unwrap();
+        assert!(String::from_utf8_lossy(&appearance.content).contains("<00010001> Tj"));
+        let fonts = appearance.dict.get(b"Resources").unwrap().as_dict().unwrap().get(b"Font").unwrap().as_dict().unwrap();
+        let font = doc.get_dictionary(fonts.get(b"EmbF").unwrap().as_reference().unwrap()).unwrap();
+        assert_eq!(font.get(b"Subtype").unwrap().as_name().unwrap(), b"Type0");
+    }
+
+    #[test]
+    fn test_redact_pdf() {
+        let content = b"BT /F1 12 Tf 72 700 Td (Name: John Smith, ID 42) Tj 0 -20 Td (Public text) Tj ET";
+        let pdf = make_pdf_with_contents(&[((612, 792), content.as_slice())]);
+        let pdf = pdf_utils_rust::add_annotations(&pdf, r#"[
+            {"type": "Square", "page": 1, "rect": [70, 670, 150, 690]},
+            {"type": "Text", "page": 1, "rect": [500, 100, 520, 120]}
+        ]"#, None).unwrap();
+
+        let redacted = pdf_utils_rust::redact_pdf(&pdf, r#"{
+            "terms": ["john smith"],
+            "regions": [{"page": 1, "rect": [70, 675, 200, 690]}]
+        }"#).unwrap();
+
+        // 被涂黑的文字从内容流中删除，其余文字保留
+        let doc = Document::load_mem(&redacted).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).into_owned();
+        assert!(!content.contains("John"));
+        assert!(!content.contains("Smith"));
+        assert!(!content.contains("Public"));
+        assert!(content.contains("(Name: "));
+        assert!(content.contains(", ID 42)"));
+        assert!(content.contains("0 0 0 rg"));
+
+        // 与涂黑区域重叠的注释被删除
+        assert_eq!(annotation_subtypes(&redacted), ["Text"]);
+    }
+
+    #[test]
+    fn test_redact_alternate_text() {
+        let content = b"/P <</MCID 0 /ActualText (Secret)>> BDC BT /F1 12 Tf 72 700 Td (Secret) Tj ET EMC \
+/Span /MC0 BDC BT /F1 12 Tf 72 650 Td (Secret) Tj ET EMC \
+/P <</MCID 2 /ActualText (Public)>> BDC BT /F1 12 Tf 72 100 Td (Public) Tj ET EMC";
+        let pdf = make_pdf_with_contents(&[((612, 792), content.as_slice())]);
+        let mut doc = Document::load_mem(&pdf).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let properties = Dictionary::from_iter(vec![("MCID", Object::Integer(1)), ("ActualText", Object::string_literal("Secret"))]);
+        let properties = Dictionary::from_iter(vec![("MC0", Object::Dictionary(properties))]);
+        doc.get_dictionary_mut(page_id).unwrap()
+            .set("Resources", Dictionary::from_iter(vec![("Properties", Object::Dictionary(properties))]));
+
+        // 结构树：Document 包含三个元素，分别对应三段标记内容
+        let root_id = doc.new_object_id();
+        let document_id = doc.new_object_id();
+        let element = |doc: &mut Document, mcid: i64, key: &str, text: &str| {
+            doc.add_object(Dictionary::from_iter(vec![
+                ("Type", Object::Name(b"StructElem".to_vec())),
+                ("S", Object::Name(b"P".to_vec())),
+                ("P", Object::Reference(document_id)),
+                ("Pg", Object::Reference(page_id)),
+                ("K", Object::Integer(mcid)),
+                (key, Object::string_literal(text)),
+            ]))
+        };
+        let kids = vec![
+            Object::Reference(element(&mut doc, 0, "Alt", "Secret")),
+            Object::Reference(element(&mut doc, 1, "E", "Secret")),
+            Object::Reference(element(&mut doc, 2, "Alt", "Public")),
+        ];
+        doc.objects.insert(document_id, Object::Dictionary(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"StructElem".to_vec())),
+            ("S", Object::Name(b"Document".to_vec())),
+            ("P", Object::Reference(root_id)),
+            ("K", Object::Array(kids)),
+            ("ActualText", Object::string_literal("Secret Secret Public")),
+        ])));
+        doc.objects.insert(root_id, Object::Dictionary(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"StructTreeRoot".to_vec())),
+            ("K", Object::Reference(document_id)),
+        ])));
+        doc.catalog_mut().unwrap().set("StructTreeRoot", Object::Reference(root_id));
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).unwrap();
+
+        let redacted = pdf_utils_rust::redact_pdf(&pdf, r#"{"terms": ["secret"]}"#).unwrap();
+
+        // 被涂黑的文字不再以任何形式出现在文件中，未涂黑部分的替代文本保留
+        let doc = Document::load_mem(&redacted).unwrap();
+        assert!(!contains(&redacted, b"Secret"));
+        for object in doc.objects.values() {
+            if let Ok(stream) = object.as_stream() {
+                let data = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+                assert!(!contains(&data, b"Secret"), "{}", String::from_utf8_lossy(&data));
+            }
+        }
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        assert!(contains(&content, b"/ActualText(Public)"));
+        assert!(contains(&content, b"/MCID 1"));
+        assert!(contains(&redacted, b"/Alt(Public)"));
+    }
+
+    #[test]
+    fn test_redact_uninspectable_forms() {
+        let pdf = make_pdf_with_contents(&[((612, 792), b"/Deep Do /Bad Do /Keep Do".as_slice())]);
+        let mut doc = Document::load_mem(&pdf).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let form = |doc: &mut Document, content: &[u8], xobjects: Option<Dictionary>| {
+            let mut dict = Dictionary::new();
+            dict.set("Type", Object::Name(b"XObject".to_vec()));
+            dict.set("Subtype", Object::Name(b"Form".to_vec()));
+            dict.set("BBox", vec![0.into(), 0.into(), 612.into(), 792.into()]);
+            if let Some(xobjects) = xobjects {
+                dict.set("Resources", Dictionary::from_iter(vec![("XObject", Object::Dictionary(xobjects))]));
+            }
+            doc.add_object(Stream::new(dict, content.to_vec()))
+        };
+
+        // 嵌套超过最大深度的表单，最内层才绘制要涂黑的文字
+        let mut deep = form(&mut doc, b"BT /F1 12 Tf 72 700 Td (Secret) Tj ET", None);
+        for _ in 0..10 {
+            deep = form(&mut doc, b"/Inner Do", Some(Dictionary::from_iter(vec![("Inner", Object::Reference(deep))])));
+        }
+        // 使用不支持的过滤器、内容无法解码的表单
+        let bad = form(&mut doc, b"BT /F1 12 Tf 72 700 Td (Secret) Tj ET", None);
+        doc.get_object_mut(bad).unwrap().as_stream_mut().unwrap().dict.set("Filter", Object::Name(b"FooDecode".to_vec()));
+        // 可以检查且不在涂黑区域内的表单
+        let keep = form(&mut doc, b"BT /F1 12 Tf 72 100 Td (Keep) Tj ET", None);
+        let xobjects = Dictionary::from_iter(vec![
+            ("Deep", Object::Reference(deep)),
+            ("Bad", Object::Reference(bad)),
+            ("Keep", Object::Reference(keep)),
+        ]);
+        doc.get_dictionary_mut(page_id).unwrap()
+            .set("Resources", Dictionary::from_iter(vec![("XObject", Object::Dictionary(xobjects))]));
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).unwrap();
+
+        let redacted = pdf_utils_rust::redact_pdf(&pdf, r#"{"regions": [{"page": 1, "rect": [70, 690, 200, 720]}]}"#).unwrap();
+
+        // 无法检查的表单（及其所在的嵌套层）连同资源一起删除，其余表单保留
+        let doc = Document::load_mem(&redacted).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).into_owned();
+        assert!(!content.contains("/Deep Do") && !content.contains("/Bad Do"), "{}", content);
+        assert!(doc.objects.values().all(|o| o.as_stream().map_or(true, |s| !s.content.windows(6).any(|w| w == b"Secret"))));
+        assert!(content.contains("/Keep Do"), "{}", content);
+        let resources = doc.get_dictionary(page_id).unwrap().get(b"Resources").unwrap();
+        let resources = doc.dereference(resources).unwrap().1.as_dict().unwrap();
+        let xobjects = doc.dereference(resources.get(b"XObject").unwrap()).unwrap().1.as_dict().unwrap();
+        assert!(!xobjects.has(b"Deep") && !xobjects.has(b"Bad"));
+        assert!(xobjects.has(b"Keep"));
+    }
+
+    /// 生成带脚本、危险动作、附件、多媒体、XFA 和元数据的测试 PDF
+    fn make_active_pdf() -> Vec<u8> {
+        let mut doc = Document::load_mem(&make_pdf(&[(612, 792)])).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let action = |doc: &mut Document, kind: &str, key: &str, value: Object| {
+            let mut dict = Dictionary::new();
+            dict.set("S", Object::Name(kind.as_bytes().to_vec()));
+            dict.set(key, value);
+            doc.add_object(dict)
+        };
+        let js = action(&mut doc, "JavaScript", "JS", Object::string_literal("app.alert(1)"));
+        let launch = action(&mut doc, "Launch", "F", Object::string_literal("calc.exe"));
+        let uri = action(&mut doc, "URI", "URI", Object::string_literal("https://example.com"));
+        let goto = action(&mut doc, "GoTo", "D", vec![Object::Reference(page_id), Object::Name(b"Fit".to_vec())].into());
+        doc.get_dictionary_mut(goto).unwrap().set("Next", Object::Reference(js));
+
+        let file = doc.add_object(Stream::new(
+            Dictionary::from_iter(vec![("Type", Object::Name(b"EmbeddedFile".to_vec()))]),
+            b"payload".to_vec(),
+        ));
+        let mut ef = Dictionary::new();
+        ef.set("F", Object::Reference(file));
+        let mut filespec = Dictionary::new();
+        filespec.set("Type", Object::Name(b"Filespec".to_vec()));
+        filespec.set("F", Object::string_literal("payload.exe"));
+        filespec.set("EF", Object::Dictionary(ef));
+        let filespec = doc.add_object(filespec);
+
+        let annot = |subtype: &str, rect: i64| {
+            let mut dict = Dictionary::new();
+            dict.set("Type", Object::Name(b"Annot".to_vec()));
+            dict.set("Subtype", Object::Name(subtype.as_bytes().to_vec()));
+            dict.set("Rect", vec![rect.into(), rect.into(), (rect + 20).into(), (rect + 20).into()]);
+            dict
+        };
+        let mut uri_link = annot("Link", 100);
+        uri_link.set("A", Object::Reference(uri));
+        let mut launch_link = annot("Link", 200);
+        launch_link.set("A", Object::Reference(launch));
+        let mut goto_link = annot("Link", 300);
+        goto_link.set("A", Object::Reference(goto));
+        let mut attachment = annot("FileAttachment", 400);
+        attachment.set("FS", Object::Reference(filespec));
+        let media = annot("RichMedia", 500);
+        let annots: Vec<Object> = [uri_link, launch_link, goto_link, attachment, media]
+            .into_iter()
+            .map(|a| Object::Reference(doc.add_object(a)))
+            .collect();
+        let page = doc.get_dictionary_mut(page_id).unwrap();
+        page.set("Annots", annots);
+        page.set("AA", Dictionary::from_iter(vec![("O", Object::Reference(js))]));
+
+        let metadata = doc.add_object(Stream::new(
+            Dictionary::from_iter(vec![("Type", Object::Name(b"Metadata".to_vec())), ("Subtype", Object::Name(b"XML".to_vec()))]),
+            b"<x:xmpmeta/>".to_vec(),
+        ));
+        let info = doc.add_object(Dictionary::from_iter(vec![("Title", Object::string_literal("secret"))]));
+        doc.trailer.set("Info", Object::Reference(info));
+
+        let mut names = Dictionary::new();
+        names.set("JavaScript", Dictionary::from_iter(vec![("Names", Object::Array(vec![Object::string_literal("init"), Object::Reference(js)]))]));
+        names.set("EmbeddedFiles", Dictionary::from_iter(vec![("Names", Object::Array(vec![Object::string_literal("payload.exe"), Object::Reference(filespec)]))]));
+        let mut form = Dictionary::new();
+        form.set("Fields", Object::Array(Vec::new()));
+        form.set("XFA", Object::string_literal("<xdp/>"));
+        let catalog = doc.catalog_mut().unwrap();
+        catalog.set("OpenAction", Object::Reference(js));
+        catalog.set("Names", names);
+        catalog.set("AcroForm", form);
+        catalog.set("Metadata", Object::Reference(metadata));
+
+        let mut buffer = Vec::new();
+        doc.save_to(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn test_sanitize_pdf() {
+        let pdf = make_active_pdf();
+
+        // 默认策略保留网址链接和元数据
+        let result = pdf_utils_rust::sanitize_pdf(&pdf, "{}", None).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&result.report()).unwrap();
+        assert_eq!(report["javascript"], 2);
+        assert_eq!(report["open_action"], 1);
+        assert_eq!(report["additional_actions"], 1);
+        assert_eq!(report["launch"], 1);
+        assert_eq!(report["uri"], 0);
+        assert_eq!(report["embedded_files"], 1);
+        assert_eq!(report["rich_media"], 1);
+        assert_eq!(report["xfa"], 1);
+        assert_eq!(report["metadata"], 0);
+
+        let cleaned = result.pdf();
+        for needle in [b"app.alert".as_slice(), b"calc.exe", b"payload", b"<xdp/>"] {
+            assert!(!contains(&cleaned, needle));
+        }
+        assert!(contains(&cleaned, b"https://example.com"));
+        assert!(contains(&cleaned, b"<x:xmpmeta/>"));
+        // 安全的跳转动作保留，动作链中的脚本被删除
+        assert_eq!(annotation_subtypes(&cleaned), ["Link", "Link", "Link"]);
+        assert!(contains(&cleaned, b"/GoTo"));
+
+        let result = pdf_utils_rust::sanitize_pdf(&pdf, r#"{"remove_uri": true, "remove_metadata": true}"#, None).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&result.report()).unwrap();
+        assert_eq!(report["uri"], 1);
+        assert_eq!(report["metadata"], 2);
+        let cleaned = result.pdf();
+        assert!(!contains(&cleaned, b"https://example.com"));
+        assert!(!contains(&cleaned, b"<x:xmpmeta/>"));
+        assert!(!contains(&cleaned, b"secret"));
+    }
+
+    #[test]
+    fn test_attachments() {
+        // 已有的附件同时出现在名称树和附件注释中，只列出一次
+        let pdf = make_active_pdf();
+        let pdf = pdf_utils_rust::add_attachment(&pdf, "report.csv", b"a,b\n1,2\n", Some("text/csv".to_string()), Some("季度数据".to_string()), None).unwrap();
+        let pdf = pdf_utils_rust::add_attachment(&pdf, "数据.txt", b"old", None, None, None).unwrap();
+        let pdf = pdf_utils_rust::add_attachment(&pdf, "数据.txt", "你好".as_bytes(), None, None, None).unwrap();
+
+        let list: serde_json::Value = serde_json::from_str(&pdf_utils_rust::list_attachments(&pdf).unwrap()).unwrap();
+        let names: Vec<&str> = list.as_array().unwrap().iter().map(|a| a["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["payload.exe", "report.csv", "数据.txt"]);
+        assert_eq!(list[0]["size"], 7);
+        assert_eq!(list[1]["file_name"], "report.csv");
+        assert_eq!(list[1]["mime"], "text/csv");
+        assert_eq!(list[1]["description"], "季度数据");
+        assert_eq!(list[1]["size"], 8);
+        assert!(list[1]["created"].as_str().unwrap().starts_with("D:20"));
+        assert!(list[1]["page"].is_null());
+
+        assert_eq!(pdf_utils_rust::extract_attachment(&pdf, "report.csv").unwrap(), b"a,b\n1,2\n");
+        assert_eq!(pdf_utils_rust::extract_attachment(&pdf, "数据.txt").unwrap(), "你好".as_bytes());
+        assert_eq!(pdf_utils_rust::extract_attachment(&pdf, "payload.exe").unwrap(), b"payload");
+        // 被替换的同名附件不再残留
+        assert!(!contains(&pdf, b"old"));
+    }
+
+    fn replace(haystack: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+        let at = haystack.windows(from.len()).position(|w| w == from).unwrap();
+        [&haystack[..at], to, &haystack[at + from.len()..]].concat()
+    }
+
+    fn issue_kinds(pdf_bytes: &[u8]) -> Vec<(String, Option<String>)> {
+        let issues: serde_json::Value = serde_json::from_str(&pdf_utils_rust::validate_pdf(pdf_bytes).unwrap()).unwrap();
+        issues.as_array().unwrap().iter()
+            .map(|i| (i["kind"].as_str().unwrap().to_string(), i["object"].as_str().map(str::to_string)))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_and_repair_pdf() {
+        let pdf = make_pdf_with_contents(&[((200, 300), b"0 0 m 10 10 l S"), ((400, 500), b"1 0 0 RG 0 0 m 5 5 l S")]);
+        assert!(issue_kinds(&pdf).is_empty());
+
+        // 流长度和页数错误，startxref 指向错误位置
+        let broken = replace(&pdf, b"/Count 2", b"/Count 5");
+        let broken = replace(&broken, b"/Length 15", b"/Length 99");
+        let at = broken.windows(9).rposition(|w| w == b"startxref").unwrap();
+        let broken = [&broken[..at], b"startxref\n7\n%%EOF".as_slice()].concat();
+        assert!(Document::load_mem(&broken).is_err());
+        let kinds: Vec<String> = issue_kinds(&broken).into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, ["invalid_stream_length", "broken_xref", "wrong_count"]);
+
+        let repaired = pdf_utils_rust::repair_pdf(&broken, None).unwrap();
+        assert!(issue_kinds(&repaired).is_empty());
+        assert_eq!(page_sizes(&repaired), [(200.0, 300.0), (400.0, 500.0)]);
+        let doc = Document::load_mem(&repaired).unwrap();
+        let first_page = *doc.get_pages().get(&1).unwrap();
+        assert_eq!(doc.get_page_content(first_page).unwrap(), b"0 0 m 10 10 l S");
+
+        // 交叉引用表中对象 4 的偏移量错误，页面 4 缺少 /Type，页面树引用了不存在的对象 9
+        let body = b"%PDF-1.4\n\
+            1 0 obj <</Type/Catalog/Pages 2 0 R>> endobj\n\
+            2 0 obj <</Type/Pages/Kids[3 0 R 4 0 R 9 0 R]/Count 3>> endobj\n\
+            3 0 obj <</Type/Page/Parent 2 0 R/MediaBox[0 0 100 100]/Contents 5 0 R>> endobj\n\
+            4 0 obj <</Parent 2 0 R/MediaBox[0 0 200 200]>> endobj\n\
+            5 0 obj <</Length 6 0 R>>stream\n0 0 m\nendstream endobj\n\
+            6 0 obj 5 endobj\n".to_vec();
+        let offset = |n: usize| body.windows(8).position(|w| w == format!("{} 0 obj ", n).as_bytes()).unwrap();
+        let mut xref = "xref\n0 7\n0000000000 65535 f \n".to_string();
+        for n in 1..=6 {
+            xref += &format!("{:010} 00000 n \n", if n == 4 { offset(4) + 3 } else { offset(n) });
+        }
+        let pdf = [body.clone(), format!("{}trailer\n<</Root 1 0 R/Size 7>>\nstartxref\n{}\n%%EOF", xref, body.len()).into_bytes()].concat();
+        assert!(Document::load_mem(&pdf).is_ok());
+        let issues = issue_kinds(&pdf);
+        for expected in [("broken_xref", "4 0"), ("missing_object", "9 0"), ("bad_page_tree", "9 0"), ("wrong_count", "2 0")] {
+            assert!(issues.contains(&(expected.0.to_string(), Some(expected.1.to_string()))), "{:?}", issues);
+        }
+
+        let repaired = pdf_utils_rust::repair_pdf(&pdf, None).unwrap();
+        assert!(issue_kinds(&repaired).is_empty());
+        assert_eq!(page_sizes(&repaired), [(100.0, 100.0), (200.0, 200.0)]);
+    }
+
+    #[test]
+    fn test_split_with_missing_objects() {
+        let pdf = make_pdf(&[(200, 300), (400, 500)]);
+        let mut doc = Document::load_mem(&pdf).unwrap();
+        let pages: Vec<_> = doc.get_pages().into_values().collect();
+        // 第 2 页引用不存在的字体，页面树中还有一个不存在的页面
+        let font = Dictionary::from_iter(vec![("F1", Object::Reference((99, 0)))]);
+        doc.get_dictionary_mut(pages[1]).unwrap().set("Resources", Dictionary::from_iter(vec![("Font", Object::Dictionary(font))]));
+        let root = doc.catalog().unwrap().get(b"Pages").unwrap().as_reference().unwrap();
+        let tree = doc.get_dictionary_mut(root).unwrap();
+        tree.set("Kids", vec![Object::Reference(pages[0]), Object::Reference((98, 0)), Object::Reference(pages[1])]);
+        tree.set("Count", 3);
+        let mut broken = Vec::new();
+        doc.save_to(&mut broken).unwrap();
+
+        let result = pdf_utils_rust::split_pdf_by_range_with_warnings(&broken, "1-2", false, None).unwrap();
+        assert_eq!(page_sizes(&result.pdf()), [(200.0, 300.0), (400.0, 500.0)]);
+        let warnings: serde_json::Value = serde_json::from_str(&result.warnings()).unwrap();
+        assert_eq!(warnings.as_array().unwrap().len(), 2);
+        assert_eq!(warnings[0]["page"], 2);
+        assert_eq!(warnings[0]["object"], "98 0");
+        // 页码按页面树中的位置计，不存在的页面也计入
+        assert_eq!(warnings[1]["page"], 3);
+        assert_eq!(warnings[1]["object"], "99 0");
+        assert!(warnings[1]["message"].as_str().unwrap().contains("第 3 页"));
+        assert!(warnings[1]["file"].is_null());
+
+        // 完好的文件在严格模式下正常分割，没有警告
+        let result = pdf_utils_rust::split_pdf_by_range_with_warnings(&pdf, "2", true, None).unwrap();
+        assert_eq!(page_sizes(&result.pdf()), [(400.0, 500.0)]);
+        assert_eq!(result.warnings(), "[]");
+
+        // 严格模式下遇到缺失直接报错
+        let error = pdf_utils_rust::split_pdf_by_range_from_bytes(&broken, "1-2", true, false).err().unwrap();
+        assert!(error.contains("98 0"), "{}", error);
+        let error = pdf_utils_rust::split_pdf_by_range_from_bytes(&broken, "1", true, false).err().unwrap();
+        assert!(error.contains("第 2 页") && error.contains("不存在"), "{}", error);
+
+        // 分割为单页和交错合并同样记录警告
+        let result = pdf_utils_rust::split_pdf_with_warnings(&broken, false, None).unwrap();
+        let warnings: serde_json::Value = serde_json::from_str(&result.warnings()).unwrap();
+        assert_eq!(warnings.as_array().unwrap().len(), 2);
+        let result = pdf_utils_rust::split_pdf_by_pages_with_warnings(&broken, "1", false, None).unwrap();
+        let warnings: serde_json::Value = serde_json::from_str(&result.warnings()).unwrap();
+        assert_eq!(warnings.as_array().unwrap().len(), 1);
+        assert_eq!(warnings[0]["object"], "98 0");
+        let result = pdf_utils_rust::interleave_pdfs_with_warnings(&pdf, &broken, true, false, None).unwrap();
+        assert_eq!(page_sizes(&result.pdf()), [(200.0, 300.0), (400.0, 500.0), (400.0, 500.0), (200.0, 300.0)]);
+        let warnings: serde_json::Value = serde_json::from_str(&result.warnings()).unwrap();
+        assert_eq!(warnings.as_array().unwrap().len(), 2);
+        assert_eq!(warnings[1]["file"], 2);
+        assert_eq!(warnings[1]["page"], 3);
+        assert_eq!(warnings[1]["object"], "99 0");
+    }
+
+    #[test]
+    fn test_merge_with_missing_objects() {
+        let pdf = make_pdf(&[(200, 300)]);
+        let mut doc = Document::load_mem(&make_pdf(&[(400, 500)])).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        // 页面引用不存在的图片
+        let xobjects = Dictionary::from_iter(vec![("Im1", Object::Reference((99, 0)))]);
+        doc.get_dictionary_mut(page_id).unwrap().set("Resources", Dictionary::from_iter(vec![("XObject", Object::Dictionary(xobjects))]));
+        let mut broken = Vec::new();
+        doc.save_to(&mut broken).unwrap();
+        let files = [pdf.clone(), broken];
+
+        let result = pdf_utils_rust::merge_pdfs_from_bytes(&files, "{}").unwrap();
+        assert_eq!(page_sizes(&result.pdf()), [(200.0, 300.0), (400.0, 500.0)]);
+        let warnings: serde_json::Value = serde_json::from_str(&result.warnings()).unwrap();
+        assert_eq!(warnings.as_array().unwrap().len(), 1);
+        assert_eq!(warnings[0]["file"], 2);
+        assert_eq!(warnings[0]["page"], 1);
+        assert_eq!(warnings[0]["object"], "99 0");
+        // 缺失的引用替换为 null
+        let merged = Document::load_mem(&result.pdf()).unwrap();
+        let page_id = *merged.get_pages().values().nth(1).unwrap();
+        let (resources, _) = merged.get_page_resources(page_id);
+        let xobjects = resources.unwrap().get(b"XObject").unwrap().as_dict().unwrap();
+        assert_eq!(xobjects.get(b"Im1").unwrap(), &Object::Null);
+
+        let error = pdf_utils_rust::merge_pdfs_from_bytes(&files, r#"{"strict": true}"#).err().unwrap();
+        assert!(error.contains("第 2 个 PDF") && error.contains("99 0"), "{}", error);
+        let result = pdf_utils_rust::merge_pdfs_from_bytes(&[pdf.clone(), pdf], r#"{"strict": true}"#).unwrap();
+        assert_eq!(result.warnings(), "[]");
+    }
+
+    fn violation_kinds(pdf_bytes: &[u8]) -> Vec<String> {
+        let violations: serde_json::Value = serde_json::from_str(&pdf_utils_rust::check_pdfa(pdf_bytes).unwrap()).unwrap();
+        violations.as_array().unwrap().iter().map(|v| v["kind"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn test_convert_and_check_pdfa() {
+        let pdf = make_pdf_with_contents(&[((200, 300), b"1 0 0 0 k 0 0 10 10 re f".as_slice())]);
+        let kinds = violation_kinds(&pdf);
+        for kind in ["header", "missing_file_id", "missing_metadata", "missing_output_intent"] {
+            assert!(kinds.iter().any(|k| k == kind), "{:?}", kinds);
+        }
+
+        // CMYK 颜色换算为 sRGB 输出意图的 RGB
+        let converted = pdf_utils_rust::convert_to_pdfa(&pdf, "2b", None).unwrap();
+        assert_eq!(pdf_utils_rust::check_pdfa(&converted).unwrap(), "[]");
+        assert!(converted.starts_with(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n"));
+        assert!(contains(&converted, b"<pdfaid:part>2</pdfaid:part>"));
+        let doc = Document::load_mem(&converted).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+        assert!(content.contains("0 1 1 rg"), "{}", content);
+
+        // 脚本、附件、多媒体和 XFA 被删除，链接注释设置打印标志
+        let converted = pdf_utils_rust::convert_to_pdfa(&make_active_pdf(), "PDF/A-1b", None).unwrap();
+        assert_eq!(pdf_utils_rust::check_pdfa(&converted).unwrap(), "[]");
+        assert!(converted.starts_with(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n"));
+        assert!(contains(&converted, b"<pdfaid:part>1</pdfaid:part>"));
+        assert!(!contains(&converted, b"app.alert"));
+        assert_eq!(annotation_subtypes(&converted), ["Link", "Link", "Link"]);
+
+        // 未嵌入的字体无法自动修正，由检查报告
+        let mut doc = Document::load_mem(&pdf).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let mut font = Dictionary::new();
+        font.set("Type", Object::Name(b"Font".to_vec()));
+        font.set("Subtype", Object::Name(b"Type1".to_vec()));
+        font.set("BaseFont", Object::Name(b"Helvetica".to_vec()));
+        let font_id = doc.add_object(font);
+        let fonts = Dictionary::from_iter(vec![("F1", Object::Reference(font_id))]);
+        doc.get_dictionary_mut(page_id).unwrap().set("Resources", Dictionary::from_iter(vec![("Font", Object::Dictionary(fonts))]));
+        let mut with_font = Vec::new();
+        doc.save_to(&mut with_font).unwrap();
+        let violations: serde_json::Value = serde_json::from_str(&pdf_utils_rust::check_pdfa(&with_font).unwrap()).unwrap();
+        let font = violations.as_array().unwrap().iter().find(|v| v["kind"] == "font_not_embedded").unwrap();
+        assert_eq!(font["object"], format!("{} 0", font_id.0));
+    }
+
+    #[test]
+    fn test_pdfa_fonts_and_attachments() {
+        // 标准字体不能直接用于 PDF/A，提供字体时替换为嵌入字体并重新编码文字
+        let pdf = pdf_utils_rust::text_to_pdf("AB", "{}").unwrap();
+        let error = pdf_utils_rust::convert_to_pdfa_from_bytes(&pdf, "2b", None, false).unwrap_err();
+        assert!(error.contains("未嵌入"), "{}", error);
+        let converted = pdf_utils_rust::convert_to_pdfa_from_bytes(&pdf, "2b", Some(&make_otf()), false).unwrap();
+        assert_eq!(pdf_utils_rust::check_pdfa(&converted).unwrap(), "[]");
+        let doc = Document::load_mem(&converted).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+        assert!(content.contains("<00010002>"), "{}", content);
+        let error = pdf_utils_rust::convert_to_pdfa_from_bytes(&pdf_utils_rust::text_to_pdf("AC", "{}").unwrap(), "2b", Some(&make_otf()), false).unwrap_err();
+        assert!(error.contains("AC"), "{}", error);
+
+        // 会产生未嵌入字体或删除附件的选项组合直接报错
+        let options = r#"{"pdfa": "2b", "images": [{"caption": "A"}]}"#;
+        let error = pdf_utils_rust::images_to_pdf_from_bytes(&[make_png(10, 10)], options, None).unwrap_err();
+        assert!(error.contains("images_to_pdf_with_font"), "{}", error);
+        let output = pdf_utils_rust::images_to_pdf_from_bytes(&[make_png(10, 10)], options, Some(&make_otf())).unwrap();
+        assert_eq!(pdf_utils_rust::check_pdfa(&output).unwrap(), "[]");
+        let error = pdf_utils_rust::merge_pdfs_from_bytes(std::slice::from_ref(&pdf), r#"{"pdfa": "2b", "keep_attachments": true}"#).err().unwrap();
+        assert!(error.contains("keep_attachments"), "{}", error);
+
+        // PDF/A-3 保留附件并作为关联文件
+        let pdf = make_pdf(&[(200, 300)]);
+        let pdf = pdf_utils_rust::add_attachment(&pdf, "data.csv", b"a,b", None, None, None).unwrap();
+        let converted = pdf_utils_rust::convert_to_pdfa(&pdf, "3b", None).unwrap();
+        assert_eq!(pdf_utils_rust::check_pdfa(&converted).unwrap(), "[]");
+        assert_eq!(pdf_utils_rust::extract_attachment(&converted, "data.csv").unwrap(), b"a,b");
+        let doc = Document::load_mem(&converted).unwrap();
+        let associated = doc.catalog().unwrap().get(b"AF").unwrap().as_array().unwrap();
+        let filespec = doc.get_dictionary(associated[0].as_reference().unwrap()).unwrap();
+        assert_eq!(filespec.get(b"AFRelationship").unwrap().as_name().unwrap(), b"Unspecified");
+        let converted = pdf_utils_rust::convert_to_pdfa(&pdf, "2b", None).unwrap();
+        assert_eq!(pdf_utils_rust::list_attachments(&converted).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_linearize_pdf() {
+        let pdf = make_pdf(&[(200, 300), (300, 400), (400, 500)]);
+        assert!(!pdf_utils_rust::is_linearized(&pdf));
+
+        let linearized = pdf_utils_rust::linearize_pdf(&pdf).unwrap();
+        assert!(pdf_utils_rust::is_linearized(&linearized));
+        assert_eq!(page_sizes(&linearized), page_sizes(&pdf));
+        let doc = Document::load_mem(&linearized).unwrap();
+        let first_page = *doc.get_pages().get(&1).unwrap();
+        assert_eq!(doc.get_page_content(first_page).unwrap(), b"0 0 m 10 10 l S");
+
+        // 文件末尾追加内容（如增量更新）后长度不符，不再视为线性化
+        let mut appended = linearized.clone();
+        appended.extend_from_slice(b"\n% update\n");
+        assert!(!pdf_utils_rust::is_linearized(&appended));
+
+        // 选项中设置 linearize 时直接输出线性化文件，只对这一次调用生效
+        let merged = pdf_utils_rust::merge_pdfs_from_bytes(&[pdf.clone(), pdf.clone()], r#"{"linearize": true}"#).unwrap().pdf();
+        assert!(pdf_utils_rust::is_linearized(&merged));
+        assert_eq!(page_sizes(&merged).len(), 6);
+        assert!(!pdf_utils_rust::is_linearized(&pdf_utils_rust::merge_pdfs_from_bytes(std::slice::from_ref(&pdf), "{}").unwrap().pdf()));
+        assert!(!pdf_utils_rust::is_linearized(&pdf_utils_rust::normalize_page_size(&pdf, "A4", "fit", None).unwrap()));
+
+        let images = vec![make_png(10, 10); 3];
+        let output = pdf_utils_rust::images_to_pdf_from_bytes(&images, r#"{"linearize": true}"#, None).unwrap();
+        assert!(pdf_utils_rust::is_linearized(&output));
+        let output = pdf_utils_rust::images_to_pdf_grid_from_bytes(&images, 2, 1, r#"{"linearize": true}"#, None).unwrap();
+        assert!(pdf_utils_rust::is_linearized(&output));
+        assert_eq!(page_sizes(&output).len(), 2);
+        let output = pdf_utils_rust::text_to_pdf("Hello", r#"{"linearize": true}"#).unwrap();
+        assert!(pdf_utils_rust::is_linearized(&output));
+
+        // 其余输出 PDF 的函数同样可以按次开启
+        let output = pdf_utils_rust::normalize_page_size(&pdf, "A4", "fit", Some(true)).unwrap();
+        assert!(pdf_utils_rust::is_linearized(&output));
+        assert_eq!(page_sizes(&output).len(), 3);
+        let output = pdf_utils_rust::split_pdf_by_range_from_bytes(&pdf, "2-3", false, true).unwrap().pdf();
+        assert!(pdf_utils_rust::is_linearized(&output));
+        assert_eq!(page_sizes(&output).len(), 2);
+        assert!(pdf_utils_rust::is_linearized(&pdf_utils_rust::repair_pdf(&pdf, Some(true)).unwrap()));
+        assert!(!pdf_utils_rust::is_linearized(&pdf_utils_rust::repair_pdf(&pdf, Some(false)).unwrap()));
+    }
+
+    /// 生成测试用 PNG 图片
+    fn make_png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([200, 100, 50]));
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut buffer, image::ImageFormat::Png).unwrap();
+        buffer.into_inner()
+    }
+
+    /// 读取每一页内容流中的 cm 矩阵
+    fn page_matrices(pdf_bytes: &[u8]) -> Vec<Vec<Vec<f32>>> {
+        let doc = Document::load_mem(pdf_bytes).unwrap();
+        doc.get_pages().values().map(|&page_id| {
+            let content = lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+            content.operations.iter()
+                .filter(|op| op.operator == "cm")
+                .map(|op| op.operands.iter().map(|v| v.as_float().unwrap()).collect())
+                .collect()
+        }).collect()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} != {:?}", actual, expected);
+        assert!(actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 0.05), "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_images_to_pdf_layout() {
+        // 200x100 像素，按 72 DPI 即 200x100 点
+        let images = vec![make_png(200, 100)];
+        let convert = |options: &str| pdf_utils_rust::images_to_pdf_from_bytes(&images, options, None).unwrap();
+
+        // 默认 A4 竖版，图片等比缩放到页宽并居中
+        let pdf = convert(r#"{"default_dpi": 72}"#);
+        assert_close(&[page_sizes(&pdf)[0].0, page_sizes(&pdf)[0].1], &[595.28, 841.89]);
+        assert_close(&page_matrices(&pdf)[0][0], &[595.28, 0.0, 0.0, 297.64, 0.0, 272.13]);
+
+        // auto 方向跟随横版图片
+        let pdf = convert(r#"{"default_dpi": 72, "orientation": "auto"}"#);
+        assert_close(&[page_sizes(&pdf)[0].0, page_sizes(&pdf)[0].1], &[841.89, 595.28]);
+        let pdf = convert(r#"{"default_dpi": 72, "page_size": "A5", "orientation": "landscape"}"#);
+        assert_close(&[page_sizes(&pdf)[0].0, page_sizes(&pdf)[0].1], &[595.28, 419.53]);
+
+        // 页面与图片一致时按原尺寸放置，四周加上边距
+        let pdf = convert(r#"{"default_dpi": 72, "page_size": "image", "margins_mm": [10, 20, 10, 20]}"#);
+        let (left, top) = (20.0 * 72.0 / 25.4, 10.0 * 72.0 / 25.4);
+        assert_close(&[page_sizes(&pdf)[0].0, page_sizes(&pdf)[0].1], &[200.0 + 2.0 * left, 100.0 + 2.0 * top]);
+        assert_close(&page_matrices(&pdf)[0][0], &[200.0, 0.0, 0.0, 100.0, left, top]);
+
+        // 边距以内的区域按缩放模式放置
+        let pdf = convert(r#"{"default_dpi": 72, "page_size": "500x500", "margin_mm": 25.4, "fit_mode": "fit"}"#);
+        assert_close(&page_matrices(&pdf)[0][0], &[356.0, 0.0, 0.0, 178.0, 72.0, 161.0]);
+        let pdf = convert(r#"{"default_dpi": 72, "page_size": "400x400", "fit_mode": "fill"}"#);
+        assert_close(&page_matrices(&pdf)[0][0], &[800.0, 0.0, 0.0, 400.0, -200.0, 0.0]);
+        let pdf = convert(r#"{"default_dpi": 72, "page_size": "400x400", "fit_mode": "stretch"}"#);
+        assert_close(&page_matrices(&pdf)[0][0], &[400.0, 0.0, 0.0, 400.0, 0.0, 0.0]);
+        let pdf = convert(r#"{"default_dpi": 72, "page_size": "400x400", "fit_mode": "actual_size", "align": "top-left"}"#);
+        assert_close(&page_matrices(&pdf)[0][0], &[200.0, 0.0, 0.0, 100.0, 0.0, 300.0]);
+    }
+
+    /// 编码测试用图片
+    fn encode_image(image: image::DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut buffer, format).unwrap();
+        buffer.into_inner()
+    }
+
+    /// 读取每一页的图片 XObject（第一张）
+    fn page_images(pdf_bytes: &[u8]) -> Vec<(Dictionary, Option<Dictionary>)> {
+        let doc = Document::load_mem(pdf_bytes).unwrap();
+        doc.get_pages().values().map(|&page_id| {
+            let resources = doc.get_dictionary(page_id).unwrap().get(b"Resources").unwrap().as_dict().unwrap();
+            let xobjects = resources.get(b"XObject").unwrap().as_dict().unwrap();
+            let (_, image) = xobjects.iter().next().unwrap();
+            let image = doc.get_object(image.as_reference().unwrap()).unwrap().as_stream().unwrap();
+            let smask = image.dict.get(b"SMask").ok()
+                .map(|smask| doc.get_object(smask.as_reference().unwrap()).unwrap().as_stream().unwrap().dict.clone());
+            (image.dict.clone(), smask)
+        }).collect()
+    }
+
+    fn name_of<'a>(dict: &'a Dictionary, key: &[u8]) -> &'a [u8] {
+        dict.get(key).unwrap().as_name().unwrap()
+    }
+
+    #[test]
+    fn test_embed_image_color_spaces() {
+        // 带透明通道的 PNG：颜色无损压缩，透明通道写入 /SMask
+        let rgba = image::RgbaImage::from_fn(4, 3, |x, _| image::Rgba([255, 0, 0, (x * 60) as u8]));
+        let png = encode_image(image::DynamicImage::ImageRgba8(rgba), image::ImageFormat::Png);
+        // 灰度 PNG 使用 /DeviceGray
+        let gray = image::GrayImage::from_fn(4, 3, |x, y| image::Luma([(x * 40 + y) as u8]));
+        let gray = encode_image(image::DynamicImage::ImageLuma8(gray), image::ImageFormat::Png);
+        // JPEG 原样嵌入
+        let jpeg = encode_image(image::DynamicImage::ImageRgb8(image::RgbImage::new(8, 8)), image::ImageFormat::Jpeg);
+
+        let pdf = pdf_utils_rust::images_to_pdf_from_bytes(&[png, gray, jpeg.clone()], "{}", None).unwrap();
+        let images = page_images(&pdf);
+        assert_eq!(images.len(), 3);
+
+        let (image, smask) = &images[0];
+        assert_eq!(name_of(image, b"ColorSpace"), b"DeviceRGB");
+        assert_eq!(name_of(image, b"Filter"), b"FlateDecode");
+        let smask = smask.as_ref().unwrap();
+        assert_eq!(name_of(smask, b"ColorSpace"), b"DeviceGray");
+        assert_eq!(smask.get(b"Width").unwrap().as_i64().unwrap(), 4);
+
+        let (image, smask) = &images[1];
+        assert_eq!(name_of(image, b"ColorSpace"), b"DeviceGray");
+        assert_eq!(image.get(b"BitsPerComponent").unwrap().as_i64().unwrap(), 8);
+        assert!(smask.is_none());
+
+        let (image, smask) = &images[2];
+        assert_eq!(name_of(image, b"Filter"), b"DCTDecode");
+        assert_eq!(name_of(image, b"ColorSpace"), b"DeviceRGB");
+        assert!(smask.is_none());
+        let doc = Document::load_mem(&pdf).unwrap();
+        assert!(doc.objects.values().any(|o| o.as_stream().is_ok_and(|s| s.content == jpeg)));
+
+        // 无损（SOF3）和 12 位 JPEG 不能原样嵌入，无法解码时报错而不是写入阅读器无法显示的 DCTDecode
+        let sof = jpeg.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        let mut lossless = jpeg.clone();
+        lossless[sof + 1] = 0xC3;
+        let error = pdf_utils_rust::images_to_pdf_from_bytes(&[lossless], "{}", None).unwrap_err();
+        assert!(error.contains("SOF3"), "{}", error);
+        let mut twelve_bit = jpeg.clone();
+        twelve_bit[sof + 4] = 12;
+        let error = pdf_utils_rust::images_to_pdf_from_bytes(&[twelve_bit], "{}", None).unwrap_err();
+        assert!(error.contains("12 位"), "{}", error);
+    }
+
+    #[test]
+    fn test_multi_frame_images() {
+        // 两帧 GIF 动画
+        let mut gif = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut gif);
+            let frames = [[255, 0, 0, 255], [0, 0, 255, 255]]
+                .map(|color| image::Frame::new(image::RgbaImage::from_pixel(6, 4, image::Rgba(color))));
+            encoder.encode_frames(frames).unwrap();
+        }
+        // 两页 TIFF
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        {
+            let mut encoder = tiff::encoder::TiffEncoder::new(&mut tiff).unwrap();
+            encoder.write_image::<tiff::encoder::colortype::Gray8>(5, 5, &[0; 25]).unwrap();
+            encoder.write_image::<tiff::encoder::colortype::RGB8>(3, 2, &[128; 18]).unwrap();
+        }
+        let tiff = tiff.into_inner();
+
+        // 每帧（页）单独成页
+        let pdf = pdf_utils_rust::images_to_pdf_from_bytes(&[gif.clone(), tiff.clone()], "{}", None).unwrap();
+        let images = page_images(&pdf);
+        assert_eq!(images.len(), 4);
+        let sizes: Vec<(i64, i64)> = images.iter()
+            .map(|(image, _)| (image.get(b"Width").unwrap().as_i64().unwrap(), image.get(b"Height").unwrap().as_i64().unwrap()))
+            .collect();
+        assert_eq!(sizes, [(6, 4), (6, 4), (5, 5), (3, 2)]);
+        assert_eq!(name_of(&images[2].0, b"ColorSpace"), b"DeviceGray");
+        assert_eq!(name_of(&images[3].0, b"ColorSpace"), b"DeviceRGB");
+
+        // 设置书签时每个文件只生成一个书签，指向第一帧
+        let pdf = pdf_utils_rust::images_to_pdf_from_bytes(&[gif, tiff], r#"{"images": [{"bookmark": "GIF"}, {"bookmark": "TIFF"}]}"#, None).unwrap();
+        let outline: serde_json::Value = serde_json::from_str(&pdf_utils_rust::get_pdf_outline(&pdf).unwrap()).unwrap();
+        let pages: Vec<_> = outline.as_array().unwrap().iter().map(|item| (item["title"].clone(), item["page"].clone())).collect();
+        assert_eq!(pages, [(serde_json::json!("GIF"), serde_json::json!(1)), (serde_json::json!("TIFF"), serde_json::json!(3))]);
+    }
+
+    #[test]
+    fn test_image_captions_rotation_and_bookmarks() {
+        let images = vec![make_png(200, 100), make_png(200, 100), make_png(200, 100)];
+        let options = r#"{
+            "page_size": "image",
+            "default_dpi": 72,
+            "images": [
+                {"caption": "Sea", "rotation": 90, "bookmark": "First"},
+                {"page_size": "A5"},
+                {"caption": "Harbor"}
+            ]
+        }"#;
+        let pdf = pdf_utils_rust::images_to_pdf_from_bytes(&images, options, None).unwrap();
+
+        // 旋转 90 度后宽高互换，标题在图片下方占一行（字号 12：间距 6 + 行高 14.4）
+        let sizes = page_sizes(&pdf);
+        assert_close(&[sizes[0].0, sizes[0].1], &[100.0, 220.4]);
+        assert_close(&page_matrices(&pdf)[0][0], &[0.0, -200.0, 100.0, 0.0, 0.0, 220.4]);
+        // 单独指定的页面尺寸覆盖全局设置
+        assert_close(&[sizes[1].0, sizes[1].1], &[419.53, 595.28]);
+        assert_close(&[sizes[2].0, sizes[2].1], &[200.0, 120.4]);
+
+        let doc = Document::load_mem(&pdf).unwrap();
+        let contents: Vec<String> = doc.get_pages().values()
+            .map(|&id| String::from_utf8(doc.get_page_content(id).unwrap()).unwrap())
+            .collect();
+        assert!(contents[0].contains("(Sea) Tj"), "{}", contents[0]);
+        assert!(!contents[1].contains("Tj"));
+        assert!(contents[2].contains("(Harbor) Tj"), "{}", contents[2]);
+
+        // 书签标题依次使用 bookmark、默认的 "图片 N" 和标题
+        let outline: serde_json::Value = serde_json::from_str(&pdf_utils_rust::get_pdf_outline(&pdf).unwrap()).unwrap();
+        let entries: Vec<(String, i64)> = outline.as_array().unwrap().iter()
+            .map(|item| (item["title"].as_str().unwrap().to_string(), item["page"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(entries, [("First".to_string(), 1), ("图片 2".to_string(), 2), ("Harbor".to_string(), 3)]);
+    }
+
+    #[test]
+    fn test_images_to_pdf_grid() {
+        // 100x100 点的正方形图片，400x400 的页面上每页 2x2 个 200x200 的单元格
+        let images = vec![make_png(100, 100); 5];
+        let options = r#"{"page_size": "400x400", "margin_mm": 0, "gutter_mm": 0}"#;
+        let pdf = pdf_utils_rust::images_to_pdf_grid_from_bytes(&images, 2, 2, options, None).unwrap();
+
+        let matrices = page_matrices(&pdf);
+        assert_eq!(matrices.len(), 2);
+        assert_eq!(matrices[0].len(), 4);
+        assert_eq!(matrices[1].len(), 1);
+        // 从左上角开始逐行排列
+        assert_close(&matrices[0][0], &[200.0, 0.0, 0.0, 200.0, 0.0, 200.0]);
+        assert_close(&matrices[0][1], &[200.0, 0.0, 0.0, 200.0, 200.0, 200.0]);
+        assert_close(&matrices[0][2], &[200.0, 0.0, 0.0, 200.0, 0.0, 0.0]);
+        assert_close(&matrices[0][3], &[200.0, 0.0, 0.0, 200.0, 200.0, 0.0]);
+        assert_close(&matrices[1][0], &[200.0, 0.0, 0.0, 200.0, 0.0, 200.0]);
+
+        // 行列数过多时报错，而不是溢出
+        for (cols, rows) in [(101, 1), (1, 101), (65536, 65536)] {
+            let error = pdf_utils_rust::images_to_pdf_grid_from_bytes(&images, cols, rows, options, None).unwrap_err();
+            assert!(error.contains("不能超过"), "{}", error);
+        }
+    }
+
+    /// 生成只含 .notdef、A、B 三个字形的最小 OpenType（CFF 轮廓）字体，A、B 的宽度为 600、700
+    fn make_otf() -> Vec<u8> {
+        fn words(values: &[u16]) -> Vec<u8> {
+            values.iter().flat_map(|v| v.to_be_bytes()).collect()
+        }
+        fn index(items: &[&[u8]]) -> Vec<u8> {
+            let mut out = words(&[items.len() as u16]);
+            if items.is_empty() {
+                return out;
+            }
+            out.push(4);
+            let mut offset = 1u32;
+            out.extend(offset.to_be_bytes());
+            for item in items {
+                offset += item.len() as u32;
+                out.extend(offset.to_be_bytes());
+            }
+            out.extend(items.concat());
+            out
+        }
+        // DICT 中固定 5 字节的整数，写入偏移前即可确定 Top DICT 的长度
+        fn int(value: usize) -> Vec<u8> {
+            [vec![29], (value as i32).to_be_bytes().to_vec()].concat()
+        }
+
+        // 10 10 rmoveto 50 0 rlineto 0 50 rlineto endchar
+        let square: &[u8] = &[149, 149, 21, 189, 139, 5, 139, 189, 5, 14];
+        let charstrings = index(&[&[14], square, square]);
+        let charset = [0, 0, 34, 0, 35]; // A、B 的标准字符串编号
+        let private = [139, 20]; // defaultWidthX 0
+        let prefix = [vec![1, 0, 4, 4], index(&[b"Test"])].concat();
+        let top_len = index(&[&[0; 23]]).len();
+        let charset_offset = prefix.len() + top_len + 4;
+        let charstrings_offset = charset_offset + charset.len();
+        let private_offset = charstrings_offset + charstrings.len();
+        let top = [
+            int(charset_offset), vec![15],
+            int(charstrings_offset), vec![17],
+            int(private.len()), int(private_offset), vec![18],
+        ].concat();
+        let cff = [prefix, index(&[&top]), index(&[]), index(&[]), charset.to_vec(), charstrings, private.to_vec()].concat();
+
+        let head = words(&[1, 0, 1, 0, 0, 0, 0x5F0F, 0x3CF5, 0, 1000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1000, 800, 0, 8, 2, 0, 0]);
+        let hhea = words(&[1, 0, 800, (-200i16) as u16, 0, 700, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3]);
+        let maxp = words(&[0, 0x5000, 3]);
+        let hmtx = words(&[500, 0, 600, 0, 700, 0]);
+        // (3, 10) 格式 12：U+0041..U+0042 映射到字形 1..2，U+4E2D（中）映射到字形 1
+        let cmap = words(&[0, 1, 3, 10, 0, 12, 12, 0, 0, 40, 0, 0, 0, 2, 0, 0x41, 0, 0x42, 0, 1, 0, 0x4E2D, 0, 0x4E2D, 0, 1]);
+        let name = [words(&[0, 1, 18, 3, 1, 0x409, 6, 8, 0]), words(&[b'T' as u16, b'e' as u16, b's' as u16, b't' as u16])].concat();
+
+        let tables: [(&[u8; 4], Vec<u8>); 7] = [
+            (b"CFF ", cff), (b"cmap", cmap), (b"head", head), (b"hhea", hhea),
+            (b"hmtx", hmtx), (b"maxp", maxp), (b"name", name),
+        ];
+        let mut directory = [b"OTTO".to_vec(), words(&[tables.len() as u16, 64, 2, 48])].concat();
+        let mut data = Vec::new();
+        for (tag, table) in &tables {
+            let offset = 12 + 16 * tables.len() + data.len();
+            directory.extend_from_slice(*tag);
+            directory.extend([0u32, offset as u32, table.len() as u32].iter().flat_map(|v| v.to_be_bytes()));
+            data.extend(table);
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+        [directory, data].concat()
+    }
+
+    /// 表单字段 name 的外观流内容
+    fn name_appearance(pdf: &[u8]) -> String {
+        let doc = Document::load_mem(pdf).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let annots = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap().clone();
+        let name = doc.get_dictionary(annots[0].as_reference().unwrap()).unwrap();
+        let appearance = doc.get_object(name.get(b"AP").unwrap().as_dict().unwrap().get(b"N").unwrap().as_reference().unwrap()).unwrap();
+        String::from_utf8_lossy(&appearance.as_stream().unwrap().content).into_owned()
+    }
+
+    fn need_appearances(pdf: &[u8]) -> bool {
+        let doc = Document::load_mem(pdf).unwrap();
+        let form = doc.catalog().unwrap().get(b"AcroForm").unwrap();
+        let form = doc.dereference(form).unwrap().1.as_dict().unwrap();
+        form.get(b"NeedAppearances").and_then(Object::as_bool).unwrap_or(false)
+    }
+
+    #[test]
+    fn test_form_text_outside_win_ansi() {
+        let pdf = make_form_pdf();
+        let values = r#"{"name": "中A"}"#;
+
+        // 没有可用字体：填写时交给阅读器生成外观，扁平化时报错而不是写入 "?"
+        let filled = pdf_utils_rust::fill_form_from_bytes(&pdf, values, None, false).unwrap();
+        assert!(need_appearances(&filled));
+        let error = pdf_utils_rust::flatten_form_from_bytes(&filled, None, false).unwrap_err();
+        assert!(error.contains("name"), "{}", error);
+
+        // 提供字体时扁平化使用嵌入字体
+        let flattened = pdf_utils_rust::flatten_form_from_bytes(&filled, Some(&make_otf()), false).unwrap();
+        let doc = Document::load_mem(&flattened).unwrap();
+        assert!(doc.objects.values().filter_map(|o| o.as_dict().ok()).any(|d| d.get(b"Subtype").and_then(Object::as_name).is_ok_and(|n| n == b"Type0")));
+        for stream in doc.objects.values().filter_map(|o| o.as_stream().ok()) {
+            let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+            assert!(!contains(&content, b"(??"));
+        }
+
+        // 填写时提供字体，外观直接使用嵌入字体，不需要阅读器重新生成
+        let filled = pdf_utils_rust::fill_form_from_bytes(&pdf, values, Some(&make_otf()), false).unwrap();
+        assert!(!need_appearances(&filled));
+        assert!(name_appearance(&filled).contains("/EmbF"), "{}", name_appearance(&filled));
+        assert!(pdf_utils_rust::flatten_form_from_bytes(&filled, None, false).is_ok());
+
+        // /DR 中使用 Identity-H 的复合字体按 ToUnicode 编码
+        let mut doc = Document::load_mem(&pdf).unwrap();
+        let to_unicode = doc.add_object(Stream::new(Dictionary::new(), b"1 beginbfchar <0005> <4E2D> endbfchar 1 beginbfchar <0024> <0041> endbfchar".to_vec()));
+        let font = doc.add_object(Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type0".to_vec())),
+            ("BaseFont", Object::Name(b"CJK".to_vec())),
+            ("Encoding", Object::Name(b"Identity-H".to_vec())),
+            ("ToUnicode", Object::Reference(to_unicode)),
+        ]));
+        let mut form = doc.catalog().unwrap().get(b"AcroForm").unwrap().as_dict().unwrap().clone();
+        form.set("DA", Object::string_literal("/CJK 0 Tf 0 g"));
+        form.set("DR", Dictionary::from_iter(vec![("Font", Object::Dictionary(Dictionary::from_iter(vec![("CJK", Object::Reference(font))])))]));
+        doc.catalog_mut().unwrap().set("AcroForm", Object::Dictionary(form));
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).unwrap();
+
+        let filled = pdf_utils_rust::fill_form_from_bytes(&pdf, values, None, false).unwrap();
+        assert!(!need_appearances(&filled));
+        assert!(name_appearance(&filled).contains("<00050024> Tj"), "{}", name_appearance(&filled));
+    }
+
+    #[test]
+    fn test_embed_opentype_cff_font() {
+        let options = r#"{"page_size": "image", "images": [{"caption": "ABBA"}]}"#;
+        let pdf = pdf_utils_rust::images_to_pdf_from_bytes(&[make_png(100, 100)], options, Some(&make_otf())).unwrap();
+        let doc = Document::load_mem(&pdf).unwrap();
+
+        let font = doc.objects.values()
+            .filter_map(|o| o.as_dict().ok())
+            .find(|d| d.get(b"Subtype").and_then(Object::as_name).is_ok_and(|n| n == b"Type0"))
+            .expect("缺少 Type0 字体");
+        let to_unicode = doc.get_object(font.get(b"ToUnicode").unwrap().as_reference().unwrap()).unwrap();
+        let cmap = String::from_utf8(to_unicode.as_stream().unwrap().decompressed_content().unwrap()).unwrap();
+        assert!(cmap.contains("<0041>") && cmap.contains("<0042>"), "{}", cmap);
+
+        // CFF 轮廓写为 CIDFontType0，字体程序为 FontFile3
+        let descendant = match font.get(b"DescendantFonts").unwrap() {
+            Object::Array(fonts) => doc.get_dictionary(fonts[0].as_reference().unwrap()).unwrap(),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(name_of(descendant, b"Subtype"), b"CIDFontType0");
+        let widths: Vec<i64> = descendant.get(b"W").unwrap().as_array().unwrap().iter()
+            .flat_map(|o| match o {
+                Object::Array(widths) => widths.iter().filter_map(|w| w.as_i64().ok()).collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        assert!(widths.contains(&600) && widths.contains(&700), "{:?}", widths);
+
+        let descriptor = doc.get_dictionary(descendant.get(b"FontDescriptor").unwrap().as_reference().unwrap()).unwrap();
+        assert!(!descriptor.has(b"FontFile2"));
+        let font_file = doc.get_object(descriptor.get(b"FontFile3").unwrap().as_reference().unwrap()).unwrap().as_stream().unwrap();
+        assert_eq!(name_of(&font_file.dict, b"Subtype"), b"CIDFontType0C");
+        // 子集仍是 CFF 数据（版本 1 的头部）
+        let cff = font_file.decompressed_content().unwrap_or_else(|_| font_file.content.clone());
+        assert_eq!(cff[..2], [1, 0]);
+    }
+}